@@ -0,0 +1,269 @@
+use solver::{
+    evaluate_with_rules, max_reachable, min_reachable_above, normalise_cards, points, LazyPrograms,
+    Programs, Ruleset, MAX_CARDS,
+};
+
+/// Help message listing the commands
+const USAGE: &str = "Commands:\n  !countdown <target> <cards...>  Solve a puzzle, eg. !countdown 523 100 75 8 8 3 1\n  !check <target> <cards...>: <equation>  Judge an answer, eg. !check 523 100 75 8 8 3 1: (8 × (75 + 3)) - 100 - 1\n  !trivia <cards...>  Largest value that can be made, eg. !trivia 100 75 8 8 3 1";
+
+/// Solves puzzles and judges answers for chat messages
+pub struct Commands {
+    /// Programs for each number of cards, generated when first needed
+    programs: LazyPrograms,
+    /// Rules to solve and judge with
+    rules: Ruleset,
+    /// Maximum number of solutions to reply with
+    solutions: usize,
+}
+
+impl Commands {
+    pub fn new(rules: Ruleset, solutions: usize) -> Self {
+        Self {
+            programs: LazyPrograms::new(),
+            rules,
+            solutions,
+        }
+    }
+
+    /// Returns the reply to a message, or None if the message isn't a command
+    pub fn reply(&self, message: &str) -> Option<String> {
+        let message = message.trim();
+        let (command, args) = message
+            .split_once(char::is_whitespace)
+            .unwrap_or((message, ""));
+
+        match command {
+            "!countdown" => Some(self.solve(args)),
+            "!check" => Some(self.check(args)),
+            "!trivia" => Some(self.trivia(args)),
+            "!help" => Some(USAGE.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Solves a puzzle, replying with the best (shortest) solutions
+    fn solve(&self, args: &str) -> String {
+        let (target, cards) = match parse_puzzle(args) {
+            Ok(puzzle) => puzzle,
+            Err(e) => return format!("{e}\n{USAGE}"),
+        };
+
+        let programs = self.programs(cards.len());
+
+        let mut solutions = programs.run_target_rules(target, &cards, self.rules.rules());
+
+        // Filter out identical equations (can happen when duplicate card is chosen)
+        programs.filter_identical(&mut solutions, &cards);
+
+        // Sort solutions by shortest and simplest first
+        programs.rank_solutions(&mut solutions, &cards);
+
+        let puzzle = format!("{target} from {}", cards_string(&cards));
+
+        if solutions.is_empty() {
+            return match self.closest(programs, target, &cards) {
+                Some(closest) => format!(
+                    "{puzzle}: no solutions, the closest is {closest} ({} away)",
+                    closest.abs_diff(target)
+                ),
+                None => format!("{puzzle}: no solutions"),
+            };
+        }
+
+        let mut reply = format!(
+            "{puzzle}: {} solution{}",
+            solutions.len(),
+            if solutions.len() == 1 { "" } else { "s" }
+        );
+
+        if solutions.len() > self.solutions {
+            reply.push_str(&format!(", the best {}:", self.solutions));
+        } else {
+            reply.push(':');
+        }
+
+        for solution in solutions.iter().take(self.solutions) {
+            reply.push_str(&format!(
+                "\n  {} = {target}",
+                programs.infix(solution.program, &cards, false)
+            ));
+        }
+
+        reply
+    }
+
+    /// Judges an answer to a puzzle
+    fn check(&self, args: &str) -> String {
+        let Some((puzzle, equation)) = args.split_once(':') else {
+            return format!("Missing ':' before the equation\n{USAGE}");
+        };
+
+        let (target, cards) = match parse_puzzle(puzzle) {
+            Ok(puzzle) => puzzle,
+            Err(e) => return format!("{e}\n{USAGE}"),
+        };
+
+        let equation = equation.trim();
+
+        match evaluate_with_rules(equation, &cards, self.rules.rules()) {
+            Ok(value) if value == target => {
+                format!(
+                    "{equation} = {value}: exactly right, {} points",
+                    points(target, value)
+                )
+            }
+            Ok(value) => format!(
+                "{equation} = {value}: {} away, {} points",
+                value.abs_diff(target),
+                points(target, value)
+            ),
+            Err(e) => format!("{equation}: not valid ({e}), 0 points"),
+        }
+    }
+
+    /// Replies with the largest value which can be made from a set of cards, and the smallest value
+    /// above the target range
+    fn trivia(&self, args: &str) -> String {
+        let cards = match parse_cards(args) {
+            Ok(cards) => cards,
+            Err(e) => return format!("{e}\n{USAGE}"),
+        };
+
+        let rules = self.rules.rules();
+
+        if !rules.is_strict() {
+            return format!("Trivia isn't available with the {} rules", self.rules);
+        }
+
+        let max_target = rules.max_target;
+        let largest = max_reachable(&cards).expect("No cards");
+
+        let above = match min_reachable_above(&cards, max_target) {
+            Some(above) => format!("the smallest above {max_target} is {above}"),
+            None => format!("nothing above {max_target} can be made"),
+        };
+
+        format!(
+            "{}: the largest value is {largest}, {above}",
+            cards_string(&cards)
+        )
+    }
+
+    /// Returns the reachable target (100 to 999) closest to a target
+    fn closest(&self, programs: &Programs, target: u32, cards: &[u32]) -> Option<u32> {
+        if !self.rules.rules().is_strict() {
+            return None;
+        }
+
+        programs.coverage(cards).nearest(target)
+    }
+
+    /// Returns the programs for a number of cards
+    fn programs(&self, cards: usize) -> &Programs {
+        self.programs.get_or_generate(cards, false)
+    }
+}
+
+/// Parses a puzzle given as the target followed by the cards, separated by spaces or commas
+fn parse_puzzle(puzzle: &str) -> Result<(u32, Vec<u32>), String> {
+    let numbers = parse_numbers(puzzle)?;
+
+    let Some((&target, cards)) = numbers.split_first() else {
+        Err("No target given")?
+    };
+
+    if target == 0 {
+        Err("Target must be greater than zero")?
+    }
+
+    Ok((target, check_cards(cards)?))
+}
+
+/// Parses cards separated by spaces or commas
+fn parse_cards(cards: &str) -> Result<Vec<u32>, String> {
+    check_cards(&parse_numbers(cards)?)
+}
+
+/// Parses numbers separated by spaces or commas
+fn parse_numbers(numbers: &str) -> Result<Vec<u32>, String> {
+    numbers
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|n| !n.is_empty())
+        .map(|n| {
+            n.parse::<u32>()
+                .map_err(|e| format!("Invalid number '{n}': {e}"))
+        })
+        .collect()
+}
+
+/// Checks the number of cards, returning them in descending order
+fn check_cards(cards: &[u32]) -> Result<Vec<u32>, String> {
+    if cards.is_empty() {
+        Err("No cards given")?
+    }
+
+    if cards.len() > MAX_CARDS {
+        Err(format!("Maximum of {MAX_CARDS} cards allowed"))?
+    }
+
+    Ok(normalise_cards(cards))
+}
+
+/// Formats cards separated by spaces
+fn cards_string(cards: &[u32]) -> String {
+    cards
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply() {
+        let commands = Commands::new(Ruleset::Tv, 3);
+
+        assert_eq!(None, commands.reply("Hello"));
+        assert_eq!(Some(USAGE.to_string()), commands.reply("  !help  "));
+
+        let reply = commands.reply("!countdown 100 25, 4").unwrap();
+
+        assert!(reply.starts_with("100 from 25 4: 1 solution:"), "{reply}");
+
+        assert_eq!(
+            "25 * 4 = 100: exactly right, 10 points",
+            commands.reply("!check 100 25 4: 25 * 4").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok((523, vec![100, 8, 3])), parse_puzzle("523 3, 100 8"));
+        assert_eq!(Err("No target given".to_string()), parse_puzzle(" "));
+        assert_eq!(
+            Err("Target must be greater than zero".to_string()),
+            parse_puzzle("0 1 2")
+        );
+        assert_eq!(Err("No cards given".to_string()), parse_puzzle("523"));
+        assert!(parse_puzzle("523 1 2 3 4 5 6 7").is_err());
+        assert!(parse_cards("100 x")
+            .unwrap_err()
+            .starts_with("Invalid number 'x'"));
+
+        let commands = Commands::new(Ruleset::Tv, 3);
+
+        assert!(commands
+            .reply("!check 100 25 4 25 * 4")
+            .unwrap()
+            .starts_with("Missing ':'"));
+        assert!(commands
+            .reply("!countdown")
+            .unwrap()
+            .starts_with("No target given"));
+    }
+}
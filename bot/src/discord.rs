@@ -0,0 +1,163 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use minihttp::{hex_decode, Request};
+use serde_json::{json, Value};
+
+use crate::commands::Commands;
+use crate::{is_recent, now};
+
+/// Interaction type of a ping sent when the endpoint is configured
+const PING: u64 = 1;
+
+/// Interaction type of a slash command
+const APPLICATION_COMMAND: u64 = 2;
+
+/// Parses an application public key given in hex
+pub fn parse_public_key(hex: &str) -> Result<VerifyingKey, String> {
+    let bytes = hex_decode(hex.trim()).ok_or("Public key is not hexadecimal")?;
+
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "Public key must be 32 bytes")?;
+
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid public key: {e}"))
+}
+
+/// Handles an interaction, returning the HTTP status and JSON response body
+pub fn handle(key: &VerifyingKey, commands: &Commands, request: &Request) -> (u16, String) {
+    if !verify(key, request, now()) {
+        return (
+            401,
+            json!({"error": "Invalid request signature"}).to_string(),
+        );
+    }
+
+    let Ok(interaction) = serde_json::from_str::<Value>(&request.body) else {
+        return (400, json!({"error": "Invalid interaction"}).to_string());
+    };
+
+    match interaction["type"].as_u64() {
+        Some(PING) => (200, json!({"type": 1}).to_string()),
+        Some(APPLICATION_COMMAND) => {
+            // Slash commands are named after the message commands and take the rest of the
+            // message as a single string option, eg. /countdown puzzle:523 100 75 8 8 3 1
+            let data = &interaction["data"];
+            let name = data["name"].as_str().unwrap_or_default();
+            let text = data["options"][0]["value"].as_str().unwrap_or_default();
+
+            let content = commands
+                .reply(&format!("!{name} {text}"))
+                .unwrap_or_else(|| format!("Unknown command '{name}'"));
+
+            (
+                200,
+                json!({"type": 4, "data": {"content": content}}).to_string(),
+            )
+        }
+        _ => (
+            400,
+            json!({"error": "Unsupported interaction type"}).to_string(),
+        ),
+    }
+}
+
+/// Checks the signature of the timestamp and body against the application public key, and the
+/// timestamp is recent
+fn verify(key: &VerifyingKey, request: &Request, now: u64) -> bool {
+    let (Some(signature), Some(timestamp)) = (
+        request.header("x-signature-ed25519"),
+        request.header("x-signature-timestamp"),
+    ) else {
+        return false;
+    };
+
+    if !is_recent(timestamp, now) {
+        return false;
+    }
+
+    let Some(signature) = hex_decode(signature).and_then(|s| Signature::from_slice(&s).ok()) else {
+        return false;
+    };
+
+    let message = format!("{timestamp}{}", request.body);
+
+    key.verify(message.as_bytes(), &signature).is_ok()
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    /// Returns bytes as a hexadecimal string
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Creates a request signed with a key at a time
+    fn signed_request(key: &SigningKey, timestamp: u64, body: &str) -> Request {
+        let signature = key.sign(format!("{timestamp}{body}").as_bytes());
+
+        Request {
+            method: "POST".to_string(),
+            path: "/discord".to_string(),
+            query: HashMap::new(),
+            headers: HashMap::from([
+                (
+                    "x-signature-ed25519".to_string(),
+                    hex(&signature.to_bytes()),
+                ),
+                ("x-signature-timestamp".to_string(), timestamp.to_string()),
+            ]),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let key = parse_public_key(&hex(signing_key.verifying_key().as_bytes())).unwrap();
+
+        let body = r#"{"type":1}"#;
+
+        assert!(verify(&key, &signed_request(&signing_key, NOW, body), NOW));
+
+        // Signed with another key
+        let other_key = SigningKey::from_bytes(&[8; 32]);
+
+        assert!(!verify(&key, &signed_request(&other_key, NOW, body), NOW));
+
+        // Body changed after signing
+        let mut request = signed_request(&signing_key, NOW, body);
+        request.body = r#"{"type":2}"#.to_string();
+
+        assert!(!verify(&key, &request, NOW));
+
+        // Too old
+        assert!(!verify(
+            &key,
+            &signed_request(&signing_key, NOW - 301, body),
+            NOW
+        ));
+
+        // Signature isn't hexadecimal
+        let mut request = signed_request(&signing_key, NOW, body);
+        request
+            .headers
+            .insert("x-signature-ed25519".to_string(), "zz".to_string());
+
+        assert!(!verify(&key, &request, NOW));
+    }
+
+    #[test]
+    fn test_parse_public_key() {
+        assert!(parse_public_key("zz").is_err());
+        assert!(parse_public_key("0102").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+mod commands;
+mod discord;
+mod slack;
+
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Parser;
+use commands::Commands;
+use ed25519_dalek::VerifyingKey;
+use minihttp::{respond, Request};
+use serde_json::json;
+use solver::{set_symbol_set, Ruleset, SymbolSet, CAPABILITIES};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct Args {
+    /// Address to listen on
+    #[clap(short = 'b', long = "bind", default_value = "127.0.0.1", value_parser)]
+    bind: String,
+
+    /// Port to listen on
+    #[clap(short = 'p', long = "port", default_value_t = 8081, value_parser)]
+    port: u16,
+
+    /// Discord application public key (hex) to verify interactions sent to /discord with
+    #[clap(long = "discord-public-key", value_parser)]
+    discord_public_key: Option<String>,
+
+    /// Slack signing secret to verify requests sent to /slack with
+    #[clap(long = "slack-signing-secret", value_parser)]
+    slack_signing_secret: Option<String>,
+
+    /// Maximum number of solutions to reply with
+    #[clap(short = 'n', long = "solutions", default_value_t = 3, value_parser)]
+    solutions: usize,
+
+    /// Rules to solve and judge answers with (tv, junior or relaxed)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Multiplication symbol to use in equations in replies (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action)]
+    capabilities: bool,
+}
+
+/// Maximum age of a signed request in seconds, to stop replays
+const MAX_AGE: u64 = 300;
+
+/// Bot state shared by the connections
+struct Bot {
+    commands: Commands,
+    discord_key: Option<VerifyingKey>,
+    slack_secret: Option<String>,
+}
+
+fn main() {
+    // Parse command line arguments
+    let args = Args::parse();
+
+    if args.capabilities {
+        println!("{}", CAPABILITIES.to_json());
+        return;
+    }
+
+    if args.discord_public_key.is_none() && args.slack_signing_secret.is_none() {
+        eprintln!("At least one of --discord-public-key and --slack-signing-secret is required");
+        process::exit(1);
+    }
+
+    let discord_key = match args
+        .discord_public_key
+        .as_deref()
+        .map(discord::parse_public_key)
+    {
+        Some(Ok(key)) => Some(key),
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        None => None,
+    };
+
+    set_symbol_set(args.symbols);
+
+    let listener = match TcpListener::bind((args.bind.as_str(), args.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Unable to listen on {}:{} ({e})", args.bind, args.port);
+            process::exit(1);
+        }
+    };
+
+    println!("Listening on {}:{}", args.bind, args.port);
+
+    let bot = Arc::new(Bot {
+        commands: Commands::new(args.rules, args.solutions),
+        discord_key,
+        slack_secret: args.slack_signing_secret,
+    });
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let bot = Arc::clone(&bot);
+
+                thread::spawn(move || connection(&bot, stream));
+            }
+            Err(e) => eprintln!("Connection failed ({e})"),
+        }
+    }
+}
+
+/// Handles a connection
+fn connection(bot: &Bot, mut stream: TcpStream) {
+    let result = Request::read(&stream).and_then(|request| {
+        let (status, body) = route(bot, &request);
+
+        respond(&mut stream, status, &[], "application/json", &body)
+    });
+
+    if let Err(e) = result {
+        eprintln!("Request failed ({e})");
+    }
+}
+
+/// Routes a request to the platform it came from
+fn route(bot: &Bot, request: &Request) -> (u16, String) {
+    let post = request.method == "POST";
+
+    match request.path.as_str() {
+        "/discord" => match &bot.discord_key {
+            Some(key) if post => discord::handle(key, &bot.commands, request),
+            Some(_) => method_not_allowed(),
+            None => not_found(),
+        },
+        "/slack" => match &bot.slack_secret {
+            Some(secret) if post => slack::handle(secret, &bot.commands, request),
+            Some(_) => method_not_allowed(),
+            None => not_found(),
+        },
+        _ => not_found(),
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before epoch")
+        .as_secs()
+}
+
+/// Returns true if a request timestamp (seconds since the Unix epoch) is within the maximum age of
+/// the current time
+fn is_recent(timestamp: &str, now: u64) -> bool {
+    timestamp
+        .parse::<u64>()
+        .is_ok_and(|sent| now.abs_diff(sent) <= MAX_AGE)
+}
+
+/// Response for an unknown path, or a platform which isn't configured
+fn not_found() -> (u16, String) {
+    (404, json!({"error": "Not found"}).to_string())
+}
+
+/// Response for a request which isn't a POST
+fn method_not_allowed() -> (u16, String) {
+    (405, json!({"error": "Method not allowed"}).to_string())
+}
@@ -0,0 +1,139 @@
+use hmac_sha256::HMAC;
+use minihttp::{hex_decode, parse_form, Request};
+use serde_json::json;
+
+use crate::commands::Commands;
+use crate::{is_recent, now};
+
+/// Handles a slash command or outgoing message, returning the HTTP status and JSON response body
+pub fn handle(secret: &str, commands: &Commands, request: &Request) -> (u16, String) {
+    if !verify(secret, request, now()) {
+        return (
+            401,
+            json!({"error": "Invalid request signature"}).to_string(),
+        );
+    }
+
+    let form = parse_form(&request.body);
+    let text = form.get("text").map(|t| t.as_str()).unwrap_or_default();
+
+    // Slash commands give the command (eg. /countdown) separately from the text
+    let message = match form.get("command") {
+        Some(command) => format!("!{} {text}", command.trim_start_matches('/')),
+        None => text.to_string(),
+    };
+
+    match commands.reply(&message) {
+        Some(reply) => (
+            200,
+            json!({"response_type": "in_channel", "text": reply}).to_string(),
+        ),
+        None => (200, String::new()),
+    }
+}
+
+/// Checks the request signature is made from the timestamp and body with the signing secret, and
+/// the timestamp is recent
+fn verify(secret: &str, request: &Request, now: u64) -> bool {
+    let (Some(signature), Some(timestamp)) = (
+        request.header("x-slack-signature"),
+        request.header("x-slack-request-timestamp"),
+    ) else {
+        return false;
+    };
+
+    let Some(signature) = signature.strip_prefix("v0=").and_then(hex_decode) else {
+        return false;
+    };
+
+    if !is_recent(timestamp, now) {
+        return false;
+    }
+
+    let expected = HMAC::mac(
+        format!("v0:{timestamp}:{}", request.body).as_bytes(),
+        secret.as_bytes(),
+    );
+
+    // Compare in constant time
+    signature.len() == expected.len()
+        && signature
+            .iter()
+            .zip(expected.iter())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+
+    const NOW: u64 = 1_700_000_000;
+
+    /// Creates a request signed with a secret at a time
+    fn signed_request(secret: &str, timestamp: u64, body: &str) -> Request {
+        let signature = HMAC::mac(
+            format!("v0:{timestamp}:{body}").as_bytes(),
+            secret.as_bytes(),
+        );
+
+        let hex = signature
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+
+        Request {
+            method: "POST".to_string(),
+            path: "/slack".to_string(),
+            query: HashMap::new(),
+            headers: HashMap::from([
+                ("x-slack-signature".to_string(), format!("v0={hex}")),
+                (
+                    "x-slack-request-timestamp".to_string(),
+                    timestamp.to_string(),
+                ),
+            ]),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let body = "command=%2Ftrivia&text=100+75";
+
+        assert!(verify(SECRET, &signed_request(SECRET, NOW, body), NOW));
+
+        // Signed with another secret
+        assert!(!verify(SECRET, &signed_request("other", NOW, body), NOW));
+
+        // Body changed after signing
+        let mut request = signed_request(SECRET, NOW, body);
+        request.body.push('0');
+
+        assert!(!verify(SECRET, &request, NOW));
+
+        // Too old or too far in the future
+        assert!(!verify(
+            SECRET,
+            &signed_request(SECRET, NOW - 301, body),
+            NOW
+        ));
+        assert!(!verify(
+            SECRET,
+            &signed_request(SECRET, NOW + 301, body),
+            NOW
+        ));
+
+        // Missing headers
+        let mut request = signed_request(SECRET, NOW, body);
+        request.headers.remove("x-slack-signature");
+
+        assert!(!verify(SECRET, &request, NOW));
+    }
+}
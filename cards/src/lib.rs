@@ -1,5 +1,17 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Cards with a value greater than this are big numbers
+pub const BIG_THRESHOLD: u32 = 10;
+
+/// Most big numbers which can be asked for in a game
+pub const MAX_BIG: usize = 4;
+
+/// Number of cards dealt for a game
+pub const CARDS_DEALT: usize = 6;
+
 // Standard set of cards
-pub fn get_default_cards() -> &'static [u8] {
+pub fn get_default_cards() -> &'static [u32] {
     &[
         100, 75, 50, 25, // Big numbers
         10, 10, 9, 9, 8, 8, 7, 7, 6, 6, 5, 5, 4, 4, 3, 3, 2, 2, 1, 1,
@@ -7,9 +19,219 @@ pub fn get_default_cards() -> &'static [u8] {
 }
 
 // Set of cards used in special editions of the show
-pub fn get_special_cards() -> &'static [u8] {
+pub fn get_special_cards() -> &'static [u32] {
     &[
         87, 62, 37, 12, // Big numbers
         10, 10, 9, 9, 8, 8, 7, 7, 6, 6, 5, 5, 4, 4, 3, 3, 2, 2, 1, 1,
     ]
 }
+
+/// A deck of cards split in to big and small numbers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardSet {
+    big: Vec<u32>,
+    small: Vec<u32>,
+}
+
+/// Reasons a deal can't be made from a deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DealError {
+    /// More big numbers were asked for than can be dealt
+    TooManyBig { requested: usize, max: usize },
+    /// The deck doesn't have enough small numbers to make up the deal
+    NotEnoughSmall { requested: usize, available: usize },
+}
+
+impl CardSet {
+    /// Creates a card set from a deck of cards
+    pub fn new(deck: &[u32]) -> Self {
+        let (big, small) = deck.iter().partition(|&&c| Self::is_big(c));
+
+        Self { big, small }
+    }
+
+    /// Creates the standard card set
+    pub fn standard() -> Self {
+        Self::new(get_default_cards())
+    }
+
+    /// Creates the card set used in special editions of the show
+    pub fn special() -> Self {
+        Self::new(get_special_cards())
+    }
+
+    /// Returns true if a card is a big number
+    pub fn is_big(value: u32) -> bool {
+        value > BIG_THRESHOLD
+    }
+
+    /// Returns the number of big numbers in a set of cards
+    pub fn big_count(cards: &[u32]) -> usize {
+        cards.iter().filter(|&&c| Self::is_big(c)).count()
+    }
+
+    /// Returns the big numbers in the deck
+    pub fn big(&self) -> &[u32] {
+        &self.big
+    }
+
+    /// Returns the small numbers in the deck
+    pub fn small(&self) -> &[u32] {
+        &self.small
+    }
+
+    /// Returns the most big numbers which can be asked for from the deck
+    pub fn max_big(&self) -> usize {
+        self.big.len().min(MAX_BIG)
+    }
+
+    /// Returns the number of different sets of cards (ignoring order) which can be picked from the
+    /// deck, eg. 13,243 sets of six cards from the standard deck
+    pub fn combination_count(&self, pick: usize) -> usize {
+        let mut copies = BTreeMap::new();
+
+        for &card in self.big.iter().chain(&self.small) {
+            *copies.entry(card).or_insert(0) += 1;
+        }
+
+        // Number of sets of each size using the card values seen so far
+        let mut counts = vec![0; pick + 1];
+        counts[0] = 1;
+
+        for &copies in copies.values() {
+            for size in (1..=pick).rev() {
+                counts[size] += (1..=copies.min(size))
+                    .map(|used| counts[size - used])
+                    .sum::<usize>();
+            }
+        }
+
+        counts[pick]
+    }
+
+    /// Deals six cards with a number of big numbers, the big numbers first. The cards are drawn
+    /// with pick, which is given the number of cards left to draw from and returns the index of the
+    /// card to draw
+    pub fn deal(
+        &self,
+        big_count: usize,
+        mut pick: impl FnMut(usize) -> usize,
+    ) -> Result<Vec<u32>, DealError> {
+        if big_count > self.max_big() {
+            return Err(DealError::TooManyBig {
+                requested: big_count,
+                max: self.max_big(),
+            });
+        }
+
+        let small_count = CARDS_DEALT - big_count;
+
+        if small_count > self.small.len() {
+            return Err(DealError::NotEnoughSmall {
+                requested: small_count,
+                available: self.small.len(),
+            });
+        }
+
+        let mut big = self.big.clone();
+        let mut small = self.small.clone();
+
+        let mut cards = Vec::with_capacity(CARDS_DEALT);
+
+        for _ in 0..big_count {
+            cards.push(big.swap_remove(pick(big.len())));
+        }
+
+        for _ in 0..small_count {
+            cards.push(small.swap_remove(pick(small.len())));
+        }
+
+        Ok(cards)
+    }
+}
+
+impl fmt::Display for DealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DealError::TooManyBig { requested, max } => {
+                write!(
+                    f,
+                    "{requested} big cards asked for, at most {max} can be dealt"
+                )
+            }
+            DealError::NotEnoughSmall {
+                requested,
+                available,
+            } => write!(
+                f,
+                "{requested} small cards needed, the deck only has {available}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DealError {}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_set() {
+        let set = CardSet::standard();
+
+        assert_eq!(&[100, 75, 50, 25], set.big());
+        assert_eq!(20, set.small().len());
+        assert_eq!(4, set.max_big());
+
+        assert!(CardSet::is_big(12));
+        assert!(!CardSet::is_big(10));
+        assert_eq!(2, CardSet::big_count(&[100, 75, 10, 9, 1, 1]));
+    }
+
+    #[test]
+    fn test_combination_count() {
+        let set = CardSet::standard();
+
+        assert_eq!(14, set.combination_count(1));
+        assert_eq!(494, set.combination_count(3));
+        assert_eq!(13_243, set.combination_count(6));
+
+        assert_eq!(13_243, CardSet::special().combination_count(6));
+        // 1 1 and 1 2
+        assert_eq!(2, CardSet::new(&[1, 1, 2]).combination_count(2));
+    }
+
+    #[test]
+    fn test_deal() {
+        let set = CardSet::special();
+
+        for big_count in 0..=MAX_BIG {
+            // Always draw the first card
+            let cards = set.deal(big_count, |_| 0).unwrap();
+
+            assert_eq!(CARDS_DEALT, cards.len());
+            assert_eq!(big_count, CardSet::big_count(&cards));
+        }
+
+        assert_eq!(vec![87, 12, 10, 1, 1, 2], set.deal(2, |_| 0).unwrap());
+
+        assert_eq!(
+            Err(DealError::TooManyBig {
+                requested: 5,
+                max: 4
+            }),
+            set.deal(5, |_| 0)
+        );
+
+        assert_eq!(
+            Err(DealError::NotEnoughSmall {
+                requested: 6,
+                available: 3
+            }),
+            CardSet::new(&[100, 75, 1, 2, 3]).deal(0, |_| 0)
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use cards::get_special_cards;
+use clap::Args;
+use itertools::Itertools;
+use solver::{DiskCache, Ruleset};
+
+use crate::coverage::{
+    calculate, card_sets, load_dir, open_disk_cache, threads, CoverageCache, DeckCoverage,
+};
+
+#[derive(Args, Debug)]
+pub struct BestSetsArgs {
+    /// Results directory written by solve_all. Coverage is calculated if not given
+    #[clap(value_parser)]
+    dir: Option<PathBuf>,
+
+    /// Number of cards in each card set
+    #[clap(short = 'n', long = "pick", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pick: u8,
+
+    /// Minimum number of targets reached for a card set to be listed (defaults to every target)
+    #[clap(short = 'm', long = "min", value_parser)]
+    min: Option<usize>,
+
+    /// Maximum number of card sets or decks to list
+    #[clap(short = 't', long = "top", default_value_t = 20, value_parser)]
+    top: usize,
+
+    /// Use special cards
+    #[clap(short = 's', long = "special", action)]
+    special_cards: bool,
+
+    /// Rules giving the deck and targets (tv or junior)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Search for decks with better coverage by replacing one small card with each value up to this
+    #[clap(long = "search", value_parser = clap::value_parser!(u32).range(1..=99))]
+    search: Option<u32>,
+
+    /// Cache card set coverage in this directory
+    #[clap(long = "cache", value_parser)]
+    cache: Option<PathBuf>,
+
+    /// Number of threads to run when calculating coverage
+    #[clap(long = "threads", value_parser)]
+    threads: Option<usize>,
+}
+
+pub fn best_sets(args: BestSetsArgs) -> i32 {
+    // Coverage is calculated with the TV rules for intermediate results
+    let rules = args.rules.rules();
+
+    if !rules.is_strict() {
+        eprintln!("Card sets can't be compared with the {} rules", args.rules);
+        return 1;
+    }
+
+    let targets = rules.targets();
+    let min = args.min.unwrap_or(targets.clone().count());
+
+    // Get card set
+    let mut deck = if args.special_cards {
+        get_special_cards().to_vec()
+    } else {
+        rules.deck()
+    };
+
+    deck.sort_by(|a, b| b.cmp(a));
+
+    let disk_cache = match open_disk_cache(args.cache.as_deref()) {
+        Ok(disk_cache) => disk_cache,
+        Err(code) => return code,
+    };
+
+    // Load card set coverage from the results directory
+    let mut cache = match &args.dir {
+        Some(dir) => match load_dir(dir, args.pick as usize) {
+            Ok(cache) => {
+                println!("{} card sets loaded from {}", cache.len(), dir.display());
+                cache
+            }
+            Err(e) => {
+                eprintln!("Failed to scan {} ({})", dir.display(), e);
+                return 2;
+            }
+        },
+        None => CoverageCache::new(),
+    };
+
+    let threads = threads(args.threads);
+
+    // Calculate coverage for any card sets in the deck not loaded
+    let sets = card_sets(&deck, args.pick);
+
+    calculate(&mut cache, &sets, args.pick, threads, disk_cache.as_ref());
+
+    // List the best card sets
+    let mut best = sets
+        .iter()
+        .map(|set| (set, cache[set].count_range(&targets)))
+        .filter(|(_, count)| *count >= min)
+        .collect::<Vec<_>>();
+
+    best.sort_by(|(s1, c1), (s2, c2)| c2.cmp(c1).then_with(|| s1.cmp(s2)));
+
+    println!(
+        "{} of {} card sets reach at least {} targets",
+        best.len(),
+        sets.len(),
+        min
+    );
+
+    for (set, count) in best.iter().take(args.top) {
+        println!("  {}: {count}", set.iter().join(" "));
+    }
+
+    if let Some(max_value) = args.search {
+        search_decks(
+            &args,
+            &deck,
+            &mut cache,
+            &targets,
+            max_value,
+            threads,
+            disk_cache.as_ref(),
+        );
+    }
+
+    0
+}
+
+/// Searches for decks with better coverage by replacing one small card
+fn search_decks(
+    args: &BestSetsArgs,
+    deck: &[u32],
+    cache: &mut CoverageCache,
+    targets: &RangeInclusive<u32>,
+    max_value: u32,
+    threads: usize,
+    disk_cache: Option<&DiskCache>,
+) {
+    let base = DeckCoverage::new(&card_sets(deck, args.pick), cache, targets);
+
+    println!();
+    println!(
+        "Current deck: average {:.2} targets, {} of {} card sets reach every target",
+        base.average, base.full, base.sets
+    );
+
+    // Build the modified decks
+    let small_cards = deck
+        .iter()
+        .copied()
+        .filter(|&c| c <= 10)
+        .unique()
+        .collect::<Vec<_>>();
+
+    let mut decks = Vec::new();
+
+    for &from in &small_cards {
+        for to in 1..=max_value {
+            if to == from {
+                continue;
+            }
+
+            let mut new_deck = deck.to_vec();
+            let pos = new_deck.iter().position(|&c| c == from).unwrap();
+
+            new_deck[pos] = to;
+            new_deck.sort_by(|a, b| b.cmp(a));
+
+            let sets = card_sets(&new_deck, args.pick);
+
+            decks.push((from, to, sets));
+        }
+    }
+
+    // Calculate coverage for all of the new card sets
+    let all_sets = decks
+        .iter()
+        .flat_map(|(_, _, sets)| sets.iter().cloned())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    calculate(cache, &all_sets, args.pick, threads, disk_cache);
+
+    // Rank the decks
+    let mut ranked = decks
+        .iter()
+        .map(|(from, to, sets)| (*from, *to, DeckCoverage::new(sets, cache, targets)))
+        .collect::<Vec<_>>();
+
+    ranked.sort_by(|(_, _, d1), (_, _, d2)| d2.average.total_cmp(&d1.average));
+
+    println!("Best decks replacing one small card:");
+
+    for (from, to, coverage) in ranked.iter().take(args.top) {
+        println!(
+            "  Replace {from} with {to}: average {:.2} targets ({:+.2}), {} of {} card sets reach every target",
+            coverage.average,
+            coverage.average - base.average,
+            coverage.full,
+            coverage.sets
+        );
+    }
+}
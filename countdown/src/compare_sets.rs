@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use clap::Args;
+use solver::{
+    expressions_equivalent, normalise_cards, set_symbol_set, Coverage, Programs, Ruleset, Solution,
+    SymbolSet, MAX_CARDS,
+};
+
+#[derive(Args, Debug)]
+pub struct CompareSetsArgs {
+    /// First card set, separated by commas (eg. 100,75,50,25,6,3)
+    #[clap(value_parser = parse_cards)]
+    a: CardList,
+
+    /// Second card set, separated by commas
+    #[clap(value_parser = parse_cards)]
+    b: CardList,
+
+    /// Compare the solutions for this target only
+    #[clap(short = 't', long = "target", value_parser)]
+    target: Option<u32>,
+
+    /// Maximum number of targets (or solutions with --target) to show solutions for
+    #[clap(short = 'e', long = "examples", default_value_t = 10, value_parser)]
+    examples: usize,
+
+    /// Rules giving the targets (tv or junior)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+}
+
+/// Card set given on the command line
+#[derive(Debug, Clone)]
+struct CardList(Vec<u32>);
+
+pub fn compare_sets(args: CompareSetsArgs) -> i32 {
+    // Coverage is calculated with the TV rules for intermediate results
+    let rules = args.rules.rules();
+
+    if !rules.is_strict() {
+        eprintln!("Card sets can't be compared with the {} rules", args.rules);
+        return 1;
+    }
+
+    set_symbol_set(args.symbols);
+
+    let a = normalise_cards(&args.a.0);
+    let b = normalise_cards(&args.b.0);
+
+    // Programs for each number of cards
+    let mut programs_map = HashMap::new();
+
+    for cards in [&a, &b] {
+        programs_map
+            .entry(cards.len())
+            .or_insert_with(|| Programs::new(cards.len() as u8, false, false));
+    }
+
+    let sets = [("A", &a), ("B", &b)].map(|(name, cards)| CardSet {
+        name,
+        cards,
+        programs: &programs_map[&cards.len()],
+    });
+
+    match args.target {
+        Some(target) => compare_target(&args, &sets, target),
+        None => compare_coverage(&args, &sets),
+    }
+
+    0
+}
+
+/// A card set being compared
+struct CardSet<'a> {
+    name: &'static str,
+    cards: &'a [u32],
+    programs: &'a Programs,
+}
+
+impl CardSet<'_> {
+    /// Returns the solutions for a target which aren't equivalent to each other, shortest first
+    fn distinct_solutions(&self, target: u32, max: usize) -> (usize, Vec<Solution>) {
+        let mut solutions = self.programs.run_all_target(target, self.cards);
+
+        solutions.sort();
+
+        let count = solutions.len();
+        let mut distinct: Vec<Solution> = Vec::new();
+
+        for solution in solutions {
+            if distinct.len() == max {
+                break;
+            }
+
+            let instructions = self.programs.instructions(solution.program);
+
+            if !distinct.iter().any(|other| {
+                expressions_equivalent(self.programs.instructions(other.program), instructions)
+            }) {
+                distinct.push(solution);
+            }
+        }
+
+        (count, distinct)
+    }
+
+    /// Formats a solution as an infix equation
+    fn infix(&self, solution: &Solution) -> String {
+        self.programs.infix(solution.program, self.cards, false)
+    }
+}
+
+/// Lists the targets reachable with one card set but not the other, with the shortest solution
+/// for each
+fn compare_coverage(args: &CompareSetsArgs, sets: &[CardSet; 2]) {
+    let targets = args.rules.rules().targets();
+
+    let coverage = sets.each_ref().map(|set| {
+        let coverage = set.programs.coverage(set.cards);
+
+        println!(
+            "{}: {} reaches {} of {} targets",
+            set.name,
+            cards_string(set.cards),
+            coverage.count_range(&targets),
+            targets.clone().count()
+        );
+
+        coverage
+    });
+
+    for (from, other) in [(0, 1), (1, 0)] {
+        let only = only_in(&coverage[from], &coverage[other], targets.clone());
+
+        println!();
+        println!(
+            "Reachable with {} but not {} ({}):",
+            sets[from].name,
+            sets[other].name,
+            only.len()
+        );
+
+        if only.is_empty() {
+            println!("  None");
+            continue;
+        }
+
+        for &target in only.iter().take(args.examples) {
+            let (_, solutions) = sets[from].distinct_solutions(target, 1);
+
+            print!("  {target}: {}", sets[from].infix(&solutions[0]));
+
+            match coverage[other].nearest(target) {
+                Some(nearest) => println!(" ({} nearest {nearest})", sets[other].name),
+                None => println!(),
+            }
+        }
+
+        if only.len() > args.examples {
+            println!(
+                "  ... and {} more: {}",
+                only.len() - args.examples,
+                only[args.examples..]
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+}
+
+/// Shows representative solutions for a target with each card set
+fn compare_target(args: &CompareSetsArgs, sets: &[CardSet; 2], target: u32) {
+    println!("Target {target}");
+
+    for set in sets {
+        let (count, solutions) = set.distinct_solutions(target, args.examples);
+
+        println!();
+
+        if count == 0 {
+            let coverage = set.programs.coverage(set.cards);
+
+            print!(
+                "{}: {} can't reach {target}",
+                set.name,
+                cards_string(set.cards)
+            );
+
+            match coverage.nearest(target) {
+                Some(nearest) => println!(" (nearest {nearest})"),
+                None => println!(),
+            }
+
+            continue;
+        }
+
+        println!(
+            "{}: {} has {count} solutions, showing {} distinct:",
+            set.name,
+            cards_string(set.cards),
+            solutions.len()
+        );
+
+        for solution in &solutions {
+            println!("  {}", set.infix(solution));
+        }
+    }
+}
+
+/// Returns the targets in range reachable with one coverage but not another
+fn only_in(coverage: &Coverage, other: &Coverage, targets: impl Iterator<Item = u32>) -> Vec<u32> {
+    targets
+        .filter(|&target| coverage.contains(target) && !other.contains(target))
+        .collect()
+}
+
+/// Formats cards separated by spaces
+fn cards_string(cards: &[u32]) -> String {
+    cards
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses a card set separated by commas
+fn parse_cards(cards: &str) -> Result<CardList, String> {
+    let cards = cards
+        .split(',')
+        .map(|c| c.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid card: {e}"))?;
+
+    if cards.len() > MAX_CARDS {
+        Err(format!("Maximum of {MAX_CARDS} cards allowed"))?
+    }
+
+    Ok(CardList(cards))
+}
@@ -0,0 +1,228 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+
+use itertools::Itertools;
+use solver::{Coverage, DiskCache, Programs, SplitMix64};
+
+/// File in a solve_all results directory written with --map-only
+const MAP_FILE: &str = "maps.txt";
+
+/// Coverage of card sets keyed by the cards in descending order
+pub type CoverageCache = HashMap<Vec<u32>, Coverage>;
+
+/// Returns the number of threads to use if not given
+pub fn threads(threads: Option<usize>) -> usize {
+    threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    })
+}
+
+/// Opens the on disk cache if a directory is given
+pub fn open_disk_cache(dir: Option<&Path>) -> Result<Option<DiskCache>, i32> {
+    match dir {
+        Some(dir) => match DiskCache::new(dir) {
+            Ok(cache) => Ok(Some(cache)),
+            Err(e) => {
+                eprintln!("Unable to open cache {} ({})", dir.display(), e);
+                Err(1)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+/// Calculates the coverage of any card sets not already in the cache, reading from and writing to
+/// the on disk cache if given
+pub fn calculate(
+    cache: &mut CoverageCache,
+    sets: &[Vec<u32>],
+    pick: u8,
+    threads: usize,
+    disk_cache: Option<&DiskCache>,
+) {
+    let mut todo = Vec::new();
+
+    for set in sets {
+        if cache.contains_key(set) {
+            continue;
+        }
+
+        match disk_cache.and_then(|disk_cache| disk_cache.coverage(set)) {
+            Some(coverage) => {
+                cache.insert(set.clone(), coverage);
+            }
+            None => todo.push(set.clone()),
+        }
+    }
+
+    let todo = Mutex::new(todo);
+
+    let count = todo.lock().unwrap().len();
+
+    if count == 0 {
+        return;
+    }
+
+    println!("Calculating coverage of {count} card sets...");
+
+    let programs = Programs::new(pick, false, false);
+    let results = Mutex::new(Vec::with_capacity(count));
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let Some(set) = todo.lock().unwrap().pop() else {
+                    break;
+                };
+
+                let coverage = programs.coverage(&set);
+
+                if let Some(disk_cache) = disk_cache {
+                    if let Err(e) = disk_cache.put_coverage(&set, &coverage) {
+                        eprintln!("Unable to write to cache ({e})");
+                    }
+                }
+
+                results.lock().unwrap().push((set, coverage));
+            });
+        }
+    });
+
+    cache.extend(results.into_inner().unwrap());
+}
+
+/// Loads the coverage of each card set from a solve_all results directory
+pub fn load_dir(dir: &Path, pick: usize) -> io::Result<CoverageCache> {
+    let mut cache = HashMap::new();
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+
+        // File stem is the cards separated by '-'
+        let Some(cards) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| {
+                stem.split('-')
+                    .map(|c| c.parse::<u32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()
+            })
+        else {
+            continue;
+        };
+
+        if cards.len() != pick {
+            continue;
+        }
+
+        // Find the solution map
+        let file = fs::File::open(&path)?;
+
+        for line in io::BufReader::new(file).lines() {
+            if let Some(map) = line?.strip_prefix("solution map: ") {
+                if let Some(coverage) = Coverage::from_map(map) {
+                    cache.insert(cards, coverage);
+                }
+
+                break;
+            }
+        }
+    }
+
+    // Card sets solved with solve_all --map-only are all in one file
+    let map_path = dir.join(MAP_FILE);
+
+    if map_path.is_file() {
+        for line in io::BufReader::new(fs::File::open(&map_path)?).lines() {
+            let line = line?;
+
+            let Some((stem, map)) = line.split_once(": ") else {
+                continue;
+            };
+
+            let Ok(cards) = stem
+                .split('-')
+                .map(|c| c.parse::<u32>())
+                .collect::<Result<Vec<_>, _>>()
+            else {
+                continue;
+            };
+
+            if cards.len() == pick && !cache.contains_key(&cards) {
+                if let Some(coverage) = Coverage::from_map(map) {
+                    cache.insert(cards, coverage);
+                }
+            }
+        }
+    }
+
+    Ok(cache)
+}
+
+/// Summary of the coverage of all card sets dealt from a deck
+pub struct DeckCoverage {
+    /// Average number of targets reached
+    pub average: f64,
+    /// Number of card sets reaching every target
+    pub full: usize,
+    /// Number of card sets
+    pub sets: usize,
+}
+
+impl DeckCoverage {
+    pub fn new(sets: &[Vec<u32>], cache: &CoverageCache, targets: &RangeInclusive<u32>) -> Self {
+        let counts = sets
+            .iter()
+            .map(|set| cache[set].count_range(targets))
+            .collect::<Vec<_>>();
+
+        let all = targets.clone().count();
+
+        Self {
+            average: counts.iter().sum::<usize>() as f64 / counts.len() as f64,
+            full: counts.iter().filter(|&&count| count == all).count(),
+            sets: sets.len(),
+        }
+    }
+}
+
+/// Returns the distinct card sets which can be dealt from a deck sorted in descending order
+pub fn card_sets(deck: &[u32], pick: u8) -> Vec<Vec<u32>> {
+    deck.iter()
+        .copied()
+        .combinations(pick as usize)
+        .unique()
+        .collect()
+}
+
+/// Returns a random sample of card sets, keeping their order
+pub fn sample_sets(sets: Vec<Vec<u32>>, sample: usize, seed: u64) -> Vec<Vec<u32>> {
+    let mut rng = SplitMix64(seed);
+    let mut indexes = (0..sets.len()).collect::<Vec<_>>();
+    let sample = sample.min(indexes.len());
+
+    // Partial Fisher-Yates shuffle
+    for i in 0..sample {
+        let j = i + rng.below(indexes.len() - i);
+        indexes.swap(i, j);
+    }
+
+    let chosen = indexes[..sample].iter().copied().collect::<HashSet<_>>();
+
+    sets.into_iter()
+        .enumerate()
+        .filter_map(|(i, set)| chosen.contains(&i).then_some(set))
+        .collect()
+}
@@ -0,0 +1,169 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cards::get_special_cards;
+use clap::Args;
+use itertools::Itertools;
+use solver::{Grade, Programs, Puzzle, Ruleset, MAX_CARDS};
+
+/// Maximum number of deals to try to find a reachable target
+const MAX_ATTEMPTS: u64 = 16;
+
+#[derive(Args, Debug)]
+pub struct DailyArgs {
+    /// Date of the puzzle (YYYY-MM-DD, defaults to today)
+    #[clap(long = "date", value_parser = parse_date)]
+    date: Option<u64>,
+
+    /// Use special cards
+    #[clap(short = 's', long = "special", action)]
+    special_cards: bool,
+
+    /// Rules to generate the puzzle with (tv, junior or relaxed)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Decode a shared puzzle code instead of generating a puzzle
+    #[clap(long = "decode", value_parser)]
+    decode: Option<String>,
+
+    /// Encode a custom puzzle (target followed by the cards) as a shareable code
+    #[clap(long = "encode", num_args = 2..=MAX_CARDS + 1, value_names = ["TARGET", "CARDS"], conflicts_with = "decode", value_parser)]
+    encode: Option<Vec<u32>>,
+}
+
+pub fn daily(args: DailyArgs) -> i32 {
+    if let Some(values) = &args.encode {
+        let puzzle = Puzzle::new(values[0], values[1..].to_vec());
+
+        print_puzzle(&puzzle);
+        println!("Code: {}", puzzle.encode());
+
+        return 0;
+    }
+
+    if let Some(code) = &args.decode {
+        return match Puzzle::decode(code) {
+            Ok(puzzle) => {
+                print_puzzle(&puzzle);
+                0
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                1
+            }
+        };
+    }
+
+    // Get day number
+    let day = args.date.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before epoch")
+            .as_secs()
+            / 86_400
+    });
+
+    // Get card set
+    let rules = args.rules.rules();
+
+    let deck = if args.special_cards {
+        get_special_cards().to_vec()
+    } else {
+        rules.deck()
+    };
+
+    println!("Generating programs...");
+    let programs = Programs::new(6, true, false);
+
+    // Deal until a target reachable with the TV rules is found. These targets are also reachable
+    // with the relaxed rules
+    for attempt in 0..MAX_ATTEMPTS {
+        let puzzle = Puzzle::from_seed((day << 8) | attempt, &deck, &rules.targets());
+
+        let diff = solver::difficulty(&programs, puzzle.target, &puzzle.cards);
+
+        if diff.grade != Grade::Impossible {
+            println!("Puzzle for {}", format_date(day));
+
+            if args.rules != Ruleset::Tv {
+                println!("Rules: {} ({})", args.rules, rules.description());
+            }
+
+            print_puzzle(&puzzle);
+            println!("Difficulty: {}", diff.grade);
+            println!("Code: {}", puzzle.encode());
+
+            return 0;
+        }
+    }
+
+    eprintln!("Unable to find a reachable puzzle");
+
+    2
+}
+
+fn print_puzzle(puzzle: &Puzzle) {
+    println!("Target: {}", puzzle.target);
+    println!("Cards: {}", puzzle.cards.iter().join(" "));
+}
+
+/// Parses a YYYY-MM-DD date in to a day number since 1970-01-01
+fn parse_date(date: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid date '{date}' (expected YYYY-MM-DD)");
+
+    let (y, m, d) = date
+        .split('-')
+        .map(|s| s.parse::<u32>())
+        .collect_tuple()
+        .ok_or_else(invalid)?;
+
+    let (y, m, d) = (
+        y.map_err(|_| invalid())?,
+        m.map_err(|_| invalid())?,
+        d.map_err(|_| invalid())?,
+    );
+
+    if y < 1970 || !(1..=12).contains(&m) || d < 1 || d > days_in_month(y, m) {
+        Err(invalid())?
+    }
+
+    Ok(days_from_civil(y, m, d))
+}
+
+fn days_in_month(y: u32, m: u32) -> u32 {
+    match m {
+        2 if (y.is_multiple_of(4) && !y.is_multiple_of(100)) || y.is_multiple_of(400) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts a civil date to days since 1970-01-01
+fn days_from_civil(y: u32, m: u32, d: u32) -> u64 {
+    let y = if m <= 2 { y - 1 } else { y } as u64;
+    let m = m as u64;
+    let d = d as u64;
+
+    let era = y / 400;
+    let yoe = y - (era * 400);
+    let doy = (((153 * ((m + 9) % 12)) + 2) / 5) + d - 1;
+    let doe = (yoe * 365) + (yoe / 4) - (yoe / 100) + doy;
+
+    (era * 146_097) + doe - 719_468
+}
+
+/// Formats days since 1970-01-01 as YYYY-MM-DD
+fn format_date(days: u64) -> String {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - (era * 146_097);
+    let yoe = (doe - (doe / 1_460) + (doe / 36_524) - (doe / 146_096)) / 365;
+    let doy = doe - ((365 * yoe) + (yoe / 4) - (yoe / 100));
+    let mp = ((5 * doy) + 2) / 153;
+    let d = doy - (((153 * mp) + 2) / 5) + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + (era * 400) + if m <= 2 { 1 } else { 0 };
+
+    format!("{y:04}-{m:02}-{d:02}")
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+
+#[cfg(feature = "parquet")]
+use std::sync::Arc;
+
+#[cfg(feature = "parquet")]
+use arrow::array::{ArrayRef, StringArray, UInt32Array, UInt8Array};
+#[cfg(feature = "parquet")]
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+#[cfg(feature = "parquet")]
+use arrow::record_batch::RecordBatch;
+use clap::{Args, ValueEnum};
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+use serde_json::json;
+use solver::{set_machine_output, set_symbol_set, Coverage, Programs, SymbolSet};
+
+use crate::coverage::{load_dir, threads};
+
+#[derive(Args, Debug)]
+pub struct ExportDatasetArgs {
+    /// Results directory written by solve_all
+    #[clap(value_parser)]
+    dir: PathBuf,
+
+    /// Number of cards in each card set
+    #[clap(short = 'n', long = "pick", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pick: u8,
+
+    /// File to write the dataset to (defaults to stdout, JSON lines only)
+    #[clap(short = 'o', long = "output", value_parser)]
+    output: Option<PathBuf>,
+
+    /// Dataset format
+    #[clap(long = "format", value_enum, default_value_t = Format::Jsonl)]
+    format: Format,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Ascii, value_parser)]
+    symbols: SymbolSet,
+
+    /// Number of threads to run when solving the card sets
+    #[clap(long = "threads", value_parser)]
+    threads: Option<usize>,
+}
+
+/// Dataset formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// A JSON object on each line
+    Jsonl,
+    /// Parquet file with the same columns
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+pub fn export_dataset(args: ExportDatasetArgs) -> i32 {
+    // Numbers in equations must be readable anywhere
+    set_machine_output(true);
+    set_symbol_set(args.symbols);
+
+    let cache = match load_dir(&args.dir, args.pick as usize) {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Unable to read {} ({})", args.dir.display(), e);
+            return 1;
+        }
+    };
+
+    if cache.is_empty() {
+        eprintln!(
+            "No results for {} cards found in {}",
+            args.pick,
+            args.dir.display()
+        );
+        return 1;
+    }
+
+    // Card sets are written in order so datasets can be compared
+    let mut sets = cache.into_iter().collect::<Vec<_>>();
+
+    sets.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let writer = match (args.format, &args.output) {
+        (Format::Jsonl, None) => DatasetWriter::Jsonl(Box::new(BufWriter::new(io::stdout()))),
+        (format, Some(path)) => match File::create(path) {
+            Ok(file) => match format {
+                Format::Jsonl => DatasetWriter::Jsonl(Box::new(BufWriter::new(file))),
+                #[cfg(feature = "parquet")]
+                Format::Parquet => match DatasetWriter::parquet(file) {
+                    Ok(writer) => writer,
+                    Err(e) => {
+                        eprintln!("Unable to create {} ({})", path.display(), e);
+                        return 1;
+                    }
+                },
+            },
+            Err(e) => {
+                eprintln!("Unable to create {} ({})", path.display(), e);
+                return 1;
+            }
+        },
+        #[cfg(feature = "parquet")]
+        (Format::Parquet, None) => {
+            eprintln!("An output file must be given for Parquet datasets");
+            return 1;
+        }
+    };
+
+    eprintln!(
+        "Exporting the best solutions of {} card sets...",
+        sets.len()
+    );
+
+    let programs = Programs::new(args.pick, false, false);
+
+    match export(&programs, &sets, writer, threads(args.threads)) {
+        Ok(mismatched) => {
+            if mismatched > 0 {
+                eprintln!(
+                    "Warning: {mismatched} card sets reach different targets to the results in {}",
+                    args.dir.display()
+                );
+            }
+
+            0
+        }
+        Err(e) => {
+            eprintln!("Error writing dataset ({e})");
+            1
+        }
+    }
+}
+
+/// Best solution for a target reached by a card set
+struct Row {
+    target: u32,
+    equation: String,
+    rpn: String,
+    cards_used: u8,
+    solutions: u32,
+}
+
+/// Writes the rows of the dataset
+enum DatasetWriter {
+    /// JSON lines
+    Jsonl(Box<dyn Write + Send>),
+    /// Parquet file
+    #[cfg(feature = "parquet")]
+    Parquet(Box<ArrowWriter<File>>, SchemaRef),
+}
+
+impl DatasetWriter {
+    /// Creates a Parquet dataset writer
+    #[cfg(feature = "parquet")]
+    fn parquet(file: File) -> io::Result<Self> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("cards", DataType::Utf8, false),
+            Field::new("target", DataType::UInt32, false),
+            Field::new("equation", DataType::Utf8, false),
+            Field::new("rpn", DataType::Utf8, false),
+            Field::new("cards_used", DataType::UInt8, false),
+            Field::new("solutions", DataType::UInt32, false),
+        ]));
+
+        let writer = ArrowWriter::try_new(file, schema.clone(), None).map_err(io::Error::other)?;
+
+        Ok(DatasetWriter::Parquet(Box::new(writer), schema))
+    }
+
+    /// Writes the rows for a card set
+    fn write(&mut self, cards: &[u32], rows: &[Row]) -> io::Result<()> {
+        match self {
+            DatasetWriter::Jsonl(writer) => {
+                for row in rows {
+                    let line = json!({
+                        "cards": cards,
+                        "target": row.target,
+                        "equation": row.equation,
+                        "rpn": row.rpn,
+                        "cards_used": row.cards_used,
+                        "solutions": row.solutions,
+                    });
+
+                    writeln!(writer, "{line}")?;
+                }
+
+                Ok(())
+            }
+            #[cfg(feature = "parquet")]
+            DatasetWriter::Parquet(writer, schema) => {
+                let cards = cards
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("-");
+
+                let columns: Vec<ArrayRef> = vec![
+                    Arc::new(StringArray::from(vec![cards; rows.len()])),
+                    Arc::new(UInt32Array::from_iter_values(
+                        rows.iter().map(|row| row.target),
+                    )),
+                    Arc::new(StringArray::from_iter_values(
+                        rows.iter().map(|row| &row.equation),
+                    )),
+                    Arc::new(StringArray::from_iter_values(
+                        rows.iter().map(|row| &row.rpn),
+                    )),
+                    Arc::new(UInt8Array::from_iter_values(
+                        rows.iter().map(|row| row.cards_used),
+                    )),
+                    Arc::new(UInt32Array::from_iter_values(
+                        rows.iter().map(|row| row.solutions),
+                    )),
+                ];
+
+                let batch =
+                    RecordBatch::try_new(schema.clone(), columns).map_err(io::Error::other)?;
+
+                writer.write(&batch).map_err(io::Error::other)
+            }
+        }
+    }
+
+    /// Finishes writing the dataset
+    fn finish(self) -> io::Result<()> {
+        match self {
+            DatasetWriter::Jsonl(mut writer) => writer.flush(),
+            #[cfg(feature = "parquet")]
+            DatasetWriter::Parquet(writer, _) => {
+                writer.close().map(|_| ()).map_err(io::Error::other)
+            }
+        }
+    }
+}
+
+/// Rows of the dataset solved by the worker threads waiting to be written in card set order
+struct Output {
+    writer: DatasetWriter,
+    next: usize,
+    pending: BTreeMap<usize, Vec<Row>>,
+    mismatched: usize,
+}
+
+/// Solves each card set and writes a row for each target reached. Returns the number of card sets
+/// whose targets don't match the coverage in the results
+fn export(
+    programs: &Programs,
+    sets: &[(Vec<u32>, Coverage)],
+    writer: DatasetWriter,
+    threads: usize,
+) -> io::Result<usize> {
+    let todo = Mutex::new(sets.iter().enumerate());
+    let output = Mutex::new(Output {
+        writer,
+        next: 0,
+        pending: BTreeMap::new(),
+        mismatched: 0,
+    });
+
+    thread::scope(|scope| {
+        let handles = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(|| -> io::Result<()> {
+                    loop {
+                        let Some((i, (cards, coverage))) = todo.lock().unwrap().next() else {
+                            break Ok(());
+                        };
+
+                        let (rows, reached) = best_solutions(programs, cards);
+
+                        let mut output = output.lock().unwrap();
+
+                        if reached != *coverage {
+                            output.mismatched += 1;
+                        }
+
+                        output.pending.insert(i, rows);
+
+                        // Write any card sets which are now next in order
+                        loop {
+                            let next = output.next;
+
+                            let Some(rows) = output.pending.remove(&next) else {
+                                break;
+                            };
+
+                            output.writer.write(&sets[next].0, &rows)?;
+
+                            output.next += 1;
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().unwrap())
+    })?;
+
+    let output = output.into_inner().unwrap();
+
+    output.writer.finish()?;
+
+    Ok(output.mismatched)
+}
+
+/// Returns a row for each target reached by a card set giving the best solution, and the targets
+/// reached. The best solution is the shortest, with the simplest equation
+fn best_solutions(programs: &Programs, cards: &[u32]) -> (Vec<Row>, Coverage) {
+    let mut solutions = programs.run_all(cards).solutions;
+
+    programs.rank_solutions(&mut solutions, cards);
+
+    let mut rows = Vec::new();
+    let mut reached = Coverage::new();
+
+    for (i, solution) in solutions.iter().enumerate() {
+        // Solutions are ranked within each target so the first is the best
+        if !reached.insert(solution.result) {
+            continue;
+        }
+
+        let count = solutions[i..]
+            .iter()
+            .take_while(|s| s.result == solution.result)
+            .count();
+
+        rows.push(Row {
+            target: solution.result,
+            equation: programs.infix(solution.program, cards, false),
+            rpn: programs.rpn(solution.program, cards, false),
+            cards_used: solution.cards_used() as u8,
+            solutions: count as u32,
+        });
+    }
+
+    (rows, reached)
+}
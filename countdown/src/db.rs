@@ -0,0 +1,420 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand};
+use rusqlite::{params, Connection, OptionalExtension};
+use solver::{MAX_TARGET, MIN_TARGET};
+
+use crate::coverage::load_dir;
+
+/// File in a solve_all results directory recording the programs and rules used
+const PROGRAMS_FILE: &str = "programs.txt";
+
+#[derive(Args, Debug)]
+pub struct DbArgs {
+    /// Database file
+    #[clap(value_parser)]
+    database: PathBuf,
+
+    #[clap(subcommand)]
+    command: DbCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Import a solve_all results directory, replacing any run with the same name
+    Import(ImportArgs),
+    /// List the runs in the database
+    Runs,
+    /// List the targets reached by few card sets in a run
+    Targets(TargetsArgs),
+    /// Compare the targets reached in two runs
+    Compare(CompareArgs),
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// Results directory written by solve_all with the text sink
+    #[clap(value_parser)]
+    dir: PathBuf,
+
+    /// Name of the run (defaults to the directory name)
+    #[clap(long = "name", value_parser)]
+    name: Option<String>,
+
+    /// Number of cards in each card set to import
+    #[clap(short = 'n', long = "pick", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pick: u8,
+}
+
+#[derive(Args, Debug)]
+struct TargetsArgs {
+    /// Name of the run
+    #[clap(value_parser)]
+    run: String,
+
+    /// List targets reached by at most this many card sets
+    #[clap(long = "max", default_value_t = 0, value_parser)]
+    max: usize,
+}
+
+#[derive(Args, Debug)]
+struct CompareArgs {
+    /// Name of the first run
+    #[clap(value_parser)]
+    run1: String,
+
+    /// Name of the second run
+    #[clap(value_parser)]
+    run2: String,
+
+    /// Number of targets to list with the largest difference in the proportion of card sets
+    /// reaching them
+    #[clap(short = 't', long = "top", default_value_t = 10, value_parser)]
+    top: usize,
+}
+
+/// Details of a run in the database
+struct Run {
+    id: i64,
+    name: String,
+    rules: String,
+    pick: usize,
+    card_sets: usize,
+}
+
+pub fn db(args: DbArgs) -> i32 {
+    let conn = match open(&args.database) {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Unable to open {} ({e})", args.database.display());
+            return 1;
+        }
+    };
+
+    let result = match &args.command {
+        DbCommand::Import(import_args) => import(&conn, import_args),
+        DbCommand::Runs => runs(&conn),
+        DbCommand::Targets(targets_args) => targets(&conn, targets_args),
+        DbCommand::Compare(compare_args) => compare(&conn, compare_args),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            2
+        }
+    }
+}
+
+/// Opens the database, creating the tables if necessary
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS runs (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            dir TEXT NOT NULL,
+            rules TEXT NOT NULL,
+            fingerprint TEXT,
+            pick INTEGER NOT NULL,
+            card_sets INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS card_sets (
+            run INTEGER NOT NULL,
+            cards TEXT NOT NULL,
+            coverage INTEGER NOT NULL,
+            map TEXT NOT NULL,
+            PRIMARY KEY (run, cards)
+        );
+        CREATE TABLE IF NOT EXISTS targets (
+            run INTEGER NOT NULL,
+            target INTEGER NOT NULL,
+            card_sets INTEGER NOT NULL,
+            PRIMARY KEY (run, target)
+        );",
+    )?;
+
+    Ok(conn)
+}
+
+/// Imports a results directory
+fn import(conn: &Connection, args: &ImportArgs) -> Result<(), String> {
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => args
+            .dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().to_string()))
+            .ok_or_else(|| format!("Unable to name the run for {}", args.dir.display()))?,
+    };
+
+    // Read the rules and fingerprint recorded by solve_all. Results written before the rules were
+    // recorded used the TV rules
+    let programs = fs::read_to_string(args.dir.join(PROGRAMS_FILE)).unwrap_or_default();
+
+    let field = |prefix: &str| {
+        programs
+            .lines()
+            .find_map(|line| line.strip_prefix(prefix))
+            .map(|value| value.trim().to_string())
+    };
+
+    let rules = field("rules:").unwrap_or_else(|| "tv".to_string());
+    let fingerprint = field("fingerprint:");
+
+    let cache = load_dir(&args.dir, args.pick as usize)
+        .map_err(|e| format!("Failed to scan {} ({e})", args.dir.display()))?;
+
+    if cache.is_empty() {
+        Err(format!(
+            "No results for {} cards found in {}",
+            args.pick,
+            args.dir.display()
+        ))?
+    }
+
+    // Count the card sets reaching each target
+    let mut reached = vec![0; (MAX_TARGET - MIN_TARGET + 1) as usize];
+
+    for coverage in cache.values() {
+        for target in coverage.iter() {
+            reached[(target - MIN_TARGET) as usize] += 1;
+        }
+    }
+
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+
+    if let Some(id) = run_id(&tx, &name)? {
+        for table in ["card_sets", "targets"] {
+            tx.execute(&format!("DELETE FROM {table} WHERE run = ?1"), [id])
+                .map_err(db_error)?;
+        }
+
+        tx.execute("DELETE FROM runs WHERE id = ?1", [id])
+            .map_err(db_error)?;
+    }
+
+    tx.execute(
+        "INSERT INTO runs (name, dir, rules, fingerprint, pick, card_sets) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            name,
+            args.dir.display().to_string(),
+            rules,
+            fingerprint,
+            args.pick,
+            cache.len()
+        ],
+    )
+    .map_err(db_error)?;
+
+    let id = tx.last_insert_rowid();
+
+    {
+        let mut stmt = tx
+            .prepare("INSERT INTO card_sets VALUES (?1, ?2, ?3, ?4)")
+            .map_err(db_error)?;
+
+        for (cards, coverage) in &cache {
+            stmt.execute(params![
+                id,
+                cards
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("-"),
+                coverage.count(),
+                coverage.to_map()
+            ])
+            .map_err(db_error)?;
+        }
+
+        let mut stmt = tx
+            .prepare("INSERT INTO targets VALUES (?1, ?2, ?3)")
+            .map_err(db_error)?;
+
+        for (i, count) in reached.iter().enumerate() {
+            stmt.execute(params![id, i as u32 + MIN_TARGET, count])
+                .map_err(db_error)?;
+        }
+    }
+
+    tx.commit().map_err(db_error)?;
+
+    println!(
+        "Imported {} card sets from {} as '{name}' ({rules} rules)",
+        cache.len(),
+        args.dir.display()
+    );
+
+    Ok(())
+}
+
+/// Lists the runs
+fn runs(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.name, r.rules, r.pick, r.card_sets, AVG(c.coverage), r.dir
+            FROM runs r JOIN card_sets c ON c.run = r.id
+            GROUP BY r.id ORDER BY r.name",
+        )
+        .map_err(db_error)?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, usize>(2)?,
+                row.get::<_, usize>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })
+        .map_err(db_error)?;
+
+    println!("Name, Rules, Cards, Card Sets, Average Targets, Directory");
+
+    for row in rows {
+        let (name, rules, pick, card_sets, average, dir) = row.map_err(db_error)?;
+
+        println!("{name}, {rules}, {pick}, {card_sets}, {average:.2}, {dir}");
+    }
+
+    Ok(())
+}
+
+/// Lists the targets reached by few card sets in a run
+fn targets(conn: &Connection, args: &TargetsArgs) -> Result<(), String> {
+    let run = get_run(conn, &args.run)?;
+    let reached = reached(conn, &run)?;
+
+    let targets = reached
+        .iter()
+        .filter(|(_, count)| *count <= args.max)
+        .collect::<Vec<_>>();
+
+    println!(
+        "{} targets reached by at most {} of the {} card sets in '{}' ({} rules)",
+        targets.len(),
+        args.max,
+        run.card_sets,
+        run.name,
+        run.rules
+    );
+
+    for (target, count) in targets {
+        println!("  {target}: {count}");
+    }
+
+    Ok(())
+}
+
+/// Compares the targets reached in two runs
+fn compare(conn: &Connection, args: &CompareArgs) -> Result<(), String> {
+    let run1 = get_run(conn, &args.run1)?;
+    let run2 = get_run(conn, &args.run2)?;
+
+    if run1.pick != run2.pick {
+        eprintln!(
+            "Warning: '{}' has card sets of {} cards and '{}' has card sets of {} cards",
+            run1.name, run1.pick, run2.name, run2.pick
+        );
+    }
+
+    let reached1 = reached(conn, &run1)?;
+    let reached2 = reached(conn, &run2)?;
+
+    let only = |(a, ra): (&Run, &[(u32, usize)]), (b, rb): (&Run, &[(u32, usize)])| {
+        let targets = ra
+            .iter()
+            .zip(rb)
+            .filter(|((_, ca), (_, cb))| *ca == 0 && *cb > 0)
+            .map(|((target, _), _)| target.to_string())
+            .collect::<Vec<_>>();
+
+        println!(
+            "{} targets unreachable in '{}' but reachable in '{}'",
+            targets.len(),
+            a.name,
+            b.name
+        );
+
+        if !targets.is_empty() {
+            println!("  {}", targets.join(" "));
+        }
+    };
+
+    only((&run1, &reached1), (&run2, &reached2));
+    only((&run2, &reached2), (&run1, &reached1));
+
+    // Proportion of card sets reaching each target
+    let share = |count: usize, run: &Run| count as f64 * 100.0 / run.card_sets as f64;
+
+    let mut diffs = reached1
+        .iter()
+        .zip(&reached2)
+        .map(|((target, c1), (_, c2))| (*target, share(*c1, &run1), share(*c2, &run2)))
+        .collect::<Vec<_>>();
+
+    diffs.sort_by(|(_, a1, a2), (_, b1, b2)| (b1 - b2).abs().total_cmp(&(a1 - a2).abs()));
+
+    println!();
+    println!("Target, {}, {}, Difference", run1.name, run2.name);
+
+    for (target, p1, p2) in diffs.iter().take(args.top) {
+        println!("{target}, {p1:.2}%, {p2:.2}%, {:+.2}%", p2 - p1);
+    }
+
+    Ok(())
+}
+
+/// Returns the number of card sets reaching each target in a run, in target order
+fn reached(conn: &Connection, run: &Run) -> Result<Vec<(u32, usize)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT target, card_sets FROM targets WHERE run = ?1 ORDER BY target")
+        .map_err(db_error)?;
+
+    let rows = stmt
+        .query_map([run.id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(db_error)?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Looks up a run by name
+fn get_run(conn: &Connection, name: &str) -> Result<Run, String> {
+    conn.query_row(
+        "SELECT id, name, rules, pick, card_sets FROM runs WHERE name = ?1",
+        [name],
+        |row| {
+            Ok(Run {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                rules: row.get(2)?,
+                pick: row.get(3)?,
+                card_sets: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(db_error)?
+    .ok_or_else(|| format!("No run named '{name}' (see 'countdown db <database> runs')"))
+}
+
+/// Returns the id of a run if it exists
+fn run_id(conn: &Connection, name: &str) -> Result<Option<i64>, String> {
+    conn.query_row("SELECT id FROM runs WHERE name = ?1", [name], |row| {
+        row.get(0)
+    })
+    .optional()
+    .map_err(db_error)
+}
+
+/// Converts a database error to a message
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Database error ({e})")
+}
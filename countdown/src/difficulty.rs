@@ -0,0 +1,58 @@
+use clap::Args;
+use solver::{set_symbol_set, Programs, SymbolSet, MAX_CARDS};
+
+#[derive(Args, Debug)]
+pub struct DifficultyArgs {
+    /// Verbose output
+    #[clap(short = 'v', long = "verbose", action)]
+    verbose: bool,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    // Target
+    target: u32,
+
+    // Cards chosen
+    cards: Vec<u32>,
+}
+
+pub fn difficulty(args: DifficultyArgs) -> i32 {
+    if args.cards.is_empty() {
+        eprintln!("No cards specified");
+        return 1;
+    }
+
+    if args.cards.len() > MAX_CARDS {
+        eprintln!("Maximum of {MAX_CARDS} cards allowed");
+        return 1;
+    }
+
+    set_symbol_set(args.symbols);
+
+    let programs = Programs::new(args.cards.len() as u8, true, args.verbose);
+
+    let diff = solver::difficulty(&programs, args.target, &args.cards);
+
+    println!("Difficulty: {}", diff.grade);
+
+    if args.verbose {
+        println!("Score: {}", diff.score);
+    }
+
+    println!("Solutions: {}", diff.solutions);
+
+    if let Some(ops) = diff.min_operations {
+        println!("Shortest solution: {ops} operations");
+    }
+
+    if let Some(easiest) = diff.easiest {
+        println!(
+            "Easiest solution: {}",
+            programs.infix(easiest, &args.cards, true)
+        );
+    }
+
+    0
+}
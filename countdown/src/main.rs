@@ -0,0 +1,90 @@
+mod best_sets;
+mod compare_sets;
+mod coverage;
+mod daily;
+mod dataset;
+#[cfg(feature = "sqlite")]
+mod db;
+mod difficulty;
+mod practice;
+mod repl;
+mod replay;
+mod sensitivity;
+mod simulate;
+
+use std::process;
+
+use clap::{Parser, Subcommand};
+use solver::CAPABILITIES;
+
+#[derive(Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about,
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action, exclusive = true)]
+    capabilities: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Grade the difficulty of reaching a target with a set of cards
+    Difficulty(difficulty::DifficultyArgs),
+    /// Generate the puzzle of the day or decode a shared puzzle
+    Daily(daily::DailyArgs),
+    /// Find the card sets reaching the most targets and suggest decks with better coverage
+    BestSets(best_sets::BestSetsArgs),
+    /// Compare the targets reachable with two card sets
+    CompareSets(compare_sets::CompareSetsArgs),
+    /// Measure how card set coverage changes with the number of copies of each card in the deck
+    Sensitivity(sensitivity::SensitivityArgs),
+    /// Simulate random games to compare the choice of the number of big numbers
+    Simulate(simulate::SimulateArgs),
+    /// Show progress from the practice history recorded with 'play --practice'
+    Practice(practice::PracticeArgs),
+    /// Review a game recorded with 'play --record' step by step
+    Replay(replay::ReplayArgs),
+    /// Solve, deal and check puzzles interactively with command history and completion
+    Repl(repl::ReplArgs),
+    /// Export the best solution for each target reached by each card set in a solve_all results
+    /// directory as a JSON lines dataset
+    ExportDataset(dataset::ExportDatasetArgs),
+    /// Import solve_all runs in to a database and compare them
+    #[cfg(feature = "sqlite")]
+    Db(db::DbArgs),
+}
+
+fn main() {
+    // Parse command line arguments
+    let args = Args::parse();
+
+    let exit_code = match args.command {
+        Some(Command::Difficulty(args)) => difficulty::difficulty(args),
+        Some(Command::Daily(args)) => daily::daily(args),
+        Some(Command::BestSets(args)) => best_sets::best_sets(args),
+        Some(Command::CompareSets(args)) => compare_sets::compare_sets(args),
+        Some(Command::Sensitivity(args)) => sensitivity::sensitivity(args),
+        Some(Command::Simulate(args)) => simulate::simulate(args),
+        Some(Command::Replay(args)) => replay::replay(args),
+        Some(Command::Practice(args)) => practice::practice(args),
+        Some(Command::Repl(args)) => repl::repl(args),
+        Some(Command::ExportDataset(args)) => dataset::export_dataset(args),
+        #[cfg(feature = "sqlite")]
+        Some(Command::Db(args)) => db::db(args),
+        None => {
+            // Only --capabilities can be given without a command
+            println!("{}", CAPABILITIES.to_json());
+            0
+        }
+    };
+
+    process::exit(exit_code)
+}
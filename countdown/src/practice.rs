@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use solver::{PracticeHistory, INITIAL_RATING};
+
+/// Width of the longest chart bar
+const BAR_WIDTH: usize = 50;
+
+#[derive(Args, Debug)]
+pub struct PracticeArgs {
+    /// Practice history file (defaults to countdown_numbers/practice.json in the configuration directory)
+    #[clap(long = "history", value_parser)]
+    history: Option<PathBuf>,
+
+    /// Number of most recent rounds to chart
+    #[clap(short = 'l', long = "last", default_value_t = 20, value_parser)]
+    last: usize,
+}
+
+pub fn practice(args: PracticeArgs) -> i32 {
+    let Some(path) = args.history.or_else(PracticeHistory::default_path) else {
+        eprintln!("Unable to find the configuration directory, use --history to give a file");
+        return 1;
+    };
+
+    let history = match PracticeHistory::load(&path) {
+        Ok(history) => history,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            return 1;
+        }
+    };
+
+    if history.rounds.is_empty() {
+        println!("No practice rounds recorded in {}", path.display());
+        println!("Use 'play --practice' to record rounds");
+        return 0;
+    }
+
+    let ratings = history.ratings();
+    let best = ratings.iter().copied().fold(INITIAL_RATING, f64::max);
+
+    println!("Rounds played: {}", history.rounds.len());
+    println!("Rating: {:.0} (best {:.0})", history.rating(), best);
+    println!(
+        "Targets reached: {}",
+        history
+            .rounds
+            .iter()
+            .filter(|r| r.distance == Some(0))
+            .count()
+    );
+    println!("Current streak: {}", history.current_streak());
+    println!("Best streak: {}", history.best_streak());
+
+    match history.average_distance() {
+        Some(avg) => println!("Average distance from target: {avg:.2}"),
+        None => println!("Average distance from target: -"),
+    }
+
+    // Chart the most recent rounds
+    let skip = history.rounds.len().saturating_sub(args.last);
+
+    println!();
+    println!("Rating");
+
+    let min = ratings[skip..].iter().copied().fold(f64::MAX, f64::min);
+    let max = ratings[skip..].iter().copied().fold(f64::MIN, f64::max);
+
+    for (i, rating) in ratings.iter().enumerate().skip(skip) {
+        // Scale the bars between the lowest and highest rating shown, leaving a minimum bar
+        let len = if max > min {
+            1 + (((rating - min) / (max - min)) * (BAR_WIDTH - 1) as f64).round() as usize
+        } else {
+            BAR_WIDTH
+        };
+
+        println!("{:>5} {:>5.0} {}", i + 1, rating, "█".repeat(len));
+    }
+
+    println!();
+    println!("Distance from target");
+
+    let max_distance = history.rounds[skip..]
+        .iter()
+        .filter_map(|r| r.distance)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (i, round) in history.rounds.iter().enumerate().skip(skip) {
+        match round.distance {
+            Some(distance) => println!(
+                "{:>5} {:>5} {}",
+                i + 1,
+                distance,
+                "█".repeat((distance as usize * BAR_WIDTH).div_ceil(max_distance as usize))
+            ),
+            None => println!("{:>5} {:>5}", i + 1, "-"),
+        }
+    }
+
+    0
+}
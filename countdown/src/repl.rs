@@ -0,0 +1,454 @@
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use cards::CardSet;
+use clap::Args;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use solver::{
+    evaluate_with_rules, normalise_cards, points, set_symbol_set, LazyPrograms, Programs, Puzzle,
+    Ruleset, SymbolSet, MAX_CARDS,
+};
+
+#[derive(Args, Debug)]
+pub struct ReplArgs {
+    /// Rules to solve and check answers with (tv, junior or relaxed)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    /// File to load command history from and save it to
+    #[clap(long = "history", value_parser)]
+    history: Option<PathBuf>,
+}
+
+/// Commands with a description of their arguments, for help and completion
+const COMMANDS: [(&str, &str, &str); 6] = [
+    (
+        "solve",
+        "[<target> <cards...>]",
+        "Solve a puzzle (the last puzzle dealt by default)",
+    ),
+    (
+        "deal",
+        "[<big>]",
+        "Deal a puzzle, with a number of big cards (random by default)",
+    ),
+    (
+        "check",
+        "[<target> <cards...>:] <equation>",
+        "Check an answer to a puzzle (the last puzzle dealt by default)",
+    ),
+    (
+        "stats",
+        "[<cards...>]",
+        "Show the targets reachable with a set of cards (the last cards dealt by default)",
+    ),
+    ("help", "", "Show this help"),
+    ("quit", "", "Leave the REPL"),
+];
+
+pub fn repl(args: ReplArgs) -> i32 {
+    set_symbol_set(args.symbols);
+
+    let mut editor = match Editor::<CommandHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Unable to start the line editor ({e})");
+            return 1;
+        }
+    };
+
+    editor.set_helper(Some(CommandHelper));
+
+    if let Some(history) = &args.history {
+        // The history file won't exist the first time
+        if history.exists() {
+            if let Err(e) = editor.load_history(history) {
+                eprintln!("Unable to load history from {} ({e})", history.display());
+            }
+        }
+    }
+
+    println!(
+        "Rules: {} ({})",
+        args.rules,
+        args.rules.rules().description()
+    );
+    println!("Type 'help' for a list of commands");
+
+    let mut session = Session::new(args.rules);
+
+    loop {
+        let line = match editor.readline("countdown> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Unable to read command ({e})");
+                break;
+            }
+        };
+
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // Failing to record history isn't fatal
+        let _ = editor.add_history_entry(line);
+
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+        let result = match command {
+            "solve" => session.solve(rest),
+            "deal" => session.deal(rest),
+            "check" => session.check(rest),
+            "stats" => session.stats(rest),
+            "help" => {
+                help();
+                Ok(())
+            }
+            "quit" | "exit" => break,
+            _ => Err(format!(
+                "Unknown command '{command}', type 'help' for a list of commands"
+            )),
+        };
+
+        if let Err(e) = result {
+            println!("{e}");
+        }
+    }
+
+    if let Some(history) = &args.history {
+        if let Err(e) = editor.save_history(history) {
+            eprintln!("Unable to save history to {} ({e})", history.display());
+            return 2;
+        }
+    }
+
+    0
+}
+
+/// Prints the commands
+fn help() {
+    let width = COMMANDS
+        .iter()
+        .map(|(name, params, _)| name.len() + params.len() + 1)
+        .max()
+        .unwrap_or(0);
+
+    for (name, params, desc) in COMMANDS {
+        println!("  {:width$}  {desc}", format!("{name} {params}"));
+    }
+}
+
+/// State kept between commands
+struct Session {
+    /// Rules to solve and check answers with
+    rules: Ruleset,
+    /// Programs for each number of cards, generated when first needed
+    programs: LazyPrograms,
+    /// Last puzzle dealt
+    puzzle: Option<Puzzle>,
+}
+
+impl Session {
+    fn new(rules: Ruleset) -> Self {
+        Self {
+            rules,
+            programs: LazyPrograms::new(),
+            puzzle: None,
+        }
+    }
+
+    /// Solves a puzzle, showing the solutions shortest first
+    fn solve(&mut self, rest: &str) -> Result<(), String> {
+        let (target, cards) = self.puzzle_or_last(rest)?;
+
+        let rules = self.rules.rules();
+        let programs = self.programs(cards.len());
+
+        let mut solutions = programs.run_target_rules(target, &cards, rules);
+
+        // Filter out identical equations (can happen when duplicate card is chosen)
+        programs.filter_identical(&mut solutions, &cards);
+
+        if solutions.is_empty() {
+            match rules
+                .is_strict()
+                .then(|| programs.coverage(&cards).nearest(target))
+                .flatten()
+            {
+                Some(nearest) => println!(
+                    "No solutions, the nearest target is {nearest} ({} away)",
+                    nearest.abs_diff(target)
+                ),
+                None => println!("No solutions"),
+            }
+
+            return Ok(());
+        }
+
+        // Sort solutions by shortest and simplest first
+        programs.rank_solutions(&mut solutions, &cards);
+
+        println!(
+            "{} {} found",
+            solutions.len(),
+            if solutions.len() == 1 {
+                "solution"
+            } else {
+                "solutions"
+            }
+        );
+
+        for s in &solutions {
+            println!("{}", programs.infix(s.program, &cards, true));
+        }
+
+        Ok(())
+    }
+
+    /// Deals a puzzle with the deck and targets of the rules
+    fn deal(&mut self, rest: &str) -> Result<(), String> {
+        let rules = self.rules.rules();
+        let deck = rules.deck();
+        let max_big = CardSet::new(&deck).max_big();
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before epoch")
+            .as_nanos() as u64;
+
+        let mut puzzle = match rest.trim() {
+            "" => Puzzle::from_seed(seed, &deck, &rules.targets()),
+            big => {
+                let big = big
+                    .parse::<usize>()
+                    .map_err(|e| format!("Invalid number of big cards '{big}': {e}"))?;
+
+                if big > max_big {
+                    Err(format!("Maximum of {max_big} big cards allowed"))?
+                }
+
+                Puzzle::from_seed_with_big(seed, &deck, big, &rules.targets())
+            }
+        };
+
+        puzzle.cards.sort_by(|a, b| b.cmp(a));
+
+        println!("Target: {}", puzzle.target);
+        println!("Cards: {}", cards_string(&puzzle.cards));
+
+        self.puzzle = Some(puzzle);
+
+        Ok(())
+    }
+
+    /// Checks an answer, showing its value and the points it scores
+    fn check(&mut self, rest: &str) -> Result<(), String> {
+        let (target, cards, equation) = match rest.split_once(':') {
+            Some((puzzle, equation)) => {
+                let (target, cards) = parse_puzzle(puzzle)?;
+                (target, cards, equation)
+            }
+            None => {
+                let (target, cards) = self.puzzle_or_last("")?;
+                (target, cards, rest)
+            }
+        };
+
+        let equation = equation.trim();
+
+        if equation.is_empty() {
+            Err("No equation given")?
+        }
+
+        let value = evaluate_with_rules(equation, &cards, self.rules.rules())
+            .map_err(|e| format!("Invalid equation ({e})"))?;
+
+        match value.abs_diff(target) {
+            0 => println!("{value}: exactly right, {} points", points(target, value)),
+            away => println!("{value}: {away} away, {} points", points(target, value)),
+        }
+
+        Ok(())
+    }
+
+    /// Shows the number of targets reachable with a set of cards and lists the others
+    fn stats(&mut self, rest: &str) -> Result<(), String> {
+        let cards = match rest.trim() {
+            "" => self
+                .puzzle
+                .as_ref()
+                .map(|puzzle| puzzle.cards.clone())
+                .ok_or("No cards given and no puzzle dealt")?,
+            cards => parse_cards(cards)?,
+        };
+
+        let rules = self.rules.rules();
+
+        if !rules.is_strict() {
+            Err(format!(
+                "Coverage can't be calculated with the {} rules",
+                self.rules
+            ))?
+        }
+
+        let targets = rules.targets();
+        let start = Instant::now();
+        let coverage = self.programs(cards.len()).coverage(&cards);
+
+        let unreachable = targets
+            .clone()
+            .filter(|&t| !coverage.contains(t))
+            .collect::<Vec<_>>();
+
+        println!(
+            "{} reaches {} of {} targets ({}ms)",
+            cards_string(&cards),
+            coverage.count_range(&targets),
+            targets.clone().count(),
+            start.elapsed().as_millis()
+        );
+
+        if !unreachable.is_empty() {
+            println!("Unreachable: {}", cards_string(&unreachable));
+        }
+
+        Ok(())
+    }
+
+    /// Parses a puzzle, or returns the last puzzle dealt if none is given
+    fn puzzle_or_last(&self, rest: &str) -> Result<(u32, Vec<u32>), String> {
+        if !rest.trim().is_empty() {
+            return parse_puzzle(rest);
+        }
+
+        self.puzzle
+            .as_ref()
+            .map(|puzzle| (puzzle.target, puzzle.cards.clone()))
+            .ok_or_else(|| "No puzzle given and no puzzle dealt".to_string())
+    }
+
+    /// Returns the programs for a number of cards, generating them the first time
+    fn programs(&self, cards: usize) -> &Programs {
+        self.programs.get_or_init(cards, || {
+            let start = Instant::now();
+            let programs = Programs::new(cards as u8, false, false);
+
+            println!(
+                "Generated programs for {cards} cards in {}ms",
+                start.elapsed().as_millis()
+            );
+
+            programs
+        })
+    }
+}
+
+/// Parses a puzzle given as the target followed by the cards
+fn parse_puzzle(puzzle: &str) -> Result<(u32, Vec<u32>), String> {
+    let numbers = parse_numbers(puzzle)?;
+
+    let Some((&target, cards)) = numbers.split_first() else {
+        Err("No target given")?
+    };
+
+    if target == 0 {
+        Err("Target must be greater than zero")?
+    }
+
+    Ok((target, check_cards(cards)?))
+}
+
+/// Parses a set of cards
+fn parse_cards(cards: &str) -> Result<Vec<u32>, String> {
+    check_cards(&parse_numbers(cards)?)
+}
+
+/// Parses numbers separated by spaces or commas
+fn parse_numbers(numbers: &str) -> Result<Vec<u32>, String> {
+    numbers
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|n| !n.is_empty())
+        .map(|n| {
+            n.parse::<u32>()
+                .map_err(|e| format!("Invalid number '{n}': {e}"))
+        })
+        .collect()
+}
+
+/// Checks the number of cards, returning them in descending order
+fn check_cards(cards: &[u32]) -> Result<Vec<u32>, String> {
+    if cards.is_empty() {
+        Err("No cards given")?
+    }
+
+    if cards.len() > MAX_CARDS {
+        Err(format!("Maximum of {MAX_CARDS} cards allowed"))?
+    }
+
+    Ok(normalise_cards(cards))
+}
+
+/// Formats numbers separated by spaces
+fn cards_string(cards: &[u32]) -> String {
+    cards
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Line editor helper completing command names
+struct CommandHelper;
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+
+        // Only the command is completed
+        if line.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+
+        let candidates = COMMANDS
+            .iter()
+            .filter(|(name, _, _)| name.starts_with(line))
+            .map(|(name, _, _)| Pair {
+                display: name.to_string(),
+                replacement: format!("{name} "),
+            })
+            .collect();
+
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
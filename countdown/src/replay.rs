@@ -0,0 +1,139 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use itertools::Itertools;
+use solver::{AnswerRecord, GameRecord, RoundRecord, Ruleset};
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// Game record file written by 'play --record'
+    #[clap(value_parser)]
+    file: PathBuf,
+
+    /// Show the whole game without pausing between steps
+    #[clap(short = 'a', long = "all", action)]
+    all: bool,
+}
+
+pub fn replay(args: ReplayArgs) -> i32 {
+    let json = match fs::read_to_string(&args.file) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("Unable to read {} ({e})", args.file.display());
+            return 1;
+        }
+    };
+
+    let record = match GameRecord::from_json(&json) {
+        Ok(record) => record,
+        Err(e) => {
+            eprintln!("{}: {e}", args.file.display());
+            return 1;
+        }
+    };
+
+    // Only pause when someone is there to press enter
+    let pause = !args.all && io::stdin().is_terminal();
+
+    if record.rules != Ruleset::Tv {
+        println!(
+            "Rules: {} ({})",
+            record.rules,
+            record.rules.rules().description()
+        );
+    }
+
+    let opponent = record.rounds.iter().any(|r| r.opponent.is_some());
+    let mut totals = (0, 0);
+
+    for (i, round) in record.rounds.iter().enumerate() {
+        replay_round(i + 1, round, opponent, pause);
+
+        totals.0 += round.player_score;
+        totals.1 += round.opponent_score;
+    }
+
+    if record.rounds.len() > 1 {
+        println!();
+        println!("== Final score ==");
+        print_scores(opponent, totals);
+    }
+
+    0
+}
+
+fn replay_round(number: usize, round: &RoundRecord, opponent: bool, pause: bool) {
+    println!();
+    println!("== Round {number} ==");
+    println!("Cards: {}", round.cards.iter().join(" "));
+    println!("Target: {}", round.target);
+    println!("Time allowed: {} seconds", round.time_limit);
+
+    step(pause);
+
+    match &round.player {
+        Some(answer) => println!("You answered {}", describe_answer(answer)),
+        None => println!("You passed"),
+    }
+
+    if opponent {
+        match &round.opponent {
+            Some(answer) => println!("Opponent answered {}", describe_answer(answer)),
+            None => println!("Opponent had no answer"),
+        }
+    }
+
+    print_scores(opponent, (round.player_score, round.opponent_score));
+
+    step(pause);
+
+    let analysis = &round.analysis;
+
+    println!(
+        "{} equation{} reach the target",
+        analysis.solutions,
+        if analysis.solutions == 1 { "" } else { "s" }
+    );
+
+    match (&analysis.best, analysis.best_value) {
+        (Some(best), Some(value)) => println!("Best answer {value}: {best}"),
+        _ => println!("No answer in range is possible"),
+    }
+
+    step(pause);
+}
+
+fn describe_answer(answer: &AnswerRecord) -> String {
+    let mut desc = match (answer.value, &answer.error) {
+        (Some(value), _) => format!("{value}: {}", answer.answer),
+        (None, Some(error)) => format!("'{}' ({error})", answer.answer),
+        (None, None) => format!("'{}'", answer.answer),
+    };
+
+    if let Some(ms) = answer.time_ms {
+        desc += &format!(" in {:.1} seconds", ms as f64 / 1000.0);
+    }
+
+    desc
+}
+
+fn print_scores(opponent: bool, (player, computer): (u32, u32)) {
+    if opponent {
+        println!("You score {player}, opponent scores {computer}");
+    } else {
+        println!("You score {player}");
+    }
+}
+
+/// Waits for enter to be pressed if pausing between steps
+fn step(pause: bool) {
+    if pause {
+        print!("[Enter]");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        let _ = io::stdin().lock().read_line(&mut line);
+    }
+}
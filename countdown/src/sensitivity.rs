@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cards::get_special_cards;
+use clap::Args;
+use itertools::Itertools;
+use solver::Ruleset;
+
+use crate::coverage::{
+    calculate, card_sets, load_dir, open_disk_cache, sample_sets, threads, CoverageCache,
+    DeckCoverage,
+};
+
+#[derive(Args, Debug)]
+pub struct SensitivityArgs {
+    /// Deck changes to try, each giving the number of copies of a card (eg. 75=3 for three 75s,
+    /// 25=0 for no 25). Defaults to one fewer and one more copy of each card in the deck
+    #[clap(value_parser)]
+    changes: Vec<DeckChange>,
+
+    /// Number of card sets to sample from each deck (0 for every card set)
+    #[clap(long = "sample", default_value_t = 2000, value_parser)]
+    sample: usize,
+
+    /// Random seed for choosing the samples (defaults to the current time)
+    #[clap(long = "seed", value_parser)]
+    seed: Option<u64>,
+
+    /// Number of cards in each card set
+    #[clap(short = 'n', long = "pick", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pick: u8,
+
+    /// Use special cards
+    #[clap(short = 's', long = "special", action)]
+    special_cards: bool,
+
+    /// Rules giving the deck and targets (tv or junior)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Results directory written by solve_all to read card set coverage from
+    #[clap(long = "dir", value_parser)]
+    dir: Option<PathBuf>,
+
+    /// Cache card set coverage in this directory
+    #[clap(long = "cache", value_parser)]
+    cache: Option<PathBuf>,
+
+    /// Number of threads to run when calculating coverage
+    #[clap(long = "threads", value_parser)]
+    threads: Option<usize>,
+}
+
+/// Change to the number of copies of a card in the deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DeckChange {
+    /// Card value
+    value: u32,
+    /// Number of copies of the card in the changed deck
+    count: usize,
+}
+
+impl DeckChange {
+    /// Applies the change to a deck, returning the new deck in descending order
+    fn apply(&self, deck: &[u32]) -> Vec<u32> {
+        let mut new_deck = deck
+            .iter()
+            .copied()
+            .filter(|&c| c != self.value)
+            .chain(std::iter::repeat_n(self.value, self.count))
+            .collect::<Vec<_>>();
+
+        new_deck.sort_by(|a, b| b.cmp(a));
+
+        new_deck
+    }
+}
+
+impl FromStr for DeckChange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, count) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid deck change '{s}' (expected <card>=<count>)"))?;
+
+        let value = value
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| format!("Invalid card '{value}': {e}"))?;
+
+        if value == 0 {
+            Err("Cards must be greater than zero")?
+        }
+
+        let count = count
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| format!("Invalid count '{count}': {e}"))?;
+
+        Ok(Self { value, count })
+    }
+}
+
+impl fmt::Display for DeckChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.value, self.count)
+    }
+}
+
+pub fn sensitivity(args: SensitivityArgs) -> i32 {
+    // Coverage is calculated with the TV rules for intermediate results
+    let rules = args.rules.rules();
+
+    if !rules.is_strict() {
+        eprintln!("Decks can't be compared with the {} rules", args.rules);
+        return 1;
+    }
+
+    let targets = rules.targets();
+
+    // Get card set
+    let mut deck = if args.special_cards {
+        get_special_cards().to_vec()
+    } else {
+        rules.deck()
+    };
+
+    deck.sort_by(|a, b| b.cmp(a));
+
+    // Changes to try
+    let changes = if args.changes.is_empty() {
+        deck.iter()
+            .copied()
+            .dedup_with_count()
+            .flat_map(|(count, value)| {
+                [
+                    DeckChange {
+                        value,
+                        count: count - 1,
+                    },
+                    DeckChange {
+                        value,
+                        count: count + 1,
+                    },
+                ]
+            })
+            .collect::<Vec<_>>()
+    } else {
+        args.changes.clone()
+    };
+
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before epoch")
+            .as_secs()
+    });
+
+    // Sample the card sets of each deck
+    let sample = |deck: &[u32]| {
+        let sets = card_sets(deck, args.pick);
+        let population = sets.len();
+
+        if args.sample == 0 {
+            (sets, population)
+        } else {
+            (sample_sets(sets, args.sample, seed), population)
+        }
+    };
+
+    let base = sample(&deck);
+
+    let mut decks = Vec::new();
+
+    for change in changes {
+        let new_deck = change.apply(&deck);
+
+        if new_deck.len() < args.pick as usize {
+            eprintln!(
+                "Skipping {change}: the deck would have fewer than {} cards",
+                args.pick
+            );
+            continue;
+        }
+
+        if new_deck == deck {
+            continue;
+        }
+
+        let was = deck.iter().filter(|&&c| c == change.value).count();
+
+        decks.push((change, was, sample(&new_deck)));
+    }
+
+    let disk_cache = match open_disk_cache(args.cache.as_deref()) {
+        Ok(disk_cache) => disk_cache,
+        Err(code) => return code,
+    };
+
+    // Load card set coverage from the results directory
+    let mut cache = match &args.dir {
+        Some(dir) => match load_dir(dir, args.pick as usize) {
+            Ok(cache) => cache,
+            Err(e) => {
+                eprintln!("Failed to scan {} ({})", dir.display(), e);
+                return 2;
+            }
+        },
+        None => CoverageCache::new(),
+    };
+
+    // Calculate coverage for all of the card sets
+    let all_sets = base
+        .0
+        .iter()
+        .chain(decks.iter().flat_map(|(_, _, (sets, _))| sets.iter()))
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    calculate(
+        &mut cache,
+        &all_sets,
+        args.pick,
+        threads(args.threads),
+        disk_cache.as_ref(),
+    );
+
+    // Report the changes in coverage
+    let base_coverage = DeckCoverage::new(&base.0, &cache, &targets);
+    let full_percent =
+        |coverage: &DeckCoverage| coverage.full as f64 * 100.0 / coverage.sets as f64;
+
+    println!("Deck: {} (seed {seed})", deck.iter().join(" "));
+    println!(
+        "  Average {:.2} targets, {:.2}% of card sets reach every target ({} of {} card sets)",
+        base_coverage.average,
+        full_percent(&base_coverage),
+        base_coverage.sets,
+        base.1
+    );
+
+    let mut ranked = decks
+        .iter()
+        .map(|(change, was, (sets, population))| {
+            (
+                change,
+                was,
+                DeckCoverage::new(sets, &cache, &targets),
+                population,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    ranked.sort_by(|(_, _, c1, _), (_, _, c2, _)| c2.average.total_cmp(&c1.average));
+
+    println!("Deck changes:");
+
+    for (change, was, coverage, population) in ranked {
+        println!(
+            "  {} × {} (was {was}): average {:.2} targets ({:+.2}), {:.2}% reach every target ({:+.2}%) ({} of {population} card sets)",
+            change.count,
+            change.value,
+            coverage.average,
+            coverage.average - base_coverage.average,
+            full_percent(&coverage),
+            full_percent(&coverage) - full_percent(&base_coverage),
+            coverage.sets
+        );
+    }
+
+    0
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cards::{get_special_cards, CardSet};
+use clap::Args;
+use itertools::Itertools;
+use solver::{
+    max_reachable, min_reachable_above, points, Programs, Puzzle, Ruleset, TargetWeights,
+};
+
+use crate::coverage::{calculate, load_dir, open_disk_cache, threads, CoverageCache};
+
+#[derive(Args, Debug)]
+pub struct SimulateArgs {
+    /// Number of games to simulate for each number of big numbers
+    #[clap(short = 'g', long = "games", default_value_t = 10_000, value_parser)]
+    games: usize,
+
+    /// Random seed (defaults to the current time)
+    #[clap(long = "seed", value_parser)]
+    seed: Option<u64>,
+
+    /// Use special cards
+    #[clap(short = 's', long = "special", action)]
+    special_cards: bool,
+
+    /// Rules to deal the games with (tv or junior)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// JSON file giving the weight of each target (every target is equally likely by default)
+    #[clap(long = "target-weights", value_parser)]
+    target_weights: Option<PathBuf>,
+
+    /// Results directory written by solve_all to read card set coverage from
+    #[clap(long = "dir", value_parser)]
+    dir: Option<PathBuf>,
+
+    /// Cache card set coverage in this directory
+    #[clap(long = "cache", value_parser)]
+    cache: Option<PathBuf>,
+
+    /// Number of threads to run when calculating coverage
+    #[clap(long = "threads", value_parser)]
+    threads: Option<usize>,
+
+    /// Also report the largest value made in any game and the games where no value above the
+    /// target range can be made
+    #[clap(long = "trivia", action)]
+    trivia: bool,
+}
+
+/// Simulation results for a number of big numbers
+#[derive(Default)]
+struct BigResults {
+    /// Number of games simulated
+    games: usize,
+    /// Number of games where the target can be reached exactly
+    exact: usize,
+    /// Number of games where a value within 5 of the target can be reached
+    within_5: usize,
+    /// Number of games where a value within 10 of the target can be reached
+    within_10: usize,
+    /// Number of games where no value in the target range can be reached
+    out_of_range: usize,
+    /// Total of the best distance from the target
+    tot_distance: usize,
+    /// Total of the points available
+    tot_points: usize,
+}
+
+pub fn simulate(args: SimulateArgs) -> i32 {
+    if args.games == 0 {
+        eprintln!("At least one game is required");
+        return 1;
+    }
+
+    // Coverage is calculated with the TV rules for intermediate results
+    let rules = args.rules.rules();
+
+    if !rules.is_strict() {
+        eprintln!("Games can't be simulated with the {} rules", args.rules);
+        return 1;
+    }
+
+    // Get card set
+    let deck = if args.special_cards {
+        get_special_cards().to_vec()
+    } else {
+        rules.deck()
+    };
+
+    let weights = match &args.target_weights {
+        Some(file) => match TargetWeights::load(file) {
+            Ok(weights) => weights,
+            Err(e) => {
+                eprintln!(
+                    "Unable to load target weights from {} ({e})",
+                    file.display()
+                );
+                return 1;
+            }
+        },
+        None => TargetWeights::uniform(),
+    };
+
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before epoch")
+            .as_secs()
+    });
+
+    println!(
+        "Simulating {} games for each number of big numbers (seed {seed})",
+        args.games
+    );
+
+    // Deal the games
+    let max_big = CardSet::new(&deck).max_big();
+
+    let games = (0..=max_big)
+        .map(|big_cnt| {
+            (0..args.games)
+                .map(|game| {
+                    let game_seed = seed.wrapping_add((big_cnt * args.games + game) as u64);
+
+                    let mut puzzle = Puzzle::from_seed_with_big_weighted(
+                        game_seed,
+                        &deck,
+                        big_cnt,
+                        &rules.targets(),
+                        &weights,
+                    );
+                    puzzle.cards.sort_by(|a, b| b.cmp(a));
+
+                    puzzle
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let disk_cache = match open_disk_cache(args.cache.as_deref()) {
+        Ok(disk_cache) => disk_cache,
+        Err(code) => return code,
+    };
+
+    // Load card set coverage from the results directory
+    let mut cache = match &args.dir {
+        Some(dir) => match load_dir(dir, 6) {
+            Ok(cache) => cache,
+            Err(e) => {
+                eprintln!("Failed to scan {} ({})", dir.display(), e);
+                return 2;
+            }
+        },
+        None => CoverageCache::new(),
+    };
+
+    // Calculate coverage for the card sets dealt
+    let sets = games
+        .iter()
+        .flatten()
+        .map(|puzzle| puzzle.cards.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    calculate(
+        &mut cache,
+        &sets,
+        6,
+        threads(args.threads),
+        disk_cache.as_ref(),
+    );
+
+    // Programs to find the closest value for card sets which can't reach any target
+    let mut programs = None;
+
+    // Play the games
+    let results = games
+        .iter()
+        .map(|puzzles| {
+            let mut results = BigResults::default();
+
+            for puzzle in puzzles {
+                results.games += 1;
+
+                let distance = match cache[&puzzle.cards].nearest(puzzle.target) {
+                    Some(nearest) => nearest.abs_diff(puzzle.target) as usize,
+                    None => {
+                        // Find the closest value outside of the target range
+                        results.out_of_range += 1;
+
+                        programs
+                            .get_or_insert_with(|| Programs::new(6, false, false))
+                            .run_all_distances(&puzzle.cards, puzzle.target, 999)
+                            .closest()
+                            .expect("No answers for card set")
+                    }
+                };
+
+                if distance == 0 {
+                    results.exact += 1;
+                }
+
+                if distance <= 5 {
+                    results.within_5 += 1;
+                }
+
+                if distance <= 10 {
+                    results.within_10 += 1;
+                }
+
+                results.tot_distance += distance;
+                results.tot_points +=
+                    points(puzzle.target, puzzle.target + distance as u32) as usize;
+            }
+
+            results
+        })
+        .collect::<Vec<_>>();
+
+    // Output the results
+    println!("Big Numbers, Exact, Within 5, Within 10, No value in range, Average distance, Average points");
+
+    for (big_cnt, results) in results.iter().enumerate() {
+        let percent = |n: usize| format!("{:.2}%", (n as f64 * 100.0) / results.games as f64);
+
+        println!(
+            "{big_cnt}, {}, {}, {}, {}, {:.2}, {:.2}",
+            percent(results.exact),
+            percent(results.within_5),
+            percent(results.within_10),
+            percent(results.out_of_range),
+            results.tot_distance as f64 / results.games as f64,
+            results.tot_points as f64 / results.games as f64
+        );
+    }
+
+    // Recommend the number of big numbers
+    let best = |key: fn(&BigResults) -> usize| {
+        results
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, r)| key(r))
+            .map(|(big_cnt, _)| big_cnt)
+            .unwrap()
+    };
+
+    println!();
+    println!(
+        "Best chance of reaching the target exactly: {} big",
+        best(|r| r.exact)
+    );
+    println!("Most points expected: {} big", best(|r| r.tot_points));
+
+    if args.trivia {
+        trivia(&sets, *rules.targets().end());
+    }
+
+    0
+}
+
+/// Reports the largest value made from any of the card sets dealt, and the number of card sets
+/// which can't make a value above the target range
+fn trivia(sets: &[Vec<u32>], max_target: u32) {
+    let largest = sets
+        .iter()
+        .filter_map(|cards| max_reachable(cards).map(|max| (max, cards)))
+        .max_by(|(max1, cards1), (max2, cards2)| max1.cmp(max2).then(cards2.cmp(cards1)));
+
+    let capped = sets
+        .iter()
+        .filter(|cards| min_reachable_above(cards, max_target).is_none())
+        .count();
+
+    println!();
+
+    if let Some((max, cards)) = largest {
+        println!("Largest value made: {max} ({})", cards.iter().join(" "));
+    }
+
+    println!(
+        "Card sets which can't make a value above {max_target}: {capped} of {}",
+        sets.len()
+    );
+}
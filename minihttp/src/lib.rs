@@ -0,0 +1,264 @@
+//! Minimal HTTP/1.1 support shared by the web server and the chat bot: reading a request (with its
+//! headers, query string and body) and writing a complete response or a server-sent event stream.
+//! Each connection handles one request and is then closed.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+/// Maximum length of the request line and each header line
+const MAX_LINE: u64 = 8192;
+
+/// Maximum number of header lines
+const MAX_HEADERS: usize = 100;
+
+/// Maximum length of a request body
+const MAX_BODY: usize = 64 * 1024;
+
+/// A parsed HTTP request
+pub struct Request {
+    /// Request method
+    pub method: String,
+    /// Request path without the query string
+    pub path: String,
+    /// Query string parameters
+    pub query: HashMap<String, String>,
+    /// Headers, keyed by the lower case header name
+    pub headers: HashMap<String, String>,
+    /// Request body
+    pub body: String,
+}
+
+impl Request {
+    /// Reads a request, including the body, from a stream
+    pub fn read(stream: impl Read) -> io::Result<Self> {
+        let mut reader = BufReader::new(stream);
+
+        let request_line = read_line(&mut reader)?;
+
+        let mut parts = request_line.split_whitespace();
+
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(invalid("Invalid request line"));
+        };
+
+        let mut headers = HashMap::new();
+
+        for _ in 0..MAX_HEADERS {
+            let line = read_line(&mut reader)?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let length = match headers.get("content-length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| invalid("Invalid content length"))?,
+            None => 0,
+        };
+
+        if length > MAX_BODY {
+            return Err(invalid("Request body too large"));
+        }
+
+        let mut body = vec![0; length];
+
+        reader.read_exact(&mut body)?;
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        Ok(Self {
+            method: method.to_string(),
+            path: percent_decode(path),
+            query: parse_form(query),
+            headers,
+            body: String::from_utf8(body).map_err(|_| invalid("Request body is not UTF-8"))?,
+        })
+    }
+
+    /// Returns a header value
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|value| value.as_str())
+    }
+}
+
+/// Reads a line, without the line ending
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+
+    reader.by_ref().take(MAX_LINE).read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Parses a query string or form encoded body in to a map of fields
+pub fn parse_form(form: &str) -> HashMap<String, String> {
+    form.split('&')
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let (name, value) = field.split_once('=').unwrap_or((field, ""));
+
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decodes a percent encoded URL or form component
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    result.push((hi << 4) | lo);
+                    i += 2;
+                }
+                _ => result.push(b'%'),
+            },
+            b'+' => result.push(b' '),
+            b => result.push(b),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Decodes a hexadecimal string
+pub fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Writes a complete response with any extra headers
+pub fn respond(
+    stream: &mut impl Write,
+    status: u16,
+    headers: &[(&str, &str)],
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{body}",
+        reason(status),
+        body.len(),
+        header_lines(headers)
+    )?;
+
+    stream.flush()
+}
+
+/// Writes the response headers, with any extra headers, for a server-sent event stream
+pub fn start_events(stream: &mut impl Write, headers: &[(&str, &str)]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n{}Connection: close\r\n\r\n",
+        header_lines(headers)
+    )?;
+
+    stream.flush()
+}
+
+/// Writes a server-sent event
+pub fn send_event(stream: &mut impl Write, event: &str, data: &str) -> io::Result<()> {
+    write!(stream, "event: {event}\ndata: {data}\n\n")?;
+
+    stream.flush()
+}
+
+/// Formats headers as lines of a response
+fn header_lines(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect()
+}
+
+/// Creates an InvalidData error
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Returns the reason phrase for a status code
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let request = Request::read(
+            "POST /solve%20it?target=523&cards=100%2C75 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 9\r\n\r\ntext=a+b&extra"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!("POST", request.method);
+        assert_eq!("/solve it", request.path);
+        assert_eq!(Some("523"), request.query.get("target").map(|t| t.as_str()));
+        assert_eq!(
+            Some("100,75"),
+            request.query.get("cards").map(|c| c.as_str())
+        );
+        assert_eq!(Some("localhost"), request.header("host"));
+        assert_eq!("text=a+b&", request.body);
+
+        assert!(Request::read("\r\n".as_bytes()).is_err());
+        assert!(
+            Request::read("POST / HTTP/1.1\r\nContent-Length: 100000\r\n\r\n".as_bytes()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!("a b,c%", percent_decode("a+b%2Cc%"));
+        assert_eq!(Some(vec![0x01, 0xab]), hex_decode("01AB"));
+        assert_eq!(None, hex_decode("123"));
+        assert_eq!(None, hex_decode("zz"));
+    }
+
+    #[test]
+    fn test_respond() {
+        let mut response = Vec::new();
+
+        respond(&mut response, 403, &[("X-Test", "1")], "text/plain", "No").unwrap();
+
+        assert_eq!(
+            "HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain\r\nContent-Length: 2\r\nX-Test: 1\r\nConnection: close\r\n\r\nNo",
+            String::from_utf8(response).unwrap()
+        );
+    }
+}
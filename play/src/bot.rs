@@ -0,0 +1,48 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use solver::Solution;
+
+/// Maximum skill level
+pub const MAX_SKILL: u8 = 5;
+
+/// Computer opponent. The skill level limits the number of operations the opponent can use
+/// and the proportion of the solutions it considers
+pub struct Bot {
+    skill: u8,
+}
+
+impl Bot {
+    /// Creates a new computer opponent with a skill level between 1 and MAX_SKILL
+    pub fn new(skill: u8) -> Self {
+        assert!((1..=MAX_SKILL).contains(&skill));
+
+        Self { skill }
+    }
+
+    /// Chooses a solution to declare from the full set of solutions for the cards
+    pub fn declare<'a, R>(
+        &self,
+        solutions: &'a [Solution],
+        target: u32,
+        rng: &mut R,
+    ) -> Option<&'a Solution>
+    where
+        R: Rng,
+    {
+        // Only consider solutions the bot is capable of finding
+        let max_ops = self.skill as usize;
+
+        let candidates: Vec<&Solution> = solutions
+            .iter()
+            .filter(|s| s.length() / 2 <= max_ops)
+            .collect();
+
+        // Sample a proportion of the candidates
+        let sample_size = ((candidates.len() * self.skill as usize) / MAX_SKILL as usize).max(1);
+
+        candidates
+            .choose_multiple(rng, sample_size)
+            .min_by_key(|s| (s.result.abs_diff(target), s.length()))
+            .copied()
+    }
+}
@@ -0,0 +1,385 @@
+mod bot;
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use bot::{Bot, MAX_SKILL};
+use cards::get_special_cards;
+use clap::Parser;
+use itertools::Itertools;
+use rand::Rng;
+use solver::{
+    difficulty, evaluate_with_rules, points, score, set_machine_output, set_symbol_set, Analysis,
+    AnswerRecord, Declaration, GameRecord, PracticeHistory, PracticeRound, Programs, Puzzle,
+    RoundRecord, Ruleset, SymbolSet, TargetWeights, CAPABILITIES,
+};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct Args {
+    /// Number of rounds to play
+    #[clap(short = 'r', long = "rounds", default_value_t = 1, value_parser)]
+    rounds: usize,
+
+    /// Skill level of the computer opponent (1-5, 0 to play alone)
+    #[clap(short = 'k', long = "skill", default_value_t = 3, value_parser = clap::value_parser!(u8).range(0..=MAX_SKILL as i64))]
+    skill: u8,
+
+    /// Number of seconds allowed to find an answer
+    #[clap(short = 't', long = "time", default_value_t = 30, value_parser)]
+    time: u64,
+
+    /// Use special cards
+    #[clap(short = 's', long = "special", action)]
+    special_cards: bool,
+
+    /// Rules to play with (tv, junior or relaxed)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Play a shared puzzle code
+    #[clap(short = 'c', long = "code", value_parser)]
+    code: Option<String>,
+
+    /// JSON file giving the weight of each target (every target is equally likely by default)
+    #[clap(long = "target-weights", value_parser, conflicts_with = "code")]
+    target_weights: Option<PathBuf>,
+
+    /// Record the game to this file so it can be reviewed with 'countdown replay'
+    #[clap(long = "record", value_parser)]
+    record: Option<PathBuf>,
+
+    /// Add each round to the practice history (see 'countdown practice')
+    #[clap(short = 'p', long = "practice", action)]
+    practice: bool,
+
+    /// Practice history file (defaults to countdown_numbers/practice.json in the configuration directory)
+    #[clap(long = "history", value_parser, requires = "practice")]
+    history: Option<PathBuf>,
+
+    /// Format numbers as plain digits whatever the locale and don't colour the output
+    #[clap(long = "machine", action)]
+    machine: bool,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action)]
+    capabilities: bool,
+}
+
+fn main() {
+    // Parse command line arguments
+    let args = Args::parse();
+
+    if args.capabilities {
+        println!("{}", CAPABILITIES.to_json());
+        return;
+    }
+
+    set_machine_output(args.machine);
+    set_symbol_set(args.symbols);
+
+    // Decode shared puzzle
+    let shared = match args.code.as_deref().map(Puzzle::decode) {
+        None => None,
+        Some(Ok(puzzle)) if puzzle.cards.len() == 6 => Some(puzzle),
+        Some(Ok(_)) => {
+            eprintln!("Puzzle must have 6 cards");
+            process::exit(1);
+        }
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    };
+
+    // Load the target weights
+    let weights = match &args.target_weights {
+        Some(file) => match TargetWeights::load(file) {
+            Ok(weights) => weights,
+            Err(e) => {
+                eprintln!(
+                    "Unable to load target weights from {} ({e})",
+                    file.display()
+                );
+                process::exit(1);
+            }
+        },
+        None => TargetWeights::uniform(),
+    };
+
+    let rounds = if shared.is_some() { 1 } else { args.rounds };
+
+    // Load the practice history
+    let mut practice = if args.practice {
+        match load_history(&args) {
+            Ok(practice) => Some(practice),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    if args.rules != Ruleset::Tv {
+        println!(
+            "Rules: {} ({})",
+            args.rules,
+            args.rules.rules().description()
+        );
+    }
+
+    println!("Generating programs...");
+    let programs = Programs::new(6, true, false);
+
+    let bot = (args.skill > 0).then(|| Bot::new(args.skill));
+    let mut rng = rand::thread_rng();
+    let mut totals = (0, 0);
+    let mut record = GameRecord::new();
+    record.rules = args.rules;
+    let rules = args.rules.rules();
+
+    for round in 1..=rounds {
+        // Deal the cards
+        let puzzle = shared.clone().unwrap_or_else(|| {
+            let deck = if args.special_cards {
+                get_special_cards().to_vec()
+            } else {
+                rules.deck()
+            };
+
+            Puzzle::from_seed_weighted(rng.gen(), &deck, &rules.targets(), &weights)
+        });
+
+        let round_record = play_round(&args, &programs, bot.as_ref(), &puzzle, round, &mut rng);
+
+        let player_answer = round_record.player.as_ref().and_then(|p| p.value);
+
+        totals.0 += round_record.player_score;
+        totals.1 += round_record.opponent_score;
+
+        // Write the record after every round so it is kept if the game is abandoned
+        if let Some(file) = &args.record {
+            record.rounds.push(round_record);
+
+            if let Err(e) = write_record(file, &record) {
+                eprintln!("Unable to write game record to {} ({e})", file.display());
+                process::exit(2);
+            }
+        }
+
+        // Add the round to the practice history
+        if let Some((path, history)) = &mut practice {
+            practice_round(&programs, &puzzle, player_answer, history);
+
+            if let Err(e) = history.save(path) {
+                eprintln!(
+                    "Unable to write practice history to {} ({e})",
+                    path.display()
+                );
+                process::exit(2);
+            }
+        }
+    }
+
+    if rounds > 1 {
+        println!();
+        println!("== Final score ==");
+        print_scores(bot.is_some(), totals);
+    }
+}
+
+fn play_round<R: Rng>(
+    args: &Args,
+    programs: &Programs,
+    bot: Option<&Bot>,
+    puzzle: &Puzzle,
+    round: usize,
+    rng: &mut R,
+) -> RoundRecord {
+    let target = puzzle.target;
+    let cards = &puzzle.cards;
+    let rules = args.rules.rules();
+
+    println!();
+    println!("== Round {round} ==");
+    println!("Cards: {}", cards.iter().join(" "));
+    println!("Target: {target}");
+    println!(
+        "You have {} seconds. Enter your answer (blank to pass):",
+        args.time
+    );
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    // Read the player's answer
+    let start = Instant::now();
+    let mut answer = String::new();
+
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        answer.clear();
+    }
+
+    let elapsed = start.elapsed();
+    let answer = answer.trim();
+
+    let player_record = |value: Option<u32>, error: Option<String>| AnswerRecord {
+        answer: answer.to_string(),
+        value,
+        error,
+        time_ms: Some(elapsed.as_millis() as u64),
+    };
+
+    let (player, player_answer) = if elapsed.as_secs() > args.time {
+        println!("Out of time!");
+        (
+            None,
+            (!answer.is_empty()).then(|| player_record(None, Some("Out of time".to_string()))),
+        )
+    } else if answer.is_empty() {
+        (None, None)
+    } else {
+        match evaluate_with_rules(answer, cards, rules) {
+            Ok(value) => {
+                println!("You declare {value}");
+                (
+                    Some(Declaration::new(value, true)),
+                    Some(player_record(Some(value), None)),
+                )
+            }
+            Err(e) => {
+                println!("Invalid answer: {e}");
+                (
+                    Some(Declaration::new(0, false)),
+                    Some(player_record(None, Some(e.to_string()))),
+                )
+            }
+        }
+    };
+
+    // Run all programs for the cards. Answers up to twice the target are considered for targets
+    // larger than the range for the rules
+    let solutions = programs.run_range_rules(
+        cards,
+        rules.min_target.min(target)..=rules.max_target.max(target.saturating_mul(2)),
+        rules,
+    );
+
+    // Get the computer's answer
+    let opponent = bot.and_then(|bot| bot.declare(&solutions, target, rng));
+
+    if let Some(opponent) = opponent {
+        println!(
+            "Opponent declares {}: {}",
+            opponent.result,
+            programs.infix(opponent.program, cards, true)
+        );
+    } else if bot.is_some() {
+        println!("Opponent has no answer");
+    }
+
+    // Score the round
+    let scores = score(
+        target,
+        player,
+        opponent.map(|s| Declaration::new(s.result, true)),
+    );
+
+    print_scores(bot.is_some(), scores);
+
+    // Show the best answer
+    let best = solutions
+        .iter()
+        .min_by_key(|s| (s.result.abs_diff(target), s.length()));
+
+    if let Some(best) = best {
+        println!(
+            "Best answer {}: {}",
+            best.result,
+            programs.infix(best.program, cards, true)
+        );
+    }
+
+    RoundRecord {
+        target,
+        cards: cards.clone(),
+        time_limit: args.time,
+        player: player_answer,
+        opponent: opponent.map(|s| AnswerRecord {
+            answer: programs.infix(s.program, cards, false),
+            value: Some(s.result),
+            error: None,
+            time_ms: None,
+        }),
+        player_score: scores.0,
+        opponent_score: scores.1,
+        analysis: Analysis {
+            solutions: solutions.iter().filter(|s| s.result == target).count(),
+            best_value: best.map(|s| s.result),
+            best: best.map(|s| programs.infix(s.program, cards, false)),
+        },
+    }
+}
+
+fn load_history(args: &Args) -> Result<(PathBuf, PracticeHistory), String> {
+    let path = args
+        .history
+        .clone()
+        .or_else(PracticeHistory::default_path)
+        .ok_or("Unable to find the configuration directory, use --history to give a file")?;
+
+    let history = PracticeHistory::load(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    Ok((path, history))
+}
+
+fn practice_round(
+    programs: &Programs,
+    puzzle: &Puzzle,
+    answer: Option<u32>,
+    history: &mut PracticeHistory,
+) {
+    let before = history.rating();
+
+    let difficulty = difficulty(programs, puzzle.target, &puzzle.cards);
+
+    history.rounds.push(PracticeRound {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        target: puzzle.target,
+        cards: puzzle.cards.clone(),
+        difficulty: difficulty.easiest.map(|_| difficulty.score),
+        distance: answer.map(|value| value.abs_diff(puzzle.target)),
+        points: answer.map_or(0, |value| points(puzzle.target, value)),
+    });
+
+    let after = history.rating();
+
+    println!(
+        "Difficulty {}, rating {:.0} ({:+.0})",
+        difficulty.grade,
+        after,
+        after - before
+    );
+}
+
+fn write_record(file: &Path, record: &GameRecord) -> io::Result<()> {
+    fs::write(file, record.to_json())
+}
+
+fn print_scores(opponent: bool, (player, computer): (u32, u32)) {
+    if opponent {
+        println!("You score {player}, opponent scores {computer}");
+    } else {
+        println!("You score {player}");
+    }
+}
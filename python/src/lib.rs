@@ -0,0 +1,240 @@
+//! Python bindings for the solver, built with maturin. Programs for each number of cards are
+//! generated when first needed and shared by every call, and the GIL is released while solving so
+//! other Python threads can run.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cards::CardSet;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use solver::{
+    explain_unreachable, normalise_cards, LazyPrograms, Programs, Puzzle, Ruleset, MAX_CARDS,
+};
+
+/// Programs for each number of cards
+static PROGRAMS: LazyPrograms = LazyPrograms::new();
+
+/// Returns the programs for a number of cards
+fn programs(cards: usize) -> &'static Programs {
+    PROGRAMS.get_or_generate(cards, false)
+}
+
+/// Checks the number of cards and returns them in descending order
+fn check_cards(cards: &[u32]) -> PyResult<Vec<u32>> {
+    if cards.is_empty() || cards.len() > MAX_CARDS {
+        return Err(PyValueError::new_err(format!(
+            "Between 1 and {MAX_CARDS} cards must be given"
+        )));
+    }
+
+    Ok(normalise_cards(cards))
+}
+
+/// Parses the name of a rules preset
+fn parse_rules(rules: &str) -> PyResult<Ruleset> {
+    rules.parse().map_err(PyValueError::new_err)
+}
+
+/// A solution to a puzzle
+#[pyclass(frozen, get_all)]
+struct Solution {
+    /// Infix equation
+    equation: String,
+    /// Equation in reverse Polish notation with card values
+    rpn: String,
+    /// Steps of the solution, eg. "75 + 3 = 78"
+    steps: Vec<String>,
+    /// Number of cards used
+    cards_used: usize,
+}
+
+#[pymethods]
+impl Solution {
+    fn __repr__(&self) -> String {
+        format!("Solution('{}')", self.equation)
+    }
+}
+
+/// Cards and target dealt for a game
+#[pyclass(frozen, get_all)]
+struct Deal {
+    /// Cards dealt
+    cards: Vec<u32>,
+    /// Target
+    target: u32,
+}
+
+#[pymethods]
+impl Deal {
+    fn __repr__(&self) -> String {
+        format!("Deal(cards={:?}, target={})", self.cards, self.target)
+    }
+}
+
+/// Explanation of why a target can't be reached
+#[pyclass(frozen, get_all)]
+struct Explanation {
+    /// Nearest reachable value below the target
+    below: Option<u32>,
+    /// Nearest reachable value above the target
+    above: Option<u32>,
+    /// Descriptions of the obstructions which apply
+    obstructions: Vec<String>,
+    /// Smallest relaxation of the rules which reaches the target
+    relaxation: Option<String>,
+    /// Example equation reaching the target with the relaxation
+    example: Option<String>,
+}
+
+/// Returns the solutions for a target, shortest and simplest first. At most `limit` solutions are
+/// returned if given
+#[pyfunction]
+#[pyo3(signature = (cards, target, rules = "tv", limit = None))]
+fn solve(
+    py: Python<'_>,
+    cards: Vec<u32>,
+    target: u32,
+    rules: &str,
+    limit: Option<usize>,
+) -> PyResult<Vec<Solution>> {
+    let cards = check_cards(&cards)?;
+    let rules = parse_rules(rules)?.rules();
+
+    Ok(py.allow_threads(|| {
+        let programs = programs(cards.len());
+
+        let mut solutions = programs.run_target_rules(target, &cards, rules);
+
+        // Equal cards give identical equations
+        programs.filter_identical(&mut solutions, &cards);
+
+        programs.rank_solutions(&mut solutions, &cards);
+
+        solutions
+            .iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|s| Solution {
+                equation: programs.infix(s.program, &cards, false),
+                rpn: programs.rpn(s.program, &cards, false),
+                steps: programs.steps_rules(s.program, &cards, false, rules),
+                cards_used: s.cards_used(),
+            })
+            .collect()
+    }))
+}
+
+/// Returns the targets (100 to 999) which can be reached with a set of cards
+#[pyfunction]
+fn coverage(py: Python<'_>, cards: Vec<u32>) -> PyResult<Vec<u32>> {
+    let cards = check_cards(&cards)?;
+
+    Ok(py.allow_threads(|| programs(cards.len()).coverage(&cards).iter().collect()))
+}
+
+/// Deals six cards and a target from the deck of a rules preset. The number of big cards is chosen
+/// at random if not given. The deal is chosen from the seed if given, otherwise at random
+#[pyfunction]
+#[pyo3(signature = (big = None, seed = None, rules = "tv"))]
+fn deal(big: Option<usize>, seed: Option<u64>, rules: &str) -> PyResult<Deal> {
+    let rules = parse_rules(rules)?.rules();
+    let deck = rules.deck();
+
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before epoch")
+            .as_nanos() as u64
+    });
+
+    let puzzle = match big {
+        Some(big) => {
+            let max_big = CardSet::new(&deck).max_big();
+
+            if big > max_big {
+                return Err(PyValueError::new_err(format!(
+                    "At most {max_big} big cards can be dealt"
+                )));
+            }
+
+            Puzzle::from_seed_with_big(seed, &deck, big, &rules.targets())
+        }
+        None => Puzzle::from_seed(seed, &deck, &rules.targets()),
+    };
+
+    Ok(Deal {
+        cards: puzzle.cards,
+        target: puzzle.target,
+    })
+}
+
+/// Explains why a target can't be reached with a set of cards. Returns None if it can be reached
+#[pyfunction]
+fn explain(py: Python<'_>, cards: Vec<u32>, target: u32) -> PyResult<Option<Explanation>> {
+    let cards = check_cards(&cards)?;
+
+    Ok(py.allow_threads(|| {
+        let programs = programs(cards.len());
+
+        explain_unreachable(programs, target, &cards).map(|explanation| Explanation {
+            below: explanation.below,
+            above: explanation.above,
+            obstructions: explanation
+                .obstructions
+                .iter()
+                .map(|o| o.to_string())
+                .collect(),
+            relaxation: explanation.relaxation.map(|(r, _)| r.to_string()),
+            example: explanation
+                .relaxation
+                .map(|(_, prog_elem)| programs.infix(prog_elem, &cards, false)),
+        })
+    }))
+}
+
+/// Countdown numbers game solver
+#[pymodule]
+fn countdown_numbers(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(coverage, m)?)?;
+    m.add_function(wrap_pyfunction!(deal, m)?)?;
+    m.add_function(wrap_pyfunction!(explain, m)?)?;
+    m.add_class::<Solution>()?;
+    m.add_class::<Deal>()?;
+    m.add_class::<Explanation>()?;
+
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_cards() {
+        assert_eq!(vec![100, 8, 3], check_cards(&[8, 3, 100]).unwrap());
+        assert!(check_cards(&[]).is_err());
+        assert!(check_cards(&[1; MAX_CARDS + 1]).is_err());
+        assert!(parse_rules("none").is_err());
+    }
+
+    #[test]
+    fn test_solve() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let solutions = solve(py, vec![8, 8, 100], 108, "tv", None).unwrap();
+
+            // 100 + 8 once, although either 8 can be used
+            assert_eq!(1, solutions.len());
+            assert_eq!("100 + 8", solutions[0].equation);
+            assert_eq!(2, solutions[0].cards_used);
+
+            assert!(solve(py, vec![8, 8, 100], 108, "tv", Some(0))
+                .unwrap()
+                .is_empty());
+            assert!(solve(py, vec![], 108, "tv", None).is_err());
+        });
+    }
+}
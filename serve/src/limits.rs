@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+/// Number of clients tracked before idle clients are forgotten
+const MAX_CLIENTS: usize = 10_000;
+
+/// Limits the number of solves running at once. Requests wait in a queue for a free slot
+pub struct SolveQueue {
+    /// Maximum number of solves running at once
+    max_solves: usize,
+    /// Maximum number of requests waiting for a slot
+    max_queued: usize,
+    state: Mutex<QueueState>,
+    freed: Condvar,
+}
+
+#[derive(Default)]
+struct QueueState {
+    running: usize,
+    queued: usize,
+}
+
+/// Permission to run a solve. The slot is freed when dropped
+pub struct SolvePermit<'a> {
+    queue: &'a SolveQueue,
+}
+
+impl SolveQueue {
+    pub fn new(max_solves: usize, max_queued: usize) -> Self {
+        Self {
+            max_solves: max_solves.max(1),
+            max_queued,
+            state: Mutex::new(QueueState::default()),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Waits for a free slot. Returns None if the queue is full
+    pub fn acquire(&self) -> Option<SolvePermit<'_>> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.running >= self.max_solves {
+            if state.queued >= self.max_queued {
+                return None;
+            }
+
+            state.queued += 1;
+
+            state = self
+                .freed
+                .wait_while(state, |state| state.running >= self.max_solves)
+                .unwrap();
+
+            state.queued -= 1;
+        }
+
+        state.running += 1;
+
+        Some(SolvePermit { queue: self })
+    }
+
+    /// Returns the number of solves running and the number of requests waiting
+    pub fn depth(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+
+        (state.running, state.queued)
+    }
+}
+
+impl Drop for SolvePermit<'_> {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().running -= 1;
+        self.queue.freed.notify_one();
+    }
+}
+
+/// Per client IP address rate limiter. Each client has a bucket of tokens which refills at the
+/// rate limit, allowing bursts of up to a minute's worth of requests
+pub struct RateLimiter {
+    /// Requests allowed per minute (0 for no limit)
+    per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns true if the client is allowed to make a request
+    pub fn allow(&self, ip: IpAddr) -> bool {
+        if self.per_minute == 0 {
+            return true;
+        }
+
+        let capacity = self.per_minute as f64;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_CLIENTS {
+            // Forget clients whose buckets have refilled
+            buckets.retain(|_, bucket| self.refill(bucket, now) < capacity);
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        if self.refill(bucket, now) >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Adds the tokens earned since the bucket was last updated and returns the new token count
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+
+        bucket.tokens =
+            (bucket.tokens + elapsed * self.per_minute as f64 / 60.0).min(self.per_minute as f64);
+        bucket.updated = now;
+
+        bucket.tokens
+    }
+}
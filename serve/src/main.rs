@@ -0,0 +1,146 @@
+mod limits;
+mod metrics;
+mod routes;
+
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use limits::{RateLimiter, SolveQueue};
+use metrics::Metrics;
+use minihttp::Request;
+use routes::{Server, Solver};
+use solver::{set_machine_output, set_symbol_set, Ruleset, SymbolSet, CAPABILITIES, MAX_CARDS};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct Args {
+    /// Address to listen on
+    #[clap(short = 'b', long = "bind", default_value = "127.0.0.1", value_parser)]
+    bind: String,
+
+    /// Port to listen on
+    #[clap(short = 'p', long = "port", default_value_t = 8080, value_parser)]
+    port: u16,
+
+    /// Maximum number of puzzles to solve at once (defaults to the number of CPUs)
+    #[clap(short = 's', long = "max-solves", value_parser)]
+    max_solves: Option<usize>,
+
+    /// Maximum number of solve requests waiting for a free slot
+    #[clap(short = 'q', long = "max-queued", default_value_t = 32, value_parser)]
+    max_queued: usize,
+
+    /// Maximum requests per minute from each client IP address (0 for no limit)
+    #[clap(short = 'r', long = "rate-limit", default_value_t = 60, value_parser)]
+    rate_limit: u32,
+
+    /// Maximum time in seconds to spend solving a puzzle for /solve (0 for no limit)
+    #[clap(short = 't', long = "timeout", default_value_t = 0.0, value_parser)]
+    timeout: f64,
+
+    /// Rules used when a request doesn't give any (tv, junior or relaxed)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Multiplication symbol to use in equations in responses (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    /// Directory to save generated programs in and map them from, shared with other processes
+    #[clap(long = "programs-dir", value_parser)]
+    programs_dir: Option<PathBuf>,
+
+    /// Numbers of cards to generate programs for at start up (eg. 4,5,6), so the first request for
+    /// each doesn't wait for them
+    #[clap(long = "warm", value_delimiter = ',', value_parser = clap::value_parser!(u8).range(1..=MAX_CARDS as i64))]
+    warm: Vec<u8>,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action)]
+    capabilities: bool,
+}
+
+fn main() {
+    // Parse command line arguments
+    let args = Args::parse();
+
+    if args.capabilities {
+        println!("{}", CAPABILITIES.to_json());
+        return;
+    }
+
+    // Responses are read by programs so numbers are always formatted as plain digits
+    set_machine_output(true);
+    set_symbol_set(args.symbols);
+
+    let listener = match TcpListener::bind((args.bind.as_str(), args.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Unable to listen on {}:{} ({e})", args.bind, args.port);
+            process::exit(1);
+        }
+    };
+
+    println!("Listening on {}:{}", args.bind, args.port);
+
+    let max_solves = args.max_solves.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+
+    let server = Arc::new(Server {
+        solver: Solver::new(args.programs_dir),
+        queue: SolveQueue::new(max_solves, args.max_queued),
+        limiter: RateLimiter::new(args.rate_limit),
+        metrics: Metrics::default(),
+        rules: args.rules,
+        timeout: (args.timeout > 0.0).then(|| Duration::from_secs_f64(args.timeout)),
+    });
+
+    // Generate programs before accepting connections
+    for &cards in &args.warm {
+        let start = Instant::now();
+
+        if server.solver.warm(cards as usize) {
+            let programs = server
+                .solver
+                .generation_stats(cards as usize)
+                .map_or(0, |stats| stats.programs);
+
+            println!(
+                "Generated {programs} programs for {cards} cards in {}ms",
+                start.elapsed().as_millis()
+            );
+        }
+    }
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let server = Arc::clone(&server);
+
+                thread::spawn(move || connection(&server, stream));
+            }
+            Err(e) => eprintln!("Connection failed ({e})"),
+        }
+    }
+}
+
+/// Handles a connection
+fn connection(server: &Server, mut stream: TcpStream) {
+    let result = stream.peer_addr().and_then(|addr| {
+        let request = Request::read(&stream)?;
+
+        routes::handle(server, &mut stream, &request, addr.ip())
+    });
+
+    if let Err(e) = result {
+        eprintln!("Request failed ({e})");
+    }
+}
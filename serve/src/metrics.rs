@@ -0,0 +1,129 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the solve latency histogram buckets in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// Server metrics, reported in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    /// Requests received
+    requests: AtomicU64,
+    /// Requests refused by the rate limiter
+    rate_limited: AtomicU64,
+    /// Solve requests refused because the queue was full
+    queue_full: AtomicU64,
+    /// Solve requests abandoned because they took too long
+    timed_out: AtomicU64,
+    /// Solve latency histogram
+    latency: Mutex<Histogram>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Count of observations in each bucket (not cumulative)
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    pub fn request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn queue_full(&self) {
+        self.queue_full.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn timed_out(&self) {
+        self.timed_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the time taken by a solve
+    pub fn solved(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+
+        let mut latency = self.latency.lock().unwrap();
+
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| secs <= le) {
+            latency.buckets[bucket] += 1;
+        }
+
+        latency.sum += secs;
+        latency.count += 1;
+    }
+
+    /// Formats the metrics in the Prometheus text format
+    pub fn render(&self, running: usize, queued: usize) -> String {
+        let mut out = String::new();
+
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = write!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n"
+            );
+        };
+
+        counter(
+            "countdown_requests_total",
+            "Requests received",
+            self.requests.load(Ordering::Relaxed),
+        );
+        counter(
+            "countdown_rate_limited_total",
+            "Requests refused by the rate limiter",
+            self.rate_limited.load(Ordering::Relaxed),
+        );
+        counter(
+            "countdown_queue_full_total",
+            "Solve requests refused because the queue was full",
+            self.queue_full.load(Ordering::Relaxed),
+        );
+        counter(
+            "countdown_timed_out_total",
+            "Solve requests abandoned because they took too long",
+            self.timed_out.load(Ordering::Relaxed),
+        );
+
+        let mut gauge = |name: &str, help: &str, value: usize| {
+            let _ = write!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"
+            );
+        };
+
+        gauge("countdown_solves_running", "Solves running", running);
+        gauge(
+            "countdown_queue_depth",
+            "Solve requests waiting in the queue",
+            queued,
+        );
+
+        let latency = self.latency.lock().unwrap();
+
+        let name = "countdown_solve_duration_seconds";
+
+        let _ = writeln!(out, "# HELP {name} Time taken to solve a puzzle");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+
+        let mut cumulative = 0;
+
+        for (le, count) in LATENCY_BUCKETS.iter().zip(latency.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", latency.count);
+        let _ = writeln!(out, "{name}_sum {}", latency.sum);
+        let _ = writeln!(out, "{name}_count {}", latency.count);
+
+        out
+    }
+}
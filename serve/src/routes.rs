@@ -0,0 +1,435 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use minihttp::{respond, send_event, start_events, Request};
+use solver::{
+    normalise_cards, CancellationToken, GenerationStats, LazyPrograms, Programs, Ruleset, Solution,
+    CAPABILITIES, MAX_CARDS,
+};
+
+use crate::limits::{RateLimiter, SolveQueue};
+use crate::metrics::Metrics;
+
+/// Headers added to every response so pages on other sites can call the server
+const CORS_HEADERS: &[(&str, &str)] = &[("Access-Control-Allow-Origin", "*")];
+
+/// Shared server state
+pub struct Server {
+    pub solver: Solver,
+    pub queue: SolveQueue,
+    pub limiter: RateLimiter,
+    pub metrics: Metrics,
+    /// Rules used when a request doesn't give any
+    pub rules: Ruleset,
+    /// Maximum time to spend solving a puzzle for /solve
+    pub timeout: Option<Duration>,
+}
+
+/// Programs for each number of cards, generated when first needed
+pub struct Solver {
+    programs: LazyPrograms,
+    /// Statistics for the programs generated by this server
+    stats: [OnceLock<GenerationStats>; MAX_CARDS],
+    /// Directory holding saved programs
+    programs_dir: Option<PathBuf>,
+}
+
+impl Solver {
+    /// Creates the solver. Programs already saved in the programs directory are mapped straight
+    /// away, which is near instant
+    pub fn new(programs_dir: Option<PathBuf>) -> Self {
+        let solver = Self {
+            programs: LazyPrograms::new(),
+            stats: std::array::from_fn(|_| OnceLock::new()),
+            programs_dir,
+        };
+
+        if let Some(dir) = &solver.programs_dir {
+            for cards in 1..=MAX_CARDS as u8 {
+                if let Ok(mapped) = Programs::load_mapped(
+                    &dir.join(Programs::file_name(cards, false)),
+                    cards,
+                    false,
+                ) {
+                    let _ = solver.programs.set(cards as usize, mapped);
+                }
+            }
+        }
+
+        solver
+    }
+
+    /// Generates the programs for a number of cards if they haven't been already. Returns true if
+    /// they were generated
+    pub fn warm(&self, cards: usize) -> bool {
+        let mut generated = false;
+
+        self.programs.get_or_init(cards, || {
+            generated = true;
+            self.generate(cards)
+        });
+
+        generated
+    }
+
+    /// Returns the numbers of cards which have programs ready
+    pub fn warm_cards(&self) -> Vec<usize> {
+        (1..=MAX_CARDS)
+            .filter(|&cards| self.programs.get(cards).is_some())
+            .collect()
+    }
+
+    /// Returns the statistics for the programs for a number of cards if they were generated by this
+    /// server rather than mapped from the programs directory
+    pub fn generation_stats(&self, cards: usize) -> Option<&GenerationStats> {
+        self.stats[cards - 1].get()
+    }
+
+    /// Returns the programs for a number of cards
+    fn programs(&self, cards: usize) -> &Programs {
+        self.programs.get_or_init(cards, || self.generate(cards))
+    }
+
+    /// Generates the programs for a number of cards and keeps the generation statistics
+    fn generate(&self, cards: usize) -> Programs {
+        let (programs, stats) = Programs::new_with_stats(cards as u8, false);
+
+        let _ = self.stats[cards - 1].set(stats);
+
+        match &self.programs_dir {
+            Some(dir) => save_programs(dir, cards as u8, programs),
+            None => programs,
+        }
+    }
+}
+
+/// Saves the generated programs for a number of cards in the programs directory. The saved file is
+/// then mapped so the memory is shared with other processes using the directory
+fn save_programs(dir: &Path, cards: u8, programs: Programs) -> Programs {
+    let path = dir.join(Programs::file_name(cards, false));
+
+    if let Err(e) = fs::create_dir_all(dir).and_then(|_| programs.save(&path)) {
+        eprintln!("Unable to save programs to {} ({e})", path.display());
+        return programs;
+    }
+
+    Programs::load_mapped(&path, cards, false).unwrap_or(programs)
+}
+
+/// Handles a request
+pub fn handle(
+    server: &Server,
+    stream: &mut TcpStream,
+    request: &Request,
+    client: IpAddr,
+) -> io::Result<()> {
+    server.metrics.request();
+
+    if request.method != "GET" {
+        return respond_error(stream, 405, "Only GET requests are supported");
+    }
+
+    // Health and metrics endpoints aren't rate limited so monitoring keeps working
+    match request.path.as_str() {
+        "/health" => {
+            return respond(
+                stream,
+                200,
+                CORS_HEADERS,
+                "application/json",
+                "{\"status\":\"ok\"}",
+            )
+        }
+        "/metrics" => {
+            let (running, queued) = server.queue.depth();
+
+            return respond(
+                stream,
+                200,
+                CORS_HEADERS,
+                "text/plain; version=0.0.4",
+                &server.metrics.render(running, queued),
+            );
+        }
+        "/warmup" => {
+            // Admin endpoint, only available locally
+            if !client.is_loopback() {
+                return respond_error(stream, 403, "Only available from the local machine");
+            }
+
+            return warmup(server, stream, request);
+        }
+        _ => (),
+    }
+
+    if !server.limiter.allow(client) {
+        server.metrics.rate_limited();
+        return respond_error(stream, 429, "Too many requests");
+    }
+
+    let stream_solutions = match request.path.as_str() {
+        "/capabilities" => {
+            return respond(
+                stream,
+                200,
+                CORS_HEADERS,
+                "application/json",
+                &CAPABILITIES.to_json(),
+            )
+        }
+        "/solve" => false,
+        "/solve/stream" => true,
+        _ => return respond_error(stream, 404, "Not found"),
+    };
+
+    let (target, cards) = match parse_puzzle(request) {
+        Ok(puzzle) => puzzle,
+        Err(e) => return respond_error(stream, 400, &e),
+    };
+
+    let rules = match parse_rules(request, server.rules) {
+        Ok(rules) => rules,
+        Err(e) => return respond_error(stream, 400, &e),
+    };
+
+    // Wait for a free solve slot
+    let Some(_permit) = server.queue.acquire() else {
+        server.metrics.queue_full();
+        return respond_error(stream, 503, "Server busy");
+    };
+
+    let start = Instant::now();
+
+    let result = if stream_solutions {
+        solve_stream(&server.solver, stream, target, &cards, rules)
+    } else {
+        solve(server, stream, target, &cards, rules)
+    };
+
+    server.metrics.solved(start.elapsed());
+
+    result
+}
+
+/// Solves a puzzle and responds with all of the solutions, shortest first. The solve is abandoned
+/// if it takes longer than the server timeout
+fn solve(
+    server: &Server,
+    stream: &mut TcpStream,
+    target: u32,
+    cards: &[u32],
+    rules: Ruleset,
+) -> io::Result<()> {
+    let programs = server.solver.programs(cards.len());
+
+    let cancel = match server.timeout {
+        Some(timeout) => CancellationToken::with_deadline(Instant::now() + timeout),
+        None => CancellationToken::new(),
+    };
+
+    let Ok(mut solutions) =
+        programs.run_target_rules_cancellable(target, cards, rules.rules(), &cancel)
+    else {
+        server.metrics.timed_out();
+        return respond_error(stream, 503, "Solve timed out");
+    };
+
+    // Filter out identical equations (can happen when duplicate card is chosen)
+    programs.filter_identical(&mut solutions, cards);
+
+    // Sort solutions by shortest first
+    solutions.sort();
+
+    let equations = solutions
+        .iter()
+        .map(|s| json_string(&programs.infix(s.program, cards, false)))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let body = format!(
+        "{{\"target\":{target},\"cards\":{},\"rules\":\"{rules}\",\"solutions\":{},\"equations\":[{equations}]}}",
+        json_cards(cards),
+        solutions.len()
+    );
+
+    respond(stream, 200, CORS_HEADERS, "application/json", &body)
+}
+
+/// Solves a puzzle, streaming each solution as a server-sent event as soon as it is found.
+/// Programs using fewer cards are run first so the first solution arrives quickly. A 'done' event
+/// is sent when all of the programs have been run. Stops early if the client disconnects. With
+/// relaxed rules the solutions are sent when all of the programs have been run
+fn solve_stream(
+    solver: &Solver,
+    stream: &mut TcpStream,
+    target: u32,
+    cards: &[u32],
+    rules: Ruleset,
+) -> io::Result<()> {
+    let start = Instant::now();
+
+    start_events(stream, CORS_HEADERS)?;
+
+    let programs = solver.programs(cards.len());
+
+    let solutions: Box<dyn Iterator<Item = Solution>> = if rules.rules().is_strict() {
+        Box::new(programs.run_target_iter(target, cards))
+    } else {
+        Box::new(
+            programs
+                .run_target_rules(target, cards, rules.rules())
+                .into_iter(),
+        )
+    };
+
+    let mut rpn_set = HashSet::new();
+
+    for solution in solutions {
+        // Filter out identical equations (can happen when duplicate card is chosen)
+        if !rpn_set.insert(programs.rpn(solution.program, cards, false)) {
+            continue;
+        }
+
+        let data = format!(
+            "{{\"equation\":{},\"length\":{},\"operations\":{},\"largest_intermediate\":{},\"elapsed_ms\":{}}}",
+            json_string(&programs.infix(solution.program, cards, false)),
+            solution.length(),
+            solution.operations(),
+            solution.largest_intermediate(),
+            start.elapsed().as_millis()
+        );
+
+        send_event(stream, "solution", &data)?;
+    }
+
+    let data = format!(
+        "{{\"rules\":\"{rules}\",\"solutions\":{},\"elapsed_ms\":{}}}",
+        rpn_set.len(),
+        start.elapsed().as_millis()
+    );
+
+    send_event(stream, "done", &data)
+}
+
+/// Parses the target and cards query parameters
+fn parse_puzzle(request: &Request) -> Result<(u32, Vec<u32>), String> {
+    let target = request
+        .query
+        .get("target")
+        .ok_or("Missing target")?
+        .parse::<u32>()
+        .map_err(|e| format!("Invalid target: {e}"))?;
+
+    if target == 0 {
+        Err("Target must be greater than zero")?
+    }
+
+    let cards = request
+        .query
+        .get("cards")
+        .ok_or("Missing cards")?
+        .split(',')
+        .map(|c| c.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid card: {e}"))?;
+
+    if cards.is_empty() {
+        Err("No cards specified")?
+    }
+
+    if cards.len() > MAX_CARDS {
+        Err(format!("Maximum of {MAX_CARDS} cards allowed"))?
+    }
+
+    Ok((target, normalise_cards(&cards)))
+}
+
+/// Generates the programs for the numbers of cards in the cards query parameter (all if not given)
+/// and responds with the numbers of cards which have programs ready
+fn warmup(server: &Server, stream: &mut TcpStream, request: &Request) -> io::Result<()> {
+    let cards = match parse_card_counts(request) {
+        Ok(cards) => cards,
+        Err(e) => return respond_error(stream, 400, &e),
+    };
+
+    let start = Instant::now();
+
+    let generated = cards
+        .into_iter()
+        .filter(|&cards| server.solver.warm(cards))
+        .collect::<Vec<_>>();
+
+    let stats = generated
+        .iter()
+        .filter_map(|&cards| server.solver.generation_stats(cards))
+        .map(|stats| stats.to_json())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let body = format!(
+        "{{\"warm\":{},\"generated\":{},\"generation\":[{stats}],\"elapsed_ms\":{}}}",
+        json_cards(&server.solver.warm_cards()),
+        json_cards(&generated),
+        start.elapsed().as_millis()
+    );
+
+    respond(stream, 200, CORS_HEADERS, "application/json", &body)
+}
+
+/// Parses the cards query parameter of /warmup, a list of numbers of cards
+fn parse_card_counts(request: &Request) -> Result<Vec<usize>, String> {
+    let Some(cards) = request.query.get("cards") else {
+        return Ok((1..=MAX_CARDS).collect());
+    };
+
+    cards
+        .split(',')
+        .map(|c| match c.trim().parse::<usize>() {
+            Ok(cards) if (1..=MAX_CARDS).contains(&cards) => Ok(cards),
+            _ => Err(format!(
+                "Invalid number of cards '{c}' (must be 1 to {MAX_CARDS})"
+            )),
+        })
+        .collect()
+}
+
+/// Parses the rules query parameter
+fn parse_rules(request: &Request, default: Ruleset) -> Result<Ruleset, String> {
+    match request.query.get("rules") {
+        Some(rules) => rules.parse(),
+        None => Ok(default),
+    }
+}
+
+/// Responds with a JSON error message
+fn respond_error(stream: &mut TcpStream, status: u16, message: &str) -> io::Result<()> {
+    respond(
+        stream,
+        status,
+        CORS_HEADERS,
+        "application/json",
+        &format!("{{\"error\":{}}}", json_string(message)),
+    )
+}
+
+/// Formats cards (or numbers of cards) as a JSON array
+fn json_cards<T: ToString>(cards: &[T]) -> String {
+    format!(
+        "[{}]",
+        cards
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+/// Formats a string as a JSON string
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("String serialisation failed")
+}
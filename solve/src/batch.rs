@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use solver::{normalise_cards, DiskCache, LazyPrograms, Programs, Ruleset, MAX_CARDS};
+
+use crate::load_programs;
+use crate::validate::validate_puzzle;
+
+/// Solves each puzzle in a file (or stdin if the file is '-') and outputs the results as JSON lines.
+/// Each puzzle line is in the format 'target: cards...'. Blank lines and lines starting with '#' are ignored
+pub fn run_batch(
+    file: &str,
+    duplicated: bool,
+    rules: Ruleset,
+    any_cards: bool,
+    cache: Option<&DiskCache>,
+    programs_dir: Option<&Path>,
+) -> Result<(), i32> {
+    let reader: Box<dyn BufRead> = if file == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match File::open(file) {
+            Ok(f) => Box::new(BufReader::new(f)),
+            Err(e) => {
+                eprintln!("Unable to open {file}: {e}");
+                Err(1)?
+            }
+        }
+    };
+
+    // Programs for each number of cards, generated when first needed
+    let programs_map = LazyPrograms::new();
+
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Error reading {file}: {e}");
+                Err(1)?
+            }
+        };
+
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let puzzle = parse_puzzle(line).and_then(|(target, cards)| {
+            validate_puzzle(&cards, &[target], rules, any_cards)?;
+            Ok((target, cards))
+        });
+
+        match puzzle {
+            Ok((target, cards)) => {
+                let programs = programs_map.get_or_init(cards.len(), || {
+                    load_programs(programs_dir, cards.len() as u8, duplicated, false)
+                });
+
+                println!("{}", solve_puzzle(programs, target, &cards, rules, cache));
+            }
+            Err(e) => {
+                println!(
+                    "{{\"line\":{},\"error\":{}}}",
+                    line_no + 1,
+                    json_string(&format!("{e} in '{line}'"))
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a puzzle line in the format 'target: cards...'
+fn parse_puzzle(line: &str) -> Result<(u32, Vec<u32>), String> {
+    let (target, cards) = line
+        .split_once(':')
+        .ok_or_else(|| "Missing ':' after target".to_string())?;
+
+    let target = target
+        .trim()
+        .parse::<u32>()
+        .map_err(|e| format!("Invalid target: {e}"))?;
+
+    let cards = cards
+        .split_whitespace()
+        .map(|c| c.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid card: {e}"))?;
+
+    if cards.is_empty() {
+        Err("No cards specified".to_string())?
+    }
+
+    if cards.len() > MAX_CARDS {
+        Err(format!("Maximum of {MAX_CARDS} cards allowed"))?
+    }
+
+    Ok((target, normalise_cards(&cards)))
+}
+
+/// Solves a puzzle and returns the result as a JSON object
+fn solve_puzzle(
+    programs: &Programs,
+    target: u32,
+    cards: &[u32],
+    rules: Ruleset,
+    cache: Option<&DiskCache>,
+) -> String {
+    let mut solutions = match cache.and_then(|cache| cache.solutions(programs, target, cards)) {
+        Some(solutions) => solutions,
+        None => {
+            let solutions = programs.run_target_rules(target, cards, rules.rules());
+
+            if let Some(cache) = cache {
+                if let Err(e) = cache.put_solutions(programs, target, cards, &solutions) {
+                    eprintln!("Unable to write to cache ({e})");
+                }
+            }
+
+            solutions
+        }
+    };
+
+    // Filter out identical equations (can happen when duplicate card is chosen)
+    programs.filter_identical(&mut solutions, cards);
+
+    // Sort solutions by shortest first
+    solutions.sort();
+
+    let shortest = match solutions.first() {
+        Some(s) => json_string(&programs.infix(s.program, cards, false)),
+        None => "null".to_string(),
+    };
+
+    format!(
+        "{{\"target\":{target},\"cards\":[{}],\"rules\":\"{rules}\",\"solutions\":{},\"shortest\":{shortest}}}",
+        cards
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        solutions.len(),
+    )
+}
+
+/// Formats a string as a JSON string
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+
+    result.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+
+    result
+}
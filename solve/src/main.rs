@@ -1,92 +1,470 @@
-use std::collections::HashSet;
+mod batch;
+mod validate;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use bitflags::bitflags;
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use numformat::NumFormat;
-use solver::{Programs, Solution};
+use solver::{
+    apply_config, explain_unreachable, hints, normalise_cards, set_colour_output,
+    set_machine_output, set_symbol_set, Board, Config, Discrepancy, DiskCache, DupStats, Programs,
+    Ruleset, Solution, SymbolSet, Worksheet, CAPABILITIES, MAX_CARDS,
+};
+use validate::validate_puzzle;
+
+/// Maximum number of cards for --verify. The reference solver is too slow for more
+const MAX_VERIFY_CARDS: usize = 5;
+
+/// Exit code when the cards or targets aren't valid for the rules
+const EXIT_INVALID_PUZZLE: i32 = 3;
 
 fn main() {
     // Parse command line arguments
     let exit_code = match parse_args() {
+        Ok(args) if args.capabilities => {
+            println!("{}", CAPABILITIES.to_json());
+            0
+        }
         Ok(args) => {
             // Arguments ok
-            if args.verbose {
-                println!("Target {}, Cards {:?}", args.target, args.cards);
+            match open_cache(&args) {
+                Ok(cache) => {
+                    if let Some(file) = &args.batch {
+                        // Batch mode
+                        match batch::run_batch(
+                            file,
+                            args.duplicated,
+                            args.rules,
+                            args.any_cards,
+                            cache.as_ref(),
+                            args.programs_dir.as_deref(),
+                        ) {
+                            Ok(()) => 0,
+                            Err(code) => code,
+                        }
+                    } else if let Some(rpn) = &args.check {
+                        // Check an equation
+                        check(&args, rpn)
+                    } else if args.verify {
+                        // Check the programs against the reference solver
+                        verify(&args)
+                    } else {
+                        solve(&args, cache.as_ref())
+                    }
+                }
+                Err(code) => code,
             }
+        }
+        Err(code) => {
+            // Invalid arguments
+            code
+        }
+    };
+
+    process::exit(exit_code)
+}
+
+/// Returns the programs for a number of cards. With a programs directory the programs are mapped
+/// from the saved file, or generated and saved if the file doesn't exist or holds different programs
+fn load_programs(dir: Option<&Path>, cards: u8, inc_duplicated: bool, verbose: bool) -> Programs {
+    let Some(dir) = dir else {
+        return Programs::new(cards, inc_duplicated, verbose);
+    };
 
-            println!("Generating programs...");
-            let programs = Programs::new(args.cards.len() as u8, true, args.verbose);
+    let path = dir.join(Programs::file_name(cards, inc_duplicated));
 
-            println!("Running programs...");
-            let mut solutions = programs.run_all_target(args.target, &args.cards);
+    if let Ok(programs) = Programs::load_mapped(&path, cards, inc_duplicated) {
+        return programs;
+    }
+
+    let programs = Programs::new(cards, inc_duplicated, verbose);
+
+    if let Err(e) = fs::create_dir_all(dir).and_then(|_| programs.save(&path)) {
+        eprintln!("Unable to save programs to {} ({e})", path.display());
+    }
 
-            if args.verbose {
-                println!("{} total solutions found", solutions.len().num_format());
+    programs
+}
+
+fn open_cache(args: &Args) -> Result<Option<DiskCache>, i32> {
+    // Solutions found with relaxed rules aren't cached
+    if !args.rules.rules().is_strict() {
+        return Ok(None);
+    }
+
+    match &args.cache {
+        Some(dir) => match DiskCache::new(dir) {
+            Ok(cache) => Ok(Some(cache)),
+            Err(e) => {
+                eprintln!("Unable to open cache {} ({})", dir.display(), e);
+                Err(1)
             }
+        },
+        None => Ok(None),
+    }
+}
 
-            if solutions.is_empty() {
-                if !args.verbose {
-                    println!("== No solutions ==");
-                }
+fn solve(args: &Args, cache: Option<&DiskCache>) -> i32 {
+    if args.verbose {
+        println!("Targets {:?}, Cards {:?}", args.targets, args.cards);
+    }
+
+    let rules = args.rules.rules();
+
+    if args.rules != Ruleset::Tv {
+        println!("Rules: {} ({})", args.rules, rules.description());
+    }
+
+    let outside = args
+        .targets
+        .iter()
+        .filter(|target| !rules.targets().contains(target))
+        .count();
+
+    if outside > 0 {
+        println!(
+            "Warning: {outside} {} outside the range {}-{} for the {} rules",
+            if outside == 1 {
+                "target is"
             } else {
-                let mut rpn_set = HashSet::with_capacity(solutions.len());
-                let mut stack = Vec::new();
-                let mut set = HashSet::new();
-
-                let mut duplicate = 0;
-                let mut identical = 0;
-
-                solutions.retain(|s| {
-                    // Filter out duplicated solutions
-                    if !args.duplicated && programs.duplicated(s.program, &mut stack, &mut set) {
-                        duplicate += 1;
-                        return false;
-                    }
+                "targets are"
+            },
+            rules.min_target,
+            rules.max_target,
+            args.rules
+        );
+    }
 
-                    // Filter out identical equations (can happen when duplicate card is chosen)
-                    let rpn = programs.rpn(s.program, &args.cards, false);
+    if args.programs_dir.is_some() {
+        println!("Loading programs...");
+    } else {
+        println!("Generating programs...");
+    }
 
-                    if rpn_set.insert(rpn) {
-                        true
-                    } else {
-                        identical += 1;
-                        false
+    let programs = load_programs(
+        args.programs_dir.as_deref(),
+        args.cards.len() as u8,
+        true,
+        args.verbose,
+    );
+
+    // Get cached solutions
+    let mut solutions = args
+        .targets
+        .iter()
+        .map(|&target| cache.and_then(|cache| cache.solutions(&programs, target, &args.cards)))
+        .collect::<Vec<_>>();
+
+    let missing = args
+        .targets
+        .iter()
+        .zip(solutions.iter())
+        .filter(|(_, solutions)| solutions.is_none())
+        .map(|(target, _)| *target)
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        println!("Running programs...");
+
+        let mut found = if rules.is_strict() {
+            programs.run_all_targets(&missing, &args.cards)
+        } else {
+            missing
+                .iter()
+                .map(|&target| programs.run_target_rules(target, &args.cards, rules))
+                .collect()
+        }
+        .into_iter();
+
+        for (target, solutions) in args.targets.iter().zip(solutions.iter_mut()) {
+            if solutions.is_none() {
+                let target_solutions = found.next().unwrap();
+
+                if let Some(cache) = cache {
+                    if let Err(e) =
+                        cache.put_solutions(&programs, *target, &args.cards, &target_solutions)
+                    {
+                        eprintln!("Unable to write to cache ({e})");
                     }
-                });
+                }
+
+                *solutions = Some(target_solutions);
+            }
+        }
+    }
+
+    let solutions = solutions.into_iter().flatten();
+
+    // Canonical programs used to explain unreachable targets and find near misses, generated when
+    // first needed
+    let mut canonical = None;
+
+    let mut worksheet = Worksheet::new("Countdown worksheet");
+
+    for (target, solutions) in args.targets.iter().zip(solutions) {
+        if args.targets.len() > 1 {
+            println!("==== Target {target} ====");
+        }
+
+        let unreachable = solutions.is_empty();
+
+        let best = process_target(args, &programs, solutions);
+
+        if let (Some(best), Some(_)) = (best, &args.worksheet) {
+            let steps = programs.steps_rules(best, &args.cards, false, rules);
+
+            let metrics = programs.metrics(best, &args.cards);
+
+            worksheet.add(*target, &args.cards, steps, metrics, *target as u64);
+        }
+
+        if unreachable && args.why {
+            let canonical = canonical
+                .get_or_insert_with(|| Programs::new(args.cards.len() as u8, false, false));
 
-                if args.verbose {
-                    println!(
-                        "Filtered out {duplicate} duplicate and {identical} identical solutions"
-                    );
+            print_why(args, canonical, *target);
+        }
+
+        if args.near_misses {
+            let canonical = canonical
+                .get_or_insert_with(|| Programs::new(args.cards.len() as u8, false, false));
+
+            print_near_misses(args, canonical, *target);
+        }
+
+        if let Some(distance) = args.near {
+            let canonical = canonical
+                .get_or_insert_with(|| Programs::new(args.cards.len() as u8, false, false));
+
+            print_near(args, canonical, *target, distance);
+        }
+    }
+
+    match &args.worksheet {
+        Some(file) => write_worksheet(file, &worksheet),
+        None => 0,
+    }
+}
+
+/// Writes a worksheet to a file, as HTML if the file name ends .html or .htm, otherwise Markdown
+fn write_worksheet(file: &Path, worksheet: &Worksheet) -> i32 {
+    if worksheet.is_empty() {
+        eprintln!("No solutions to write to the worksheet");
+        return 1;
+    }
+
+    let html = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"));
+
+    let contents = if html {
+        worksheet.to_html()
+    } else {
+        worksheet.to_markdown()
+    };
+
+    if let Err(e) = fs::write(file, contents) {
+        eprintln!("Unable to write worksheet to {} ({e})", file.display());
+        return 2;
+    }
+
+    println!(
+        "Worksheet with {} {} written to {}",
+        worksheet.len(),
+        if worksheet.len() == 1 {
+            "puzzle"
+        } else {
+            "puzzles"
+        },
+        file.display()
+    );
+
+    0
+}
+
+/// Checks run_all_target for each target against the reference solver, with and without duplicated
+/// programs, listing any discrepancies. Returns 1 if any are found
+fn verify(args: &Args) -> i32 {
+    let mut found = 0;
+
+    for inc_duplicated in [false, true] {
+        let programs = Programs::new(args.cards.len() as u8, inc_duplicated, args.verbose);
+
+        let discrepancies = programs.verify_reference(&args.cards, args.targets.iter().copied());
+
+        println!(
+            "Programs {} duplicates ({} programs, {} targets): {} discrepancies",
+            if inc_duplicated { "with" } else { "without" },
+            programs.len(),
+            args.targets.len(),
+            discrepancies.len()
+        );
+
+        for discrepancy in &discrepancies {
+            match discrepancy {
+                Discrepancy::Missed(target) => {
+                    println!("  {target}: reached by the reference solver but not the programs")
                 }
+                Discrepancy::Unexpected(target) => {
+                    println!("  {target}: solved by the programs but not the reference solver")
+                }
+                Discrepancy::WrongAnswer { target, program } => println!(
+                    "  {target}: {} doesn't give the target",
+                    programs.infix(*program, &args.cards, false)
+                ),
+            }
+        }
 
-                println!(
-                    "{} {} found",
-                    solutions.len(),
-                    if solutions.len() == 1 {
-                        "solution"
-                    } else {
-                        "solutions"
-                    }
-                );
+        found += discrepancies.len();
+    }
+
+    if found == 0 {
+        0
+    } else {
+        1
+    }
+}
 
-                // Sort solutions by shortest first
-                solutions.sort();
+fn check(args: &Args, rpn: &str) -> i32 {
+    let board = Board::new(&args.cards);
 
-                // Output solutions
-                print_solutions(&args, &programs, &solutions);
+    let programs = match board.parse_rpn(rpn) {
+        Ok(programs) => programs,
+        Err(e) => {
+            eprintln!("Invalid equation '{rpn}': {e}");
+            return 1;
+        }
+    };
+
+    let numbers = board.numbers();
+    let rules = args.rules.rules();
+
+    println!("Equation: {}", programs.infix(0, numbers, true));
+
+    let answer = if rules.is_strict() {
+        programs.run(0, numbers).map_err(|e| e.to_string())
+    } else {
+        programs
+            .run_range_rules(numbers, 1..=u32::MAX, rules)
+            .first()
+            .map(|s| s.result)
+            .ok_or_else(|| {
+                format!(
+                    "The equation doesn't give a positive whole number with the {} rules",
+                    args.rules
+                )
+            })
+    };
+
+    match answer {
+        Ok(answer) => {
+            println!("Steps:");
+
+            for l in programs.steps_rules(0, numbers, true, rules) {
+                println!("  {l}");
+            }
+
+            for &target in &args.targets {
+                if answer == target {
+                    println!("Reaches {target}");
+                } else {
+                    println!("{} away from {target}", answer.abs_diff(target));
+                }
             }
 
             0
         }
-        Err(code) => {
-            // Invalid arguments
-            code
+        Err(e) => {
+            println!("{e}");
+            2
         }
-    };
+    }
+}
 
-    process::exit(exit_code)
+/// Outputs the solutions for a target, returning the program of the first solution listed
+fn process_target(args: &Args, programs: &Programs, mut solutions: Vec<Solution>) -> Option<usize> {
+    if args.verbose {
+        println!("{} total solutions found", solutions.len().num_format());
+    }
+
+    if let Some(min_cards) = args.min_cards {
+        // Filter out solutions using too few cards
+        let before = solutions.len();
+
+        solutions.retain(|s| s.cards_used() >= min_cards as usize);
+
+        if args.verbose {
+            println!(
+                "Filtered out {} solutions using fewer than {min_cards} cards",
+                before - solutions.len()
+            );
+        }
+    }
+
+    if solutions.is_empty() {
+        if !args.verbose {
+            println!("== No solutions ==");
+        }
+
+        None
+    } else {
+        // Filter out duplicated solutions
+        let dups = if args.duplicated {
+            DupStats::default()
+        } else {
+            programs.filter_duplicates(&mut solutions)
+        };
+
+        // Filter out identical equations (can happen when duplicate card is chosen)
+        let identical = programs.filter_identical(&mut solutions, &args.cards);
+
+        if args.verbose {
+            println!(
+                "Filtered out {} duplicate ({} with terms out of order, {} with the same equation) and {identical} identical solutions",
+                dups.total(),
+                dups.term_order,
+                dups.infix
+            );
+        }
+
+        let use_all = solutions
+            .iter()
+            .filter(|s| s.cards_used() == args.cards.len())
+            .count();
+
+        println!(
+            "{} {} found ({use_all} using all {} cards)",
+            solutions.len(),
+            if solutions.len() == 1 {
+                "solution"
+            } else {
+                "solutions"
+            },
+            args.cards.len()
+        );
+
+        // Sort solutions by shortest (fewest cards) and simplest first, or most cards first if requested
+        if args.most_cards {
+            solutions.sort_by(|a, b| b.cards_used().cmp(&a.cards_used()).then(a.cmp(b)));
+        } else {
+            programs.rank_solutions(&mut solutions, &args.cards);
+        }
+
+        if let Some(level) = args.hint {
+            // Output hints for the shortest solution
+            print_hints(args, programs, &solutions[0], level);
+        } else {
+            // Output solutions
+            print_solutions(args, programs, &solutions);
+        }
+
+        Some(solutions[0].program)
+    }
 }
 
 fn print_solutions(args: &Args, programs: &Programs, solutions: &[Solution]) {
@@ -95,8 +473,20 @@ fn print_solutions(args: &Args, programs: &Programs, solutions: &[Solution]) {
     let headings = num_outputs > 1 || args.output.contains(Output::STEPS);
 
     for (i, s) in solutions.iter().enumerate() {
+        // Shorter equivalent solution, eg. 75 × 2 for 75 × (6 + 2 - 6)
+        let simplified = if args.rules.rules().is_strict() {
+            programs.simplify_solution(s, &args.cards)
+        } else {
+            None
+        };
+
         if headings {
-            println!("== Solution {} ==", i + 1);
+            println!(
+                "== Solution {} (uses {} of {} cards) ==",
+                i + 1,
+                s.cards_used(),
+                args.cards.len()
+            );
         }
 
         if args.output.contains(Output::RPN) {
@@ -110,7 +500,19 @@ fn print_solutions(args: &Args, programs: &Programs, solutions: &[Solution]) {
             if num_outputs > 1 {
                 print!("Equation: ");
             }
-            println!("{}", programs.infix(s.program, &args.cards, true));
+            print!("{}", programs.infix(s.program, &args.cards, true));
+
+            match &simplified {
+                Some(simplified) if num_outputs > 1 => println!(
+                    "\nSimplified: {}",
+                    programs.infix(simplified.program, &args.cards, true)
+                ),
+                Some(simplified) => println!(
+                    " (simplifies to {})",
+                    programs.infix(simplified.program, &args.cards, true)
+                ),
+                None => println!(),
+            }
         }
 
         if args.output.contains(Output::FULLINFIX) {
@@ -124,7 +526,7 @@ fn print_solutions(args: &Args, programs: &Programs, solutions: &[Solution]) {
             if num_outputs > 1 {
                 println!("Steps:");
             }
-            for l in programs.steps(s.program, &args.cards, true) {
+            for l in programs.steps_rules(s.program, &args.cards, true, args.rules.rules()) {
                 if num_outputs > 1 {
                     print!("  ");
                 }
@@ -134,6 +536,129 @@ fn print_solutions(args: &Args, programs: &Programs, solutions: &[Solution]) {
     }
 }
 
+fn print_near_misses(args: &Args, programs: &Programs, target: u32) {
+    let mut near_misses = programs.run_target_near_misses(target, &args.cards);
+
+    // Filter out identical equations (can happen when duplicate card is chosen)
+    let mut rpn_set = HashSet::with_capacity(near_misses.len());
+
+    near_misses.retain(|n| rpn_set.insert(programs.rpn(n.solution.program, &args.cards, false)));
+    near_misses.sort_by(|a, b| a.solution.cmp(&b.solution));
+
+    println!(
+        "{} near {} breaking the rules at one step",
+        near_misses.len(),
+        if near_misses.len() == 1 {
+            "miss"
+        } else {
+            "misses"
+        }
+    );
+
+    for near_miss in &near_misses {
+        let program = near_miss.solution.program;
+        let steps = programs.steps_rules(program, &args.cards, true, Ruleset::Relaxed.rules());
+
+        println!(
+            "{} ({} needs {})",
+            programs.infix(program, &args.cards, true),
+            steps[near_miss.step],
+            near_miss.relaxation
+        );
+    }
+}
+
+/// Outputs the best equation for each answer within a distance of a target (but not reaching it),
+/// grouped by distance, closest first
+fn print_near(args: &Args, programs: &Programs, target: u32, distance: u32) {
+    let range = target.saturating_sub(distance)..=target.saturating_add(distance);
+
+    let mut solutions = programs.run_range_rules(&args.cards, range, args.rules.rules());
+
+    solutions.retain(|s| s.result != target);
+
+    if solutions.is_empty() {
+        println!("No answers within {distance} of {target}");
+        return;
+    }
+
+    // Sorted by answer then best first
+    programs.rank_solutions(&mut solutions, &args.cards);
+
+    // Best equation and number of equations for each answer, grouped by distance
+    let mut by_distance: BTreeMap<u32, Vec<(&Solution, usize)>> = BTreeMap::new();
+
+    for answer in solutions.chunk_by(|a, b| a.result == b.result) {
+        by_distance
+            .entry(answer[0].result.abs_diff(target))
+            .or_default()
+            .push((&answer[0], answer.len()));
+    }
+
+    println!("Nearest answers within {distance} of {target}:");
+
+    for (away, answers) in by_distance {
+        println!("== {away} away ==");
+
+        for (best, count) in answers {
+            println!(
+                "{} = {} ({count} {})",
+                programs.infix(best.program, &args.cards, true),
+                best.result,
+                if count == 1 { "equation" } else { "equations" }
+            );
+        }
+    }
+}
+
+fn print_why(args: &Args, programs: &Programs, target: u32) {
+    let Some(explanation) = explain_unreachable(programs, target, &args.cards) else {
+        return;
+    };
+
+    match (explanation.below, explanation.above) {
+        (None, None) => println!("No positive value can be reached"),
+        (below, above) => {
+            let desc = |value: Option<u32>| match value {
+                Some(value) => format!("{value} ({} away)", value.abs_diff(target)),
+                None => "none".to_string(),
+            };
+
+            println!(
+                "Nearest reachable: below {}, above {}",
+                desc(below),
+                desc(above)
+            );
+        }
+    }
+
+    for obstruction in &explanation.obstructions {
+        println!("{obstruction}");
+    }
+
+    match explanation.relaxation {
+        Some((relaxation, program)) => println!(
+            "Reachable allowing {relaxation}: {}",
+            programs.infix(program, &args.cards, true)
+        ),
+        None => {
+            println!("Not reachable even allowing negative and fractional intermediate results")
+        }
+    }
+}
+
+fn print_hints(args: &Args, programs: &Programs, solution: &Solution, level: u8) {
+    let hints = hints(programs, solution.program, &args.cards, true);
+
+    for (i, hint) in hints.iter().take(level as usize).enumerate() {
+        println!("Hint {}: {}", i + 1, hint);
+    }
+
+    if hints.len() < level as usize {
+        println!("No more hints available");
+    }
+}
+
 bitflags! {
     #[derive(Default, Debug)]
     struct Output: u8 {
@@ -171,31 +696,204 @@ struct Args {
     #[clap(skip)]
     output: Output,
 
+    /// Show hints up to this level (1-3) for the shortest solution instead of the solutions
+    #[clap(long = "hint", value_parser = clap::value_parser!(u8).range(1..=3))]
+    hint: Option<u8>,
+
+    /// Only show solutions using at least this many cards
+    #[clap(long = "min-cards", value_parser = clap::value_parser!(u8).range(1..=MAX_CARDS as i64), conflicts_with = "batch")]
+    min_cards: Option<u8>,
+
+    /// Only show solutions using all of the cards
+    #[clap(long = "use-all", action, conflicts_with_all = ["min_cards", "batch"])]
+    use_all: bool,
+
+    /// List solutions using the most cards first
+    #[clap(long = "most-cards", action, conflicts_with = "batch")]
+    most_cards: bool,
+
+    /// Explain why a target can't be reached
+    #[clap(short = 'w', long = "why", action)]
+    why: bool,
+
+    /// Also list equations reaching the target if one step may give a negative or fractional result
+    #[clap(long = "near-misses", action, conflicts_with = "batch")]
+    near_misses: bool,
+
+    /// Also list the best equation for each answer within this distance of the target, closest
+    /// first
+    #[clap(long = "near", value_parser, conflicts_with = "batch")]
+    near: Option<u32>,
+
+    /// Write a worksheet of the shortest solution to each target to this file, with the steps
+    /// scrambled and the results blanked out (HTML if the file name ends .html, otherwise Markdown)
+    #[clap(long = "worksheet", value_parser, conflicts_with_all = ["batch", "hint"])]
+    worksheet: Option<PathBuf>,
+
+    /// Check an equation written in RPN with card values (eg. '100 75 + 2 ×') instead of solving
+    #[clap(long = "check", value_parser, conflicts_with_all = ["batch", "hint", "near_misses", "near"])]
+    check: Option<String>,
+
+    /// Check the solutions for the targets against a naive reference solver (at most 5 cards)
+    #[clap(long = "verify", action, hide = true, conflicts_with_all = ["batch", "check"])]
+    verify: bool,
+
+    /// Rules to solve with (tv, junior or relaxed)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
+    /// Allow cards which aren't in the deck and targets outside the range of the rules (eg. for big
+    /// Countdown variants)
+    #[clap(long = "any-cards", action)]
+    any_cards: bool,
+
     /// Verbose output
     #[clap(short = 'v', long = "verbose", action)]
     verbose: bool,
 
-    // Target
-    target: u32,
+    /// Solve puzzles read from a file ('-' for stdin), one per line as 'target: cards...', outputting JSON lines
+    #[clap(short = 'b', long = "batch", value_parser, conflicts_with = "hint")]
+    batch: Option<String>,
+
+    /// Cache solutions in this directory
+    #[clap(long = "cache", value_parser)]
+    cache: Option<PathBuf>,
+
+    /// Directory to save generated programs in and map them from, shared with other processes
+    #[clap(long = "programs-dir", value_parser)]
+    programs_dir: Option<PathBuf>,
+
+    /// Format numbers as plain digits whatever the locale and don't colour the output
+    #[clap(long = "machine", action)]
+    machine: bool,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action)]
+    capabilities: bool,
+
+    /// Config file giving default options, layered over the user's config file
+    #[clap(long = "config", value_parser)]
+    config: Option<PathBuf>,
+
+    // Target, target range (eg. 100-110) or list of targets (eg. 256,512,768)
+    #[clap(required_unless_present_any = ["batch", "capabilities"])]
+    target: Option<String>,
+
+    /// Targets to solve
+    #[clap(skip)]
+    targets: Vec<u32>,
 
     // Cards chosen
-    cards: Vec<u8>,
+    cards: Vec<u32>,
 }
 
 fn parse_args() -> Result<Args, i32> {
     // Parse command line arguments
-    let mut args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    if args.capabilities {
+        return Ok(args);
+    }
+
+    // Fill in defaults from the config files
+    let config = match Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            Err(1)?
+        }
+    };
+
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+    apply_config(&mut args.rules, given("rules"), config.rules);
+    apply_config(&mut args.machine, given("machine"), config.machine);
+    apply_config(&mut args.symbols, given("symbols"), config.symbols);
+
+    set_colour_output(config.colour.unwrap_or(true));
+    set_machine_output(args.machine);
+    set_symbol_set(args.symbols);
+
+    if args.batch.is_some() {
+        if args.target.is_some() {
+            eprintln!("Target and cards can't be given in batch mode");
+            Err(1)?
+        }
+
+        return Ok(args);
+    }
 
     if args.cards.is_empty() {
         eprintln!("No cards specified");
         Err(1)?
     }
 
-    if args.cards.len() > 6 {
-        eprintln!("Maximum of 6 cards allowed");
+    if args.cards.len() > MAX_CARDS {
+        eprintln!("Maximum of {MAX_CARDS} cards allowed");
+        Err(1)?
+    }
+
+    if args
+        .min_cards
+        .is_some_and(|min_cards| min_cards as usize > args.cards.len())
+    {
+        eprintln!(
+            "Minimum number of cards is more than the {} given",
+            args.cards.len()
+        );
+        Err(1)?
+    }
+
+    if args.hint.is_some() && !args.rules.rules().is_strict() {
+        eprintln!("Hints aren't available with the {} rules", args.rules);
         Err(1)?
     }
 
+    if args.verify {
+        if !args.rules.rules().is_strict() {
+            eprintln!("Solutions can't be verified with the {} rules", args.rules);
+            Err(1)?
+        }
+
+        if args.cards.len() > MAX_VERIFY_CARDS {
+            eprintln!("Maximum of {MAX_VERIFY_CARDS} cards allowed when verifying");
+            Err(1)?
+        }
+    }
+
+    if args.near_misses && !args.rules.rules().is_strict() {
+        eprintln!("Near misses aren't available with the {} rules", args.rules);
+        Err(1)?
+    }
+
+    if args.use_all {
+        args.min_cards = Some(args.cards.len() as u8);
+    }
+
+    // Results don't depend on the order of the cards
+    args.cards = normalise_cards(&args.cards);
+
+    // Parse the targets
+    let target = args.target.as_deref().unwrap_or_default();
+
+    args.targets = match parse_targets(target) {
+        Ok(targets) => targets,
+        Err(e) => {
+            eprintln!("Invalid target '{target}': {e}");
+            Err(1)?
+        }
+    };
+
+    if let Err(e) = validate_puzzle(&args.cards, &args.targets, args.rules, args.any_cards) {
+        eprintln!("{e}");
+        Err(EXIT_INVALID_PUZZLE)?
+    }
+
     // Convert arg booleans to bitmask
     if args.infix {
         args.output |= Output::INFIX
@@ -220,3 +918,34 @@ fn parse_args() -> Result<Args, i32> {
 
     Ok(args)
 }
+
+fn parse_targets(target: &str) -> Result<Vec<u32>, String> {
+    let mut targets = Vec::new();
+
+    for part in target.split(',') {
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<u32>()
+                .map_err(|e| format!("'{}' {e}", s.trim()))
+        };
+
+        if let Some((from, to)) = part.split_once('-') {
+            let from = parse(from)?;
+            let to = parse(to)?;
+
+            if from > to {
+                Err(format!("range {from}-{to} is empty"))?
+            }
+
+            targets.extend(from..=to);
+        } else {
+            targets.push(parse(part)?);
+        }
+    }
+
+    // Remove repeated targets
+    let mut seen = HashSet::new();
+    targets.retain(|t| seen.insert(*t));
+
+    Ok(targets)
+}
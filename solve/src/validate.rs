@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use solver::Ruleset;
+
+/// Checks that the cards and targets of a puzzle are valid for the rules. Cards must be dealt from
+/// the deck of the rules and targets must be in the target range of the rules, unless any cards are
+/// allowed. Cards and targets must always be positive
+pub fn validate_puzzle(
+    cards: &[u32],
+    targets: &[u32],
+    rules: Ruleset,
+    any_cards: bool,
+) -> Result<(), String> {
+    if cards.contains(&0) {
+        Err("Cards must be positive".to_string())?
+    }
+
+    if targets.contains(&0) {
+        Err("Targets must be positive".to_string())?
+    }
+
+    if any_cards {
+        return Ok(());
+    }
+
+    // Count the cards of each value in the deck
+    let mut in_deck = HashMap::new();
+
+    for card in rules.rules().deck() {
+        *in_deck.entry(card).or_insert(0) += 1;
+    }
+
+    let mut used = HashMap::new();
+
+    for &card in cards {
+        let Some(&deck_count) = in_deck.get(&card) else {
+            return Err(format!(
+                "Card {card} isn't in the {rules} deck (use --any-cards to allow any cards)"
+            ));
+        };
+
+        let count = used.entry(card).or_insert(0);
+        *count += 1;
+
+        if *count > deck_count {
+            Err(format!(
+                "Too many {card} cards, the {rules} deck only has {deck_count} (use --any-cards to allow any cards)"
+            ))?
+        }
+    }
+
+    let range = rules.rules().targets();
+
+    if let Some(target) = targets.iter().find(|target| !range.contains(target)) {
+        Err(format!(
+            "Target {target} is outside the range {}-{} for the {rules} rules (use --any-cards to allow any target)",
+            range.start(),
+            range.end()
+        ))?
+    }
+
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_puzzle() {
+        // Valid puzzles
+        assert!(validate_puzzle(&[100, 75, 8, 8, 3, 1], &[523], Ruleset::Tv, false).is_ok());
+        assert!(validate_puzzle(&[50, 25, 20, 1], &[100, 500], Ruleset::Junior, false).is_ok());
+
+        // Cards not in the deck
+        assert_eq!(
+            validate_puzzle(&[100, 11], &[523], Ruleset::Tv, false),
+            Err("Card 11 isn't in the tv deck (use --any-cards to allow any cards)".to_string())
+        );
+        assert!(validate_puzzle(&[100, 2], &[523], Ruleset::Junior, false).is_err());
+
+        // Too many of a card
+        assert_eq!(
+            validate_puzzle(&[100, 100, 2], &[523], Ruleset::Tv, false),
+            Err(
+                "Too many 100 cards, the tv deck only has 1 (use --any-cards to allow any cards)"
+                    .to_string()
+            )
+        );
+        assert!(validate_puzzle(&[3, 3, 3], &[523], Ruleset::Tv, false).is_err());
+
+        // Targets outside the range
+        assert_eq!(
+            validate_puzzle(&[100, 2], &[99], Ruleset::Tv, false),
+            Err("Target 99 is outside the range 100-999 for the tv rules (use --any-cards to allow any target)".to_string())
+        );
+        assert!(validate_puzzle(&[50, 2], &[501], Ruleset::Junior, false).is_err());
+
+        // Any cards
+        assert!(validate_puzzle(&[1000, 250, 100, 7, 3, 1], &[4357], Ruleset::Tv, true).is_ok());
+        assert!(validate_puzzle(&[3, 3, 3], &[1], Ruleset::Tv, true).is_ok());
+
+        // Zero is never valid
+        assert!(validate_puzzle(&[0, 2], &[523], Ruleset::Tv, true).is_err());
+        assert!(validate_puzzle(&[100, 2], &[0], Ruleset::Tv, true).is_err());
+    }
+}
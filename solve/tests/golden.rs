@@ -0,0 +1,120 @@
+//! Golden output tests. The solve binary is run on small fixed puzzles and its output is compared
+//! with the files in tests/golden, so changes to the output formats are noticed.
+//!
+//! After an intended change to the output run the tests with UPDATE_GOLDEN=1 to rewrite the golden
+//! files, and review the differences before committing them.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs solve with machine output and returns what it wrote to stdout
+fn run_solve(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_solve"))
+        .arg("--machine")
+        .args(args)
+        .output()
+        .expect("Failed to run solve");
+
+    assert!(
+        output.status.success(),
+        "solve {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).expect("Output is not UTF-8")
+}
+
+/// Returns the path of a file in the tests directory
+fn test_path(rel: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(rel)
+}
+
+/// Compares output with a golden file, or rewrites the golden file if UPDATE_GOLDEN is set
+fn check_golden(name: &str, actual: &str) {
+    let path = test_path(&format!("golden/{name}"));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).expect("Failed to write golden file");
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read {} ({e})", path.display()));
+
+    assert!(
+        expected == actual,
+        "Output differs from {}\n--- expected\n{expected}\n--- actual\n{actual}",
+        path.display()
+    );
+}
+
+#[test]
+fn test_infix() {
+    check_golden("infix.txt", &run_solve(&["120", "10", "4", "3", "2"]));
+}
+
+#[test]
+fn test_all_outputs() {
+    check_golden(
+        "all.txt",
+        &run_solve(&["-r", "-i", "-f", "-s", "120", "10", "4", "3", "2"]),
+    );
+}
+
+#[test]
+fn test_ascii_symbols() {
+    check_golden(
+        "ascii.txt",
+        &run_solve(&["--symbols", "ascii", "-r", "-i", "120", "10", "4", "3", "2"]),
+    );
+}
+
+#[test]
+fn test_why() {
+    check_golden("why.txt", &run_solve(&["--why", "999", "4", "3", "2", "1"]));
+}
+
+#[test]
+fn test_near() {
+    check_golden(
+        "near.txt",
+        &run_solve(&["--near", "3", "123", "10", "4", "3", "2"]),
+    );
+}
+
+#[test]
+fn test_batch() {
+    let puzzles = test_path("fixtures/puzzles.txt");
+
+    check_golden(
+        "batch.jsonl",
+        &run_solve(&["--batch", puzzles.to_str().unwrap()]),
+    );
+}
+
+#[test]
+fn test_invalid_puzzle() {
+    // Cards not in the deck and targets out of range are rejected unless any cards are allowed
+    for args in [
+        &["523", "100", "100", "3"][..],
+        &["523", "11", "3"],
+        &["1000", "100", "3"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_solve"))
+            .args(args)
+            .output()
+            .expect("Failed to run solve");
+
+        assert_eq!(
+            Some(3),
+            output.status.code(),
+            "solve {args:?} wasn't rejected"
+        );
+    }
+
+    run_solve(&["--any-cards", "1000", "100", "11", "3"]);
+}
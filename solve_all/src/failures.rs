@@ -0,0 +1,232 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+/// File in the output directory listing the card combinations which failed
+pub const FAILURES_FILE: &str = "failures.json";
+
+thread_local! {
+    /// Backtrace of the last panic on this thread
+    static BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A card combination which couldn't be solved or written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub cards: Vec<u32>,
+    pub error: String,
+    pub backtrace: Option<String>,
+}
+
+/// Failures collected from the solver threads
+#[derive(Default)]
+pub struct Failures {
+    failures: Mutex<Vec<Failure>>,
+}
+
+impl Failures {
+    /// Records a failure
+    pub fn record(&self, failure: Failure) {
+        self.failures.lock().unwrap().push(failure);
+    }
+
+    /// Keeps the failures of an earlier run for the card combinations which weren't attempted again
+    /// (matched by card_key), so they stay listed until they are solved and written
+    pub fn keep_unattempted(&self, earlier: Vec<Failure>, attempted: &HashSet<Vec<u32>>) {
+        self.failures.lock().unwrap().extend(
+            earlier
+                .into_iter()
+                .filter(|failure| !attempted.contains(&failure.cards)),
+        );
+    }
+
+    /// Returns the failures in card order
+    pub fn into_sorted(self) -> Vec<Failure> {
+        let mut failures = self.failures.into_inner().unwrap();
+
+        failures.sort_by(|a, b| b.cards.cmp(&a.cards));
+
+        failures
+    }
+}
+
+/// Installs a panic hook recording the backtrace of each panic for the failures file, as well as
+/// reporting the panic as usual
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        BACKTRACE.with(|backtrace| {
+            *backtrace.borrow_mut() = Some(Backtrace::force_capture().to_string())
+        });
+
+        default_hook(info);
+    }));
+}
+
+/// Runs the solving of a card combination, catching a panic and returning it as a failure
+pub fn catch<T>(cards: &[u32], f: impl FnOnce() -> T) -> Result<T, Failure> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| Failure {
+        cards: cards.to_vec(),
+        error: panic_message(payload.as_ref()),
+        backtrace: BACKTRACE.with(|backtrace| backtrace.borrow_mut().take()),
+    })
+}
+
+/// Returns the message of a panic
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
+
+/// Writes the failures to the output directory, or removes the failures file if there are none
+pub fn write_failures(dir: &Path, failures: &[Failure]) -> io::Result<()> {
+    let path = dir.join(FAILURES_FILE);
+
+    if failures.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+
+    let json = failures
+        .iter()
+        .map(|failure| {
+            json!({
+                "cards": failure.cards,
+                "error": failure.error,
+                "backtrace": failure.backtrace,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    fs::write(path, serde_json::to_string_pretty(&json)? + "\n")
+}
+
+/// Returns the cards of a card combination in descending order, so combinations can be matched
+/// with the failures file whatever order the deck was given in
+pub fn card_key(cards: &[u32]) -> Vec<u32> {
+    let mut cards = cards.to_vec();
+
+    cards.sort_by(|a, b| b.cmp(a));
+
+    cards
+}
+
+/// Reads the card combinations which failed from the failures file in the output directory. The
+/// cards of each are in descending order (see card_key)
+pub fn read_failures(dir: &Path) -> io::Result<Vec<Failure>> {
+    let path = dir.join(FAILURES_FILE);
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid {FAILURES_FILE}"),
+        )
+    };
+
+    let json = serde_json::from_str::<Value>(&fs::read_to_string(path)?)?;
+
+    json.as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|failure| {
+            let cards = failure["cards"]
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|card| card.as_u64().and_then(|card| u32::try_from(card).ok()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)?;
+
+            Ok(Failure {
+                cards: card_key(&cards),
+                error: failure["error"].as_str().unwrap_or_default().to_string(),
+                backtrace: failure["backtrace"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn test_catch() {
+        assert_eq!(Ok(3), catch(&[1, 2], || 1 + 2));
+
+        let failure = catch(&[9, 8, 3], || -> u32 { panic!("Solver bug") }).unwrap_err();
+
+        assert_eq!(vec![9, 8, 3], failure.cards);
+        assert_eq!("Solver bug", failure.error);
+    }
+
+    #[test]
+    fn test_failures_file() {
+        let dir = env::temp_dir().join(format!("solve_all-failures-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let failures = Failures::default();
+
+        failures.record(Failure {
+            cards: vec![9, 8, 3],
+            error: "Solver bug".to_string(),
+            backtrace: Some("backtrace".to_string()),
+        });
+        failures.record(Failure {
+            cards: vec![75, 9, 5],
+            error: "Disk full".to_string(),
+            backtrace: None,
+        });
+
+        let failures = failures.into_sorted();
+
+        assert_eq!(vec![75, 9, 5], failures[0].cards);
+
+        write_failures(&dir, &failures).unwrap();
+
+        let earlier = read_failures(&dir).unwrap();
+
+        assert_eq!(failures, earlier);
+
+        // Only failures of card combinations which weren't attempted again are kept
+        let failures = Failures::default();
+
+        failures.keep_unattempted(earlier, &HashSet::from([vec![75, 9, 5]]));
+
+        assert_eq!(
+            vec![vec![9, 8, 3]],
+            failures
+                .into_sorted()
+                .into_iter()
+                .map(|failure| failure.cards)
+                .collect::<Vec<_>>()
+        );
+
+        // No failures removes the file
+        write_failures(&dir, &[]).unwrap();
+
+        assert!(!dir.join(FAILURES_FILE).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,76 @@
+mod failures;
+mod numa;
+mod outcomes;
+mod runlog;
+mod sink;
+mod space;
+mod template;
+mod timings;
+mod verify;
+
 use std::collections::{HashSet, VecDeque};
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::BufWriter;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{fs, io, thread};
 
-use cards::{get_default_cards, get_special_cards};
-use clap::Parser;
+use cards::{get_special_cards, CardSet};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use failures::{Failure, Failures};
 use itertools::Itertools;
+use numa::{format_cpu_list, Topology};
 use numformat::NumFormat;
-use solver::Programs;
+use outcomes::{Outcomes, OUTCOME_LABELS};
+use runlog::{Completion, RunLog, RUN_LOG_FILE};
+use sink::{
+    create_sink, scratch_stats, CardResults, EqnSort, ResultSink, RetrySink, SinkOptions, SinkType,
+};
+use solver::{
+    apply_config, set_colour_output, set_machine_output, set_symbol_set, Config, Programs, Results,
+    Ruleset, ScratchStats, SplitMix64, SymbolSet, CAPABILITIES, PROGRAM_ORDER_VERSION,
+};
+use template::{expand_template, today, TemplateValues};
+use timings::{format_time, Timings, PERCENTILES};
+
+/// Prints progress messages to stdout, or to stderr when results are streamed to stdout
+macro_rules! progress {
+    ($args:expr, $($arg:tt)*) => {
+        if $args.sink == SinkType::Stdout {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Prints the start of a progress message line, to be finished by progress!
+macro_rules! progress_start {
+    ($args:expr, $($arg:tt)*) => {
+        if $args.sink == SinkType::Stdout {
+            eprint!($($arg)*);
+            io::stderr().flush().unwrap();
+        } else {
+            print!($($arg)*);
+            io::stdout().flush().unwrap();
+        }
+    };
+}
+
+/// File in the output directory recording the details of a sample
+const SAMPLE_FILE: &str = "sample.txt";
+
+/// File in the output directory recording the collection of programs which generated the results
+const PROGRAMS_FILE: &str = "programs.txt";
+
+/// File in the output directory recording the number of card combinations the last run covers
+const COMBINATIONS_FILE: &str = "combinations.txt";
+
+/// Queue of numbered card combinations waiting to be solved
+type CardQueue = Arc<Mutex<VecDeque<(usize, Vec<u32>)>>>;
 
 // Structure to hold parsed command line arguments
 
@@ -20,58 +81,232 @@ struct Args {
     #[clap(short = 'e', long = "equations", action)]
     output_equations: bool,
 
+    /// Only write the first of the equations which are the same after rearranging the terms
+    /// (when duplicated equations are included)
+    #[clap(long = "eqn-unique", requires = "output_equations", action)]
+    eqn_unique: bool,
+
+    /// Order of the equations in results files
+    #[clap(long = "eqn-sort", value_enum, default_value_t = EqnSort::Result, requires = "output_equations")]
+    eqn_sort: EqnSort,
+
     /// Number of threads to run
     #[clap(short, long, default_value_t = num_cpus::get(), value_parser)]
     threads: usize,
 
-    /// Output directory
-    #[clap(short = 'o', long = "outdir", value_parser)]
+    /// Pin each thread to a CPU core
+    #[clap(long = "pin", action)]
+    pin: bool,
+
+    /// Pin threads to the cores of each NUMA node in turn and give each node its own copy of the
+    /// programs (implies --pin)
+    #[clap(long = "numa", action)]
+    numa: bool,
+
+    /// Output directory. May contain the placeholders {cards}, {rules}, {date} and {ops}
+    #[clap(short = 'o', long = "outdir", env = "SOLVE_ALL_OUTDIR", value_parser)]
     out_dir: Option<PathBuf>,
 
+    /// Where to store the results
+    #[clap(long = "sink", value_enum, default_value_t = SinkType::Text)]
+    sink: SinkType,
+
+    /// Write a record of each card combination completed to this file, in card combination order
+    /// whatever order the threads finish them in (defaults to run.log in the output directory)
+    #[clap(long = "run-log", value_parser)]
+    run_log: Option<PathBuf>,
+
+    /// Number of times to retry writing the results of a card combination before leaving it to be
+    /// retried at the end of the run
+    #[clap(long = "write-retries", default_value_t = 3, value_parser)]
+    write_retries: u32,
+
+    /// Milliseconds to wait before retrying a failed write, doubled for each further retry
+    #[clap(long = "retry-delay", default_value_t = 1000, value_parser)]
+    retry_delay: u64,
+
+    /// Only solve the card combinations listed in failures.json in the output directory by an
+    /// earlier run
+    #[clap(long = "retry-failures", conflicts_with = "sample", action)]
+    retry_failures: bool,
+
+    /// Record the programs for an output directory holding results but no programs.txt (eg. from
+    /// before programs.txt was written). Only use this if the results were generated by the same
+    /// programs
+    #[clap(long = "adopt-results", action)]
+    adopt_results: bool,
+
+    /// Number of the slowest card combinations to list at the end of the run
+    #[clap(long = "slowest", default_value_t = 10, value_parser)]
+    slowest: usize,
+
+    /// Only store the solution map of each card set, in a single file (the same as --sink map)
+    #[clap(long = "map-only", conflicts_with_all = ["sink", "output_equations"], action)]
+    map_only: bool,
+
     /// Use special cards
     #[clap(short = 's', long = "special", action)]
     special_cards: bool,
 
+    /// Rules giving the deck (tv or junior)
+    #[clap(long = "rules", default_value_t = Ruleset::Tv, value_parser)]
+    rules: Ruleset,
+
     /// Card set in use
     #[clap(skip)]
-    cards: &'static [u8],
+    cards: Vec<u32>,
 
     /// Include duplicated equations
     #[clap(short = 'd', long = "duplicated", action)]
     inc_duplicated: bool,
 
+    /// Filter duplicated equations by generating all equations and rejecting duplicates
+    /// (slow, used to verify the canonical equation generation)
+    #[clap(
+        short = 'f',
+        long = "filtered",
+        action,
+        conflicts_with = "inc_duplicated"
+    )]
+    filtered: bool,
+
+    /// Abandon equations as soon as an intermediate result is too large to give an answer in range
+    #[clap(short = 'p', long = "prune", action)]
+    prune: bool,
+
+    /// Number of cards to pick for each card combination
+    #[clap(short = 'n', long = "pick", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pick: u8,
+
+    /// Solve a random sample of this many card combinations instead of all of them
+    #[clap(long = "sample", value_parser = clap::value_parser!(u64).range(1..))]
+    sample: Option<u64>,
+
+    /// Random seed for choosing the sample (defaults to the current time)
+    #[clap(long = "seed", requires = "sample", value_parser)]
+    seed: Option<u64>,
+
+    /// Verify the duplicate filter for this number of cards instead of solving
+    #[clap(long = "verify-dedup", value_parser = clap::value_parser!(u8).range(1..=5))]
+    verify_dedup: Option<u8>,
+
+    /// Maximum number of card assignments to check when verifying the duplicate filter
+    #[clap(long = "verify-samples", default_value_t = 2000, value_parser)]
+    verify_samples: usize,
+
+    /// Format numbers in equations as plain digits whatever the locale
+    #[clap(long = "machine", action)]
+    machine: bool,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
     /// Verbose output
     #[clap(short, long, action)]
     verbose: bool,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action)]
+    capabilities: bool,
+
+    /// Config file giving default options, layered over the user's config file
+    #[clap(long = "config", value_parser)]
+    config: Option<PathBuf>,
 }
 
 fn main() {
     // Parse command line arguments
     let args = parse_args();
 
+    if args.capabilities {
+        println!("{}", CAPABILITIES.to_json());
+        return;
+    }
+
+    if let Some(nums) = args.verify_dedup {
+        let ok = verify::verify_dedup(
+            nums,
+            args.filtered,
+            &args.cards,
+            args.verify_samples.max(1),
+            args.verbose,
+        );
+
+        if !ok {
+            std::process::exit(2);
+        }
+
+        return;
+    }
+
+    let sink = match create_sink(
+        args.sink,
+        args.out_dir.as_deref(),
+        SinkOptions {
+            output_equations: args.output_equations,
+            eqn_unique: args.eqn_unique,
+            eqn_sort: args.eqn_sort,
+            inc_duplicated: args.inc_duplicated,
+            prune: args.prune,
+            rules: args.rules,
+        },
+    ) {
+        Ok(sink) => sink,
+        Err(e) => {
+            eprintln!("Unable to create result sink ({e})");
+            std::process::exit(1);
+        }
+    };
+
     // Generate RPN equations
     if !args.verbose {
-        print!("Generating programs...");
-        io::stdout().flush().unwrap();
+        progress_start!(args, "Generating programs...");
     } else {
-        println!("Generating programs...");
+        progress!(args, "Generating programs...");
     }
 
-    let programs = Programs::new(6, args.inc_duplicated, args.verbose);
+    let programs = if args.filtered {
+        Programs::new_filtered(args.pick, args.verbose)
+    } else {
+        Programs::new(args.pick, args.inc_duplicated, args.verbose)
+    };
 
     if !args.verbose {
-        println!(" {} programs generated", programs.len().num_format());
+        progress!(args, " {} programs generated", programs.len().num_format());
     }
 
+    // Make sure existing results were generated by the same programs
+    if let Some(dir) = output_dir(&args) {
+        let has_results = || {
+            args.cards
+                .iter()
+                .copied()
+                .combinations(args.pick as usize)
+                .any(|numbers| sink.exists(&numbers))
+        };
+
+        if !check_programs_file(dir, &programs, args.rules, has_results, args.adopt_results) {
+            std::process::exit(1);
+        }
+    }
+
+    // Card combinations which failed in an earlier run are solved again even if results exist
+    // (matched by card_key)
+    let earlier_failures = read_earlier_failures(&args);
+    let retrying = earlier_failures
+        .iter()
+        .map(|failure| failure.cards.clone())
+        .collect::<HashSet<_>>();
+
     // Generate card combinations
-    print!("Generating card combinations...");
-    io::stdout().flush().unwrap();
+    progress_start!(args, "Generating card combinations...");
 
     let card_combs = Arc::new(Mutex::new({
-        let mut card_combs: VecDeque<Vec<u8>> = VecDeque::new();
-        let mut hash: HashSet<Vec<&u8>> = HashSet::new();
+        let mut card_combs: VecDeque<Vec<u32>> = VecDeque::new();
+        let mut hash: HashSet<Vec<&u32>> = HashSet::new();
 
-        for choice in args.cards.iter().combinations(6) {
+        for choice in args.cards.iter().combinations(args.pick as usize) {
             if !hash.contains(&choice) {
                 let numbers = choice.iter().map(|x| **x).collect();
                 hash.insert(choice);
@@ -79,32 +314,196 @@ fn main() {
             }
         }
 
-        println!(" {} card combinations generated", card_combs.len());
+        progress!(args, " {} card combinations generated", card_combs.len());
+
+        // Make sure every set of cards has been generated once
+        let expected = CardSet::new(&args.cards).combination_count(args.pick as usize);
+
+        if card_combs.len() != expected {
+            eprintln!(
+                "{} card combinations generated, expected {} for the deck",
+                card_combs.len(),
+                expected
+            );
+            std::process::exit(1);
+        }
+
+        if args.retry_failures {
+            card_combs.retain(|numbers| retrying.contains(&failures::card_key(numbers)));
+
+            progress!(
+                args,
+                "Retrying {} card combinations which failed",
+                card_combs.len()
+            );
+        } else if let (Some(sample), Some(seed)) = (args.sample, args.seed) {
+            let population = card_combs.len();
+
+            card_combs = sample_combs(card_combs, sample as usize, seed);
+
+            progress!(
+                args,
+                "Sampling {} of {} card combinations (seed {seed})",
+                card_combs.len(),
+                population
+            );
+
+            if let Some(dir) = output_dir(&args) {
+                write_sample_file(dir, card_combs.len(), population, seed);
+            }
+        } else if let Some(dir) = output_dir(&args) {
+            // Results in this directory are no longer only from a sample
+            let _ = fs::remove_file(dir.join(SAMPLE_FILE));
+        }
+
+        // The combinations being solved are still those of the run which failed
+        if let Some(dir) = output_dir(&args).filter(|_| !args.retry_failures) {
+            write_combinations_file(dir, card_combs.len(), args.pick, &args.cards);
+        }
 
-        card_combs
+        // Number the card combinations for the run log
+        card_combs.into_iter().enumerate().collect::<VecDeque<_>>()
     }));
 
+    // Retry failed writes
+    let sink = RetrySink::new(
+        sink.as_ref(),
+        args.write_retries,
+        Duration::from_millis(args.retry_delay),
+    );
+
+    // Make sure there's room for the results
+    if let Some(dir) = output_dir(&args) {
+        check_free_space(&args, dir, &card_combs.lock().unwrap(), &sink, &retrying);
+    }
+
+    let run_log = create_run_log(&args);
+
+    // Catch panics solving a card combination so the others can carry on
+    failures::install_panic_hook();
+
+    let failures = Failures::default();
+
+    // Earlier failures stay recorded until their card combinations are solved again
+    let attempted = card_combs
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, numbers)| failures::card_key(numbers))
+        .collect::<HashSet<_>>();
+
+    failures.keep_unattempted(earlier_failures, &attempted);
+
     // Run solver threads
-    run_solve_threads(&args, card_combs, &programs);
+    let start = Instant::now();
+
+    let (big_outcomes, timings) = run_solve_threads(
+        &args,
+        card_combs,
+        &programs,
+        &sink,
+        &retrying,
+        run_log.as_ref(),
+        &failures,
+    );
+
+    // Try the card combinations which couldn't be written again
+    retry_failed_writes(&args, &programs, &sink, &failures);
+
+    let failures = failures.into_sorted();
+
+    report_failures(&args, &failures);
+
+    if let Some(run_log) = run_log {
+        if let Err(e) = run_log.finish() {
+            eprintln!("Error writing run log ({e})");
+        }
+    }
+
+    // Output program outcome telemetry
+    output_outcomes(&args, &big_outcomes, start);
+
+    // Output the time taken to solve each card combination
+    output_timings(&args, &timings);
+
+    if !failures.is_empty() {
+        std::process::exit(3);
+    }
 }
 
 fn parse_args() -> Args {
-    let mut args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Fill in defaults from the config files
+    let config = match Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let given = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+
+    apply_config(&mut args.threads, given("threads"), config.threads);
+    apply_config(
+        &mut args.out_dir,
+        given("out_dir"),
+        config.output_dir.map(Some),
+    );
+    apply_config(
+        &mut args.special_cards,
+        given("special_cards"),
+        config.special_cards,
+    );
+    apply_config(&mut args.rules, given("rules"), config.rules);
+    apply_config(&mut args.machine, given("machine"), config.machine);
+    apply_config(&mut args.symbols, given("symbols"), config.symbols);
+
+    set_colour_output(config.colour.unwrap_or(true));
+    set_machine_output(args.machine);
+    set_symbol_set(args.symbols);
+
+    if args.map_only {
+        args.sink = SinkType::Map;
+    }
 
     // Sanitise number of threads
     if args.threads == 0 {
         args.threads = 1;
     }
 
+    // Choose a seed for the sample if not given
+    if args.sample.is_some() && args.seed.is_none() {
+        args.seed = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("System time before epoch")
+                .as_nanos() as u64,
+        );
+    }
+
+    // Results are found with the TV rules for intermediate results
+    if !args.rules.rules().is_strict() {
+        eprintln!("Results can't be generated with the {} rules", args.rules);
+        std::process::exit(1);
+    }
+
     // Get card set
     args.cards = if args.special_cards {
-        get_special_cards()
+        get_special_cards().to_vec()
     } else {
-        get_default_cards()
+        args.rules.rules().deck()
     };
 
     // Make sure we have a valid output path
-    if !create_out_dir(&mut args) {
+    if args.verify_dedup.is_none() && args.sink.needs_dir() && !create_out_dir(&mut args) {
         std::process::exit(1);
     }
 
@@ -114,20 +513,58 @@ fn parse_args() -> Args {
 fn create_out_dir(args: &mut Args) -> bool {
     let mut ok = true;
 
-    if args.out_dir.is_none() {
-        // Create default directory name
-        let comm_str = if args.inc_duplicated { "C" } else { "NC" };
-
-        args.out_dir = Some(
-            format!(
-                "solutions-{}-{}",
-                comm_str,
-                args.cards.iter().map(|c| c.to_string()).join("-")
-            )
-            .into(),
-        );
+    // Equations generated
+    let ops_str = if args.inc_duplicated {
+        "C"
+    } else if args.filtered {
+        "NCF"
+    } else {
+        "NC"
     };
 
+    let cards_str = args.cards.iter().map(|c| c.to_string()).join("-");
+
+    match &args.out_dir {
+        None => {
+            // Create default directory name
+
+            // Include the number of cards picked if not a full game
+            let pick_str = if args.pick == 6 {
+                String::new()
+            } else {
+                format!("P{}-", args.pick)
+            };
+
+            // Include the sample size and seed if sampling
+            let sample_str = match (args.sample, args.seed) {
+                (Some(sample), Some(seed)) => format!("S{sample}s{seed}-"),
+                _ => String::new(),
+            };
+
+            args.out_dir =
+                Some(format!("solutions-{ops_str}-{pick_str}{sample_str}{cards_str}").into());
+        }
+        Some(out_dir) => {
+            // Fill in the placeholders of a templated directory
+            if let Some(template) = out_dir.to_str() {
+                let values = TemplateValues {
+                    cards: cards_str,
+                    rules: args.rules.name().to_string(),
+                    date: today(),
+                    ops: ops_str.to_string(),
+                };
+
+                match expand_template(template, &values) {
+                    Ok(dir) => args.out_dir = Some(dir.into()),
+                    Err(e) => {
+                        eprintln!("Invalid output directory ({e})");
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
     // Convert to Path
     let path = args.out_dir.as_ref().unwrap().as_path();
 
@@ -139,8 +576,8 @@ fn create_out_dir(args: &mut Args) -> bool {
             ok = false;
         }
     } else {
-        // Try and create the directory
-        if let Err(e) = fs::create_dir(path) {
+        // Try and create the directory (and any parents a template adds)
+        if let Err(e) = fs::create_dir_all(path) {
             eprintln!("Error creating {} ({})", path.display(), e);
             ok = false;
         }
@@ -149,35 +586,178 @@ fn create_out_dir(args: &mut Args) -> bool {
     ok
 }
 
-fn file_paths(args: &Args, numbers: &[u8]) -> (PathBuf, PathBuf) {
-    let nums_str = numbers.iter().map(|n| format!("{n}")).join("-");
+/// Returns the output directory if the result sink writes to it
+fn output_dir(args: &Args) -> Option<&Path> {
+    args.out_dir.as_deref().filter(|_| args.sink.needs_dir())
+}
+
+/// Chooses a random sample of card combinations without replacement, in their original order
+fn sample_combs(card_combs: VecDeque<Vec<u32>>, sample: usize, seed: u64) -> VecDeque<Vec<u32>> {
+    let mut rng = SplitMix64(seed);
+    let mut indexes = (0..card_combs.len()).collect::<Vec<_>>();
+    let sample = sample.min(indexes.len());
 
-    let file_name = format!("{nums_str}.txt");
-    let mut file_path = args.out_dir.clone().unwrap();
-    file_path.push(file_name);
+    // Partial Fisher-Yates shuffle
+    for i in 0..sample {
+        let j = i + rng.below(indexes.len() - i);
+        indexes.swap(i, j);
+    }
 
-    let eqn_file_name = format!("{nums_str}-eqn.txt");
-    let mut eqn_file_path = args.out_dir.clone().unwrap();
-    eqn_file_path.push(eqn_file_name);
+    let chosen = indexes[..sample].iter().copied().collect::<HashSet<_>>();
 
-    (file_path, eqn_file_path)
+    card_combs
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, numbers)| chosen.contains(&i).then_some(numbers))
+        .collect()
 }
 
-fn needs_calculating(args: &Args, file_path: &PathBuf, eqn_file_path: &PathBuf) -> bool {
-    // Already calculated this set?
-    if !Path::new(file_path).exists() {
-        return true;
-    };
+/// Records the sample details in the output directory so stats can report confidence intervals
+fn write_sample_file(dir: &Path, sample: usize, population: usize, seed: u64) {
+    let path = dir.join(SAMPLE_FILE);
+
+    if let Err(e) = fs::write(
+        &path,
+        format!("sample size: {sample}\npopulation: {population}\nseed: {seed}\n"),
+    ) {
+        eprintln!("Error writing {} ({})", path.display(), e);
+    }
+}
+
+/// Creates the run log, in the output directory unless a file is given
+fn create_run_log(args: &Args) -> Option<RunLog<BufWriter<File>>> {
+    let path = args
+        .run_log
+        .clone()
+        .or_else(|| output_dir(args).map(|dir| dir.join(RUN_LOG_FILE)))?;
+
+    match RunLog::create(&path) {
+        Ok(run_log) => Some(run_log),
+        Err(e) => {
+            eprintln!("Error creating run log {} ({})", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Records the number of card combinations to be solved, and the number of cards picked from the
+/// deck, so stats can estimate the work remaining and check the card combinations are all there
+fn write_combinations_file(dir: &Path, combinations: usize, pick: u8, deck: &[u32]) {
+    let path = dir.join(COMBINATIONS_FILE);
+
+    if let Err(e) = fs::write(
+        &path,
+        format!(
+            "combinations: {combinations}\npick: {pick}\ndeck: {}\n",
+            deck.iter().join("-")
+        ),
+    ) {
+        eprintln!("Error writing {} ({})", path.display(), e);
+    }
+}
+
+/// Checks the programs and rules which generated any existing results in the output directory
+/// match, and records them if there are none. Returns false if they don't match
+fn check_programs_file(
+    dir: &Path,
+    programs: &Programs,
+    rules: Ruleset,
+    has_results: impl FnOnce() -> bool,
+    adopt: bool,
+) -> bool {
+    let path = dir.join(PROGRAMS_FILE);
+    let fingerprint = programs.fingerprint_hex();
+
+    if let Ok(contents) = fs::read_to_string(&path) {
+        let field = |name: &str| {
+            contents
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|f| f.trim())
+        };
+
+        let existing = field("fingerprint:");
+
+        if existing != Some(fingerprint.as_str()) {
+            eprintln!(
+                "Results in {} were generated by different programs (fingerprint {}, now {fingerprint}), use another output directory",
+                dir.display(),
+                existing.unwrap_or("missing"),
+            );
+
+            return false;
+        }
+
+        // Results written before rules were recorded used the TV rules
+        let existing_rules = field("rules:").unwrap_or(Ruleset::Tv.name());
+
+        if existing_rules != rules.name() {
+            eprintln!(
+                "Results in {} were generated with the {existing_rules} rules, use another output directory",
+                dir.display(),
+            );
+
+            return false;
+        }
+    } else {
+        // Results without a programs file may be from other programs, so aren't added to unless
+        // asked to
+        if !adopt && has_results() {
+            eprintln!(
+                "Results in {} have no {PROGRAMS_FILE} so the programs which generated them are unknown, use another output directory or --adopt-results if they were generated by the same programs",
+                dir.display(),
+            );
+
+            return false;
+        }
 
-    if args.output_equations && !Path::new(eqn_file_path).exists() {
-        return true;
+        if let Err(e) = fs::write(
+            &path,
+            format!(
+                "program order version: {PROGRAM_ORDER_VERSION}\nprograms: {}\nfingerprint: {fingerprint}\nrules: {rules}\n",
+                programs.len()
+            ),
+        ) {
+            eprintln!("Error writing {} ({})", path.display(), e);
+        }
     }
 
-    false
+    true
 }
 
-fn run_solve_threads(args: &Args, card_combs: Arc<Mutex<VecDeque<Vec<u8>>>>, programs: &Programs) {
-    println!("Starting {} threads...", args.threads);
+fn run_solve_threads(
+    args: &Args,
+    card_combs: CardQueue,
+    programs: &Programs,
+    sink: &dyn ResultSink,
+    retrying: &HashSet<Vec<u32>>,
+    run_log: Option<&RunLog<BufWriter<File>>>,
+    failures: &Failures,
+) -> (Vec<Outcomes>, Timings) {
+    // Find the cores to pin the threads to
+    let topology = (args.pin || args.numa).then(|| Topology::detect(args.numa));
+
+    if args.verbose {
+        if let Some(topology) = &topology {
+            for node in 0..topology.nodes() {
+                progress!(
+                    args,
+                    "Node {node}: Cores {}",
+                    format_cpu_list(topology.cores(node))
+                );
+            }
+        }
+    }
+
+    // Copy the programs to each NUMA node
+    let replicas = match &topology {
+        Some(topology) if args.numa && topology.nodes() > 1 => {
+            replicate_programs(args, topology, programs)
+        }
+        _ => Vec::new(),
+    };
+
+    progress!(args, "Starting {} threads...", args.threads);
 
     // Start thread scope
     thread::scope(|thread_scope| {
@@ -188,6 +768,12 @@ fn run_solve_threads(args: &Args, card_combs: Arc<Mutex<VecDeque<Vec<u8>>>>, pro
             // Clone reference to card combinations
             let thread_card_combs = card_combs.clone();
 
+            // Choose the node and core to run on, and the programs for the node
+            let (node, core) = topology
+                .as_ref()
+                .map_or((0, None), |topology| topology.place(thread_no));
+            let programs = replicas.get(node).unwrap_or(programs);
+
             // Start a thread
             let handle = thread::Builder::new()
                 .name(format!("{}", thread_no + 1))
@@ -195,25 +781,104 @@ fn run_solve_threads(args: &Args, card_combs: Arc<Mutex<VecDeque<Vec<u8>>>>, pro
                     let thread = thread::current();
                     let thread_name = thread.name().unwrap();
 
+                    if let Some(core) = core {
+                        if !numa::pin(core) {
+                            eprintln!("Thread {thread_name:4<}: Unable to pin to core {core}");
+                        }
+                    }
+
                     if args.verbose {
-                        println!("Thread {thread_name:4<}: Started");
+                        match core {
+                            Some(core) => progress!(
+                                args,
+                                "Thread {thread_name:4<}: Started on core {core} (node {node})"
+                            ),
+                            None => progress!(args, "Thread {thread_name:4<}: Started"),
+                        }
                     }
 
-                    // Get next card selection
-                    while let Some(numbers) = thread_card_combs.lock().unwrap().pop_front() {
-                        let (file_path, eqn_file_path) = file_paths(args, &numbers);
+                    // Program outcomes by number of big numbers
+                    let mut big_outcomes = vec![Outcomes::default(); 7];
+
+                    // Time taken to solve each card combination
+                    let mut timings = Timings::default();
 
-                        if needs_calculating(args, &file_path, &eqn_file_path) {
-                            // Run all equations for this card selection
-                            println!("Thread {thread_name:4<}: Calculating {numbers:?}...");
+                    let thread_start = Instant::now();
+                    let mut solved = 0;
 
-                            solve(args, programs, &numbers, &file_path, &eqn_file_path);
+                    // Get next card selection
+                    while let Some((index, numbers)) = thread_card_combs.lock().unwrap().pop_front() {
+                        // Already calculated this set? Sets which failed before are always solved
+                        if !retrying.contains(&failures::card_key(&numbers)) && sink.exists(&numbers) {
+                            log_completion(run_log, thread_name, index, &numbers, Completion::Skipped);
+                            continue;
                         }
+
+                        // Run all equations for this card selection
+                        progress!(args, "Thread {thread_name:4<}: Calculating {numbers:?}...");
+
+                        let comb_start = Instant::now();
+
+                        // A panic solving or writing the card combination is recorded as a failure
+                        let solved_comb = failures::catch(&numbers, || {
+                            let results = solve(args, programs, &numbers);
+
+                            let written = sink.write(&CardResults {
+                                numbers: &numbers,
+                                programs,
+                                results: &results,
+                            });
+
+                            (results, written)
+                        });
+
+                        let comb_elapsed = comb_start.elapsed();
+
+                        let results = match solved_comb {
+                            Ok((results, Ok(()))) => results,
+                            Ok((results, Err(e))) => {
+                                eprintln!("Thread {thread_name:4<}: Error writing results for {numbers:?} ({e})");
+                                results
+                            }
+                            Err(failure) => {
+                                eprintln!("Thread {thread_name:4<}: Failed solving {numbers:?} ({})", failure.error);
+                                failures.record(failure);
+                                log_completion(run_log, thread_name, index, &numbers, Completion::Failed);
+                                continue;
+                            }
+                        };
+
+                        log_completion(
+                            run_log,
+                            thread_name,
+                            index,
+                            &numbers,
+                            Completion::Solved {
+                                elapsed: comb_elapsed,
+                                solutions: results.solutions.len(),
+                            },
+                        );
+
+                        timings.add(&numbers, comb_elapsed);
+
+                        let big_cnt = CardSet::big_count(&numbers);
+                        big_outcomes[big_cnt].add(&results);
+
+                        solved += 1;
                     }
 
+                    let elapsed = thread_start.elapsed();
+
                     if args.verbose {
-                        println!("Thread {thread_name:4<}: Finished");
+                        progress!(
+                            args,
+                            "Thread {thread_name:4<}: Finished, {solved} card combinations in {:.2}s ({:.2}/s)",
+                            elapsed.as_secs_f64(),
+                            solved as f64 / elapsed.as_secs_f64()
+                        );
                     }
+
+                    (big_outcomes, timings, node, solved, elapsed, scratch_stats())
                 })
                 .unwrap();
 
@@ -221,80 +886,401 @@ fn run_solve_threads(args: &Args, card_combs: Arc<Mutex<VecDeque<Vec<u8>>>>, pro
             handles.push(handle);
         }
 
-        // Wait for all threads to finish
+        // Wait for all threads to finish and merge the program outcomes
+        let mut big_outcomes = vec![Outcomes::default(); 7];
+        let mut timings = Timings::default();
+        let mut node_solved = vec![(0, Duration::ZERO); replicas.len().max(1)];
+        let mut scratch_stats = ScratchStats::default();
+
         for handle in handles {
-            handle.join().unwrap();
+            let (thread_outcomes, thread_timings, node, solved, elapsed, thread_scratch) =
+                handle.join().unwrap();
+
+            scratch_stats.merge(&thread_scratch);
+            timings.merge(thread_timings);
+
+            for (total, outcomes) in big_outcomes.iter_mut().zip(thread_outcomes) {
+                total.merge(&outcomes);
+            }
+
+            if let Some((node_count, node_elapsed)) = node_solved.get_mut(node) {
+                *node_count += solved;
+                *node_elapsed = (*node_elapsed).max(elapsed);
+            }
+        }
+
+        // Report the throughput of each NUMA node
+        if args.verbose && !replicas.is_empty() {
+            for (node, (solved, elapsed)) in node_solved.iter().enumerate() {
+                progress!(
+                    args,
+                    "Node {node}: {solved} card combinations in {:.2}s ({:.2}/s)",
+                    elapsed.as_secs_f64(),
+                    *solved as f64 / elapsed.as_secs_f64()
+                );
+            }
+        }
+
+        // Report the allocations saved by the scratch space of the threads
+        if args.verbose && scratch_stats.without_scratch() > 0 {
+            progress!(
+                args,
+                "Scratch space: {} allocations ({} without scratch space): groups {} allocated / {} reused, strings {} allocated / {} reused",
+                scratch_stats.allocated().num_format(),
+                scratch_stats.without_scratch().num_format(),
+                scratch_stats.groups_allocated.num_format(),
+                scratch_stats.groups_reused.num_format(),
+                scratch_stats.strings_allocated.num_format(),
+                scratch_stats.strings_reused.num_format()
+            );
         }
-    });
+
+        (big_outcomes, timings)
+    })
 }
 
-fn solve(
+/// Warns if the file system holding the output directory may not have room for the results of the
+/// card combinations still to be solved
+fn check_free_space(
     args: &Args,
-    programs: &Programs,
-    numbers: &[u8],
-    file_path: &PathBuf,
-    eqn_file_path: &PathBuf,
+    dir: &Path,
+    card_combs: &VecDeque<(usize, Vec<u32>)>,
+    sink: &dyn ResultSink,
+    retrying: &HashSet<Vec<u32>>,
 ) {
-    // Run all of the programs for this set of numbers
-    let results = programs.run_all(numbers);
+    let Some(set_size) = space::estimated_set_size(args.sink, args.output_equations) else {
+        return;
+    };
 
-    // Count the number of solutions for each target number
-    let mut sol_cnt: [u32; 900] = [0; 900];
+    let free = match space::free_space(dir) {
+        Ok(free) => free,
+        Err(e) => {
+            if args.verbose {
+                progress!(
+                    args,
+                    "Unable to find the free space in {} ({e})",
+                    dir.display()
+                );
+            }
 
-    for solution in results.solutions.iter() {
-        sol_cnt[solution.result as usize - 100] += 1;
-    }
+            return;
+        }
+    };
 
-    // Create a solutions map string where '#' is > 0 and '.' = 0
-    let sol_map: String = sol_cnt
+    let to_solve = card_combs
         .iter()
-        .map(|x| if *x > 0 { '#' } else { '.' })
-        .collect();
+        .filter(|(_, numbers)| {
+            retrying.contains(&failures::card_key(numbers)) || !sink.exists(numbers)
+        })
+        .count();
 
-    // Create a string listing all of the target numbers with the number of solutions
-    let sol_cnt_str = sol_cnt
-        .iter()
-        .enumerate()
-        .map(|(i, c)| format!("{}={}", i + 100, c))
-        .join(", ");
-
-    // Count how many target numbers have > 0 solutions
-    let covered = sol_cnt.iter().filter(|&&c| c > 0).count();
-
-    // Create the output file
-    let mut file = File::create(file_path).unwrap();
-
-    // Write details to the output file
-    writeln!(&mut file, "solution map: {sol_map}").unwrap();
-    writeln!(&mut file, "solution coverage: {covered}").unwrap();
-    writeln!(&mut file, "solution counts: {sol_cnt_str}").unwrap();
-    writeln!(&mut file, "results: {}", results.solutions.len()).unwrap();
-    writeln!(&mut file, "zero intermediate: {}", results.zero).unwrap();
-    writeln!(&mut file, "negative intermediate: {}", results.negative).unwrap();
-    writeln!(&mut file, "div by zero: {}", results.div_zero).unwrap();
-    writeln!(&mut file, "non-integer: {}", results.non_integer).unwrap();
-    writeln!(&mut file, "multiply by 1: {}", results.mult_by_1).unwrap();
-    writeln!(&mut file, "divide by 1: {}", results.div_by_1).unwrap();
-    writeln!(&mut file, "< 100: {}", results.under_range).unwrap();
-    writeln!(&mut file, "> 999: {}", results.above_range).unwrap();
-    writeln!(
-        &mut file,
-        "duplicates included: {}",
-        if args.inc_duplicated { "Yes" } else { "No" }
-    )
-    .unwrap();
-
-    if args.output_equations {
-        // Write all equations to the equation output file
-        let mut eqn_file = File::create(eqn_file_path).unwrap();
-
-        for solution in results.solutions.iter().sorted() {
-            writeln!(
-                &mut eqn_file,
-                "{}",
-                programs.infix(solution.program, numbers, false)
-            )
-            .unwrap();
+    let needed = to_solve as u64 * set_size;
+
+    if needed > free {
+        eprintln!(
+            "Warning: {} card combinations may need around {} MB but only {} MB is free in {}",
+            to_solve.num_format(),
+            (needed / 1_000_000).num_format(),
+            (free / 1_000_000).num_format(),
+            dir.display()
+        );
+    }
+}
+
+/// Solves and writes the card combinations whose results couldn't be written during the run again,
+/// recording those which fail again. If the sink can't undo a failed write they are recorded as
+/// failures without writing again
+fn retry_failed_writes(args: &Args, programs: &Programs, sink: &RetrySink, failures: &Failures) {
+    let failed = sink.take_failed();
+
+    if failed.is_empty() {
+        return;
+    }
+
+    // Writing again could duplicate part of the results already written
+    if !sink.retryable() {
+        for (cards, error) in failed {
+            failures.record(Failure {
+                cards,
+                error,
+                backtrace: None,
+            });
+        }
+
+        return;
+    }
+
+    progress!(
+        args,
+        "Retrying {} card combinations which couldn't be written...",
+        failed.len()
+    );
+
+    for (numbers, _) in &failed {
+        let written = failures::catch(numbers, || {
+            let results = solve(args, programs, numbers);
+
+            sink.write(&CardResults {
+                numbers,
+                programs,
+                results: &results,
+            })
+        });
+
+        match written {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => eprintln!("Error writing results for {numbers:?} ({e})"),
+            Err(failure) => failures.record(failure),
+        }
+    }
+
+    for (cards, error) in sink.take_failed() {
+        failures.record(Failure {
+            cards,
+            error,
+            backtrace: None,
+        });
+    }
+}
+
+/// Reads the failures recorded by an earlier run in the output directory. With --retry-failures
+/// the failures file must exist, otherwise there may be none
+fn read_earlier_failures(args: &Args) -> Vec<Failure> {
+    let Some(dir) = output_dir(args) else {
+        if args.retry_failures {
+            eprintln!("--retry-failures needs a sink writing to the output directory");
+            std::process::exit(1);
         }
+
+        return Vec::new();
+    };
+
+    match failures::read_failures(dir) {
+        Ok(failures) => failures,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !args.retry_failures => Vec::new(),
+        Err(e) => {
+            eprintln!(
+                "Unable to read {} ({e})",
+                dir.join(failures::FAILURES_FILE).display()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Reports the card combinations which couldn't be solved or written, and lists them in the
+/// failures file in the output directory so they can be retried with --retry-failures
+fn report_failures(args: &Args, failures: &[Failure]) {
+    if !failures.is_empty() {
+        eprintln!(
+            "{} card combinations failed: {}",
+            failures.len(),
+            failures
+                .iter()
+                .map(|failure| format!("{:?}", failure.cards))
+                .join(", ")
+        );
+    }
+
+    if let Some(dir) = output_dir(args) {
+        if let Err(e) = failures::write_failures(dir, failures) {
+            eprintln!(
+                "Error writing {} ({e})",
+                dir.join(failures::FAILURES_FILE).display()
+            );
+        }
+    }
+}
+
+/// Records the completion of a card combination in the run log if there is one
+fn log_completion(
+    run_log: Option<&RunLog<BufWriter<File>>>,
+    thread_name: &str,
+    index: usize,
+    numbers: &[u32],
+    completion: Completion,
+) {
+    if let Some(run_log) = run_log {
+        if let Err(e) = run_log.record(index, numbers, completion) {
+            eprintln!("Thread {thread_name:4<}: Error writing run log for {numbers:?} ({e})");
+        }
+    }
+}
+
+/// Copies the programs to each NUMA node. Each copy is made by a thread pinned to the node so the
+/// memory is allocated on that node
+fn replicate_programs(args: &Args, topology: &Topology, programs: &Programs) -> Vec<Programs> {
+    thread::scope(|thread_scope| {
+        let handles = (0..topology.nodes())
+            .map(|node| {
+                let core = topology.cores(node)[0];
+
+                thread_scope.spawn(move || {
+                    numa::pin(core);
+
+                    let start = Instant::now();
+                    let replica = programs.clone();
+
+                    (replica, start.elapsed())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .enumerate()
+            .map(|(node, handle)| {
+                let (replica, elapsed) = handle.join().unwrap();
+
+                if args.verbose {
+                    progress!(
+                        args,
+                        "Node {node}: Programs copied in {:.2}ms",
+                        elapsed.as_secs_f64() * 1000.0
+                    );
+                }
+
+                replica
+            })
+            .collect()
+    })
+}
+
+fn output_outcomes(args: &Args, big_outcomes: &[Outcomes], start: Instant) {
+    let mut total = Outcomes::default();
+
+    for outcomes in big_outcomes {
+        total.merge(outcomes);
+    }
+
+    if total.combinations == 0 {
+        // Nothing calculated in this run
+        return;
+    }
+
+    progress!(args, "");
+    progress!(
+        args,
+        "Solved {} card combinations in {:.2}s",
+        total.combinations.num_format(),
+        start.elapsed().as_secs_f64()
+    );
+
+    progress!(args, "");
+    progress!(args, "Program Outcomes");
+    progress!(
+        args,
+        "Big Numbers, Combinations, Programs, {}",
+        OUTCOME_LABELS.join(", ")
+    );
+
+    for (i, outcomes) in big_outcomes.iter().enumerate() {
+        if outcomes.combinations > 0 {
+            progress!(args, "{}", outcomes.output(&i.to_string()));
+        }
+    }
+
+    progress!(args, "{}", total.output("Total"));
+
+    progress!(args, "");
+    progress!(args, "Intermediate Value Headroom");
+    progress!(args, "Big Numbers, Largest, Bits, Overflowed");
+
+    for (i, outcomes) in big_outcomes.iter().enumerate() {
+        if outcomes.combinations > 0 {
+            progress!(args, "{}", outcomes.headroom_output(&i.to_string()));
+        }
+    }
+
+    progress!(args, "{}", total.headroom_output("Total"));
+
+    // Distribution of the largest intermediate value of each successful program
+    let successful = total.intermediate_bits.iter().sum::<usize>();
+
+    progress!(args, "");
+    progress!(args, "Bits, Programs, Percent");
+
+    for (i, &count) in total.intermediate_bits.iter().enumerate() {
+        if count > 0 {
+            progress!(
+                args,
+                "{}, {}, {:.2}%",
+                i + 1,
+                count,
+                (count as f64 / successful as f64) * 100_f64
+            );
+        }
+    }
+
+    if total.pruned_instructions > 0 {
+        progress!(
+            args,
+            "Instructions not executed due to pruning: {}",
+            total.pruned_instructions.num_format()
+        );
+    }
+}
+
+/// Outputs the distribution of the time taken to solve each card combination and the slowest
+fn output_timings(args: &Args, timings: &Timings) {
+    if timings.is_empty() {
+        // Nothing calculated in this run
+        return;
+    }
+
+    progress!(args, "");
+    progress!(args, "Solve Times");
+    progress!(
+        args,
+        "Mean, {}, Max",
+        PERCENTILES.map(|percent| format!("{percent}%")).join(", ")
+    );
+    progress!(
+        args,
+        "{}, {}, {}",
+        format_time(timings.mean()),
+        PERCENTILES
+            .map(|percent| format_time(timings.percentile(percent)))
+            .join(", "),
+        format_time(timings.percentile(100))
+    );
+
+    progress!(args, "");
+    progress!(args, "Up To, Combinations, Percent");
+
+    for (limit, count) in timings.histogram() {
+        if count > 0 {
+            progress!(
+                args,
+                "{}, {}, {:.2}%",
+                format_time(limit),
+                count,
+                (count as f64 / timings.len() as f64) * 100_f64
+            );
+        }
+    }
+
+    if args.slowest > 0 {
+        progress!(args, "");
+        progress!(args, "Slowest Card Combinations");
+        progress!(args, "Cards, Time");
+
+        for (elapsed, numbers) in timings.slowest(args.slowest) {
+            progress!(
+                args,
+                "{}, {}",
+                numbers.iter().join("-"),
+                format_time(elapsed)
+            );
+        }
+    }
+}
+
+fn solve(args: &Args, programs: &Programs, numbers: &[u32]) -> Results {
+    // Run all of the programs for this set of numbers
+    if args.prune {
+        programs.run_all_pruned(numbers)
+    } else {
+        programs.run_all(numbers)
     }
 }
@@ -0,0 +1,136 @@
+use std::fs;
+
+use core_affinity::CoreId;
+
+/// Cores grouped by NUMA node
+pub struct Topology {
+    nodes: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    /// Finds the cores this process may run on. With numa set the cores are grouped by the NUMA
+    /// nodes listed in /sys/devices/system/node, otherwise (or if the nodes can't be read) all of
+    /// the cores are in one node
+    pub fn detect(numa: bool) -> Self {
+        let cores = core_affinity::get_core_ids()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|core| core.id)
+            .collect::<Vec<_>>();
+
+        let nodes = if numa {
+            read_nodes()
+                .into_iter()
+                .map(|node| {
+                    node.into_iter()
+                        .filter(|core| cores.contains(core))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|node| !node.is_empty())
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
+        if nodes.is_empty() {
+            Self { nodes: vec![cores] }
+        } else {
+            Self { nodes }
+        }
+    }
+
+    /// Returns the number of nodes
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the cores in a node
+    pub fn cores(&self, node: usize) -> &[usize] {
+        &self.nodes[node]
+    }
+
+    /// Returns the node and core to run a thread on. Threads are spread over the nodes in turn,
+    /// then over the cores of each node
+    pub fn place(&self, thread_no: usize) -> (usize, Option<usize>) {
+        let node = thread_no % self.nodes.len();
+        let cores = &self.nodes[node];
+
+        if cores.is_empty() {
+            (node, None)
+        } else {
+            (
+                node,
+                Some(cores[(thread_no / self.nodes.len()) % cores.len()]),
+            )
+        }
+    }
+}
+
+/// Pins the current thread to a core. Returns false if the thread couldn't be pinned
+pub fn pin(core: usize) -> bool {
+    core_affinity::set_for_current(CoreId { id: core })
+}
+
+/// Formats a list of cores, eg. 0-3,8-11
+pub fn format_cpu_list(cores: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for &core in cores {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == core => *end = core,
+            _ => ranges.push((core, core)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                format!("{start}")
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Reads the cores in each NUMA node from sysfs. Returns no nodes if they can't be read
+fn read_nodes() -> Vec<Vec<usize>> {
+    let Ok(dir) = fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+
+    let mut nodes = dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let node = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse::<usize>()
+                .ok()?;
+            let list = fs::read_to_string(entry.path().join("cpulist")).ok()?;
+
+            Some((node, parse_cpu_list(list.trim())?))
+        })
+        .collect::<Vec<_>>();
+
+    nodes.sort();
+
+    nodes.into_iter().map(|(_, cores)| cores).collect()
+}
+
+/// Parses a list of cores, eg. 0-3,8-11
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+
+    for range in list.split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cores.extend(start.parse::<usize>().ok()?..=end.parse().ok()?),
+            None => cores.push(range.parse().ok()?),
+        }
+    }
+
+    Some(cores)
+}
@@ -0,0 +1,116 @@
+use solver::Results;
+
+/// Program outcome labels, in the order they are held in the Outcomes counts array
+pub const OUTCOME_LABELS: [&str; OUTCOME_COUNT] = [
+    "In range",
+    "< 100",
+    "> 999",
+    "Zero intermediate",
+    "Negative intermediate",
+    "Div by zero",
+    "Non-integer",
+    "Multiply by 1",
+    "Divide by 1",
+    "Pruned",
+    "Symmetric",
+];
+
+const OUTCOME_COUNT: usize = 11;
+
+/// Accumulated program outcome counts over a number of card combinations
+#[derive(Default, Clone)]
+pub struct Outcomes {
+    pub combinations: usize,
+    pub counts: [usize; OUTCOME_COUNT],
+    pub pruned_instructions: usize,
+    pub overflow: usize,
+    pub largest_intermediate: u32,
+    pub intermediate_bits: [usize; 32],
+}
+
+impl Outcomes {
+    pub fn add(&mut self, results: &Results) {
+        let counts = [
+            results.solutions.len(),
+            results.under_range,
+            results.above_range,
+            results.zero,
+            results.negative,
+            results.div_zero,
+            results.non_integer,
+            results.mult_by_1,
+            results.div_by_1,
+            results.pruned,
+            results.symmetric,
+        ];
+
+        for (total, count) in self.counts.iter_mut().zip(counts) {
+            *total += count;
+        }
+
+        self.combinations += 1;
+        self.pruned_instructions += results.pruned_instructions;
+        self.add_headroom(
+            results.overflow,
+            results.largest_intermediate,
+            &results.intermediate_bits,
+        );
+    }
+
+    pub fn merge(&mut self, other: &Outcomes) {
+        for (total, count) in self.counts.iter_mut().zip(other.counts) {
+            *total += count;
+        }
+
+        self.combinations += other.combinations;
+        self.pruned_instructions += other.pruned_instructions;
+        self.add_headroom(
+            other.overflow,
+            other.largest_intermediate,
+            &other.intermediate_bits,
+        );
+    }
+
+    fn add_headroom(&mut self, overflow: usize, largest: u32, bits: &[usize; 32]) {
+        self.overflow += overflow;
+        self.largest_intermediate = self.largest_intermediate.max(largest);
+
+        for (total, count) in self.intermediate_bits.iter_mut().zip(bits) {
+            *total += count;
+        }
+    }
+
+    /// Returns a line describing the largest intermediate value
+    pub fn headroom_output(&self, desc: &str) -> String {
+        let bits = (u32::BITS - self.largest_intermediate.leading_zeros()).max(1);
+
+        format!(
+            "{desc}, {}, {}, {}",
+            self.largest_intermediate, bits, self.overflow
+        )
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns a line of outcome proportions
+    pub fn output(&self, desc: &str) -> String {
+        let total = self.total();
+
+        let pcts = self
+            .counts
+            .iter()
+            .map(|&c| {
+                if total == 0 {
+                    "0.00%".to_string()
+                } else {
+                    format!("{:.2}%", (c as f64 / total as f64) * 100_f64)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!("{desc}, {}, {}, {pcts}", self.combinations, total)
+    }
+}
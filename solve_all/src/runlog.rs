@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// File in the output directory holding the run log
+pub const RUN_LOG_FILE: &str = "run.log";
+
+/// What happened to a card combination
+pub enum Completion {
+    /// The card combination was solved
+    Solved {
+        /// Time taken to solve the card combination
+        elapsed: Duration,
+        /// Number of solutions found
+        solutions: usize,
+    },
+    /// The results were already in the output directory
+    Skipped,
+    /// The card combination couldn't be solved
+    Failed,
+}
+
+/// Writes a record of each card combination completed by the solver threads to a log, in card
+/// combination order whatever order the threads finish them in, so the logs of two runs can be
+/// compared
+pub struct RunLog<W: Write> {
+    state: Mutex<RunLogState<W>>,
+}
+
+/// Records waiting to be written in order
+struct RunLogState<W: Write> {
+    writer: W,
+    next: usize,
+    pending: BTreeMap<usize, (Vec<u32>, Completion)>,
+}
+
+impl RunLog<BufWriter<File>> {
+    /// Creates a run log file
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> RunLog<W> {
+    /// Creates a run log writing to a writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            state: Mutex::new(RunLogState {
+                writer,
+                next: 0,
+                pending: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Records the completion of the card combination with an index, writing it and any later
+    /// combinations already completed once all of the earlier combinations have been recorded
+    pub fn record(&self, index: usize, numbers: &[u32], completion: Completion) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        state.pending.insert(index, (numbers.to_vec(), completion));
+
+        // Write any card combinations which are now next in order
+        loop {
+            let next = state.next;
+
+            let Some((numbers, completion)) = state.pending.remove(&next) else {
+                break;
+            };
+
+            write_record(&mut state.writer, &numbers, &completion)?;
+
+            state.next += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Writes any records still waiting for earlier card combinations (which will be missing from
+    /// the log) and returns the writer
+    pub fn finish(self) -> io::Result<W> {
+        let mut state = self.state.into_inner().unwrap();
+
+        for (numbers, completion) in std::mem::take(&mut state.pending).into_values() {
+            write_record(&mut state.writer, &numbers, &completion)?;
+        }
+
+        state.writer.flush()?;
+
+        Ok(state.writer)
+    }
+}
+
+/// Writes the record of a card combination
+fn write_record<W: Write>(
+    writer: &mut W,
+    numbers: &[u32],
+    completion: &Completion,
+) -> io::Result<()> {
+    match completion {
+        Completion::Solved { elapsed, solutions } => writeln!(
+            writer,
+            "{numbers:?}: {solutions} solutions in {:.3}s",
+            elapsed.as_secs_f64()
+        ),
+        Completion::Skipped => writeln!(writer, "{numbers:?}: already solved"),
+        Completion::Failed => writeln!(writer, "{numbers:?}: failed"),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_log_order() {
+        let log = RunLog::new(Vec::new());
+
+        let solved = |solutions| Completion::Solved {
+            elapsed: Duration::from_millis(1500),
+            solutions,
+        };
+
+        // Completed out of order
+        log.record(2, &[3, 2, 1], solved(30)).unwrap();
+        log.record(1, &[4, 2, 1], Completion::Skipped).unwrap();
+        log.record(0, &[5, 2, 1], solved(50)).unwrap();
+        log.record(4, &[7, 2, 1], solved(70)).unwrap();
+
+        let output = String::from_utf8(log.finish().unwrap()).unwrap();
+
+        assert_eq!(
+            "[5, 2, 1]: 50 solutions in 1.500s\n\
+             [4, 2, 1]: already solved\n\
+             [3, 2, 1]: 30 solutions in 1.500s\n\
+             [7, 2, 1]: 70 solutions in 1.500s\n",
+            output
+        );
+    }
+}
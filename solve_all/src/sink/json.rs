@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use serde_json::{json, Map, Value};
+use solver::OP_NAMES;
+
+use super::{completed_time, file_stem, write_file, CardResults, ResultSink, SinkOptions};
+
+/// Writes a JSON file for each set of cards
+pub struct JsonSink {
+    dir: PathBuf,
+    options: SinkOptions,
+}
+
+impl JsonSink {
+    pub fn new(dir: PathBuf, options: SinkOptions) -> Self {
+        Self { dir, options }
+    }
+
+    /// Returns the JSON file path for a set of cards
+    fn file_path(&self, numbers: &[u32]) -> PathBuf {
+        self.dir.join(format!("{}.json", file_stem(numbers)))
+    }
+}
+
+impl ResultSink for JsonSink {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        // Equations are included in the file so it must be rewritten if they weren't written before
+        fs::read_to_string(self.file_path(numbers))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+            .is_some_and(|value| !self.options.output_equations || value.get("equations").is_some())
+    }
+
+    fn write(&self, results: &CardResults) -> io::Result<()> {
+        let mut value = to_json(results, &self.options);
+
+        value["completed"] = json!(completed_time());
+
+        write_file(&self.file_path(results.numbers), |file| {
+            write!(file, "{value}")
+        })
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the results for a set of cards as a JSON object
+pub fn to_json(results: &CardResults, options: &SinkOptions) -> Value {
+    let sol_cnt = results.solution_counts();
+
+    let outcomes = results
+        .outcomes(options)
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), json!(count)))
+        .collect::<Map<_, _>>();
+
+    let op_usage = results.results.op_usage();
+    let op_map = |counts: &[usize; 4]| {
+        OP_NAMES
+            .iter()
+            .zip(counts)
+            .map(|(name, count)| (name.to_string(), json!(count)))
+            .collect::<Map<_, _>>()
+    };
+
+    let op_combinations = op_usage
+        .combinations
+        .iter()
+        .map(|(ops, count)| {
+            let mut combination = OP_NAMES
+                .iter()
+                .zip(ops.to_array())
+                .map(|(name, count)| (name.to_string(), json!(count)))
+                .collect::<Map<_, _>>();
+
+            combination.insert("solutions".to_string(), json!(count));
+
+            Value::Object(combination)
+        })
+        .collect::<Vec<_>>();
+
+    let mut value = json!({
+        "cards": results.numbers,
+        "coverage": sol_cnt.iter().filter(|&&c| c > 0).count(),
+        "solution_counts": sol_cnt.as_slice(),
+        "outcomes": outcomes,
+        "largest_intermediate": results.results.largest_intermediate,
+        "operators": {
+            "using": op_map(&op_usage.using),
+            "needing": op_map(&op_usage.needing),
+            "combinations": op_combinations,
+        },
+        "duplicates_included": options.inc_duplicated,
+        "rules": options.rules.name(),
+        "programs_fingerprint": results.programs.fingerprint_hex(),
+    });
+
+    if options.output_equations {
+        value["equations"] = json!(results.equations(options));
+    }
+
+    value
+}
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{file_stem, CardResults, ResultSink, SinkOptions, MAP_FILE};
+
+/// Appends a line giving the solution map of each set of cards to a single file
+pub struct MapSink {
+    /// Card sets already in the file
+    done: HashSet<String>,
+    file: Mutex<MapFile>,
+}
+
+/// The map file and the length of its complete lines
+struct MapFile {
+    file: File,
+    end: u64,
+    /// A failed write may have left part of a line which couldn't be removed
+    dirty: bool,
+}
+
+impl MapFile {
+    /// Removes anything after the last complete line. The file is opened for appending, so the next
+    /// write goes to the new end without seeking
+    fn truncate(&mut self) -> io::Result<()> {
+        self.file.set_len(self.end)?;
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+impl MapSink {
+    /// Opens the map file in a directory, reading the card sets already written. An incomplete line
+    /// (left by an interrupted run) is removed
+    pub fn new(dir: &Path, options: SinkOptions) -> io::Result<Self> {
+        let path = dir.join(MAP_FILE);
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => Err(e)?,
+        };
+
+        // Length of the complete lines
+        let complete = contents.rfind('\n').map_or(0, |i| i + 1);
+
+        let done = contents[..complete]
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .filter(|(stem, _)| stem.starts_with(|c: char| c.is_ascii_digit()))
+            .map(|(stem, _)| stem.to_string())
+            .collect();
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        file.set_len(complete as u64)?;
+
+        let mut end = complete as u64;
+
+        if end == 0 {
+            let header = format!(
+                "duplicates included: {}\n",
+                if options.inc_duplicated { "Yes" } else { "No" }
+            );
+
+            file.write_all(header.as_bytes())?;
+            end = header.len() as u64;
+        }
+
+        Ok(Self {
+            done,
+            file: Mutex::new(MapFile {
+                file,
+                end,
+                dirty: false,
+            }),
+        })
+    }
+}
+
+impl ResultSink for MapSink {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        self.done.contains(&file_stem(numbers))
+    }
+
+    fn write(&self, results: &CardResults) -> io::Result<()> {
+        let line = format!(
+            "{}: {}\n",
+            file_stem(results.numbers),
+            results.solution_map()
+        );
+
+        // Write the whole line while holding the lock so lines from different threads don't mix
+        let mut file = self.file.lock().unwrap();
+
+        if file.dirty {
+            file.truncate()?;
+        }
+
+        match file.file.write_all(line.as_bytes()) {
+            Ok(()) => {
+                file.end += line.len() as u64;
+                Ok(())
+            }
+            Err(e) => {
+                // Remove a partly written line so it isn't joined to the next one
+                file.dirty = true;
+                let _ = file.truncate();
+                Err(e)
+            }
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,340 @@
+//! Result sinks store the results of running all of the programs for each set of cards. The
+//! solving loop only sees the `ResultSink` trait so new storage backends can be added here.
+
+mod json;
+mod map;
+mod null;
+mod pack;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod retry;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stdout;
+mod text;
+
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use solver::{Programs, Results, Ruleset, Scratch, ScratchStats, MIN_TARGET};
+
+use json::JsonSink;
+use map::MapSink;
+use null::NullSink;
+use pack::PackSink;
+// Named from self to tell the module apart from the parquet crate
+#[cfg(feature = "parquet")]
+use self::parquet::ParquetSink;
+#[cfg(feature = "sqlite")]
+use sqlite::SqliteSink;
+use stdout::StdoutSink;
+use text::TextSink;
+
+pub use retry::RetrySink;
+
+/// Number of targets in the standard game
+pub const TARGET_COUNT: usize = 900;
+
+/// File in the output directory written by the map sink
+pub const MAP_FILE: &str = "maps.txt";
+
+/// File in the output directory holding the results written by the pack sink
+pub const PACK_FILE: &str = "results.pack";
+
+/// File in the output directory giving the offset and length of each set of cards in the pack
+pub const PACK_INDEX_FILE: &str = "results.idx";
+
+thread_local! {
+    /// Scratch space of each worker thread for duplicate checks and equations
+    static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::new());
+}
+
+/// Returns the allocations made and avoided by the scratch space of the calling thread
+pub fn scratch_stats() -> ScratchStats {
+    SCRATCH.with(|scratch| scratch.borrow().stats())
+}
+
+/// Hands equations back to the scratch space of the calling thread once they have been written
+pub fn recycle_equations(equations: Vec<String>) {
+    SCRATCH.with(|scratch| scratch.borrow_mut().recycle_strings(equations));
+}
+
+/// Result sink types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SinkType {
+    /// Text files in the output directory (read by stats)
+    Text,
+    /// JSON files in the output directory
+    Json,
+    /// A single file in the output directory with a line giving the solution map of each card set
+    /// (read by stats)
+    Map,
+    /// A single file in the output directory with the text results of every card set, and an index
+    /// (read by stats)
+    Pack,
+    /// A Parquet file for each set of cards in the output directory, with the number of solutions for
+    /// each target
+    #[cfg(feature = "parquet")]
+    Parquet,
+    /// SQLite database in the output directory
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// JSON lines on stdout
+    Stdout,
+    /// Discard the results (for benchmarking)
+    Null,
+}
+
+impl SinkType {
+    /// Returns true if the sink writes to the output directory
+    pub fn needs_dir(&self) -> bool {
+        !matches!(self, SinkType::Stdout | SinkType::Null)
+    }
+}
+
+/// Order of the equations stored for each set of cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EqnSort {
+    /// By result, then by length
+    Result,
+    /// By length, then by result
+    Length,
+    /// By result, then by the canonical (operator grouped) equation text, so equivalent
+    /// equations are next to each other
+    Canonical,
+}
+
+/// Options controlling what is stored
+#[derive(Debug, Clone, Copy)]
+pub struct SinkOptions {
+    /// Store the equations of every solution
+    pub output_equations: bool,
+    /// Only store the first of the equations which are the same after rearranging the terms
+    pub eqn_unique: bool,
+    /// Order of the stored equations
+    pub eqn_sort: EqnSort,
+    /// Duplicated equations were included
+    pub inc_duplicated: bool,
+    /// Equations were pruned
+    pub prune: bool,
+    /// Rules giving the deck
+    pub rules: Ruleset,
+}
+
+/// Results of running all of the programs for a set of cards
+pub struct CardResults<'a> {
+    pub numbers: &'a [u32],
+    pub programs: &'a Programs,
+    pub results: &'a Results,
+}
+
+impl CardResults<'_> {
+    /// Returns the number of solutions for each target
+    pub fn solution_counts(&self) -> [u32; TARGET_COUNT] {
+        let mut sol_cnt = [0; TARGET_COUNT];
+
+        for solution in self.results.solutions.iter() {
+            sol_cnt[(solution.result - MIN_TARGET) as usize] += 1;
+        }
+
+        sol_cnt
+    }
+
+    /// Returns a string with a character for each target, '#' if it is reached and '.' if not
+    pub fn solution_map(&self) -> String {
+        self.solution_counts()
+            .iter()
+            .map(|x| if *x > 0 { '#' } else { '.' })
+            .collect()
+    }
+
+    /// Returns the equation of every solution in the order chosen by the options. Solutions which
+    /// are duplicated by rearranging the terms are left out if unique equations are wanted. The
+    /// strings come from the scratch space of the calling thread and can be handed back with
+    /// recycle_equations
+    pub fn equations(&self, options: &SinkOptions) -> Vec<String> {
+        let mut solutions = self.results.solutions.iter().sorted().collect::<Vec<_>>();
+
+        let mut equations = SCRATCH.with(|scratch| {
+            let scratch = &mut *scratch.borrow_mut();
+
+            if options.eqn_unique && options.inc_duplicated {
+                // Keep the first program of each canonical equation
+                scratch.clear_duplicates();
+
+                solutions
+                    .retain(|solution| !self.programs.duplicated_with(solution.program, scratch));
+            }
+
+            solutions
+                .into_iter()
+                .map(|solution| {
+                    (
+                        solution,
+                        self.programs
+                            .infix_with(solution.program, self.numbers, scratch),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+
+        match options.eqn_sort {
+            EqnSort::Result => (),
+            EqnSort::Length => equations.sort_by(|(s1, _), (s2, _)| {
+                (s1.length(), s1.result, s1.program).cmp(&(s2.length(), s2.result, s2.program))
+            }),
+            EqnSort::Canonical => equations.sort_by(|(s1, e1), (s2, e2)| {
+                (s1.result, e1, s1.program).cmp(&(s2.result, e2, s2.program))
+            }),
+        }
+
+        equations
+            .into_iter()
+            .map(|(_, equation)| equation)
+            .collect()
+    }
+
+    /// Returns the name and count of each program outcome
+    pub fn outcomes(&self, options: &SinkOptions) -> Vec<(&'static str, usize)> {
+        let results = self.results;
+
+        let mut outcomes = vec![
+            ("results", results.solutions.len()),
+            ("zero", results.zero),
+            ("negative", results.negative),
+            ("div_zero", results.div_zero),
+            ("non_integer", results.non_integer),
+            ("mult_by_1", results.mult_by_1),
+            ("div_by_1", results.div_by_1),
+            ("under_range", results.under_range),
+            ("above_range", results.above_range),
+            ("symmetric", results.symmetric),
+        ];
+
+        if options.prune {
+            outcomes.push(("pruned", results.pruned));
+            outcomes.push(("pruned_instructions", results.pruned_instructions));
+        }
+
+        outcomes
+    }
+}
+
+/// Stores the results for each set of cards
+pub trait ResultSink: Sync {
+    /// Returns true if the results for a set of cards are already stored so they don't need
+    /// calculating again
+    fn exists(&self, _numbers: &[u32]) -> bool {
+        false
+    }
+
+    /// Stores the results for a set of cards
+    fn write(&self, results: &CardResults) -> io::Result<()>;
+
+    /// Returns true if a failed write leaves nothing behind, so writing the results again can't
+    /// duplicate or corrupt them
+    fn retryable(&self) -> bool {
+        false
+    }
+}
+
+/// Creates a result sink. The output directory must exist for sinks which need it
+pub fn create_sink(
+    sink_type: SinkType,
+    out_dir: Option<&Path>,
+    options: SinkOptions,
+) -> io::Result<Box<dyn ResultSink>> {
+    let dir = || out_dir.expect("No output directory").to_path_buf();
+
+    Ok(match sink_type {
+        SinkType::Text => Box::new(TextSink::new(dir(), options)),
+        SinkType::Json => Box::new(JsonSink::new(dir(), options)),
+        SinkType::Map => Box::new(MapSink::new(&dir(), options)?),
+        SinkType::Pack => Box::new(PackSink::new(&dir(), options)?),
+        #[cfg(feature = "parquet")]
+        SinkType::Parquet => Box::new(ParquetSink::new(dir())),
+        #[cfg(feature = "sqlite")]
+        SinkType::Sqlite => Box::new(SqliteSink::new(&dir(), options)?),
+        SinkType::Stdout => Box::new(StdoutSink::new(options)),
+        SinkType::Null => Box::new(NullSink),
+    })
+}
+
+/// Returns the time results are written in seconds since the Unix epoch, recorded so the progress
+/// of incremental runs can be followed
+fn completed_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before epoch")
+        .as_secs()
+}
+
+/// Writes a file under a temporary name and renames it in to place, so a failed or interrupted
+/// write never leaves a partly written file to be taken as complete by a later run
+fn write_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let result = File::create(&tmp_path).and_then(|file| {
+        let mut file = BufWriter::new(file);
+
+        write(&mut file)?;
+
+        file.flush()
+    });
+
+    match result.and_then(|()| fs::rename(&tmp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Returns the file name stem for a set of cards
+fn file_stem(numbers: &[u32]) -> String {
+    numbers.iter().join("-")
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn test_write_file() {
+        let dir = env::temp_dir().join(format!("solve_all-write-file-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("9-8-3.txt");
+
+        write_file(&path, |file| writeln!(file, "complete")).unwrap();
+
+        assert_eq!("complete\n", fs::read_to_string(&path).unwrap());
+
+        // A failed write leaves the previous file and no temporary file
+        let result = write_file(&path, |file| {
+            writeln!(file, "partial")?;
+            Err(io::Error::other("Disk full"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!("complete\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,16 @@
+use std::io;
+
+use super::{CardResults, ResultSink};
+
+/// Discards the results so benchmarks measure the solving only
+pub struct NullSink;
+
+impl ResultSink for NullSink {
+    fn write(&self, _results: &CardResults) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+}
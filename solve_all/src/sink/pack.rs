@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::text::write_record;
+use super::{file_stem, CardResults, ResultSink, SinkOptions, PACK_FILE, PACK_INDEX_FILE};
+
+/// Appends the results for each set of cards to a single file in the text format, recording the
+/// offset and length of each in an index file so they can be read without scanning the whole file
+pub struct PackSink {
+    /// Card sets already in the pack
+    done: HashSet<String>,
+    files: Mutex<PackFiles>,
+    options: SinkOptions,
+}
+
+/// The pack and index files, the length of the pack covered by the index and the length of the
+/// complete index entries
+struct PackFiles {
+    pack: File,
+    index: File,
+    end: u64,
+    index_end: u64,
+    /// A failed write may have left part of a record or index entry which couldn't be removed
+    dirty: bool,
+}
+
+impl PackFiles {
+    /// Appends a record to the pack and its index entry to the index
+    fn append(&mut self, stem: &str, record: &[u8]) -> io::Result<()> {
+        let entry = format!("{stem} {} {}\n", self.end, record.len());
+
+        self.pack.write_all(record)?;
+        self.pack.flush()?;
+
+        self.index.write_all(entry.as_bytes())?;
+        self.index.flush()?;
+
+        self.end += record.len() as u64;
+        self.index_end += entry.len() as u64;
+
+        Ok(())
+    }
+
+    /// Removes anything after the last complete record and index entry. Both files are opened for
+    /// appending, so the next write goes to the new end without seeking
+    fn truncate(&mut self) -> io::Result<()> {
+        self.pack.set_len(self.end)?;
+        self.index.set_len(self.index_end)?;
+
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+impl PackSink {
+    /// Opens the pack and index in a directory, reading the card sets already written. Anything in
+    /// the pack after the last indexed record, or an incomplete index entry (left by an interrupted
+    /// run), is removed
+    pub fn new(dir: &Path, options: SinkOptions) -> io::Result<Self> {
+        let index_path = dir.join(PACK_INDEX_FILE);
+
+        let contents = match fs::read_to_string(&index_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => Err(e)?,
+        };
+
+        // Length of the complete index entries
+        let complete = contents.rfind('\n').map_or(0, |i| i + 1);
+
+        let mut done = HashSet::new();
+        let mut end = 0;
+
+        for line in contents[..complete].lines() {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid index entry '{line}' in {}", index_path.display()),
+                )
+            };
+
+            // Each entry is 'cards offset length'
+            let mut fields = line.split(' ');
+
+            let (Some(stem), Some(offset), Some(length), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                Err(invalid())?
+            };
+
+            let offset = offset.parse::<u64>().map_err(|_| invalid())?;
+            let length = length.parse::<u64>().map_err(|_| invalid())?;
+
+            end = end.max(offset + length);
+            done.insert(stem.to_string());
+        }
+
+        let pack = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(PACK_FILE))?;
+
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index_path)?;
+
+        index.set_len(complete as u64)?;
+
+        if pack.metadata()?.len() < end {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{PACK_FILE} is shorter than its index"),
+            ))?
+        }
+
+        pack.set_len(end)?;
+
+        Ok(Self {
+            done,
+            files: Mutex::new(PackFiles {
+                pack,
+                index,
+                end,
+                index_end: complete as u64,
+                dirty: false,
+            }),
+            options,
+        })
+    }
+}
+
+impl ResultSink for PackSink {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        self.done.contains(&file_stem(numbers))
+    }
+
+    fn write(&self, card_results: &CardResults) -> io::Result<()> {
+        let mut record = Vec::new();
+
+        write_record(&mut record, card_results, &self.options)?;
+
+        // Write the record and its index entry while holding the lock so records from different
+        // threads don't mix
+        let mut files = self.files.lock().unwrap();
+
+        if files.dirty {
+            files.truncate()?;
+        }
+
+        let result = files.append(&file_stem(card_results.numbers), &record);
+
+        // Remove a partly written record or index entry so the next record starts at the offset
+        // given in its index entry, and a retry doesn't leave a duplicate
+        if result.is_err() {
+            files.dirty = true;
+            let _ = files.truncate();
+        }
+
+        result
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use solver::{Programs, Ruleset};
+
+    use super::*;
+    use crate::sink::EqnSort;
+
+    #[test]
+    fn test_failed_write_removed() {
+        let dir = env::temp_dir().join(format!("solve_all-pack-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let options = SinkOptions {
+            output_equations: false,
+            eqn_unique: false,
+            eqn_sort: EqnSort::Result,
+            inc_duplicated: false,
+            prune: false,
+            rules: Ruleset::default(),
+        };
+
+        let programs = Programs::new(2, false, false);
+        let sink = PackSink::new(&dir, options).unwrap();
+
+        let write = |numbers: &[u32]| {
+            let results = programs.run_all(numbers);
+
+            sink.write(&CardResults {
+                numbers,
+                programs: &programs,
+                results: &results,
+            })
+            .unwrap();
+        };
+
+        write(&[75, 9]);
+
+        // Leave part of a record and index entry behind as a failed write would
+        {
+            let mut files = sink.files.lock().unwrap();
+
+            files.pack.write_all(b"solution map: ...").unwrap();
+            files.index.write_all(b"50-7 ").unwrap();
+            files.dirty = true;
+        }
+
+        write(&[50, 7]);
+
+        // Every index entry gives the offset of its record
+        let pack = fs::read_to_string(dir.join(PACK_FILE)).unwrap();
+        let index = fs::read_to_string(dir.join(PACK_INDEX_FILE)).unwrap();
+
+        let mut end = 0;
+
+        for line in index.lines() {
+            let fields = line.split(' ').collect::<Vec<_>>();
+            let offset = fields[1].parse::<usize>().unwrap();
+            let length = fields[2].parse::<usize>().unwrap();
+
+            assert_eq!(end, offset);
+            assert!(pack[offset..offset + length].starts_with("solution map: "));
+
+            end += length;
+        }
+
+        assert_eq!(2, index.lines().count());
+        assert_eq!(pack.len(), end);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
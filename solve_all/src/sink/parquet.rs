@@ -0,0 +1,79 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, StringArray, UInt32Array, UInt8Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use cards::CardSet;
+use parquet::arrow::ArrowWriter;
+use solver::MIN_TARGET;
+
+use super::{file_stem, CardResults, ResultSink};
+
+/// Writes a Parquet file for each set of cards with a row for each target giving the number of
+/// solutions. The output directory can be read as one table by DataFusion, pandas etc.
+pub struct ParquetSink {
+    dir: PathBuf,
+    schema: SchemaRef,
+}
+
+impl ParquetSink {
+    pub fn new(dir: PathBuf) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("cards", DataType::Utf8, false),
+            Field::new("big", DataType::UInt8, false),
+            Field::new("target", DataType::UInt32, false),
+            Field::new("solutions", DataType::UInt32, false),
+        ]));
+
+        Self { dir, schema }
+    }
+
+    /// Returns the Parquet file path for a set of cards
+    fn file_path(&self, numbers: &[u32]) -> PathBuf {
+        self.dir.join(format!("{}.parquet", file_stem(numbers)))
+    }
+}
+
+impl ResultSink for ParquetSink {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        self.file_path(numbers).exists()
+    }
+
+    fn write(&self, results: &CardResults) -> io::Result<()> {
+        let sol_cnt = results.solution_counts();
+        let rows = sol_cnt.len();
+
+        let cards = file_stem(results.numbers);
+        let big = CardSet::big_count(results.numbers) as u8;
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec![cards; rows])),
+            Arc::new(UInt8Array::from(vec![big; rows])),
+            Arc::new(UInt32Array::from_iter_values(
+                (0..rows as u32).map(|i| i + MIN_TARGET),
+            )),
+            Arc::new(UInt32Array::from(sol_cnt.to_vec())),
+        ];
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(io::Error::other)?;
+
+        // Write to a temporary file first so an interrupted write isn't taken as complete
+        let path = self.file_path(results.numbers);
+        let tmp_path = path.with_extension("parquet.tmp");
+
+        let mut writer = ArrowWriter::try_new(File::create(&tmp_path)?, self.schema.clone(), None)
+            .map_err(io::Error::other)?;
+
+        writer.write(&batch).map_err(io::Error::other)?;
+        writer.close().map_err(io::Error::other)?;
+
+        fs::rename(tmp_path, path)
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+}
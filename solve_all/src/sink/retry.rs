@@ -0,0 +1,167 @@
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use super::{CardResults, ResultSink};
+
+/// Longest wait before a retry, however many retries there have been
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Retries failed writes to another sink, waiting twice as long before each attempt (up to five
+/// minutes), so transient errors (eg. a network file system or a full disk being cleared) don't
+/// lose results. Only sinks whose failed writes leave nothing behind are retried. Card sets which
+/// still can't be written are remembered so they can be solved and written again at the end of the
+/// run
+pub struct RetrySink<'a> {
+    sink: &'a dyn ResultSink,
+    retries: u32,
+    delay: Duration,
+    failed: Mutex<Vec<(Vec<u32>, String)>>,
+}
+
+impl<'a> RetrySink<'a> {
+    /// Creates a sink retrying failed writes to another sink a number of times, waiting for the
+    /// delay before the first retry
+    pub fn new(sink: &'a dyn ResultSink, retries: u32, delay: Duration) -> Self {
+        Self {
+            sink,
+            retries,
+            delay,
+            failed: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the card sets which couldn't be written with the last error, in card order, and
+    /// forgets them
+    pub fn take_failed(&self) -> Vec<(Vec<u32>, String)> {
+        let mut failed = std::mem::take(&mut *self.failed.lock().unwrap());
+
+        failed.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        failed
+    }
+}
+
+impl ResultSink for RetrySink<'_> {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        self.sink.exists(numbers)
+    }
+
+    fn write(&self, results: &CardResults) -> io::Result<()> {
+        let retries = if self.sink.retryable() {
+            self.retries
+        } else {
+            0
+        };
+
+        let mut delay = self.delay;
+        let mut attempt = 0;
+
+        loop {
+            match self.sink.write(results) {
+                Ok(()) => return Ok(()),
+                Err(_) if attempt < retries => {
+                    thread::sleep(delay);
+
+                    delay = delay.saturating_mul(2).min(MAX_DELAY.max(self.delay));
+                    attempt += 1;
+                }
+                Err(e) => {
+                    self.failed
+                        .lock()
+                        .unwrap()
+                        .push((results.numbers.to_vec(), e.to_string()));
+
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        self.sink.retryable()
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use solver::Programs;
+
+    use super::*;
+
+    /// Sink failing a number of times before each write succeeds
+    struct FlakySink {
+        failures: u32,
+        attempts: AtomicU32,
+        retryable: bool,
+    }
+
+    impl FlakySink {
+        fn new(failures: u32, retryable: bool) -> Self {
+            Self {
+                failures,
+                attempts: AtomicU32::new(0),
+                retryable,
+            }
+        }
+    }
+
+    impl ResultSink for FlakySink {
+        fn write(&self, _results: &CardResults) -> io::Result<()> {
+            if self.attempts.fetch_add(1, Ordering::Relaxed) % (self.failures + 1) < self.failures {
+                Err(io::Error::other("Transient error"))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn retryable(&self) -> bool {
+            self.retryable
+        }
+    }
+
+    #[test]
+    fn test_retry_sink() {
+        let programs = Programs::new(2, false, false);
+        let numbers = [5, 2];
+        let results = programs.run_all(&numbers);
+
+        let card_results = CardResults {
+            numbers: &numbers,
+            programs: &programs,
+            results: &results,
+        };
+
+        // Succeeds on the third attempt
+        let flaky = FlakySink::new(2, true);
+        let sink = RetrySink::new(&flaky, 2, Duration::from_millis(1));
+
+        assert!(sink.write(&card_results).is_ok());
+        assert_eq!(3, flaky.attempts.load(Ordering::Relaxed));
+        assert!(sink.take_failed().is_empty());
+
+        // Gives up after one retry
+        let flaky = FlakySink::new(2, true);
+        let sink = RetrySink::new(&flaky, 1, Duration::from_millis(1));
+
+        assert!(sink.write(&card_results).is_err());
+        assert_eq!(
+            vec![(vec![5, 2], "Transient error".to_string())],
+            sink.take_failed()
+        );
+        assert!(sink.take_failed().is_empty());
+
+        // Sinks which can't undo a failed write aren't retried
+        let flaky = FlakySink::new(1, false);
+        let sink = RetrySink::new(&flaky, 2, Duration::from_millis(1));
+
+        assert!(sink.write(&card_results).is_err());
+        assert_eq!(1, flaky.attempts.load(Ordering::Relaxed));
+        assert_eq!(1, sink.take_failed().len());
+    }
+}
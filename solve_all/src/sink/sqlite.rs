@@ -0,0 +1,195 @@
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use solver::{MIN_TARGET, OP_NAMES};
+
+use super::{file_stem, recycle_equations, CardResults, ResultSink, SinkOptions};
+
+/// Database file name in the output directory
+const DB_FILE: &str = "results.db";
+
+/// Writes the results for each set of cards to a SQLite database
+pub struct SqliteSink {
+    conn: Mutex<Connection>,
+    options: SinkOptions,
+}
+
+impl SqliteSink {
+    /// Opens the database in a directory, creating the tables if necessary
+    pub fn new(dir: &Path, options: SinkOptions) -> io::Result<Self> {
+        let conn = Connection::open(dir.join(DB_FILE)).map_err(to_io)?;
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS card_sets (
+                cards TEXT PRIMARY KEY,
+                coverage INTEGER NOT NULL,
+                duplicates_included INTEGER NOT NULL,
+                has_equations INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS outcomes (
+                cards TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (cards, outcome)
+            );
+            CREATE TABLE IF NOT EXISTS solution_counts (
+                cards TEXT NOT NULL,
+                target INTEGER NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (cards, target)
+            );
+            CREATE TABLE IF NOT EXISTS operators (
+                cards TEXT NOT NULL,
+                operator TEXT NOT NULL,
+                solutions_using INTEGER NOT NULL,
+                targets_needing INTEGER NOT NULL,
+                PRIMARY KEY (cards, operator)
+            );
+            CREATE TABLE IF NOT EXISTS operator_counts (
+                cards TEXT NOT NULL,
+                add_count INTEGER NOT NULL,
+                sub_count INTEGER NOT NULL,
+                mul_count INTEGER NOT NULL,
+                div_count INTEGER NOT NULL,
+                solutions INTEGER NOT NULL,
+                PRIMARY KEY (cards, add_count, sub_count, mul_count, div_count)
+            );
+            CREATE TABLE IF NOT EXISTS equations (
+                cards TEXT NOT NULL,
+                equation TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS equations_cards ON equations (cards);",
+        )
+        .map_err(to_io)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            options,
+        })
+    }
+}
+
+impl ResultSink for SqliteSink {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        let conn = self.conn.lock().unwrap();
+
+        let has_equations: Option<bool> = conn
+            .query_row(
+                "SELECT has_equations FROM card_sets WHERE cards = ?1",
+                [file_stem(numbers)],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap_or(None);
+
+        has_equations.is_some_and(|has_equations| has_equations || !self.options.output_equations)
+    }
+
+    fn write(&self, results: &CardResults) -> io::Result<()> {
+        let cards = file_stem(results.numbers);
+        let sol_cnt = results.solution_counts();
+        let op_usage = results.results.op_usage();
+
+        let equations = if self.options.output_equations {
+            results.equations(&self.options)
+        } else {
+            Vec::new()
+        };
+
+        let mut conn = self.conn.lock().unwrap();
+
+        let tx = conn.transaction().map_err(to_io)?;
+
+        for table in [
+            "card_sets",
+            "outcomes",
+            "solution_counts",
+            "operators",
+            "operator_counts",
+            "equations",
+        ] {
+            tx.execute(&format!("DELETE FROM {table} WHERE cards = ?1"), [&cards])
+                .map_err(to_io)?;
+        }
+
+        tx.execute(
+            "INSERT INTO card_sets VALUES (?1, ?2, ?3, ?4)",
+            params![
+                cards,
+                sol_cnt.iter().filter(|&&c| c > 0).count(),
+                self.options.inc_duplicated,
+                self.options.output_equations
+            ],
+        )
+        .map_err(to_io)?;
+
+        {
+            let mut stmt = tx
+                .prepare("INSERT INTO outcomes VALUES (?1, ?2, ?3)")
+                .map_err(to_io)?;
+
+            for (outcome, count) in results.outcomes(&self.options) {
+                stmt.execute(params![cards, outcome, count])
+                    .map_err(to_io)?;
+            }
+
+            let mut stmt = tx
+                .prepare("INSERT INTO solution_counts VALUES (?1, ?2, ?3)")
+                .map_err(to_io)?;
+
+            for (i, count) in sol_cnt.iter().enumerate() {
+                stmt.execute(params![cards, i as u32 + MIN_TARGET, count])
+                    .map_err(to_io)?;
+            }
+
+            let mut stmt = tx
+                .prepare("INSERT INTO operators VALUES (?1, ?2, ?3, ?4)")
+                .map_err(to_io)?;
+
+            for (op, name) in OP_NAMES.iter().enumerate() {
+                stmt.execute(params![
+                    cards,
+                    name,
+                    op_usage.using[op],
+                    op_usage.needing[op]
+                ])
+                .map_err(to_io)?;
+            }
+
+            let mut stmt = tx
+                .prepare("INSERT INTO operator_counts VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+                .map_err(to_io)?;
+
+            for (ops, count) in &op_usage.combinations {
+                stmt.execute(params![cards, ops.add, ops.sub, ops.mul, ops.div, count])
+                    .map_err(to_io)?;
+            }
+
+            let mut stmt = tx
+                .prepare("INSERT INTO equations VALUES (?1, ?2)")
+                .map_err(to_io)?;
+
+            for equation in &equations {
+                stmt.execute(params![cards, equation]).map_err(to_io)?;
+            }
+        }
+
+        tx.commit().map_err(to_io)?;
+
+        recycle_equations(equations);
+
+        Ok(())
+    }
+
+    fn retryable(&self) -> bool {
+        // The card set's rows are replaced in a transaction which is rolled back on failure
+        true
+    }
+}
+
+/// Converts a SQLite error to an I/O error
+fn to_io(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
@@ -0,0 +1,27 @@
+use std::io::{self, Write};
+
+use super::json::to_json;
+use super::{CardResults, ResultSink, SinkOptions};
+
+/// Streams the results for each set of cards to stdout as JSON lines
+pub struct StdoutSink {
+    options: SinkOptions,
+}
+
+impl StdoutSink {
+    pub fn new(options: SinkOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl ResultSink for StdoutSink {
+    fn write(&self, results: &CardResults) -> io::Result<()> {
+        let line = to_json(results, &self.options).to_string();
+
+        // Write the whole line while holding the lock so lines from different threads don't mix
+        let mut stdout = io::stdout().lock();
+
+        writeln!(stdout, "{line}")?;
+        stdout.flush()
+    }
+}
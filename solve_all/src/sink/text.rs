@@ -0,0 +1,150 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use solver::{MIN_TARGET, OP_NAMES};
+
+use super::{
+    completed_time, file_stem, recycle_equations, write_file, CardResults, ResultSink, SinkOptions,
+};
+
+/// Writes a text file for each set of cards, and optionally a file of equations
+pub struct TextSink {
+    dir: PathBuf,
+    options: SinkOptions,
+}
+
+impl TextSink {
+    pub fn new(dir: PathBuf, options: SinkOptions) -> Self {
+        Self { dir, options }
+    }
+
+    /// Returns the results file and equations file paths for a set of cards
+    fn file_paths(&self, numbers: &[u32]) -> (PathBuf, PathBuf) {
+        let stem = file_stem(numbers);
+
+        (
+            self.dir.join(format!("{stem}.txt")),
+            self.dir.join(format!("{stem}-eqn.txt")),
+        )
+    }
+}
+
+impl ResultSink for TextSink {
+    fn exists(&self, numbers: &[u32]) -> bool {
+        let (file_path, eqn_file_path) = self.file_paths(numbers);
+
+        file_path.exists() && (!self.options.output_equations || eqn_file_path.exists())
+    }
+
+    fn write(&self, card_results: &CardResults) -> io::Result<()> {
+        let (file_path, eqn_file_path) = self.file_paths(card_results.numbers);
+
+        // Write the output file
+        write_file(&file_path, |file| {
+            write_record(file, card_results, &self.options)
+        })?;
+
+        if self.options.output_equations {
+            // Write all equations to the equation output file
+            let equations = card_results.equations(&self.options);
+
+            write_file(&eqn_file_path, |eqn_file| {
+                for equation in &equations {
+                    writeln!(eqn_file, "{equation}")?;
+                }
+
+                Ok(())
+            })?;
+
+            recycle_equations(equations);
+        }
+
+        Ok(())
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+}
+
+/// Writes the results for a set of cards in the text format read by stats
+pub fn write_record(
+    file: &mut impl Write,
+    card_results: &CardResults,
+    options: &SinkOptions,
+) -> io::Result<()> {
+    let results = card_results.results;
+
+    // Count the number of solutions for each target number
+    let sol_cnt = card_results.solution_counts();
+
+    // Create a solutions map string where '#' is > 0 and '.' = 0
+    let sol_map = card_results.solution_map();
+
+    // Create a string listing all of the target numbers with the number of solutions
+    let sol_cnt_str = sol_cnt
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{}={}", i as u32 + MIN_TARGET, c))
+        .join(", ");
+
+    // Count how many target numbers have > 0 solutions
+    let covered = sol_cnt.iter().filter(|&&c| c > 0).count();
+
+    // Write details
+    writeln!(file, "solution map: {sol_map}")?;
+    writeln!(file, "solution coverage: {covered}")?;
+    writeln!(file, "solution counts: {sol_cnt_str}")?;
+    writeln!(file, "results: {}", results.solutions.len())?;
+    writeln!(file, "zero intermediate: {}", results.zero)?;
+    writeln!(file, "negative intermediate: {}", results.negative)?;
+    writeln!(file, "div by zero: {}", results.div_zero)?;
+    writeln!(file, "non-integer: {}", results.non_integer)?;
+    writeln!(file, "multiply by 1: {}", results.mult_by_1)?;
+    writeln!(file, "divide by 1: {}", results.div_by_1)?;
+    writeln!(file, "< 100: {}", results.under_range)?;
+    writeln!(file, "> 999: {}", results.above_range)?;
+    writeln!(file, "symmetric: {}", results.symmetric)?;
+    writeln!(
+        file,
+        "largest intermediate: {}",
+        results.largest_intermediate
+    )?;
+
+    // Operators used by the solutions
+    let op_usage = results.op_usage();
+    let op_list = |counts: &[usize; 4]| {
+        OP_NAMES
+            .iter()
+            .zip(counts)
+            .map(|(name, count)| format!("{name}={count}"))
+            .join(", ")
+    };
+
+    writeln!(file, "solutions using: {}", op_list(&op_usage.using))?;
+    writeln!(file, "targets needing: {}", op_list(&op_usage.needing))?;
+    writeln!(
+        file,
+        "operator counts (add:sub:mul:div): {}",
+        op_usage
+            .combinations
+            .iter()
+            .map(|(ops, count)| format!("{}={count}", ops.to_array().iter().join(":")))
+            .join(", ")
+    )?;
+
+    if options.prune {
+        writeln!(file, "pruned: {}", results.pruned)?;
+        writeln!(file, "pruned instructions: {}", results.pruned_instructions)?;
+    }
+
+    writeln!(
+        file,
+        "duplicates included: {}",
+        if options.inc_duplicated { "Yes" } else { "No" }
+    )?;
+    writeln!(file, "completed: {}", completed_time())?;
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use std::io;
+use std::path::Path;
+
+use crate::sink::SinkType;
+
+/// Returns a rough number of bytes written for each card set by a result sink, measured on sets of
+/// six cards. None if the sink doesn't write to the output directory
+pub fn estimated_set_size(sink: SinkType, output_equations: bool) -> Option<u64> {
+    let (without, with) = match sink {
+        SinkType::Text => (9_000, 380_000),
+        SinkType::Json => (6_000, 400_000),
+        SinkType::Map => (1_000, 1_000),
+        SinkType::Pack => (9_000, 9_000),
+        #[cfg(feature = "parquet")]
+        SinkType::Parquet => (8_000, 8_000),
+        #[cfg(feature = "sqlite")]
+        SinkType::Sqlite => (20_000, 400_000),
+        SinkType::Stdout | SinkType::Null => return None,
+    };
+
+    Some(if output_equations { with } else { without })
+}
+
+/// Returns the space available to the user on the file system holding a directory
+#[cfg(unix)]
+pub fn free_space(dir: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(dir.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: path is a nul terminated string and stat is filled in when statvfs succeeds
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        stat.assume_init()
+    };
+
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Returns the space available to the user on the file system holding a directory
+#[cfg(not(unix))]
+pub fn free_space(_dir: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Free space can't be found on this platform",
+    ))
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_free_space() {
+        assert!(free_space(&std::env::temp_dir()).unwrap() > 0);
+        assert!(free_space(Path::new("/no/such/directory")).is_err());
+    }
+
+    #[test]
+    fn test_estimated_set_size() {
+        assert_eq!(None, estimated_set_size(SinkType::Null, false));
+        assert!(
+            estimated_set_size(SinkType::Text, true) > estimated_set_size(SinkType::Text, false)
+        );
+    }
+}
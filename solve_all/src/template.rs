@@ -0,0 +1,151 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Placeholders which can be used in an output directory template
+pub const PLACEHOLDERS: [&str; 4] = ["cards", "rules", "date", "ops"];
+
+/// Values of the placeholders in an output directory template
+pub struct TemplateValues {
+    /// Card set in use, eg. 100-75-50-25-10-10-...
+    pub cards: String,
+    /// Name of the rules
+    pub rules: String,
+    /// Date of the run (YYYY-MM-DD)
+    pub date: String,
+    /// Equations generated (C with duplicates, NC canonical or NCF filtered)
+    pub ops: String,
+}
+
+impl TemplateValues {
+    /// Returns the value of a placeholder
+    fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "cards" => Some(&self.cards),
+            "rules" => Some(&self.rules),
+            "date" => Some(&self.date),
+            "ops" => Some(&self.ops),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces the placeholders (eg. {rules}) in a template with their values. Braces are written
+/// as {{ and }}. Returns an error for an unknown placeholder or an unmatched brace
+pub fn expand_template(template: &str, values: &TemplateValues) -> Result<String, String> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let rest = chars.as_str();
+
+                if let Some(rest) = rest.strip_prefix('{') {
+                    result.push('{');
+                    chars = rest.chars();
+                    continue;
+                }
+
+                let Some(end) = rest.find('}') else {
+                    return Err(format!("Unmatched '{{' in '{template}'"));
+                };
+
+                let name = &rest[..end];
+
+                let Some(value) = values.get(name) else {
+                    return Err(format!(
+                        "Unknown placeholder '{{{name}}}' in '{template}' (expected one of {})",
+                        PLACEHOLDERS.map(|p| format!("{{{p}}}")).join(", ")
+                    ));
+                };
+
+                result.push_str(value);
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                let rest = chars.as_str();
+
+                let Some(rest) = rest.strip_prefix('}') else {
+                    return Err(format!("Unmatched '}}' in '{template}'"));
+                };
+
+                result.push('}');
+                chars = rest.chars();
+            }
+            c => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns today's date (UTC) as YYYY-MM-DD
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before epoch")
+        .as_secs()
+        / 86_400;
+
+    format_date(days)
+}
+
+/// Formats days since 1970-01-01 as YYYY-MM-DD
+fn format_date(days: u64) -> String {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - (era * 146_097);
+    let yoe = (doe - (doe / 1_460) + (doe / 36_524) - (doe / 146_096)) / 365;
+    let doy = doe - ((365 * yoe) + (yoe / 4) - (yoe / 100));
+    let mp = ((5 * doy) + 2) / 153;
+    let d = doy - (((153 * mp) + 2) / 5) + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + (era * 400) + if m <= 2 { 1 } else { 0 };
+
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> TemplateValues {
+        TemplateValues {
+            cards: "50-25-20".to_string(),
+            rules: "junior".to_string(),
+            date: "2024-02-29".to_string(),
+            ops: "NC".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_template() {
+        let values = values();
+
+        assert_eq!(
+            Ok("results/junior-NC/2024-02-29-50-25-20".to_string()),
+            expand_template("results/{rules}-{ops}/{date}-{cards}", &values)
+        );
+        assert_eq!(Ok("plain".to_string()), expand_template("plain", &values));
+        assert_eq!(
+            Ok("{rules}-junior".to_string()),
+            expand_template("{{rules}}-{rules}", &values)
+        );
+
+        // Invalid templates
+        assert!(expand_template("{rule}", &values)
+            .unwrap_err()
+            .starts_with("Unknown placeholder '{rule}'"));
+        assert!(expand_template("{rules", &values).is_err());
+        assert!(expand_template("rules}", &values).is_err());
+        assert!(expand_template("{}", &values).is_err());
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!("1970-01-01", format_date(0));
+        assert_eq!("2000-02-29", format_date(11_016));
+        assert_eq!("2024-12-31", format_date(20_088));
+    }
+}
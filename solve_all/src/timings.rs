@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+/// Percentiles of the solve times reported
+pub const PERCENTILES: [usize; 3] = [50, 90, 99];
+
+/// Time taken to solve each card combination
+#[derive(Default, Clone)]
+pub struct Timings {
+    times: Vec<(Duration, Vec<u32>)>,
+}
+
+impl Timings {
+    /// Records the time taken to solve a card combination
+    pub fn add(&mut self, numbers: &[u32], elapsed: Duration) {
+        self.times.push((elapsed, numbers.to_vec()));
+    }
+
+    /// Adds the times recorded by another thread
+    pub fn merge(&mut self, other: Timings) {
+        self.times.extend(other.times);
+    }
+
+    /// Returns the number of card combinations timed
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Returns true if no card combinations were timed
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// Returns the mean solve time
+    pub fn mean(&self) -> Duration {
+        if self.times.is_empty() {
+            return Duration::ZERO;
+        }
+
+        self.times
+            .iter()
+            .map(|(elapsed, _)| *elapsed)
+            .sum::<Duration>()
+            / self.times.len() as u32
+    }
+
+    /// Returns the solve time which a percentage of the card combinations took no longer than
+    pub fn percentile(&self, percent: usize) -> Duration {
+        let mut times = self
+            .times
+            .iter()
+            .map(|(elapsed, _)| *elapsed)
+            .collect::<Vec<_>>();
+
+        if times.is_empty() {
+            return Duration::ZERO;
+        }
+
+        times.sort_unstable();
+
+        // Nearest rank
+        let rank = (percent * times.len()).div_ceil(100).max(1);
+
+        times[rank.min(times.len()) - 1]
+    }
+
+    /// Returns the slowest card combinations, slowest first. Card combinations taking the same time
+    /// are in card order
+    pub fn slowest(&self, count: usize) -> Vec<(Duration, &[u32])> {
+        let mut times = self
+            .times
+            .iter()
+            .map(|(elapsed, numbers)| (*elapsed, numbers.as_slice()))
+            .collect::<Vec<_>>();
+
+        times.sort_by(|(e1, n1), (e2, n2)| e2.cmp(e1).then_with(|| n2.cmp(n1)));
+        times.truncate(count);
+
+        times
+    }
+
+    /// Returns the number of card combinations solved within each power of two number of
+    /// milliseconds, from 1ms up to the slowest
+    pub fn histogram(&self) -> Vec<(Duration, usize)> {
+        let mut buckets: Vec<(Duration, usize)> = Vec::new();
+
+        for (elapsed, _) in &self.times {
+            let mut bucket = 0;
+            let mut limit = Duration::from_millis(1);
+
+            while *elapsed > limit {
+                bucket += 1;
+                limit *= 2;
+            }
+
+            while buckets.len() <= bucket {
+                buckets.push((Duration::from_millis(1 << buckets.len()), 0));
+            }
+
+            buckets[bucket].1 += 1;
+        }
+
+        buckets
+    }
+}
+
+/// Formats a solve time
+pub fn format_time(elapsed: Duration) -> String {
+    if elapsed < Duration::from_secs(1) {
+        format!("{:.2}ms", elapsed.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.3}s", elapsed.as_secs_f64())
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings() {
+        let mut timings = Timings::default();
+        let mut other = Timings::default();
+
+        timings.add(&[75, 9, 5], Duration::from_millis(5));
+        timings.add(&[9, 9, 5], Duration::from_micros(500));
+        other.add(&[9, 8, 3], Duration::from_millis(5));
+        other.add(&[7, 6, 1], Duration::from_millis(2));
+
+        timings.merge(other);
+
+        assert_eq!(4, timings.len());
+        assert_eq!(Duration::from_micros(3125), timings.mean());
+        assert_eq!(Duration::from_millis(2), timings.percentile(50));
+        assert_eq!(Duration::from_millis(5), timings.percentile(99));
+
+        // Slowest first, then in card order
+        assert_eq!(
+            vec![
+                (Duration::from_millis(5), &[75, 9, 5][..]),
+                (Duration::from_millis(5), &[9, 8, 3][..]),
+                (Duration::from_millis(2), &[7, 6, 1][..]),
+            ],
+            timings.slowest(3)
+        );
+
+        // Up to 1ms, 2ms, 4ms and 8ms
+        assert_eq!(
+            vec![1, 1, 0, 2],
+            timings
+                .histogram()
+                .iter()
+                .map(|(_, count)| *count)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Duration::from_millis(8), timings.histogram()[3].0);
+
+        assert_eq!("0.25ms", format_time(Duration::from_micros(250)));
+        assert_eq!("1.500s", format_time(Duration::from_millis(1500)));
+    }
+}
@@ -0,0 +1,92 @@
+use numformat::NumFormat;
+use solver::{assignments, Programs};
+
+/// Maximum number of examples of each problem to output
+const MAX_EXAMPLES: usize = 10;
+
+/// Maximum number of programs to output for each group of identical programs
+const MAX_GROUP_EXAMPLES: usize = 4;
+
+/// Verifies the duplicate filter for a number of cards against all programs.
+/// Returns false if any values can't be reached after filtering
+pub fn verify_dedup(
+    nums: u8,
+    filtered: bool,
+    cards: &[u32],
+    samples: usize,
+    verbose: bool,
+) -> bool {
+    println!("Verifying duplicate filtering for {nums} cards...");
+
+    // Generate programs
+    let retained = if filtered {
+        Programs::new_filtered(nums, verbose)
+    } else {
+        Programs::new(nums, false, verbose)
+    };
+
+    let all = Programs::new(nums, true, verbose);
+
+    // Generate card assignments, sampling evenly if there are too many
+    let mut assignments = assignments(cards, nums as usize);
+
+    let sampled = assignments.len() > samples;
+
+    if sampled {
+        let step = assignments.len().div_ceil(samples);
+
+        assignments = assignments.into_iter().step_by(step).collect();
+    }
+
+    let report = retained.verify_dedup(&all, &assignments);
+
+    println!(
+        "Card assignments checked: {}",
+        report.assignments.num_format()
+    );
+    println!("Programs retained: {}", report.retained.num_format());
+    println!("Programs removed: {}", report.removed.num_format());
+
+    // Output over-filtering
+    println!(
+        "Values only reached by removed programs (over-filtering): {}",
+        report.over_filtered.len().num_format()
+    );
+
+    for (numbers, value, prog_elem) in report.over_filtered.iter().take(MAX_EXAMPLES) {
+        println!(
+            "  {:?}: {} = {}",
+            numbers,
+            value,
+            all.infix_full(*prog_elem, numbers, false)
+        );
+    }
+
+    // Output under-filtering
+    println!(
+        "Groups of retained programs giving identical results (under-filtering): {}",
+        report.under_filtered.len().num_format()
+    );
+
+    let elems = (0..nums as u32).collect::<Vec<_>>();
+
+    for group in report.under_filtered.iter().take(MAX_EXAMPLES) {
+        let equations = group
+            .iter()
+            .take(MAX_GROUP_EXAMPLES)
+            .map(|&prog_elem| retained.infix_full(prog_elem, &elems, false))
+            .collect::<Vec<_>>();
+
+        if group.len() > MAX_GROUP_EXAMPLES {
+            println!("  {} ({} programs)", equations.join(" == "), group.len());
+        } else {
+            println!("  {}", equations.join(" == "));
+        }
+    }
+
+    if sampled && !report.under_filtered.is_empty() {
+        println!("NB: programs which give identical results for the sampled assignments may still differ");
+    }
+
+    report.over_filtered.is_empty()
+}
@@ -0,0 +1,181 @@
+//! Golden output tests. solve_all is run on a small fixed sample of card combinations writing to a
+//! temporary directory, and every file written is compared with the files in tests/golden, so
+//! changes to the result writers are noticed.
+//!
+//! After an intended change to the output run the tests with UPDATE_GOLDEN=1 to rewrite the golden
+//! files, and review the differences before committing them.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+/// Arguments giving a small, deterministic run: four combinations of three cards on one thread
+const RUN_ARGS: [&str; 12] = [
+    "--machine",
+    "--pick",
+    "3",
+    "--sample",
+    "4",
+    "--seed",
+    "1",
+    "--threads",
+    "1",
+    "--equations",
+    "--sink",
+    "",
+];
+
+/// Temporary directory removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("solve_all-golden-{}-{name}", process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs solve_all with a sink and returns what it wrote to stdout
+fn run_solve_all(sink: &str, out_dir: Option<&Path>) -> String {
+    let mut args = RUN_ARGS;
+    args[RUN_ARGS.len() - 1] = sink;
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_solve_all"));
+
+    command.args(args);
+
+    if let Some(out_dir) = out_dir {
+        command.arg("--outdir").arg(out_dir);
+    }
+
+    let output = command.output().expect("Failed to run solve_all");
+
+    assert!(
+        output.status.success(),
+        "solve_all --sink {sink} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).expect("Output is not UTF-8")
+}
+
+/// Returns the path of a golden file or directory
+fn golden_path(rel: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(rel)
+}
+
+/// Returns the sorted names of the files in a directory
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read {} ({e})", dir.display()))
+        .map(|entry| {
+            entry
+                .expect("Failed to read directory entry")
+                .file_name()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect::<Vec<_>>();
+
+    names.sort();
+
+    names
+}
+
+/// Replaces the completion times recorded in results and the run log, which change with every run
+fn normalise_times(contents: &str) -> String {
+    let mut normalised = contents.to_string();
+
+    for marker in ["completed: ", "\"completed\":", "solutions in "] {
+        let mut from = 0;
+
+        while let Some(pos) = normalised[from..].find(marker) {
+            let start = from + pos + marker.len();
+            let digits = normalised[start..]
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(normalised.len() - start);
+
+            normalised.replace_range(start..start + digits, "0");
+            from = start;
+        }
+    }
+
+    normalised
+}
+
+/// Compares contents with a golden file, or rewrites the golden file if UPDATE_GOLDEN is set
+fn check_golden(path: &Path, actual: &str) {
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create golden directory");
+        fs::write(path, actual).expect("Failed to write golden file");
+        return;
+    }
+
+    let expected = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read {} ({e})", path.display()));
+
+    assert!(
+        expected == actual,
+        "Output differs from {}\n--- expected\n{expected}\n--- actual\n{actual}",
+        path.display()
+    );
+}
+
+/// Runs solve_all with a sink writing to the output directory and compares every file written
+fn check_dir_sink(sink: &str) {
+    let out_dir = TempDir::new(sink);
+
+    run_solve_all(sink, Some(&out_dir.0));
+
+    let golden_dir = golden_path(sink);
+    let names = file_names(&out_dir.0);
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        let _ = fs::remove_dir_all(&golden_dir);
+    } else {
+        assert_eq!(file_names(&golden_dir), names, "Files written differ");
+    }
+
+    for name in names {
+        let actual = fs::read_to_string(out_dir.0.join(&name)).expect("Failed to read output");
+
+        check_golden(&golden_dir.join(name), &normalise_times(&actual));
+    }
+}
+
+#[test]
+fn test_text_sink() {
+    check_dir_sink("text");
+}
+
+#[test]
+fn test_json_sink() {
+    check_dir_sink("json");
+}
+
+#[test]
+fn test_map_sink() {
+    check_dir_sink("map");
+}
+
+#[test]
+fn test_pack_sink() {
+    check_dir_sink("pack");
+}
+
+#[test]
+fn test_stdout_sink() {
+    check_golden(&golden_path("stdout.jsonl"), &run_solve_all("stdout", None));
+}
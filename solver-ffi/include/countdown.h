@@ -0,0 +1,88 @@
+#ifndef COUNTDOWN_H
+#define COUNTDOWN_H
+
+/* Generated by cbindgen from solver-ffi/src/lib.rs - do not edit */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Solutions for a target, shortest and simplest first
+typedef struct CountdownSolutions CountdownSolutions;
+
+// Solver holding the programs for each number of cards
+typedef struct CountdownSolver CountdownSolver;
+
+// Creates a solver. Free it with countdown_solver_free
+CountdownSolver *countdown_solver_new(void);
+
+// Frees a solver
+//
+// # Safety
+//
+// The solver must have been created by countdown_solver_new and not already freed, or be null
+void countdown_solver_free(CountdownSolver *solver);
+
+// Solves a puzzle with the TV rules, returning the solutions for the target (which may be none).
+// Returns null if the solver or cards are null or the number of cards is not between 1 and 6. Free
+// the solutions with countdown_solutions_free
+//
+// # Safety
+//
+// The solver must be valid, and cards must point to card_count values
+CountdownSolutions *countdown_solve(const CountdownSolver *solver,
+                                    const uint32_t *cards,
+                                    size_t card_count,
+                                    uint32_t target);
+
+// Solves a puzzle with a rules preset ("tv", "junior" or "relaxed"), returning the solutions for
+// the target (which may be none). Null rules are the TV rules. Returns null if the solver or cards
+// are null, the number of cards is not between 1 and 6 or the rules aren't known. Free the
+// solutions with countdown_solutions_free
+//
+// # Safety
+//
+// The solver must be valid, cards must point to card_count values and rules must be a nul
+// terminated string or null
+CountdownSolutions *countdown_solve_rules(const CountdownSolver *solver,
+                                          const uint32_t *cards,
+                                          size_t card_count,
+                                          uint32_t target,
+                                          const char *rules);
+
+// Returns the number of solutions
+//
+// # Safety
+//
+// The solutions must be valid or null
+size_t countdown_solutions_count(const CountdownSolutions *solutions);
+
+// Returns the infix equation of a solution, or null if the index is out of range
+//
+// # Safety
+//
+// The solutions must be valid or null
+const char *countdown_solutions_equation(const CountdownSolutions *solutions, size_t index);
+
+// Returns the RPN of a solution with card values, or null if the index is out of range
+//
+// # Safety
+//
+// The solutions must be valid or null
+const char *countdown_solutions_rpn(const CountdownSolutions *solutions, size_t index);
+
+// Returns the steps of a solution separated by newlines, or null if the index is out of range
+//
+// # Safety
+//
+// The solutions must be valid or null
+const char *countdown_solutions_steps(const CountdownSolutions *solutions, size_t index);
+
+// Frees solutions returned by countdown_solve or countdown_solve_rules
+//
+// # Safety
+//
+// The solutions must have been returned by countdown_solve or countdown_solve_rules and not
+// already freed, or be null
+void countdown_solutions_free(CountdownSolutions *solutions);
+
+#endif  /* COUNTDOWN_H */
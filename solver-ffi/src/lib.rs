@@ -0,0 +1,373 @@
+//! C API for the solver, for embedding it in other languages (eg. Swift or Kotlin on mobile).
+//!
+//! A solver is created with countdown_solver_new and generates the programs for each number of
+//! cards when first needed. countdown_solve (or countdown_solve_rules for other rules) returns the
+//! solutions for a target, which are read with the countdown_solutions_* functions. Strings returned
+//! belong to the solutions and are valid until they are freed with countdown_solutions_free. The
+//! header is include/countdown.h, generated with cbindgen (see cbindgen.toml).
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use std::slice;
+
+use solver::{normalise_cards, LazyPrograms, Ruleset, MAX_CARDS};
+
+/// Solver holding the programs for each number of cards
+pub struct CountdownSolver {
+    programs: LazyPrograms,
+}
+
+/// Solutions for a target, shortest and simplest first
+pub struct CountdownSolutions {
+    solutions: Vec<FormattedSolution>,
+}
+
+/// Formatted strings for a solution
+struct FormattedSolution {
+    equation: CString,
+    rpn: CString,
+    steps: CString,
+}
+
+/// Creates a solver. Free it with countdown_solver_free
+#[no_mangle]
+pub extern "C" fn countdown_solver_new() -> *mut CountdownSolver {
+    Box::into_raw(Box::new(CountdownSolver {
+        programs: LazyPrograms::new(),
+    }))
+}
+
+/// Frees a solver
+///
+/// # Safety
+///
+/// The solver must have been created by countdown_solver_new and not already freed, or be null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solver_free(solver: *mut CountdownSolver) {
+    if !solver.is_null() {
+        drop(Box::from_raw(solver));
+    }
+}
+
+/// Solves a puzzle with the TV rules, returning the solutions for the target (which may be none).
+/// Returns null if the solver or cards are null or the number of cards is not between 1 and 6. Free
+/// the solutions with countdown_solutions_free
+///
+/// # Safety
+///
+/// The solver must be valid, and cards must point to card_count values
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solve(
+    solver: *const CountdownSolver,
+    cards: *const u32,
+    card_count: usize,
+    target: u32,
+) -> *mut CountdownSolutions {
+    countdown_solve_rules(solver, cards, card_count, target, ptr::null())
+}
+
+/// Solves a puzzle with a rules preset ("tv", "junior" or "relaxed"), returning the solutions for
+/// the target (which may be none). Null rules are the TV rules. Returns null if the solver or cards
+/// are null, the number of cards is not between 1 and 6 or the rules aren't known. Free the
+/// solutions with countdown_solutions_free
+///
+/// # Safety
+///
+/// The solver must be valid, cards must point to card_count values and rules must be a nul
+/// terminated string or null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solve_rules(
+    solver: *const CountdownSolver,
+    cards: *const u32,
+    card_count: usize,
+    target: u32,
+    rules: *const c_char,
+) -> *mut CountdownSolutions {
+    if solver.is_null() || cards.is_null() || card_count == 0 || card_count > MAX_CARDS {
+        return ptr::null_mut();
+    }
+
+    let ruleset = if rules.is_null() {
+        Ruleset::Tv
+    } else {
+        match CStr::from_ptr(rules).to_str().map(str::parse::<Ruleset>) {
+            Ok(Ok(ruleset)) => ruleset,
+            _ => return ptr::null_mut(),
+        }
+    };
+
+    let rules = ruleset.rules();
+
+    let solver = &*solver;
+    let cards = normalise_cards(slice::from_raw_parts(cards, card_count));
+
+    let programs = solver.programs.get_or_generate(card_count, false);
+
+    let mut solutions = programs.run_target_rules(target, &cards, rules);
+
+    // Equal cards give identical equations
+    programs.filter_identical(&mut solutions, &cards);
+
+    programs.rank_solutions(&mut solutions, &cards);
+
+    // Card values and operators never contain a nul
+    let c_string = |s: String| CString::new(s).expect("Nul in formatted string");
+
+    let solutions = solutions
+        .iter()
+        .map(|s| FormattedSolution {
+            equation: c_string(programs.infix(s.program, &cards, false)),
+            rpn: c_string(programs.rpn(s.program, &cards, false)),
+            steps: c_string(
+                programs
+                    .steps_rules(s.program, &cards, false, rules)
+                    .join("\n"),
+            ),
+        })
+        .collect();
+
+    Box::into_raw(Box::new(CountdownSolutions { solutions }))
+}
+
+/// Returns the number of solutions
+///
+/// # Safety
+///
+/// The solutions must be valid or null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solutions_count(solutions: *const CountdownSolutions) -> usize {
+    solutions
+        .as_ref()
+        .map_or(0, |solutions| solutions.solutions.len())
+}
+
+/// Returns the infix equation of a solution, or null if the index is out of range
+///
+/// # Safety
+///
+/// The solutions must be valid or null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solutions_equation(
+    solutions: *const CountdownSolutions,
+    index: usize,
+) -> *const c_char {
+    solution_string(solutions, index, |s| &s.equation)
+}
+
+/// Returns the RPN of a solution with card values, or null if the index is out of range
+///
+/// # Safety
+///
+/// The solutions must be valid or null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solutions_rpn(
+    solutions: *const CountdownSolutions,
+    index: usize,
+) -> *const c_char {
+    solution_string(solutions, index, |s| &s.rpn)
+}
+
+/// Returns the steps of a solution separated by newlines, or null if the index is out of range
+///
+/// # Safety
+///
+/// The solutions must be valid or null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solutions_steps(
+    solutions: *const CountdownSolutions,
+    index: usize,
+) -> *const c_char {
+    solution_string(solutions, index, |s| &s.steps)
+}
+
+/// Frees solutions returned by countdown_solve or countdown_solve_rules
+///
+/// # Safety
+///
+/// The solutions must have been returned by countdown_solve or countdown_solve_rules and not
+/// already freed, or be null
+#[no_mangle]
+pub unsafe extern "C" fn countdown_solutions_free(solutions: *mut CountdownSolutions) {
+    if !solutions.is_null() {
+        drop(Box::from_raw(solutions));
+    }
+}
+
+/// Returns a string of a solution, or null if the solutions are null or the index is out of range
+unsafe fn solution_string(
+    solutions: *const CountdownSolutions,
+    index: usize,
+    string: impl Fn(&FormattedSolution) -> &CString,
+) -> *const c_char {
+    solutions
+        .as_ref()
+        .and_then(|solutions| solutions.solutions.get(index))
+        .map_or(ptr::null(), |solution| string(solution).as_ptr())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    /// Returns a string returned by the API
+    unsafe fn string(ptr: *const c_char) -> String {
+        assert!(!ptr.is_null());
+
+        CStr::from_ptr(ptr).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        unsafe {
+            let solver = countdown_solver_new();
+            let cards = [3, 25, 100, 7];
+
+            let solutions = countdown_solve(solver, cards.as_ptr(), cards.len(), 128);
+
+            assert!(!solutions.is_null());
+            assert!(countdown_solutions_count(solutions) > 0);
+
+            // The shortest solution comes first
+            assert_eq!(
+                "100 + 25 + 3",
+                string(countdown_solutions_equation(solutions, 0))
+            );
+            assert_eq!(
+                "100 25 + 3 +",
+                string(countdown_solutions_rpn(solutions, 0))
+            );
+            assert_eq!(
+                "100 + 25 = 125\n125 + 3 = 128",
+                string(countdown_solutions_steps(solutions, 0))
+            );
+
+            // Every solution has each string
+            let count = countdown_solutions_count(solutions);
+
+            for i in 0..count {
+                assert!(!string(countdown_solutions_equation(solutions, i)).is_empty());
+                assert!(!string(countdown_solutions_rpn(solutions, i)).is_empty());
+                assert!(!string(countdown_solutions_steps(solutions, i)).is_empty());
+            }
+
+            assert!(countdown_solutions_equation(solutions, count).is_null());
+
+            countdown_solutions_free(solutions);
+
+            // No solutions
+            let cards = [1, 2];
+            let solutions = countdown_solve(solver, cards.as_ptr(), cards.len(), 999);
+
+            assert_eq!(0, countdown_solutions_count(solutions));
+
+            countdown_solutions_free(solutions);
+
+            countdown_solver_free(solver);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_cards() {
+        unsafe {
+            let solver = countdown_solver_new();
+            let cards = [8, 100, 8];
+
+            let solutions = countdown_solve(solver, cards.as_ptr(), cards.len(), 108);
+
+            // Either 8 gives the same equation, which is only returned once
+            let equations = (0..countdown_solutions_count(solutions))
+                .map(|i| string(countdown_solutions_equation(solutions, i)))
+                .collect::<Vec<_>>();
+
+            assert_eq!(vec!["100 + 8"], equations);
+
+            countdown_solutions_free(solutions);
+            countdown_solver_free(solver);
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        unsafe {
+            let solver = countdown_solver_new();
+            let cards = [75, 6, 2, 1];
+
+            // 75 × (1 + 2 / 6) = 100 needs a fraction
+            let tv =
+                countdown_solve_rules(solver, cards.as_ptr(), cards.len(), 100, c"tv".as_ptr());
+            let relaxed = countdown_solve_rules(
+                solver,
+                cards.as_ptr(),
+                cards.len(),
+                100,
+                c"relaxed".as_ptr(),
+            );
+
+            assert_eq!(0, countdown_solutions_count(tv));
+            assert_ne!(0, countdown_solutions_count(relaxed));
+
+            countdown_solutions_free(tv);
+            countdown_solutions_free(relaxed);
+
+            // Unknown rules
+            assert!(countdown_solve_rules(
+                solver,
+                cards.as_ptr(),
+                cards.len(),
+                100,
+                c"none".as_ptr()
+            )
+            .is_null());
+
+            countdown_solver_free(solver);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        unsafe {
+            let solver = countdown_solver_new();
+            let cards = [1; MAX_CARDS + 1];
+
+            assert!(countdown_solve(solver, cards.as_ptr(), 0, 100).is_null());
+            assert!(countdown_solve(solver, cards.as_ptr(), cards.len(), 100).is_null());
+            assert!(countdown_solve(solver, ptr::null(), 1, 100).is_null());
+            assert!(countdown_solve(ptr::null(), cards.as_ptr(), 1, 100).is_null());
+
+            // Null pointers are accepted
+            assert_eq!(0, countdown_solutions_count(ptr::null()));
+            assert!(countdown_solutions_rpn(ptr::null(), 0).is_null());
+            countdown_solutions_free(ptr::null_mut());
+            countdown_solver_free(ptr::null_mut());
+
+            countdown_solver_free(solver);
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        // Every exported function must be declared in the header
+        let source = include_str!("lib.rs");
+        let header = include_str!("../include/countdown.h");
+
+        for line in source.lines() {
+            let Some(decl) = line
+                .strip_prefix("pub extern \"C\" fn ")
+                .or_else(|| line.strip_prefix("pub unsafe extern \"C\" fn "))
+            else {
+                continue;
+            };
+
+            let name = &decl[..decl.find('(').unwrap()];
+
+            assert!(
+                header.contains(&format!("{name}(")),
+                "{name} is missing from include/countdown.h, regenerate it with cbindgen"
+            );
+        }
+    }
+}
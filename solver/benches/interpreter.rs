@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use solver::{EvalStrategy, Programs};
+
+fn run_all(c: &mut Criterion) {
+    let programs = Programs::new(6, true, false);
+
+    c.bench_function("run_all", |b| {
+        b.iter(|| programs.run_all(&[100, 75, 50, 25, 10, 10]))
+    });
+
+    // Group the programs by skeleton before timing
+    programs.run_all_with(&[100, 75, 50, 25, 10, 10], EvalStrategy::Batch);
+
+    c.bench_function("run_all (batch)", |b| {
+        b.iter(|| programs.run_all_with(&[100, 75, 50, 25, 10, 10], EvalStrategy::Batch))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = run_all
+}
+
+criterion_main!(benches);
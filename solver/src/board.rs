@@ -0,0 +1,248 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for keeping card indexes and card values apart. Programs refer to the
+//! cards by index, so the RPN `0 1 +` adds the first two cards, while players write equations with
+//! card values, eg. `100 75 +`. A board holds the cards of a game and converts between the two.
+//!
+//! When RPN written with card values is parsed each value uses the first card with that value which
+//! hasn't been used already, so `10 10 +` uses both 10s and a third 10 is an error.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::programs::{ProgOp, Programs};
+
+/// Index of a card on a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CardIdx(pub u8);
+
+/// Value of a card
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CardVal(pub u32);
+
+impl fmt::Display for CardIdx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for CardVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Errors generated when parsing RPN written with card values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpnError {
+    /// The RPN contains a token which isn't a number or an operator
+    InvalidToken(String),
+    /// A value was used which isn't one of the remaining cards
+    CardNotAvailable(CardVal),
+    /// The operator at a position (zero based) doesn't have two values to work on
+    MissingOperand(usize),
+    /// The RPN leaves a number of values on the stack other than one
+    Unbalanced(usize),
+}
+
+impl fmt::Display for RpnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpnError::InvalidToken(token) => write!(f, "Invalid token '{token}'"),
+            RpnError::CardNotAvailable(value) => write!(f, "Card {value} is not available"),
+            RpnError::MissingOperand(pos) => {
+                write!(
+                    f,
+                    "Not enough values for the operator at position {}",
+                    pos + 1
+                )
+            }
+            RpnError::Unbalanced(0) => write!(f, "No values given"),
+            RpnError::Unbalanced(values) => {
+                write!(f, "{values} values are left (missing operators)")
+            }
+        }
+    }
+}
+
+impl Error for RpnError {}
+
+/// Checks the stack discipline of a program: every operator has two values to work on and exactly
+/// one value is left at the end
+pub fn validate_rpn(instructions: &[ProgOp]) -> Result<(), RpnError> {
+    let mut depth = 0usize;
+
+    for (pos, op) in instructions.iter().enumerate() {
+        if op.is_number() {
+            depth += 1;
+        } else if depth < 2 {
+            Err(RpnError::MissingOperand(pos))?
+        } else {
+            depth -= 1;
+        }
+    }
+
+    if depth != 1 {
+        Err(RpnError::Unbalanced(depth))?
+    }
+
+    Ok(())
+}
+
+/// The cards of a game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    cards: Vec<u32>,
+}
+
+impl Board {
+    /// Creates a board from the card values. The index of each card is its position in the slice
+    pub fn new(cards: &[u32]) -> Self {
+        Self {
+            cards: cards.to_vec(),
+        }
+    }
+
+    /// Returns the card values in index order, as taken by the program formatting functions
+    pub fn numbers(&self) -> &[u32] {
+        &self.cards
+    }
+
+    /// Returns the number of cards
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Returns true if there are no cards
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Returns the value of a card
+    pub fn value(&self, idx: CardIdx) -> CardVal {
+        CardVal(self.cards[idx.0 as usize])
+    }
+
+    /// Returns the indexes of the cards with a value
+    pub fn indexes(&self, value: CardVal) -> Vec<CardIdx> {
+        self.cards
+            .iter()
+            .enumerate()
+            .filter(|(_, &card)| card == value.0)
+            .map(|(i, _)| CardIdx(i as u8))
+            .collect()
+    }
+
+    /// Parses RPN written with card values (eg. `100 75 + 2 ×`) in to a program using card indexes
+    pub fn parse_rpn(&self, rpn: &str) -> Result<Programs, RpnError> {
+        let mut used = vec![false; self.cards.len()];
+        let mut instructions = Vec::new();
+
+        for token in rpn.split_whitespace() {
+            let op = match token {
+                "+" => ProgOp::PROG_OP_ADD,
+                "-" | "−" => ProgOp::PROG_OP_SUB,
+                "*" | "x" | "X" | "×" => ProgOp::PROG_OP_MUL,
+                "/" | "÷" => ProgOp::PROG_OP_DIV,
+                _ => {
+                    let value = CardVal(
+                        token
+                            .parse()
+                            .map_err(|_| RpnError::InvalidToken(token.to_string()))?,
+                    );
+
+                    let idx = self
+                        .indexes(value)
+                        .into_iter()
+                        .find(|idx| !used[idx.0 as usize])
+                        .ok_or(RpnError::CardNotAvailable(value))?;
+
+                    used[idx.0 as usize] = true;
+
+                    ProgOp::new_card(idx)
+                }
+            };
+
+            instructions.push(op);
+        }
+
+        validate_rpn(&instructions)?;
+
+        Ok(Programs::from_instructions(instructions))
+    }
+
+    /// Formats a program as RPN using card values, the reverse of parse_rpn
+    pub fn rpn(&self, programs: &Programs, prog_elem: usize) -> String {
+        programs.rpn(prog_elem, &self.cards, false)
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values() {
+        let board = Board::new(&[100, 10, 10, 3]);
+
+        assert_eq!(CardVal(10), board.value(CardIdx(2)));
+        assert_eq!(vec![CardIdx(1), CardIdx(2)], board.indexes(CardVal(10)));
+        assert!(board.indexes(CardVal(7)).is_empty());
+    }
+
+    #[test]
+    fn test_parse_rpn() {
+        let board = Board::new(&[100, 10, 10, 3]);
+
+        let programs = board.parse_rpn("100 10 10 + ×").unwrap();
+
+        assert_eq!(
+            Programs::from("0 1 2 + *").instructions(0),
+            programs.instructions(0)
+        );
+        assert_eq!(Ok(2000), programs.run(0, board.numbers()));
+        assert_eq!("100 10 10 + ×", board.rpn(&programs, 0));
+
+        assert_eq!(
+            Err(RpnError::CardNotAvailable(CardVal(10))),
+            board.parse_rpn("10 10 + 10 +").map(|_| ())
+        );
+        assert_eq!(
+            Err(RpnError::CardNotAvailable(CardVal(0))),
+            board.parse_rpn("0 1 +").map(|_| ())
+        );
+        assert_eq!(
+            Err(RpnError::InvalidToken("a".to_string())),
+            board.parse_rpn("100 a +").map(|_| ())
+        );
+        assert_eq!(
+            Err(RpnError::MissingOperand(1)),
+            board.parse_rpn("100 +").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_validate_rpn() {
+        let validate = |rpn: &str| {
+            let instructions = rpn
+                .split_whitespace()
+                .map(|token| match token {
+                    "+" => ProgOp::PROG_OP_ADD,
+                    "*" => ProgOp::PROG_OP_MUL,
+                    _ => ProgOp::new_number(token.parse().unwrap()),
+                })
+                .collect::<Vec<_>>();
+
+            validate_rpn(&instructions)
+        };
+
+        assert_eq!(Ok(()), validate("0"));
+        assert_eq!(Ok(()), validate("0 1 + 2 *"));
+        assert_eq!(Err(RpnError::MissingOperand(0)), validate("+"));
+        assert_eq!(Err(RpnError::MissingOperand(3)), validate("0 1 + * 2"));
+        assert_eq!(Err(RpnError::Unbalanced(2)), validate("0 1 2 +"));
+        assert_eq!(Err(RpnError::Unbalanced(0)), validate(""));
+    }
+}
@@ -0,0 +1,325 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for caching results on disk. The results for a set of cards don't
+//! depend on the order of the cards, so cards are normalised (sorted in to descending order) before
+//! being used in a cache key.
+//!
+//! Each entry is held in a separate file in the cache directory. Reading an entry updates the file
+//! modification time and when the number of entries grows past the capacity the least recently used
+//! entries are removed.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::programs::{Coverage, Programs, Solution};
+
+/// Default maximum number of cache entries
+const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Cache entry file extension
+const EXTENSION: &str = "cache";
+
+/// Cache format version, included in every key
+const VERSION: u32 = 1;
+
+/// Returns the cards in normalised (descending) order
+pub fn normalise_cards(cards: &[u32]) -> Vec<u32> {
+    let mut cards = cards.to_vec();
+
+    cards.sort_by(|a, b| b.cmp(a));
+
+    cards
+}
+
+/// Least recently used cache of results on disk
+pub struct DiskCache {
+    /// Cache directory
+    dir: PathBuf,
+    /// Maximum number of entries
+    capacity: usize,
+    /// Number of entries in the cache directory, counted when first needed
+    entries: Mutex<Option<usize>>,
+}
+
+impl DiskCache {
+    /// Opens a cache in a directory, creating the directory if necessary
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        Self::with_capacity(dir, DEFAULT_CAPACITY)
+    }
+
+    /// Opens a cache in a directory with a maximum number of entries, creating the directory if necessary
+    pub fn with_capacity(dir: impl Into<PathBuf>, capacity: usize) -> io::Result<Self> {
+        let dir = dir.into();
+
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            capacity: capacity.max(1),
+            entries: Mutex::new(None),
+        })
+    }
+
+    /// Returns the value for a key, marking the entry as recently used
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.path(key);
+
+        let value = fs::read_to_string(&path).ok()?;
+
+        // Update the modification time so the entry isn't evicted
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(value)
+    }
+
+    /// Stores the value for a key, evicting the least recently used entries if the cache is full
+    pub fn put(&self, key: &str, value: &str) -> io::Result<()> {
+        let path = self.path(key);
+        let existed = path.exists();
+
+        // Write to a temporary file and rename so readers never see a partial entry
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        fs::write(&tmp_path, value)?;
+        fs::rename(&tmp_path, &path)?;
+
+        if !existed {
+            let mut entries = self.entries.lock().unwrap();
+
+            let count = match *entries {
+                Some(count) => count + 1,
+                None => self.count()?,
+            };
+
+            *entries = Some(if count > self.capacity {
+                self.evict()?
+            } else {
+                count
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the cached coverage for a set of cards
+    pub fn coverage(&self, cards: &[u32]) -> Option<Coverage> {
+        Coverage::from_map(&self.get(&Self::coverage_key(cards))?)
+    }
+
+    /// Stores the coverage for a set of cards
+    pub fn put_coverage(&self, cards: &[u32], coverage: &Coverage) -> io::Result<()> {
+        self.put(&Self::coverage_key(cards), &coverage.to_map())
+    }
+
+    /// Returns the cached solutions for a target and set of cards. The solutions refer to program
+    /// elements of the given programs collection and the cards in normalised order
+    pub fn solutions(
+        &self,
+        programs: &Programs,
+        target: u32,
+        cards: &[u32],
+    ) -> Option<Vec<Solution>> {
+        let value = self.get(&Self::solutions_key(programs, target, cards))?;
+        let cards = normalise_cards(cards);
+
+        value
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let prog_elem = s.parse::<usize>().ok()?;
+
+                // Reject entries which don't give the target
+                if prog_elem >= programs.len() {
+                    return None;
+                }
+
+                programs
+                    .solution(prog_elem, &cards)
+                    .ok()
+                    .filter(|solution| solution.result == target)
+            })
+            .collect()
+    }
+
+    /// Stores the solutions for a target and set of cards. The solutions must refer to the cards in
+    /// normalised order
+    pub fn put_solutions(
+        &self,
+        programs: &Programs,
+        target: u32,
+        cards: &[u32],
+        solutions: &[Solution],
+    ) -> io::Result<()> {
+        let value = solutions
+            .iter()
+            .map(|s| s.program.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        self.put(&Self::solutions_key(programs, target, cards), &value)
+    }
+
+    /// Returns the coverage key for a set of cards
+    fn coverage_key(cards: &[u32]) -> String {
+        format!("cov-v{VERSION}-{}", Self::cards_key(cards))
+    }
+
+    /// Returns the solutions key for a target and set of cards. The programs fingerprint is included
+    /// so entries are only reused with the collection of programs which generated them
+    fn solutions_key(programs: &Programs, target: u32, cards: &[u32]) -> String {
+        format!(
+            "sol-v{VERSION}-{}-{target}-{}",
+            programs.fingerprint_hex(),
+            Self::cards_key(cards)
+        )
+    }
+
+    /// Returns the normalised cards as a key
+    fn cards_key(cards: &[u32]) -> String {
+        normalise_cards(cards)
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Returns the path of the file for a key
+    fn path(&self, key: &str) -> PathBuf {
+        assert!(
+            key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+            "Invalid cache key"
+        );
+
+        self.dir.join(format!("{key}.{EXTENSION}"))
+    }
+
+    /// Returns the cache entry files with their modification times
+    fn entry_files(&self) -> io::Result<Vec<(SystemTime, PathBuf)>> {
+        let mut files = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let path = entry.path();
+
+            if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                let modified = entry.metadata()?.modified()?;
+
+                files.push((modified, path));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Counts the entries in the cache
+    fn count(&self) -> io::Result<usize> {
+        Ok(self.entry_files()?.len())
+    }
+
+    /// Removes the least recently used entries, leaving the cache 90% full.
+    /// Returns the number of entries remaining
+    fn evict(&self) -> io::Result<usize> {
+        let mut files = self.entry_files()?;
+
+        let keep = (self.capacity * 9) / 10;
+
+        if files.len() > keep {
+            files.sort();
+
+            for (_, path) in files.drain(..files.len() - keep) {
+                let _ = fs::remove_file(path);
+            }
+        }
+
+        Ok(files.len())
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("solver-cache-{name}-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn test_normalise() {
+        assert_eq!(vec![100, 25, 7, 7, 1], normalise_cards(&[7, 100, 1, 7, 25]));
+    }
+
+    #[test]
+    fn test_get_put() {
+        let dir = test_dir("get-put");
+        let cache = DiskCache::new(&dir).unwrap();
+
+        assert_eq!(None, cache.get("key"));
+
+        cache.put("key", "value").unwrap();
+
+        assert_eq!(Some("value".to_string()), cache.get("key"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_eviction() {
+        let dir = test_dir("eviction");
+        let cache = DiskCache::with_capacity(&dir, 10).unwrap();
+
+        for i in 0..25 {
+            cache.put(&format!("key{i}"), "value").unwrap();
+        }
+
+        let count = cache.count().unwrap();
+
+        assert!(count <= 10);
+        assert!(count >= 9);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_coverage_and_solutions() {
+        let dir = test_dir("coverage");
+        let cache = DiskCache::new(&dir).unwrap();
+
+        let programs = Programs::new(4, false, false);
+        let cards = normalise_cards(&[3, 100, 7, 25]);
+
+        // Coverage is keyed by the multiset of cards
+        let coverage = programs.coverage(&cards);
+
+        cache.put_coverage(&cards, &coverage).unwrap();
+
+        assert_eq!(Some(coverage), cache.coverage(&[25, 7, 3, 100]));
+
+        // Solutions are keyed by the programs, target and cards
+        let solutions = programs.run_all_target(128, &cards);
+
+        assert!(cache.solutions(&programs, 128, &cards).is_none());
+
+        cache
+            .put_solutions(&programs, 128, &cards, &solutions)
+            .unwrap();
+
+        assert_eq!(Some(solutions), cache.solutions(&programs, 128, &cards));
+        assert!(cache
+            .solutions(&Programs::new(4, true, false), 128, &cards)
+            .is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for cancelling long running program generation and runs. A token is
+//! shared between the thread doing the work and any thread wanting to stop it. The work checks the
+//! token every few thousand programs and returns `Cancelled` once it has been cancelled or its
+//! deadline has passed.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Number of programs generated or run between checks of a cancellation token. Must be a power of 2
+pub(crate) const CANCEL_CHECK_INTERVAL: usize = 1 << 14;
+
+/// Token used to cancel generating or running programs. Clones share the same state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token which is cancelled by calling cancel
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token which is cancelled by calling cancel or when the deadline passes
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Cancels the work using the token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed)
+    }
+
+    /// Returns true if the token has been cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Returns an error if the token has been cancelled
+    pub(crate) fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)?
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned when work is cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_cancel() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+
+        assert_eq!(Ok(()), token.check());
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+        assert_eq!(Err(Cancelled), token.check());
+    }
+
+    #[test]
+    fn test_deadline() {
+        let token = CancellationToken::with_deadline(Instant::now() + Duration::from_secs(3600));
+
+        assert!(!token.is_cancelled());
+
+        let token = CancellationToken::with_deadline(Instant::now());
+
+        assert!(token.is_cancelled());
+    }
+}
@@ -0,0 +1,84 @@
+#![warn(missing_docs)]
+
+//! This module describes what the solver supports so front ends can adapt to it without
+//! hardcoding the operators, number of cards or target range.
+
+use crate::programs::ProgOp;
+pub use crate::programs::{MAX_TARGET, MIN_TARGET};
+
+/// Maximum number of cards in a game
+pub const MAX_CARDS: usize = 6;
+
+/// Description of the solver's capabilities
+pub struct Capabilities {
+    /// Solver version
+    pub version: &'static str,
+    /// Operators supported
+    pub operators: &'static [ProgOp],
+    /// Maximum number of cards in a game
+    pub max_cards: usize,
+    /// Lowest target in the standard game. Any positive target can be solved
+    pub min_target: u32,
+    /// Highest target in the standard game. Any positive target can be solved
+    pub max_target: u32,
+    /// Rule presets supported (see Ruleset). Each card may be used at most once in every preset
+    pub rulesets: &'static [&'static str],
+}
+
+/// The solver's capabilities
+pub const CAPABILITIES: Capabilities = Capabilities {
+    version: env!("CARGO_PKG_VERSION"),
+    operators: &ProgOp::OPERATORS,
+    max_cards: MAX_CARDS,
+    min_target: MIN_TARGET,
+    max_target: MAX_TARGET,
+    rulesets: &["tv", "junior", "relaxed"],
+};
+
+impl Capabilities {
+    /// Returns the capabilities as a JSON object
+    pub fn to_json(&self) -> String {
+        let operators = self
+            .operators
+            .iter()
+            .filter_map(|op| {
+                Some(format!(
+                    "{{\"symbol\":\"{}\",\"name\":\"{}\"}}",
+                    op.symbol()?,
+                    op.name()?
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let rulesets = self
+            .rulesets
+            .iter()
+            .map(|r| format!("\"{r}\""))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"version\":\"{}\",\"operators\":[{operators}],\"max_cards\":{},\"targets\":{{\"min\":{},\"max\":{}}},\"rulesets\":[{rulesets}]}}",
+            self.version, self.max_cards, self.min_target, self.max_target
+        )
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            format!(
+                "{{\"version\":\"{}\",\"operators\":[{{\"symbol\":\"+\",\"name\":\"add\"}},{{\"symbol\":\"-\",\"name\":\"subtract\"}},{{\"symbol\":\"×\",\"name\":\"multiply\"}},{{\"symbol\":\"/\",\"name\":\"divide\"}}],\"max_cards\":6,\"targets\":{{\"min\":100,\"max\":999}},\"rulesets\":[\"tv\",\"junior\",\"relaxed\"]}}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            CAPABILITIES.to_json()
+        );
+    }
+}
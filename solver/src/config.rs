@@ -0,0 +1,224 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for the configuration file giving default options for the binaries.
+//!
+//! The configuration is built from layers, each overriding the one before:
+//!  * The defaults of each binary
+//!  * The user's configuration file, `countdown/config.toml` in the user's configuration directory
+//!    (eg. `~/.config/countdown/config.toml`), if it exists
+//!  * A configuration file given with `--config`
+//!  * Command line arguments
+//!
+//! Every setting is optional, and a binary ignores settings it has no use for. For example:
+//!
+//! ```toml
+//! threads = 8
+//! output_dir = "/data/countdown"
+//! special_cards = false
+//! rules = "tv"
+//! colour = false
+//! machine = true
+//! symbols = "ascii"
+//! ```
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::format::SymbolSet;
+use crate::rules::Ruleset;
+
+/// Default options from the configuration files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Number of threads to run
+    pub threads: Option<usize>,
+    /// Output directory
+    pub output_dir: Option<PathBuf>,
+    /// Use the special edition card set instead of the deck of the rules
+    pub special_cards: Option<bool>,
+    /// Rule preset
+    pub rules: Option<Ruleset>,
+    /// Colour the output
+    pub colour: Option<bool>,
+    /// Format numbers as plain digits whatever the locale
+    pub machine: Option<bool>,
+    /// Symbol set for operators
+    pub symbols: Option<SymbolSet>,
+}
+
+/// Errors generated when loading a configuration file
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file couldn't be read
+    Read(PathBuf, io::Error),
+    /// The file isn't valid TOML or has an invalid setting
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => {
+                write!(f, "Unable to read config file {} ({e})", path.display())
+            }
+            ConfigError::Invalid(path, e) => {
+                write!(f, "Invalid config file {} ({e})", path.display())
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Settings as written in a configuration file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    threads: Option<usize>,
+    output_dir: Option<PathBuf>,
+    special_cards: Option<bool>,
+    rules: Option<String>,
+    colour: Option<bool>,
+    machine: Option<bool>,
+    symbols: Option<String>,
+}
+
+impl Config {
+    /// Returns the default configuration file path in the user's configuration directory
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("countdown").join("config.toml"))
+    }
+
+    /// Loads the configuration from the default file if it exists, with the settings from a file
+    /// given on the command line layered on top. The file given on the command line must exist
+    pub fn load(file: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match Self::default_path() {
+            Some(path) if path.exists() => Self::from_file(&path)?,
+            _ => Self::default(),
+        };
+
+        if let Some(file) = file {
+            config = config.layer(Self::from_file(file)?);
+        }
+
+        Ok(config)
+    }
+
+    /// Loads the configuration from a file
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let toml =
+            fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+
+        Self::from_toml(&toml).map_err(|e| ConfigError::Invalid(path.to_path_buf(), e))
+    }
+
+    /// Parses the configuration from TOML
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(toml).map_err(|e| e.message().to_string())?;
+
+        Ok(Self {
+            threads: file.threads,
+            output_dir: file.output_dir,
+            special_cards: file.special_cards,
+            rules: file.rules.map(|rules| rules.parse()).transpose()?,
+            colour: file.colour,
+            machine: file.machine,
+            symbols: file.symbols.map(|symbols| symbols.parse()).transpose()?,
+        })
+    }
+
+    /// Returns the configuration with the settings of another configuration layered on top
+    pub fn layer(self, over: Self) -> Self {
+        Self {
+            threads: over.threads.or(self.threads),
+            output_dir: over.output_dir.or(self.output_dir),
+            special_cards: over.special_cards.or(self.special_cards),
+            rules: over.rules.or(self.rules),
+            colour: over.colour.or(self.colour),
+            machine: over.machine.or(self.machine),
+            symbols: over.symbols.or(self.symbols),
+        }
+    }
+}
+
+/// Replaces a default argument value with a configured value. Values given on the command line are
+/// kept
+pub fn apply_config<T>(value: &mut T, on_command_line: bool, configured: Option<T>) {
+    if !on_command_line {
+        if let Some(configured) = configured {
+            *value = configured;
+        }
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(
+            "threads = 4\noutput_dir = \"results\"\nrules = \"junior\"\nsymbols = \"ascii\"\ncolour = false\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Config {
+                threads: Some(4),
+                output_dir: Some(PathBuf::from("results")),
+                special_cards: None,
+                rules: Some(Ruleset::Junior),
+                colour: Some(false),
+                machine: None,
+                symbols: Some(SymbolSet::Ascii),
+            },
+            config
+        );
+
+        assert_eq!(Ok(Config::default()), Config::from_toml(""));
+
+        // Invalid settings
+        assert!(Config::from_toml("rules = \"chess\"").is_err());
+        assert!(Config::from_toml("symbols = \"emoji\"").is_err());
+        assert!(Config::from_toml("threads = \"many\"").is_err());
+        assert!(Config::from_toml("colour = true\ncolor = true").is_err());
+    }
+
+    #[test]
+    fn test_layers() {
+        let user = Config::from_toml("threads = 4\nrules = \"junior\"").unwrap();
+        let file = Config::from_toml("threads = 8\nmachine = true").unwrap();
+
+        let config = user.layer(file);
+
+        assert_eq!(Some(8), config.threads);
+        assert_eq!(Some(Ruleset::Junior), config.rules);
+        assert_eq!(Some(true), config.machine);
+        assert_eq!(None, config.colour);
+
+        // Command line values win
+        let mut threads = 2;
+        apply_config(&mut threads, true, config.threads);
+        assert_eq!(2, threads);
+
+        apply_config(&mut threads, false, config.threads);
+        assert_eq!(8, threads);
+
+        apply_config(&mut threads, false, None);
+        assert_eq!(8, threads);
+    }
+}
@@ -0,0 +1,159 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for grading how difficult a target is to reach for a set of cards.
+//!
+//! The grade is derived from a score made up of:
+//!  * The effort required by the easiest solution - two points per operation, plus one point for each
+//!    multiplication, two points for each division and one point for each pair of brackets
+//!  * The scarcity of solutions - fewer distinct solutions adds up to four points
+
+use std::fmt;
+
+use crate::programs::{Programs, Solution};
+
+/// Difficulty grade of a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Grade {
+    /// Target can be reached with little effort
+    Easy,
+    /// Target requires a few operations
+    Moderate,
+    /// Target requires a longer or more unusual calculation
+    Hard,
+    /// Target has very few solutions which are all long
+    VeryHard,
+    /// Target can't be reached
+    Impossible,
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let desc = match self {
+            Grade::Easy => "Easy",
+            Grade::Moderate => "Moderate",
+            Grade::Hard => "Hard",
+            Grade::VeryHard => "Very hard",
+            Grade::Impossible => "Impossible",
+        };
+
+        write!(f, "{desc}")
+    }
+}
+
+/// Details of the difficulty of a target for a set of cards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difficulty {
+    /// Difficulty grade
+    pub grade: Grade,
+    /// Difficulty score (higher is harder)
+    pub score: u32,
+    /// Number of distinct solutions
+    pub solutions: usize,
+    /// Number of operations in the shortest solution
+    pub min_operations: Option<usize>,
+    /// Program element of the easiest solution
+    pub easiest: Option<usize>,
+}
+
+/// Calculates the difficulty of reaching a target with a set of cards
+pub fn difficulty(programs: &Programs, target: u32, numbers: &[u32]) -> Difficulty {
+    let mut solutions = programs.run_all_target(target, numbers);
+
+    // Filter out duplicated and identical solutions
+    programs.filter_duplicates(&mut solutions);
+    programs.filter_identical(&mut solutions, numbers);
+
+    // Find the easiest solution
+    let easiest = solutions
+        .iter()
+        .map(|s| (effort(programs, s, numbers), s.program))
+        .min();
+
+    let Some((effort, easiest)) = easiest else {
+        return Difficulty {
+            grade: Grade::Impossible,
+            score: 0,
+            solutions: 0,
+            min_operations: None,
+            easiest: None,
+        };
+    };
+
+    let min_operations = solutions.iter().map(|s| s.operations()).min();
+
+    let scarcity = match solutions.len() {
+        1..=2 => 4,
+        3..=9 => 2,
+        10..=49 => 1,
+        _ => 0,
+    };
+
+    let score = effort + scarcity;
+
+    let grade = match score {
+        0..=6 => Grade::Easy,
+        7..=10 => Grade::Moderate,
+        11..=14 => Grade::Hard,
+        _ => Grade::VeryHard,
+    };
+
+    Difficulty {
+        grade,
+        score,
+        solutions: solutions.len(),
+        min_operations,
+        easiest: Some(easiest),
+    }
+}
+
+/// Calculates the effort required to perform the calculations in a solution
+fn effort(programs: &Programs, solution: &Solution, numbers: &[u32]) -> u32 {
+    let ops = solution.ops();
+    let metrics = solution.metrics(programs, numbers);
+
+    (ops.add as u32 + ops.sub as u32) * 2
+        + ops.mul as u32 * 3
+        + ops.div as u32 * 4
+        + metrics.brackets as u32
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impossible() {
+        let programs = Programs::new(2, false, false);
+
+        let diff = difficulty(&programs, 999, &[1, 2]);
+
+        assert_eq!(Grade::Impossible, diff.grade);
+        assert_eq!(0, diff.solutions);
+        assert_eq!(None, diff.min_operations);
+    }
+
+    #[test]
+    fn test_easy() {
+        let programs = Programs::new(2, false, false);
+
+        let diff = difficulty(&programs, 102, &[100, 2]);
+
+        assert_eq!(Grade::Easy, diff.grade);
+        assert_eq!(1, diff.solutions);
+        assert_eq!(Some(1), diff.min_operations);
+    }
+
+    #[test]
+    fn test_harder() {
+        let programs = Programs::new(4, false, false);
+
+        let easy = difficulty(&programs, 82, &[75, 7, 3, 2]);
+        let hard = difficulty(&programs, 239, &[75, 7, 3, 2]);
+
+        assert_eq!(Grade::Easy, easy.grade);
+        assert_eq!(Grade::Hard, hard.grade);
+        assert!(easy.score < hard.score);
+    }
+}
@@ -0,0 +1,225 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for deciding if two RPN programs are algebraically equivalent, ie. they
+//! give the same answer for any values of the cards. The game rules (positive whole number
+//! intermediate results etc.) are ignored.
+//!
+//! Both programs are first converted to a normal form where nested additions and subtractions (and
+//! multiplications and divisions) are flattened in to a single group with the terms sorted. If the
+//! normal forms match the programs are equivalent. Otherwise (eg. (a + b) × c and a × c + b × c) both
+//! programs are evaluated with exact fractions for a number of pseudo random card values.
+
+use crate::programs::{ProgOp, Programs};
+use crate::rng::SplitMix64;
+
+/// Number of random card value assignments to evaluate
+const ASSIGNMENTS: usize = 16;
+
+/// Maximum number of attempts to find assignments which can be evaluated
+const MAX_ATTEMPTS: usize = 64;
+
+/// Returns true if two RPN programs give the same answer for any values of the cards
+pub fn expressions_equivalent(a: &[ProgOp], b: &[ProgOp]) -> bool {
+    if normal_form(a) == normal_form(b) {
+        return true;
+    }
+
+    let max_num = a
+        .iter()
+        .chain(b.iter())
+        .filter(|op| op.is_number())
+        .map(|op| op.bits() as usize)
+        .max()
+        .unwrap_or(0);
+
+    let mut rng = SplitMix64(max_num as u64);
+    let mut values = vec![0; max_num + 1];
+    let mut stack = Vec::with_capacity(a.len().max(b.len()));
+    let mut evaluated = 0;
+
+    for _ in 0..MAX_ATTEMPTS {
+        for value in values.iter_mut() {
+            *value = 2 + rng.below(999) as i128;
+        }
+
+        // Skip assignments giving division by zero or overflow
+        if let (Some(ans_a), Some(ans_b)) = (
+            evaluate(a, &values, &mut stack),
+            evaluate(b, &values, &mut stack),
+        ) {
+            if ans_a != ans_b {
+                return false;
+            }
+
+            evaluated += 1;
+
+            if evaluated == ASSIGNMENTS {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Normal form of a program
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Norm {
+    /// A card
+    Number(u8),
+    /// A group of terms added and subtracted (additive) or multiplied and divided
+    Group {
+        additive: bool,
+        positive: Vec<Norm>,
+        negative: Vec<Norm>,
+    },
+}
+
+/// Converts a program to normal form
+pub(crate) fn normal_form(instructions: &[ProgOp]) -> Option<Norm> {
+    let mut stack = Vec::with_capacity(instructions.len());
+
+    Programs::process_instructions(
+        instructions,
+        &mut stack,
+        |n| Some(Norm::Number(n)),
+        |t1, op, t2| {
+            let (additive, invert) = match op & ProgOp::PROG_OP_MASK {
+                ProgOp::PROG_OP_ADD => (true, false),
+                ProgOp::PROG_OP_SUB => (true, true),
+                ProgOp::PROG_OP_MUL => (false, false),
+                ProgOp::PROG_OP_DIV => (false, true),
+                _ => panic!("Operator expected"),
+            };
+
+            // Split a term in to positive and negative terms, flattening compatible groups
+            let split = |term| match term {
+                Norm::Group {
+                    additive: grp_additive,
+                    positive,
+                    negative,
+                } if grp_additive == additive => (positive, negative),
+                _ => (vec![term], Vec::new()),
+            };
+
+            let (mut positive, mut negative) = split(t1);
+            let (t2_positive, t2_negative) = split(t2);
+
+            if invert {
+                positive.extend(t2_negative);
+                negative.extend(t2_positive);
+            } else {
+                positive.extend(t2_positive);
+                negative.extend(t2_negative);
+            }
+
+            positive.sort();
+            negative.sort();
+
+            Some(Norm::Group {
+                additive,
+                positive,
+                negative,
+            })
+        },
+    )
+}
+
+/// Evaluates a program with exact fractions returning the reduced numerator and denominator.
+/// Returns None on division by zero or overflow
+fn evaluate(
+    instructions: &[ProgOp],
+    values: &[i128],
+    stack: &mut Vec<(i128, i128)>,
+) -> Option<(i128, i128)> {
+    let (n, d) = Programs::process_instructions(
+        instructions,
+        stack,
+        |n| Some((values[n as usize], 1)),
+        |(n2, d2), op, (n1, d1)| {
+            let (n, d) = match op & ProgOp::PROG_OP_MASK {
+                ProgOp::PROG_OP_ADD => (
+                    n2.checked_mul(d1)?.checked_add(n1.checked_mul(d2)?)?,
+                    d2.checked_mul(d1)?,
+                ),
+                ProgOp::PROG_OP_SUB => (
+                    n2.checked_mul(d1)?.checked_sub(n1.checked_mul(d2)?)?,
+                    d2.checked_mul(d1)?,
+                ),
+                ProgOp::PROG_OP_MUL => (n2.checked_mul(n1)?, d2.checked_mul(d1)?),
+                ProgOp::PROG_OP_DIV => (n2.checked_mul(d1)?, d2.checked_mul(n1)?),
+                _ => panic!("Operator expected"),
+            };
+
+            reduce(n, d)
+        },
+    )?;
+
+    Some((n, d))
+}
+
+/// Reduces a fraction, keeping the denominator positive
+fn reduce(n: i128, d: i128) -> Option<(i128, i128)> {
+    if d == 0 {
+        None?
+    }
+
+    let mut a = n.abs();
+    let mut b = d.abs();
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    let sign = d.signum();
+
+    Some((sign * n / a, sign * d / a))
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn equivalent(a: &str, b: &str) -> bool {
+        let a: Programs = a.into();
+        let b: Programs = b.into();
+
+        expressions_equivalent(a.instructions(0), b.instructions(0))
+    }
+
+    #[test]
+    fn test_normal_form() {
+        assert!(equivalent("0 1 +", "1 0 +"));
+        assert!(equivalent("0 1 2 + -", "0 1 - 2 -"));
+        assert!(equivalent("0 1 2 - -", "0 2 + 1 -"));
+        assert!(equivalent("0 1 2 / /", "0 2 * 1 /"));
+        assert!(equivalent("0 1 * 2 3 + *", "2 3 + 1 0 * *"));
+    }
+
+    #[test]
+    fn test_evaluation() {
+        // Distributive
+        assert!(equivalent("0 1 + 2 *", "0 2 * 1 2 * +"));
+        // Cancelling terms
+        assert!(equivalent("0 1 + 1 -", "0"));
+        assert!(equivalent("0 1 * 1 /", "0"));
+    }
+
+    #[test]
+    fn test_not_equivalent() {
+        assert!(!equivalent("0 1 -", "1 0 -"));
+        assert!(!equivalent("0 1 /", "1 0 /"));
+        assert!(!equivalent("0 1 + 2 *", "0 1 2 * +"));
+        assert!(!equivalent("0 1 +", "0 2 +"));
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(Some((2, 3)), reduce(4, 6));
+        assert_eq!(Some((-2, 3)), reduce(4, -6));
+        assert_eq!(Some((0, 1)), reduce(0, 5));
+        assert_eq!(None, reduce(1, 0));
+    }
+}
@@ -0,0 +1,347 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for evaluating an infix expression written using card values, such as
+//! an answer given by a player, and checking it follows the rules of the game:
+//!  * Each card may only be used once
+//!  * All intermediate results must be positive integers, unless the rules allow negative or
+//!    fractional intermediate results. The answer must always be a positive integer
+//!
+//! Multiplication may be written as '*', 'x' or '×' and division as '/' or '÷'.
+
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+
+use crate::rules::{Rules, Ruleset};
+
+/// A fraction (numerator, denominator) in its lowest terms with a positive denominator
+type Fraction = (i64, i64);
+
+/// Errors generated when evaluating an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    /// The expression contains an unexpected character
+    InvalidCharacter(char),
+    /// The expression is not well formed
+    Syntax,
+    /// A number was used which isn't one of the remaining cards
+    CardNotAvailable(u32),
+    /// An intermediate result was zero or negative
+    NotPositive,
+    /// A division gave a fraction
+    NonInteger,
+    /// An intermediate result was too large
+    TooLarge,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::InvalidCharacter(c) => write!(f, "Invalid character '{c}'"),
+            ExprError::Syntax => write!(f, "Invalid expression"),
+            ExprError::CardNotAvailable(n) => write!(f, "Card {n} is not available"),
+            ExprError::NotPositive => write!(f, "Intermediate result is not positive"),
+            ExprError::NonInteger => write!(f, "Intermediate result is not a whole number"),
+            ExprError::TooLarge => write!(f, "Intermediate result is too large"),
+        }
+    }
+}
+
+impl Error for ExprError {}
+
+/// Evaluates an infix expression using the given card values with the TV rules
+pub fn evaluate(expr: &str, cards: &[u32]) -> Result<u32, ExprError> {
+    evaluate_with_rules(expr, cards, Ruleset::Tv.rules())
+}
+
+/// Evaluates an infix expression using the given card values with a set of rules
+pub fn evaluate_with_rules(expr: &str, cards: &[u32], rules: &Rules) -> Result<u32, ExprError> {
+    let tokens = tokenise(expr)?;
+
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+        cards: cards.to_vec(),
+        rules,
+    };
+
+    let (n, d) = parser.expr()?;
+
+    if parser.tokens.next().is_some() {
+        Err(ExprError::Syntax)?
+    }
+
+    if n <= 0 {
+        Err(ExprError::NotPositive)?
+    }
+
+    if d != 1 {
+        Err(ExprError::NonInteger)?
+    }
+
+    u32::try_from(n).map_err(|_| ExprError::TooLarge)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Number(u32),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Open,
+    Close,
+}
+
+fn tokenise(expr: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '0'..='9' => {
+                let mut n = c.to_digit(10).unwrap();
+
+                while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    n = n
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(d))
+                        .ok_or(ExprError::TooLarge)?;
+                    chars.next();
+                }
+
+                Token::Number(n)
+            }
+            '+' => Token::Add,
+            '-' | '−' => Token::Sub,
+            '*' | 'x' | 'X' | '×' => Token::Mul,
+            '/' | '÷' => Token::Div,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            _ if c.is_whitespace() => continue,
+            _ => Err(ExprError::InvalidCharacter(c))?,
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a, I: Iterator<Item = Token>> {
+    tokens: Peekable<I>,
+    cards: Vec<u32>,
+    rules: &'a Rules,
+}
+
+impl<I: Iterator<Item = Token>> Parser<'_, I> {
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Fraction, ExprError> {
+        let mut n1 = self.term()?;
+
+        while let Some(op @ (Token::Add | Token::Sub)) = self.tokens.peek().copied() {
+            self.tokens.next();
+
+            let n2 = self.term()?;
+
+            n1 = self.apply(n1, op, n2)?;
+        }
+
+        Ok(n1)
+    }
+
+    /// term := factor (('*' | '/') factor)*
+    fn term(&mut self) -> Result<Fraction, ExprError> {
+        let mut n1 = self.factor()?;
+
+        while let Some(op @ (Token::Mul | Token::Div)) = self.tokens.peek().copied() {
+            self.tokens.next();
+
+            let n2 = self.factor()?;
+
+            n1 = self.apply(n1, op, n2)?;
+        }
+
+        Ok(n1)
+    }
+
+    /// factor := number | '(' expr ')'
+    fn factor(&mut self) -> Result<Fraction, ExprError> {
+        match self.tokens.next() {
+            Some(Token::Number(n)) => {
+                // Use up the card
+                let pos = self
+                    .cards
+                    .iter()
+                    .position(|&c| c == n)
+                    .ok_or(ExprError::CardNotAvailable(n))?;
+
+                self.cards.swap_remove(pos);
+
+                Ok((n as i64, 1))
+            }
+            Some(Token::Open) => {
+                let n = self.expr()?;
+
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(n),
+                    _ => Err(ExprError::Syntax),
+                }
+            }
+            _ => Err(ExprError::Syntax),
+        }
+    }
+
+    fn apply(
+        &self,
+        (n1, d1): Fraction,
+        op: Token,
+        (n2, d2): Fraction,
+    ) -> Result<Fraction, ExprError> {
+        let too_large = || ExprError::TooLarge;
+
+        let (n, d) = match op {
+            Token::Add | Token::Sub => {
+                let a = n1.checked_mul(d2).ok_or_else(too_large)?;
+                let b = n2.checked_mul(d1).ok_or_else(too_large)?;
+
+                let n = if op == Token::Add {
+                    a.checked_add(b)
+                } else {
+                    a.checked_sub(b)
+                };
+
+                (
+                    n.ok_or_else(too_large)?,
+                    d1.checked_mul(d2).ok_or_else(too_large)?,
+                )
+            }
+            Token::Mul => (
+                n1.checked_mul(n2).ok_or_else(too_large)?,
+                d1.checked_mul(d2).ok_or_else(too_large)?,
+            ),
+            Token::Div => {
+                if n2 == 0 {
+                    Err(ExprError::NonInteger)?
+                }
+
+                (
+                    n1.checked_mul(d2).ok_or_else(too_large)?,
+                    d1.checked_mul(n2).ok_or_else(too_large)?,
+                )
+            }
+            _ => panic!("Operator expected"),
+        };
+
+        // Normalise so the denominator is positive and the fraction is in its lowest terms
+        let g = gcd(n, d) * d.signum();
+        let (n, d) = (n / g, d / g);
+
+        if n == 0 || (n < 0 && !self.rules.negative) {
+            Err(ExprError::NotPositive)?
+        }
+
+        if d != 1 && !self.rules.fractions {
+            Err(ExprError::NonInteger)?
+        }
+
+        // Values must fit the solver's integers
+        if n.unsigned_abs() > u32::MAX as u64 || d > u32::MAX as i64 {
+            Err(ExprError::TooLarge)?
+        }
+
+        Ok((n, d))
+    }
+}
+
+/// Greatest common divisor of the magnitudes
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let cards = [100, 75, 50, 25, 10, 10];
+
+        assert_eq!(Ok(175), evaluate("100 + 75", &cards));
+        assert_eq!(
+            Ok(192),
+            evaluate("100 × ((25 × 10) - 10) / (75 + 50)", &cards)
+        );
+        assert_eq!(Ok(192), evaluate("100*((25x10)-10)/(75+50)", &cards));
+        assert_eq!(Ok(20), evaluate("10 + 10", &cards));
+        assert_eq!(Ok(60), evaluate("100 - 50 + 10", &cards));
+        assert_eq!(Ok(5), evaluate("100 / 10 / 2", &[100, 10, 2]));
+    }
+
+    #[test]
+    fn test_errors() {
+        let cards = [100, 75, 50, 25, 10, 10];
+
+        assert_eq!(
+            Err(ExprError::CardNotAvailable(7)),
+            evaluate("100 + 7", &cards)
+        );
+        assert_eq!(
+            Err(ExprError::CardNotAvailable(100)),
+            evaluate("100 + 100", &cards)
+        );
+        assert_eq!(
+            Err(ExprError::CardNotAvailable(10)),
+            evaluate("10 + 10 + 10", &cards)
+        );
+        assert_eq!(Err(ExprError::NotPositive), evaluate("50 - 75", &cards));
+        assert_eq!(Err(ExprError::NotPositive), evaluate("10 - 10", &cards));
+        assert_eq!(Err(ExprError::NonInteger), evaluate("75 / 10", &cards));
+        assert_eq!(Err(ExprError::Syntax), evaluate("100 +", &cards));
+        assert_eq!(Err(ExprError::Syntax), evaluate("(100 + 75", &cards));
+        assert_eq!(Err(ExprError::Syntax), evaluate("100 75", &cards));
+        assert_eq!(Err(ExprError::Syntax), evaluate("", &cards));
+        assert_eq!(
+            Err(ExprError::InvalidCharacter('^')),
+            evaluate("100 ^ 2", &cards)
+        );
+    }
+
+    #[test]
+    fn test_relaxed() {
+        let cards = [75, 6, 2, 1];
+        let relaxed = Ruleset::Relaxed.rules();
+
+        assert_eq!(
+            Ok(100),
+            evaluate_with_rules("75 × (1 + 2 / 6)", &cards, relaxed)
+        );
+        assert_eq!(
+            Ok(296),
+            evaluate_with_rules("(2 - 6) × (1 - 75)", &cards, relaxed)
+        );
+        assert_eq!(Ok(150), evaluate_with_rules("75 × 2", &cards, relaxed));
+
+        // The answer must still be a positive integer
+        assert_eq!(
+            Err(ExprError::NotPositive),
+            evaluate_with_rules("2 - 6", &cards, relaxed)
+        );
+        assert_eq!(
+            Err(ExprError::NonInteger),
+            evaluate_with_rules("75 / 6", &cards, relaxed)
+        );
+
+        assert_eq!(
+            Err(ExprError::NonInteger),
+            evaluate("75 × (1 + 2 / 6)", &cards)
+        );
+    }
+}
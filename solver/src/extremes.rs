@@ -0,0 +1,180 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for finding the largest value which can be made from a set of
+//! numbers, and the smallest value above a floor, without enumerating every reachable value.
+//!
+//! Both search the ways of repeatedly replacing two of the remaining values with the result of
+//! combining them, skipping sets of remaining values which have already been searched. The largest
+//! value search also skips sets of values which can't beat the best value found so far, and the
+//! smallest value search stops as soon as the value just above the floor is found.
+//!
+//! Results follow the TV rules: every intermediate result must be a positive integer which fits in
+//! 32 bits.
+
+use std::collections::HashSet;
+
+/// Returns the largest value which can be made from the numbers with the TV rules, using each
+/// number at most once. Returns None if there are no numbers
+pub fn max_reachable(numbers: &[u32]) -> Option<u32> {
+    let mut best = *numbers.iter().max()?;
+
+    max_search(&sorted(numbers), &mut best, &mut HashSet::new());
+
+    Some(best)
+}
+
+/// Returns the smallest value greater than the floor which can be made from the numbers with the TV
+/// rules, using each number at most once. Returns None if no value above the floor can be made
+pub fn min_reachable_above(numbers: &[u32], floor: u32) -> Option<u32> {
+    let mut best = None;
+
+    if floor < u32::MAX {
+        min_above_search(&sorted(numbers), floor, &mut best, &mut HashSet::new());
+    }
+
+    best
+}
+
+/// Searches for the largest value, updating the best value found
+fn max_search(values: &[u32], best: &mut u32, seen: &mut HashSet<Vec<u32>>) {
+    // Values are in descending order
+    *best = (*best).max(values[0]);
+
+    if values.len() < 2 || upper_bound(values) <= *best as u64 || !seen.insert(values.to_vec()) {
+        return;
+    }
+
+    for_each_combination(values, |next| max_search(next, best, seen));
+}
+
+/// Searches for the smallest value above the floor, updating the best value found
+fn min_above_search(
+    values: &[u32],
+    floor: u32,
+    best: &mut Option<u32>,
+    seen: &mut HashSet<Vec<u32>>,
+) {
+    for &value in values {
+        if value > floor && best.is_none_or(|best| value < best) {
+            *best = Some(value);
+        }
+    }
+
+    // Nothing can beat the value just above the floor
+    if *best == Some(floor + 1) || values.len() < 2 || !seen.insert(values.to_vec()) {
+        return;
+    }
+
+    for_each_combination(values, |next| min_above_search(next, floor, best, seen));
+}
+
+/// Calls a function with the remaining values (in descending order) for every way of combining
+/// two of the values
+fn for_each_combination(values: &[u32], mut f: impl FnMut(&[u32])) {
+    let mut next = Vec::with_capacity(values.len() - 1);
+
+    for i in 0..values.len() {
+        for j in i + 1..values.len() {
+            // Values are in descending order so a >= b
+            let (a, b) = (values[i], values[j]);
+
+            // Multiplication first so large values are found early
+            let results = [
+                a.checked_mul(b),
+                a.checked_add(b),
+                if a > b { Some(a - b) } else { None },
+                if b != 0 && a.is_multiple_of(b) {
+                    Some(a / b)
+                } else {
+                    None
+                },
+            ];
+
+            for result in results.into_iter().flatten() {
+                next.clear();
+                next.extend(
+                    values
+                        .iter()
+                        .enumerate()
+                        .filter(|&(k, _)| k != i && k != j)
+                        .map(|(_, &v)| v),
+                );
+
+                let pos = next.partition_point(|&v| v > result);
+                next.insert(pos, result);
+
+                f(&next);
+            }
+        }
+    }
+}
+
+/// Returns a bound on the values which can be made from the values. Combining a and b never gives
+/// more than (a + 1)(b + 1) - 1, so no value can be more than the product of each value plus one,
+/// less one
+fn upper_bound(values: &[u32]) -> u64 {
+    values
+        .iter()
+        .fold(1u64, |bound, &v| bound.saturating_mul(v as u64 + 1))
+        - 1
+}
+
+/// Returns the numbers in descending order
+fn sorted(numbers: &[u32]) -> Vec<u32> {
+    let mut sorted = numbers.to_vec();
+
+    sorted.sort_by(|a, b| b.cmp(a));
+
+    sorted
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference_reachable;
+
+    #[test]
+    fn test_max_reachable() {
+        assert_eq!(None, max_reachable(&[]));
+        assert_eq!(Some(7), max_reachable(&[7]));
+        assert_eq!(Some(100), max_reachable(&[25, 4]));
+
+        // (1 + 1) × 3 beats 1 × 1 × 3
+        assert_eq!(Some(6), max_reachable(&[1, 1, 3]));
+
+        // Overflowing values are skipped
+        assert_eq!(Some(u32::MAX), max_reachable(&[u32::MAX, 2]));
+    }
+
+    #[test]
+    fn test_min_reachable_above() {
+        assert_eq!(Some(4), min_reachable_above(&[25, 4], 0));
+        assert_eq!(Some(29), min_reachable_above(&[25, 4], 25));
+        assert_eq!(Some(100), min_reachable_above(&[25, 4], 29));
+        assert_eq!(None, min_reachable_above(&[25, 4], 100));
+        assert_eq!(None, min_reachable_above(&[25, 4], u32::MAX));
+    }
+
+    #[test]
+    fn test_reference() {
+        for numbers in [
+            vec![100, 75, 6, 2, 1],
+            vec![50, 25, 10, 3, 3],
+            vec![9, 8, 7, 2, 2],
+        ] {
+            let reachable = reference_reachable(&numbers);
+
+            assert_eq!(reachable.iter().max().copied(), max_reachable(&numbers));
+
+            for floor in [0, 99, 500, 999, 5000] {
+                assert_eq!(
+                    reachable.iter().filter(|&&v| v > floor).min().copied(),
+                    min_reachable_above(&numbers, floor),
+                    "{numbers:?} above {floor}"
+                );
+            }
+        }
+    }
+}
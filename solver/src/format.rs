@@ -0,0 +1,218 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for formatting numbers and colouring output. Both depend on optional
+//! crates enabled by the `numformat` and `colour` features. When a feature is disabled the
+//! formatting falls back to plain strings.
+//!
+//! Numbers are formatted for the system locale, so equations and steps can differ between machines
+//! (eg. "1,234" or "1.234"). Machine output mode formats numbers as plain ASCII digits and turns off
+//! colouring so anything written to files can be read back on any machine.
+//!
+//! Operators are shown with a symbol set. The default uses the Unicode multiplication sign (×),
+//! which some terminals and parsers can't handle, so ASCII alternatives are available.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Machine output mode
+static MACHINE_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Colour output
+static COLOUR_OUTPUT: AtomicBool = AtomicBool::new(true);
+
+/// Symbol set in use
+static SYMBOL_SET: AtomicU8 = AtomicU8::new(SymbolSet::Unicode as u8);
+
+/// Symbols used to show operators
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SymbolSet {
+    /// Unicode multiplication sign (×)
+    #[default]
+    Unicode,
+    /// ASCII asterisk (*)
+    Ascii,
+    /// Letter x
+    Letter,
+}
+
+impl SymbolSet {
+    /// All of the symbol sets
+    pub const ALL: [SymbolSet; 3] = [SymbolSet::Unicode, SymbolSet::Ascii, SymbolSet::Letter];
+
+    /// Returns the name of the symbol set as accepted by from_str
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymbolSet::Unicode => "unicode",
+            SymbolSet::Ascii => "ascii",
+            SymbolSet::Letter => "x",
+        }
+    }
+
+    /// Returns the multiplication symbol. The other operators are the same in every set
+    pub fn multiply(&self) -> &'static str {
+        match self {
+            SymbolSet::Unicode => "×",
+            SymbolSet::Ascii => "*",
+            SymbolSet::Letter => "x",
+        }
+    }
+}
+
+impl fmt::Display for SymbolSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SymbolSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SymbolSet::ALL
+            .into_iter()
+            .find(|set| set.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown symbol set '{s}' (expected one of {})",
+                    SymbolSet::ALL.map(|s| s.name()).join(", ")
+                )
+            })
+    }
+}
+
+/// Sets the symbol set used to show operators in equations and steps
+pub fn set_symbol_set(set: SymbolSet) {
+    SYMBOL_SET.store(set as u8, Ordering::Relaxed);
+}
+
+/// Returns the symbol set used to show operators
+pub fn symbol_set() -> SymbolSet {
+    SymbolSet::ALL[SYMBOL_SET.load(Ordering::Relaxed) as usize]
+}
+
+/// Sets machine output mode. When on, numbers in equations and steps are formatted as plain ASCII
+/// digits regardless of the locale and no colour is added
+pub fn set_machine_output(machine: bool) {
+    MACHINE_OUTPUT.store(machine, Ordering::Relaxed);
+}
+
+/// Returns true if machine output mode is on
+pub fn machine_output() -> bool {
+    MACHINE_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Turns colouring of equations and steps on or off. Colour is never added in machine output mode
+pub fn set_colour_output(colour: bool) {
+    COLOUR_OUTPUT.store(colour, Ordering::Relaxed);
+}
+
+/// Returns true if equations and steps are coloured
+pub fn colour_output() -> bool {
+    COLOUR_OUTPUT.load(Ordering::Relaxed) && !machine_output()
+}
+
+/// Formats a number with thousands separators for the locale (plain when the numformat feature is
+/// disabled or machine output mode is on)
+pub(crate) trait NumFormat {
+    /// Returns the formatted number
+    fn num_format(&self) -> String;
+}
+
+#[cfg(feature = "numformat")]
+impl<T: ToString + numformat::NumFormat> NumFormat for T {
+    fn num_format(&self) -> String {
+        if machine_output() {
+            self.to_string()
+        } else {
+            numformat::NumFormat::num_format(self)
+        }
+    }
+}
+
+#[cfg(not(feature = "numformat"))]
+impl<T: ToString> NumFormat for T {
+    fn num_format(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Returns the string dimmed
+pub(crate) fn dimmed(s: &str) -> String {
+    if !colour_output() {
+        return s.to_string();
+    }
+
+    #[cfg(feature = "colour")]
+    {
+        use colored::Colorize;
+
+        s.dimmed().to_string()
+    }
+
+    #[cfg(not(feature = "colour"))]
+    s.to_string()
+}
+
+/// Returns the string on a blue background
+pub(crate) fn on_blue(s: &str) -> String {
+    if !colour_output() {
+        return s.to_string();
+    }
+
+    #[cfg(feature = "colour")]
+    {
+        use colored::Colorize;
+
+        s.on_blue().to_string()
+    }
+
+    #[cfg(not(feature = "colour"))]
+    s.to_string()
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machine_output() {
+        set_machine_output(true);
+
+        assert!(machine_output());
+        assert_eq!("1234567", 1_234_567u32.num_format());
+        assert_eq!("-1234", (-1234i64).num_format());
+        assert_eq!("=", dimmed("="));
+        assert_eq!("75", on_blue("75"));
+
+        set_machine_output(false);
+
+        assert!(!machine_output());
+    }
+
+    #[test]
+    fn test_colour_output() {
+        set_colour_output(false);
+
+        assert!(!colour_output());
+        assert_eq!("=", dimmed("="));
+        assert_eq!("75", on_blue("75"));
+
+        set_colour_output(true);
+    }
+
+    #[test]
+    fn test_symbol_sets() {
+        for set in SymbolSet::ALL {
+            assert_eq!(Ok(set), set.name().parse());
+        }
+
+        assert_eq!(Ok(SymbolSet::Ascii), "ASCII".parse());
+        assert!("emoji".parse::<SymbolSet>().is_err());
+
+        assert_eq!(SymbolSet::Unicode, SymbolSet::default());
+        assert_eq!("*", SymbolSet::Ascii.multiply());
+    }
+}
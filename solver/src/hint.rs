@@ -0,0 +1,113 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for producing progressively more revealing hints for a solution
+//! without giving away the whole answer. The hints are, in order:
+//!  * The cards used by the solution
+//!  * The first operation of the solution
+//!  * All of the steps of the solution except the last
+
+use std::fmt;
+
+use crate::format::NumFormat;
+use crate::programs::Programs;
+use itertools::Itertools;
+
+/// A hint for a solution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// Card values used by the solution
+    Cards(Vec<u32>),
+    /// The first step of the solution
+    FirstStep(String),
+    /// All of the steps of the solution except the last
+    Steps(Vec<String>),
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hint::Cards(cards) => write!(
+                f,
+                "Use the cards {}",
+                cards.iter().map(|c| c.num_format()).join(", ")
+            ),
+            Hint::FirstStep(step) => write!(f, "Start with {step}"),
+            Hint::Steps(steps) => {
+                // The first step is given by the previous hint
+                write!(f, "Then {}", steps[1..].join(", then "))
+            }
+        }
+    }
+}
+
+/// Returns the hints for a solution, least revealing first.
+/// Hints which would reveal the whole solution are not returned
+pub fn hints(programs: &Programs, prog_elem: usize, numbers: &[u32], colour: bool) -> Vec<Hint> {
+    let mut hints = Vec::with_capacity(3);
+
+    // Cards used, largest first
+    let cards = programs
+        .instructions(prog_elem)
+        .iter()
+        .filter(|op| op.is_number())
+        .map(|op| numbers[op.bits() as usize])
+        .sorted_by(|a, b| b.cmp(a))
+        .collect();
+
+    hints.push(Hint::Cards(cards));
+
+    let mut steps = programs.steps(prog_elem, numbers, colour);
+
+    // Drop the final step
+    steps.pop();
+
+    if !steps.is_empty() {
+        hints.push(Hint::FirstStep(steps[0].clone()));
+    }
+
+    if steps.len() > 1 {
+        hints.push(Hint::Steps(steps));
+    }
+
+    hints
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints() {
+        let programs: Programs = "0 1 * 2 - 3 +".into();
+        let numbers = [25, 10, 7, 3];
+
+        let hints = hints(&programs, 0, &numbers, false);
+
+        assert_eq!(
+            vec![
+                Hint::Cards(vec![25, 10, 7, 3]),
+                Hint::FirstStep("25 × 10 = 250".to_string()),
+                Hint::Steps(vec![
+                    "25 × 10 = 250".to_string(),
+                    "250 - 7 = 243".to_string()
+                ]),
+            ],
+            hints
+        );
+
+        assert_eq!("Use the cards 25, 10, 7, 3", hints[0].to_string());
+        assert_eq!("Start with 25 × 10 = 250", hints[1].to_string());
+        assert_eq!("Then 250 - 7 = 243", hints[2].to_string());
+    }
+
+    #[test]
+    fn test_short_hints() {
+        let programs: Programs = "0 1 +".into();
+
+        let hints = hints(&programs, 0, &[100, 2], false);
+
+        assert_eq!(vec![Hint::Cards(vec![100, 2])], hints);
+    }
+}
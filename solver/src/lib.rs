@@ -2,6 +2,67 @@
 
 //! This module is responsible for generating, storing and running a collection of RPN programs
 
+mod board;
+mod cache;
+mod cancel;
+mod capabilities;
+#[cfg(feature = "config")]
+mod config;
+mod difficulty;
+mod equivalence;
+mod expression;
+mod extremes;
+mod format;
+mod hint;
+mod near_miss;
+#[cfg(feature = "record")]
+mod practice;
 mod programs;
+mod puzzle;
+#[cfg(feature = "record")]
+mod record;
+mod reference;
+mod relaxed;
+mod rng;
+mod rules;
+mod scoring;
+mod unreachable;
+mod weights;
+mod worksheet;
 
-pub use programs::{Programs, Solution};
+pub use board::{validate_rpn, Board, CardIdx, CardVal, RpnError};
+pub use cache::{normalise_cards, DiskCache};
+pub use cancel::{CancellationToken, Cancelled};
+pub use capabilities::{Capabilities, CAPABILITIES, MAX_CARDS, MAX_TARGET, MIN_TARGET};
+#[cfg(feature = "config")]
+pub use config::{apply_config, Config, ConfigError};
+pub use difficulty::{difficulty, Difficulty, Grade};
+pub use equivalence::expressions_equivalent;
+pub use expression::{evaluate, evaluate_with_rules, ExprError};
+pub use extremes::{max_reachable, min_reachable_above};
+pub use format::{
+    colour_output, machine_output, set_colour_output, set_machine_output, set_symbol_set,
+    symbol_set, SymbolSet,
+};
+pub use hint::{hints, Hint};
+pub use near_miss::NearMiss;
+#[cfg(feature = "record")]
+pub use practice::{
+    puzzle_rating, PracticeHistory, PracticeRound, INITIAL_RATING, PRACTICE_VERSION,
+};
+pub use programs::{
+    assignments, Coverage, DedupReport, DupReason, DupSet, DupStats, EvalStrategy, Evaluation,
+    GenerationStats, LazyPrograms, Metrics, NumCountStats, OpCounts, OpUsage, Operand, ProgErr,
+    ProgOp, Programs, Provenance, Results, Scratch, ScratchStats, Segment, Solution, Step,
+    StepAnnotation, Trace, TraceError, OP_NAMES, PROGRAM_ORDER_VERSION,
+};
+pub use puzzle::{Puzzle, PuzzleError};
+#[cfg(feature = "record")]
+pub use record::{Analysis, AnswerRecord, GameRecord, RecordError, RoundRecord, RECORD_VERSION};
+pub use reference::{reference_reachable, Discrepancy};
+pub use rng::SplitMix64;
+pub use rules::{Rules, Ruleset};
+pub use scoring::{points, score, Declaration};
+pub use unreachable::{explain_unreachable, Explanation, Obstruction, Relaxation};
+pub use weights::{TargetWeights, WEIGHTS_VERSION};
+pub use worksheet::Worksheet;
@@ -0,0 +1,139 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for finding near misses: programs which reach a target if exactly one
+//! step is allowed to break the rules by giving a negative or fractional intermediate result, eg.
+//! 75 × (1 + 2 / 6) = 100 where 2 / 6 = 1/3.
+//!
+//! Only the step turning positive whole numbers in to a negative or fractional value breaks the
+//! rules. The steps which carry on calculating with the value don't count, so the value can cancel
+//! out later. The answer must still be a positive integer.
+
+use crate::programs::{Programs, Solution};
+use crate::relaxed::process;
+use crate::unreachable::Relaxation;
+
+/// A program which reaches a target if one step is allowed to break the rules
+#[derive(Debug)]
+pub struct NearMiss {
+    /// The solution
+    pub solution: Solution,
+    /// Step breaking the rules (zero based)
+    pub step: usize,
+    /// Relaxation of the rules needed by the step
+    pub relaxation: Relaxation,
+}
+
+impl Programs {
+    /// Returns the programs which reach a target with a set of numbers if exactly one step is allowed
+    /// to break the rules. Programs reaching the target within the rules are not included
+    pub fn run_target_near_misses(&self, target: u32, numbers: &[u32]) -> Vec<NearMiss> {
+        (0..self.len())
+            .filter_map(|i| {
+                if self.run(i, numbers).is_ok() {
+                    return None;
+                }
+
+                let instructions = self.instructions(i);
+
+                let mut step = 0;
+                let mut breaks = Vec::new();
+                let mut largest = 0;
+
+                let ans = process(
+                    instructions,
+                    numbers,
+                    true,
+                    true,
+                    |(n2, d2), _, (n1, d1), (n, d)| {
+                        let whole = |n: i64, d: i64| n > 0 && d == 1;
+
+                        if whole(n2, d2) && whole(n1, d1) && !whole(n, d) {
+                            let relaxation = if n < 0 {
+                                Relaxation::Negative
+                            } else {
+                                Relaxation::Fractions
+                            };
+
+                            breaks.push((step, relaxation));
+                        }
+
+                        if n > 0 {
+                            largest = largest.max(u32::try_from(n / d).unwrap_or(u32::MAX));
+                        }
+
+                        step += 1;
+                    },
+                )?;
+
+                if ans != (target as i64, 1) || breaks.len() != 1 {
+                    return None;
+                }
+
+                let (step, relaxation) = breaks[0];
+
+                Some(NearMiss {
+                    solution: Solution::with_largest(i, instructions, target, largest),
+                    step,
+                    relaxation,
+                })
+            })
+            .collect()
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn test_near_misses() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [75, 6, 2, 1];
+
+        // 75 × (1 + 2 / 6) = 100 with 2 / 6 = 1/3
+        let near_misses = programs.run_target_near_misses(100, &numbers);
+
+        assert!(!near_misses.is_empty());
+
+        for near_miss in &near_misses {
+            assert_eq!(100, near_miss.solution.result);
+            assert!(programs.run(near_miss.solution.program, &numbers).is_err());
+        }
+
+        let fraction = near_misses
+            .iter()
+            .find(|near_miss| {
+                programs.steps_rules(
+                    near_miss.solution.program,
+                    &numbers,
+                    false,
+                    Ruleset::Relaxed.rules(),
+                )[near_miss.step]
+                    == "2 / 6 = 1/3"
+            })
+            .unwrap();
+
+        assert_eq!(Relaxation::Fractions, fraction.relaxation);
+    }
+
+    #[test]
+    fn test_two_breaks() {
+        let programs: Programs = "2 1 - 3 0 - *".into();
+
+        // (2 - 6) × (1 - 75) = 296 needs two negative intermediate results
+        assert!(programs
+            .run_target_near_misses(296, &[75, 6, 2, 1])
+            .is_empty());
+
+        // 2 - 6 + 75 = 71 needs one
+        let programs: Programs = "2 1 - 0 +".into();
+        let near_misses = programs.run_target_near_misses(71, &[75, 6, 2, 1]);
+
+        assert_eq!(1, near_misses.len());
+        assert_eq!(0, near_misses[0].step);
+        assert_eq!(Relaxation::Negative, near_misses[0].relaxation);
+    }
+}
@@ -0,0 +1,280 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for keeping a history of practice rounds and measuring progress.
+//!
+//! The history is a versioned JSON file, by default `countdown_numbers/practice.json` in the user's
+//! configuration directory. Progress is measured with an Elo style rating: each round is treated as
+//! a game against the puzzle, which is rated from its difficulty score. Reaching the target exactly
+//! counts as a win, 7 or 5 points as a partial win and no points as a loss. Rounds with an
+//! unreachable target don't change the rating.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::record::RecordError;
+
+/// Current practice history format version
+pub const PRACTICE_VERSION: u32 = 1;
+
+/// Rating before any rounds are played
+pub const INITIAL_RATING: f64 = 1200.0;
+
+/// Maximum rating change from one round
+const K_FACTOR: f64 = 32.0;
+
+/// Rating of a puzzle with a difficulty score of zero
+const PUZZLE_BASE_RATING: f64 = 800.0;
+
+/// Rating added to a puzzle for each difficulty score point
+const PUZZLE_RATING_PER_POINT: f64 = 40.0;
+
+/// A practice round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PracticeRound {
+    /// Time the round was played (seconds since the Unix epoch)
+    pub time: u64,
+    /// Target number
+    pub target: u32,
+    /// Card values
+    pub cards: Vec<u32>,
+    /// Difficulty score of the target, None if the target can't be reached
+    pub difficulty: Option<u32>,
+    /// Distance of the player's answer from the target, None if no valid answer was given
+    pub distance: Option<u32>,
+    /// Points scored
+    pub points: u32,
+}
+
+/// History of practice rounds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PracticeHistory {
+    /// Format version
+    pub version: u32,
+    /// Rounds played, oldest first
+    pub rounds: Vec<PracticeRound>,
+}
+
+impl PracticeHistory {
+    /// Creates a new empty history
+    pub fn new() -> Self {
+        Self {
+            version: PRACTICE_VERSION,
+            rounds: Vec::new(),
+        }
+    }
+
+    /// Returns the default history file path in the user's configuration directory
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("countdown_numbers").join("practice.json"))
+    }
+
+    /// Loads the history from a file. A missing file gives an empty history
+    pub fn load(path: &Path) -> Result<Self, RecordError> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => Err(RecordError::Invalid(e.to_string()))?,
+        };
+
+        let history: Self =
+            serde_json::from_str(&json).map_err(|e| RecordError::Invalid(e.to_string()))?;
+
+        if history.version == 0 || history.version > PRACTICE_VERSION {
+            Err(RecordError::UnsupportedVersion(history.version))?
+        }
+
+        Ok(history)
+    }
+
+    /// Saves the history to a file, creating the directory if necessary
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json =
+            serde_json::to_string_pretty(self).expect("Practice history serialisation failed");
+
+        // Write to a temporary file and rename so the history is never left half written
+        let tmp_path = path.with_extension("json.tmp");
+
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Returns the rating after each round
+    pub fn ratings(&self) -> Vec<f64> {
+        let mut rating = INITIAL_RATING;
+
+        self.rounds
+            .iter()
+            .map(|round| {
+                rating = next_rating(rating, round);
+                rating
+            })
+            .collect()
+    }
+
+    /// Returns the current rating
+    pub fn rating(&self) -> f64 {
+        self.ratings().last().copied().unwrap_or(INITIAL_RATING)
+    }
+
+    /// Returns the number of rounds in a row, up to the latest, where the target was reached exactly
+    pub fn current_streak(&self) -> usize {
+        self.rounds
+            .iter()
+            .rev()
+            .take_while(|round| round.distance == Some(0))
+            .count()
+    }
+
+    /// Returns the most rounds in a row where the target was reached exactly
+    pub fn best_streak(&self) -> usize {
+        self.rounds
+            .iter()
+            .fold((0, 0), |(best, current), round| {
+                let current = if round.distance == Some(0) {
+                    current + 1
+                } else {
+                    0
+                };
+
+                (best.max(current), current)
+            })
+            .0
+    }
+
+    /// Returns the average distance from the target of valid answers
+    pub fn average_distance(&self) -> Option<f64> {
+        let distances = self
+            .rounds
+            .iter()
+            .filter_map(|round| round.distance)
+            .collect::<Vec<_>>();
+
+        (!distances.is_empty())
+            .then(|| distances.iter().sum::<u32>() as f64 / distances.len() as f64)
+    }
+}
+
+impl Default for PracticeHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the rating of a puzzle from its difficulty score
+pub fn puzzle_rating(difficulty: u32) -> f64 {
+    PUZZLE_BASE_RATING + (difficulty as f64 * PUZZLE_RATING_PER_POINT)
+}
+
+/// Calculates the rating after a round
+fn next_rating(rating: f64, round: &PracticeRound) -> f64 {
+    let Some(difficulty) = round.difficulty else {
+        // Unreachable targets aren't rated
+        return rating;
+    };
+
+    let expected = 1.0 / (1.0 + 10f64.powf((puzzle_rating(difficulty) - rating) / 400.0));
+    let actual = round.points as f64 / 10.0;
+
+    rating + K_FACTOR * (actual - expected)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(difficulty: Option<u32>, distance: Option<u32>, points: u32) -> PracticeRound {
+        PracticeRound {
+            time: 0,
+            target: 500,
+            cards: vec![100, 5],
+            difficulty,
+            distance,
+            points,
+        }
+    }
+
+    #[test]
+    fn test_rating() {
+        let mut history = PracticeHistory::new();
+
+        assert_eq!(INITIAL_RATING, history.rating());
+
+        // Beating a hard puzzle gains more than beating an easy one
+        history.rounds.push(round(Some(4), Some(0), 10));
+        let easy_gain = history.rating() - INITIAL_RATING;
+
+        history.rounds = vec![round(Some(14), Some(0), 10)];
+        let hard_gain = history.rating() - INITIAL_RATING;
+
+        assert!(easy_gain > 0.0);
+        assert!(hard_gain > easy_gain);
+
+        // Failing loses rating, unreachable targets are ignored
+        history.rounds.push(round(Some(10), None, 0));
+        assert!(history.rating() < INITIAL_RATING + hard_gain);
+
+        let before = history.rating();
+        history.rounds.push(round(None, Some(3), 7));
+        assert_eq!(before, history.rating());
+
+        assert_eq!(3, history.ratings().len());
+    }
+
+    #[test]
+    fn test_streaks_and_distance() {
+        let mut history = PracticeHistory::new();
+
+        assert_eq!(None, history.average_distance());
+
+        for (distance, points) in [
+            (Some(0), 10),
+            (Some(0), 10),
+            (Some(0), 10),
+            (Some(4), 7),
+            (None, 0),
+            (Some(0), 10),
+            (Some(0), 10),
+        ] {
+            history.rounds.push(round(Some(8), distance, points));
+        }
+
+        assert_eq!(2, history.current_streak());
+        assert_eq!(3, history.best_streak());
+        assert_eq!(Some(4.0 / 6.0), history.average_distance());
+    }
+
+    #[test]
+    fn test_load_save() {
+        let path = env::temp_dir()
+            .join(format!("solver-practice-{}", std::process::id()))
+            .join("practice.json");
+
+        // Missing file is an empty history
+        assert_eq!(Ok(PracticeHistory::new()), PracticeHistory::load(&path));
+
+        let mut history = PracticeHistory::new();
+        history.rounds.push(round(Some(8), Some(2), 7));
+
+        history.save(&path).unwrap();
+
+        assert_eq!(Ok(history), PracticeHistory::load(&path));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,379 @@
+#![warn(missing_docs)]
+
+//! This module contains an experimental batch evaluator which runs programs with the same skeleton
+//! side by side.
+//!
+//! The skeleton of a program is its instructions with the number indexes removed, so programs with
+//! the same skeleton carry out the same operators in the same order on different numbers. A batch
+//! of programs sharing a skeleton is run one instruction at a time across all of the programs in
+//! the batch (the lanes). Instead of abandoning a program at its first error, every operation is
+//! carried out and the first error of each lane is recorded in a flags word, and programs are only
+//! accepted or rejected at the end. The loops over the lanes have no branches so the compiler can
+//! vectorise them.
+//!
+//! Results are identical to the standard interpreter (see [`EvalStrategy`]).
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use super::progop::ProgOp;
+use super::solution::{analyse_ops, Answer};
+use super::stack::STACK_SIZE;
+use super::{ProgErr, Programs, Results, MAX_TARGET, MIN_TARGET};
+
+/// Number of programs run side by side
+const LANES: usize = 8;
+
+/// How programs are evaluated by Programs::run_all_with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvalStrategy {
+    /// Run each program in turn, abandoning it at the first error
+    #[default]
+    Standard,
+    /// Run batches of programs with the same skeleton side by side, recording errors in a flags
+    /// word and rejecting programs at the end
+    Batch,
+}
+
+/// Programs grouped by skeleton
+#[derive(Debug, Clone)]
+pub(crate) struct Skeletons {
+    groups: Vec<SkeletonGroup>,
+}
+
+/// Programs sharing a skeleton
+#[derive(Debug, Clone)]
+struct SkeletonGroup {
+    /// Instructions with the number indexes removed
+    skeleton: Vec<ProgOp>,
+    /// Program elements in order
+    programs: Vec<u32>,
+}
+
+/// Error flags. 0 is no error, the others are the errors in ProgErr order
+const ERR_NONE: u8 = 0;
+const ERR_ZERO: u8 = 1;
+const ERR_NEGATIVE: u8 = 2;
+const ERR_DIV_ZERO: u8 = 3;
+const ERR_NON_INTEGER: u8 = 4;
+const ERR_MUL1: u8 = 5;
+const ERR_DIV1: u8 = 6;
+const ERR_OVERFLOW: u8 = 7;
+
+impl Skeletons {
+    /// Groups the programs of a collection by skeleton. Groups are in order of the first program
+    /// with the skeleton
+    fn new(programs: &Programs) -> Self {
+        let mut index = HashMap::new();
+        let mut groups: Vec<SkeletonGroup> = Vec::new();
+
+        for prog_elem in 0..programs.len() {
+            let skeleton = programs
+                .instructions(prog_elem)
+                .iter()
+                .map(|op| *op & ProgOp::PROG_OP_MASK)
+                .collect::<Vec<_>>();
+
+            let group = *index.entry(skeleton.clone()).or_insert_with(|| {
+                groups.push(SkeletonGroup {
+                    skeleton,
+                    programs: Vec::new(),
+                });
+
+                groups.len() - 1
+            });
+
+            groups[group].programs.push(prog_elem as u32);
+        }
+
+        Self { groups }
+    }
+
+    /// Returns the number of skeletons
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+impl Programs {
+    /// Runs all of the programs in the programs collection with a given set of numbers like
+    /// run_all, using the chosen evaluation strategy. The results are the same whichever strategy
+    /// is used
+    pub fn run_all_with(&self, numbers: &[u32], strategy: EvalStrategy) -> Results {
+        match strategy {
+            EvalStrategy::Standard => self.run_all(numbers),
+            EvalStrategy::Batch => self.run_all_batch(numbers, MIN_TARGET..=MAX_TARGET),
+        }
+    }
+
+    /// Returns the programs grouped by skeleton. They are grouped when first needed
+    fn skeletons(&self) -> &Skeletons {
+        self.skeletons.get_or_init(|| Skeletons::new(self))
+    }
+
+    /// Runs all of the programs in batches of programs with the same skeleton
+    fn run_all_batch(&self, numbers: &[u32], range: RangeInclusive<u32>) -> Results {
+        assert!(numbers.len() == self.nums as usize);
+
+        let mut results = Results::default();
+        let symmetry = self.symmetry(numbers);
+
+        for group in &self.skeletons().groups {
+            // Leave out the programs which are symmetric with another
+            let mut lanes = [0u32; LANES];
+            let mut used = 0;
+
+            for &prog_elem in &group.programs {
+                let instructions = self.instructions(prog_elem as usize);
+
+                if symmetry.as_ref().is_some_and(|s| s.skip(instructions)) {
+                    results.symmetric += 1;
+                    continue;
+                }
+
+                lanes[used] = prog_elem;
+                used += 1;
+
+                if used == LANES {
+                    self.run_batch(&group.skeleton, &lanes, used, numbers, &range, &mut results);
+                    used = 0;
+                }
+            }
+
+            if used > 0 {
+                self.run_batch(&group.skeleton, &lanes, used, numbers, &range, &mut results);
+            }
+        }
+
+        // Solutions are found out of order
+        results
+            .solutions
+            .sort_unstable_by_key(|solution| solution.program);
+
+        results
+    }
+
+    /// Runs a batch of programs with the same skeleton and adds the outcome of each to the results.
+    /// Lanes past the number used repeat the first program and are ignored
+    fn run_batch(
+        &self,
+        skeleton: &[ProgOp],
+        lanes: &[u32; LANES],
+        used: usize,
+        numbers: &[u32],
+        range: &RangeInclusive<u32>,
+        results: &mut Results,
+    ) {
+        let starts: [usize; LANES] = std::array::from_fn(|lane| {
+            let prog_elem = if lane < used { lanes[lane] } else { lanes[0] };
+
+            self.programs[prog_elem as usize].start as usize
+        });
+
+        let mut stack = [[0u32; LANES]; STACK_SIZE];
+        let mut sp = 0;
+        let mut flags = [ERR_NONE; LANES];
+        let mut largest = [0u32; LANES];
+
+        // Every program in the batch has the operators of the skeleton
+        let ops = analyse_ops(skeleton);
+
+        for (pos, op) in skeleton.iter().enumerate() {
+            if op.is_number() {
+                for lane in 0..LANES {
+                    stack[sp][lane] =
+                        numbers[self.instructions[starts[lane] + pos].bits() as usize];
+                }
+
+                sp += 1;
+                continue;
+            }
+
+            let n1 = stack[sp - 1];
+            let n2 = stack[sp - 2];
+            let mut ans = [0u32; LANES];
+            let mut err = [ERR_NONE; LANES];
+
+            match *op {
+                ProgOp::PROG_OP_ADD => {
+                    for lane in 0..LANES {
+                        let (sum, overflow) = n2[lane].overflowing_add(n1[lane]);
+
+                        ans[lane] = sum;
+                        err[lane] = select(overflow, ERR_OVERFLOW, ERR_NONE);
+                    }
+                }
+                ProgOp::PROG_OP_SUB => {
+                    for lane in 0..LANES {
+                        let diff = n2[lane].wrapping_sub(n1[lane]);
+
+                        ans[lane] = diff;
+                        err[lane] = select(
+                            n2[lane] < n1[lane],
+                            ERR_NEGATIVE,
+                            select(diff == 0, ERR_ZERO, ERR_NONE),
+                        );
+                    }
+                }
+                ProgOp::PROG_OP_MUL => {
+                    for lane in 0..LANES {
+                        let (product, overflow) = n2[lane].overflowing_mul(n1[lane]);
+
+                        ans[lane] = product;
+                        err[lane] = select(
+                            n1[lane] == 1 || n2[lane] == 1,
+                            ERR_MUL1,
+                            select(
+                                overflow,
+                                ERR_OVERFLOW,
+                                select(product == 0, ERR_ZERO, ERR_NONE),
+                            ),
+                        );
+                    }
+                }
+                _ => {
+                    for lane in 0..LANES {
+                        // Divide by 1 instead of 0 so the division can't fault
+                        let divisor = n1[lane].max(1);
+
+                        ans[lane] = n2[lane] / divisor;
+                        err[lane] = select(
+                            n1[lane] == 0,
+                            ERR_DIV_ZERO,
+                            select(
+                                n1[lane] == 1,
+                                ERR_DIV1,
+                                select(n2[lane] % divisor != 0, ERR_NON_INTEGER, ERR_NONE),
+                            ),
+                        );
+                    }
+                }
+            }
+
+            // Keep the first error and the largest value of each lane
+            for lane in 0..LANES {
+                largest[lane] = largest[lane].max(ans[lane]);
+                flags[lane] = select(flags[lane] == ERR_NONE, err[lane], flags[lane]);
+            }
+
+            sp -= 1;
+            stack[sp - 1] = ans;
+        }
+
+        // Accept or reject each program
+        for lane in 0..used {
+            let prog_elem = lanes[lane] as usize;
+
+            let result = match flags[lane] {
+                ERR_NONE => Ok(Answer {
+                    value: stack[0][lane],
+                    largest: largest[lane].max(stack[0][lane]),
+                    ops,
+                }),
+                ERR_ZERO => Err(ProgErr::Zero),
+                ERR_NEGATIVE => Err(ProgErr::Negative),
+                ERR_DIV_ZERO => Err(ProgErr::DivZero),
+                ERR_NON_INTEGER => Err(ProgErr::NonInteger),
+                ERR_MUL1 => Err(ProgErr::Mul1),
+                ERR_DIV1 => Err(ProgErr::Div1),
+                _ => Err(ProgErr::Overflow),
+            };
+
+            results.add(prog_elem, self.instructions(prog_elem), result, range);
+        }
+    }
+}
+
+/// Chooses between two error flags without a branch
+#[inline(always)]
+fn select(cond: bool, if_true: u8, if_false: u8) -> u8 {
+    let mask = (cond as u8).wrapping_neg();
+
+    (if_true & mask) | (if_false & !mask)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the batch strategy gives the same results as the standard strategy
+    fn check_parity(programs: &Programs, numbers: &[u32]) {
+        let standard = programs.run_all_with(numbers, EvalStrategy::Standard);
+        let batch = programs.run_all_with(numbers, EvalStrategy::Batch);
+
+        assert_eq!(
+            standard.solutions, batch.solutions,
+            "Solutions for {numbers:?}"
+        );
+
+        for (name, s, b) in [
+            ("under_range", standard.under_range, batch.under_range),
+            ("above_range", standard.above_range, batch.above_range),
+            ("zero", standard.zero, batch.zero),
+            ("negative", standard.negative, batch.negative),
+            ("div_zero", standard.div_zero, batch.div_zero),
+            ("non_integer", standard.non_integer, batch.non_integer),
+            ("mult_by_1", standard.mult_by_1, batch.mult_by_1),
+            ("div_by_1", standard.div_by_1, batch.div_by_1),
+            ("symmetric", standard.symmetric, batch.symmetric),
+            ("overflow", standard.overflow, batch.overflow),
+        ] {
+            assert_eq!(s, b, "{name} for {numbers:?}");
+        }
+
+        assert_eq!(standard.largest_intermediate, batch.largest_intermediate);
+        assert_eq!(standard.intermediate_bits, batch.intermediate_bits);
+    }
+
+    #[test]
+    fn test_parity() {
+        for inc_duplicated in [true, false] {
+            let programs = Programs::new(5, inc_duplicated, false);
+
+            for numbers in [
+                [100, 75, 50, 25, 10],
+                [10, 10, 1, 1, 2],
+                [7, 3, 2, 1, 1],
+                [9, 6, 3, 3, 3],
+                [0, 5, 5, 2, 1],
+            ] {
+                check_parity(&programs, &numbers);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parity_overflow() {
+        let programs = Programs::new(4, true, false);
+
+        check_parity(&programs, &[u32::MAX, 65_536, 65_537, 2]);
+        check_parity(&programs, &[u32::MAX - 1, u32::MAX, 1, 1]);
+    }
+
+    #[test]
+    fn test_skeletons() {
+        let programs = Programs::new(3, true, false);
+        let skeletons = Skeletons::new(&programs);
+
+        // 1 skeleton with one number, 4 operators for two numbers and 2 placements of 16 operator
+        // combinations for three
+        assert_eq!(1 + 4 + 32, skeletons.len());
+
+        let total = skeletons
+            .groups
+            .iter()
+            .map(|group| group.programs.len())
+            .sum::<usize>();
+
+        assert_eq!(programs.len(), total);
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(ERR_ZERO, select(true, ERR_ZERO, ERR_MUL1));
+        assert_eq!(ERR_MUL1, select(false, ERR_ZERO, ERR_MUL1));
+    }
+}
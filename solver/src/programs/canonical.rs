@@ -0,0 +1,342 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for generating only the canonical RPN programs for a game directly,
+//! rather than generating every program and rejecting the duplicates.
+//!
+//! Each program is a tree of operator groups. An additive group is a list of elements joined by the
+//! operators + and -, and a multiplicative group is a list of elements joined by × and /. The
+//! elements of an additive group are numbers or multiplicative groups and vice versa, so a - (b + c)
+//! is only generated as a - b - c and a / (b / c) as a × c / b.
+//!
+//! Within a group the elements are ordered:
+//!  * Elements with the first operator (+ or ×) followed by elements with the second (- or /)
+//!  * Numbers in ascending order followed by sub-groups in ascending order of their lowest number
+//!
+//! Each group is evaluated from left to right so all of the additions (multiplications) are
+//! performed before any subtraction (division). Apart from overflow this order of evaluation
+//! succeeds whenever any other order of evaluating the same terms does, so no reachable answer is
+//! lost with the standard cards.
+//!
+//! Adding (multiplying) first gives the largest intermediate values, so with large cards an
+//! intermediate value can overflow where another order wouldn't, eg. 3000000 × 70000 / 60000
+//! overflows but 3000000 / 60000 × 70000 is 3500000. Such answers are only found by the programs
+//! which include duplicates.
+
+use std::time::Instant;
+
+use itertools::Itertools;
+
+use super::gen_stats::NumCountStats;
+use super::progop::ProgOp;
+use super::ProgInstr;
+use crate::cancel::{CancellationToken, Cancelled};
+
+/// Operator group kinds
+#[derive(Clone, Copy)]
+enum GroupKind {
+    /// Elements joined by + and -
+    Additive = 0,
+    /// Elements joined by × and /
+    Multiplicative = 1,
+}
+
+impl GroupKind {
+    /// Returns the group kind allowed as a sub-group element
+    fn sub_kind(self) -> GroupKind {
+        match self {
+            GroupKind::Additive => GroupKind::Multiplicative,
+            GroupKind::Multiplicative => GroupKind::Additive,
+        }
+    }
+
+    /// Returns the first and second operators for the group kind
+    fn operators(self) -> (ProgOp, ProgOp) {
+        match self {
+            GroupKind::Additive => (ProgOp::PROG_OP_ADD, ProgOp::PROG_OP_SUB),
+            GroupKind::Multiplicative => (ProgOp::PROG_OP_MUL, ProgOp::PROG_OP_DIV),
+        }
+    }
+}
+
+/// Group element
+#[derive(Clone, Copy)]
+enum Elem {
+    /// A number
+    Number(u8),
+    /// A sub-group (program element)
+    Group(u32),
+}
+
+/// Returns true if programs can be generated canonically for the set of operators.
+/// The second operator of a group kind can't be used without the first
+pub(crate) fn canonical_supported(operators: &[ProgOp]) -> bool {
+    let has = |op| operators.contains(&op);
+
+    (has(ProgOp::PROG_OP_ADD) || !has(ProgOp::PROG_OP_SUB))
+        && (has(ProgOp::PROG_OP_MUL) || !has(ProgOp::PROG_OP_DIV))
+}
+
+/// Generates the canonical RPN programs for the given number of numbers and operators.
+/// Returns the number of programs generated for each number of numbers
+pub(crate) fn generate_canonical_programs(
+    programs: &mut Vec<ProgInstr>,
+    instructions: &mut Vec<ProgOp>,
+    nums: u8,
+    operators: &[ProgOp],
+    cancel: Option<&CancellationToken>,
+) -> Result<Vec<NumCountStats>, Cancelled> {
+    let mut generator = Generator {
+        programs,
+        instructions,
+        operators,
+        groups: vec![[Vec::new(), Vec::new()]; 1 << nums],
+        elems: Vec::with_capacity(nums as usize),
+    };
+
+    let mut counts = Vec::with_capacity(nums as usize);
+
+    // Loop for the number of numbers in the RPN program
+    for num_cnt in 1..=nums {
+        let start = Instant::now();
+        let start_cnt = generator.programs.len();
+
+        // Sub-groups are always generated before the groups which contain them
+        for comb in (0..nums).combinations(num_cnt as usize) {
+            if let Some(cancel) = cancel {
+                cancel.check()?;
+            }
+
+            let mask = comb.iter().fold(0, |mask, n| mask | (1 << n));
+
+            if num_cnt == 1 {
+                generator.elems.push((false, Elem::Number(comb[0])));
+                generator.emit(mask, GroupKind::Additive);
+                generator.elems.pop();
+            } else {
+                for kind in [GroupKind::Additive, GroupKind::Multiplicative] {
+                    if operators.contains(&kind.operators().0) {
+                        generator.group_rec(mask, mask, kind);
+                    }
+                }
+            }
+        }
+
+        counts.push(NumCountStats {
+            numbers: num_cnt,
+            programs: generator.programs.len() - start_cnt,
+            elapsed: start.elapsed(),
+            ..Default::default()
+        });
+    }
+
+    Ok(counts)
+}
+
+struct Generator<'a> {
+    programs: &'a mut Vec<ProgInstr>,
+    instructions: &'a mut Vec<ProgOp>,
+    operators: &'a [ProgOp],
+    /// Additive and multiplicative group program elements by number mask
+    groups: Vec<[Vec<u32>; 2]>,
+    /// Elements of the group being built (negative flag and element)
+    elems: Vec<(bool, Elem)>,
+}
+
+impl Generator<'_> {
+    /// Splits the remaining numbers of a group in to elements
+    fn group_rec(&mut self, mask: usize, remaining: usize, kind: GroupKind) {
+        if remaining == 0 {
+            self.emit(mask, kind);
+            return;
+        }
+
+        let (_, second_op) = kind.operators();
+        let signs: &[bool] = if self.operators.contains(&second_op) {
+            &[false, true]
+        } else {
+            &[false]
+        };
+
+        // The element containing the lowest remaining number
+        let lowest = remaining.trailing_zeros() as usize;
+        let rest = remaining & !(1 << lowest);
+
+        // Loop through each subset of the rest of the numbers
+        let mut subset = rest;
+
+        loop {
+            let block = (1 << lowest) | subset;
+
+            if block != mask {
+                if subset == 0 {
+                    for &neg in signs {
+                        self.elems.push((neg, Elem::Number(lowest as u8)));
+                        self.group_rec(mask, rest & !subset, kind);
+                        self.elems.pop();
+                    }
+                } else {
+                    for i in 0..self.groups[block][kind.sub_kind() as usize].len() {
+                        let prog_elem = self.groups[block][kind.sub_kind() as usize][i];
+
+                        for &neg in signs {
+                            self.elems.push((neg, Elem::Group(prog_elem)));
+                            self.group_rec(mask, rest & !subset, kind);
+                            self.elems.pop();
+                        }
+                    }
+                }
+            }
+
+            if subset == 0 {
+                break;
+            }
+
+            subset = (subset - 1) & rest;
+        }
+    }
+
+    /// Adds the program for the group elements
+    fn emit(&mut self, mask: usize, kind: GroupKind) {
+        let (first_op, second_op) = kind.operators();
+
+        // Must have a positive element to start the group
+        if self.elems.iter().all(|(neg, _)| *neg) {
+            return;
+        }
+
+        let inst_start = self.instructions.len();
+
+        // Positive numbers, positive sub-groups, negative numbers, negative sub-groups
+        let order = [(false, true), (false, false), (true, true), (true, false)];
+        let mut first = true;
+
+        for (neg, number) in order {
+            for &(elem_neg, elem) in &self.elems {
+                if elem_neg != neg {
+                    continue;
+                }
+
+                match elem {
+                    Elem::Number(n) if number => self.instructions.push(ProgOp::new_number(n)),
+                    Elem::Group(prog_elem) if !number => {
+                        let program = &self.programs[prog_elem as usize];
+
+                        self.instructions
+                            .extend_from_within(program.start as usize..=program.end as usize);
+                    }
+                    _ => continue,
+                }
+
+                if first {
+                    first = false;
+                } else {
+                    self.instructions
+                        .push(if neg { second_op } else { first_op });
+                }
+            }
+        }
+
+        let prog_elem = self.programs.len() as u32;
+
+        self.programs.push(ProgInstr {
+            start: inst_start as u32,
+            end: (self.instructions.len() - 1) as u32,
+        });
+
+        if self.elems.len() > 1 {
+            self.groups[mask][kind as usize].push(prog_elem);
+        }
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::programs::Programs;
+
+    fn answers(programs: &Programs, numbers: &[u32]) -> HashSet<u32> {
+        (0..programs.len())
+            .filter_map(|i| programs.run(i, numbers).ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_counts() {
+        let counts = [1, 8, 89, 1572];
+
+        for (i, count) in counts.iter().enumerate() {
+            assert_eq!(*count, Programs::new(i as u8 + 1, false, false).len());
+        }
+    }
+
+    #[test]
+    fn test_canonical_rpn() {
+        let programs = Programs::new(3, false, false);
+
+        let numbers = [0, 1, 2];
+
+        let rpn = (0..programs.len())
+            .map(|i| programs.rpn(i, &numbers, false))
+            .collect::<HashSet<_>>();
+
+        // Left to right evaluation, positive terms first
+        assert!(rpn.contains("0 1 + 2 -"));
+        assert!(rpn.contains("2 0 - 1 -"));
+        assert!(rpn.contains("0 1 2 + ×"));
+
+        // Not canonical
+        assert!(!rpn.contains("2 0 1 + -"));
+        assert!(!rpn.contains("0 2 - 1 +"));
+        assert!(!rpn.contains("1 0 +"));
+    }
+
+    #[test]
+    fn test_answers() {
+        let deck = [100, 75, 25, 10, 10, 7, 3, 2, 1];
+
+        for nums in 1..=4 {
+            let canonical = Programs::new(nums, false, false);
+            let all = Programs::new(nums, true, false);
+
+            for numbers in deck.iter().copied().combinations(nums as usize) {
+                assert_eq!(
+                    answers(&all, &numbers),
+                    answers(&canonical, &numbers),
+                    "Answers differ for {numbers:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_cards() {
+        // 3000000 / 60000 × 70000 is 3500000 but the canonical order multiplies first, and
+        // 3000000 × 70000 doesn't fit in 32 bits
+        let numbers = [3_000_000, 60_000, 70_000];
+
+        let canonical = answers(&Programs::new(3, false, false), &numbers);
+        let all = answers(&Programs::new(3, true, false), &numbers);
+
+        assert!(all.contains(&3_500_000));
+        assert!(!canonical.contains(&3_500_000));
+        assert!(canonical.is_subset(&all));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(canonical_supported(&[ProgOp::PROG_OP_ADD]));
+        assert!(canonical_supported(&[
+            ProgOp::PROG_OP_MUL,
+            ProgOp::PROG_OP_DIV
+        ]));
+        assert!(!canonical_supported(&[ProgOp::PROG_OP_SUB]));
+        assert!(!canonical_supported(&[
+            ProgOp::PROG_OP_ADD,
+            ProgOp::PROG_OP_DIV
+        ]));
+    }
+}
@@ -0,0 +1,264 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for recording which of the targets 100 to 999 can be reached with a
+//! set of numbers. The targets are held in a bitset so coverage of many card sets can be stored,
+//! combined and compared cheaply.
+
+use std::ops::RangeInclusive;
+
+use super::stack::ProgStack;
+use super::Programs;
+
+/// Lowest target
+pub const MIN_TARGET: u32 = 100;
+
+/// Highest target
+pub const MAX_TARGET: u32 = 999;
+
+/// Number of targets
+const TARGETS: usize = (MAX_TARGET - MIN_TARGET + 1) as usize;
+
+/// Number of 64 bit words needed to hold a bit for each target
+const WORDS: usize = TARGETS.div_ceil(64);
+
+/// Set of reachable targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coverage {
+    bits: [u64; WORDS],
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    /// Creates an empty coverage set
+    pub fn new() -> Self {
+        Self { bits: [0; WORDS] }
+    }
+
+    /// Adds a target to the set. Returns true if the target was not already in the set.
+    /// Values outside of the target range are ignored
+    pub fn insert(&mut self, target: u32) -> bool {
+        match Self::position(target) {
+            Some((word, bit)) => {
+                let new = self.bits[word] & bit == 0;
+                self.bits[word] |= bit;
+                new
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the target is in the set
+    pub fn contains(&self, target: u32) -> bool {
+        match Self::position(target) {
+            Some((word, bit)) => self.bits[word] & bit != 0,
+            None => false,
+        }
+    }
+
+    /// Returns the number of targets in the set
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns the number of targets in the set within a range
+    pub fn count_range(&self, range: &RangeInclusive<u32>) -> usize {
+        range.clone().filter(|&t| self.contains(t)).count()
+    }
+
+    /// Returns true if every target is in the set
+    pub fn is_full(&self) -> bool {
+        self.count() == TARGETS
+    }
+
+    /// Returns the target in the set closest to a value. If two targets are equally close the
+    /// lower is returned
+    pub fn nearest(&self, value: u32) -> Option<u32> {
+        self.iter().min_by_key(|&t| t.abs_diff(value))
+    }
+
+    /// Returns the targets in either set
+    pub fn union(&self, other: &Coverage) -> Coverage {
+        let mut result = *self;
+
+        for (w, o) in result.bits.iter_mut().zip(other.bits.iter()) {
+            *w |= o;
+        }
+
+        result
+    }
+
+    /// Returns the targets in both sets
+    pub fn intersection(&self, other: &Coverage) -> Coverage {
+        let mut result = *self;
+
+        for (w, o) in result.bits.iter_mut().zip(other.bits.iter()) {
+            *w &= o;
+        }
+
+        result
+    }
+
+    /// Returns an iterator over the targets in the set
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (MIN_TARGET..=MAX_TARGET).filter(|&t| self.contains(t))
+    }
+
+    /// Returns an iterator over the targets not in the set
+    pub fn missing(&self) -> impl Iterator<Item = u32> + '_ {
+        (MIN_TARGET..=MAX_TARGET).filter(|&t| !self.contains(t))
+    }
+
+    /// Parses a solution map as written by solve_all, where '#' is a reachable target and '.'
+    /// is an unreachable target
+    pub fn from_map(map: &str) -> Option<Coverage> {
+        if map.len() != TARGETS {
+            None?
+        }
+
+        let mut coverage = Coverage::new();
+
+        for (target, c) in (MIN_TARGET..=MAX_TARGET).zip(map.chars()) {
+            match c {
+                '#' => {
+                    coverage.insert(target);
+                }
+                '.' => (),
+                _ => None?,
+            }
+        }
+
+        Some(coverage)
+    }
+
+    /// Returns the solution map for the set, where '#' is a reachable target and '.' is an
+    /// unreachable target
+    pub fn to_map(&self) -> String {
+        (MIN_TARGET..=MAX_TARGET)
+            .map(|t| if self.contains(t) { '#' } else { '.' })
+            .collect()
+    }
+
+    /// Returns the word and bit mask for a target
+    #[inline]
+    fn position(target: u32) -> Option<(usize, u64)> {
+        if (MIN_TARGET..=MAX_TARGET).contains(&target) {
+            let index = (target - MIN_TARGET) as usize;
+            Some((index / 64, 1 << (index % 64)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Programs {
+    /// Runs the programs with a given set of numbers and returns the set of targets reached.
+    /// Stops early if every target is reached
+    pub fn coverage(&self, numbers: &[u32]) -> Coverage {
+        let mut stack = ProgStack::new();
+        let mut coverage = Coverage::new();
+        let mut count = 0;
+
+        assert!(numbers.len() == self.nums as usize);
+
+        let symmetry = self.symmetry(numbers);
+
+        for program in &self.programs {
+            let instructions = self.instructions_for_program(program);
+
+            if symmetry.as_ref().is_some_and(|s| s.skip(instructions)) {
+                continue;
+            }
+
+            if let Ok(ans) = Self::run_instructions(instructions, numbers, &mut stack) {
+                if coverage.insert(ans) {
+                    count += 1;
+
+                    if count == TARGETS {
+                        break;
+                    }
+                }
+            }
+        }
+
+        coverage
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let mut coverage = Coverage::new();
+
+        assert!(coverage.insert(100));
+        assert!(coverage.insert(999));
+        assert!(coverage.insert(164));
+        assert!(!coverage.insert(164));
+        assert!(!coverage.insert(99));
+        assert!(!coverage.insert(1000));
+
+        assert_eq!(3, coverage.count());
+        assert_eq!(2, coverage.count_range(&(100..=500)));
+        assert!(coverage.contains(164));
+        assert!(!coverage.contains(165));
+        assert_eq!(vec![100, 164, 999], coverage.iter().collect::<Vec<_>>());
+        assert_eq!(TARGETS - 3, coverage.missing().count());
+        assert!(!coverage.is_full());
+        assert_eq!(Some(164), coverage.nearest(150));
+        assert_eq!(Some(100), coverage.nearest(132));
+        assert_eq!(Some(999), coverage.nearest(2000));
+        assert_eq!(None, Coverage::new().nearest(500));
+
+        let mut other = Coverage::new();
+        other.insert(164);
+        other.insert(500);
+
+        assert_eq!(4, coverage.union(&other).count());
+        assert_eq!(
+            vec![164],
+            coverage.intersection(&other).iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let mut coverage = Coverage::new();
+
+        coverage.insert(100);
+        coverage.insert(555);
+
+        let map = coverage.to_map();
+
+        assert_eq!(TARGETS, map.len());
+        assert!(map.starts_with("#."));
+        assert_eq!(Some(coverage), Coverage::from_map(&map));
+        assert_eq!(None, Coverage::from_map("#."));
+        assert_eq!(None, Coverage::from_map(&map.replace('#', "x")));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let programs = Programs::new(4, false, false);
+
+        for numbers in [[100, 75, 3, 2], [10, 9, 8, 7], [1, 1, 2, 2]] {
+            let results = programs.run_all(&numbers);
+
+            let mut expected = Coverage::new();
+
+            for solution in &results.solutions {
+                expected.insert(solution.result);
+            }
+
+            assert_eq!(expected, programs.coverage(&numbers));
+        }
+    }
+}
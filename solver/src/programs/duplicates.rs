@@ -13,23 +13,45 @@
 //! differently. The first program produces a NonInteger error because the ((25 × 10) - 5) / (75 + 50)
 //! term is evaluated first (1.96).
 
-use std::collections::HashSet;
-
-use super::infix::{infix_group_cb_stack, InfixGrpTypeElem};
+use super::dupset::DupSet;
+use super::infix::{infix_group_cb_stack, GroupPool, InfixGrpTypeElem};
 use super::progop::ProgOp;
 
-#[derive(Debug, PartialEq, Eq)]
-pub(crate) enum DupReason {
+/// Why a program is duplicated by another program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DupReason {
+    /// The program is not a duplicate
     NotDup,
+    /// The terms or operators of a bracket group are not in order, so another program gives the
+    /// same equation with the terms rearranged
     TermOrder,
+    /// The bracket groups are in order but another program already seen gives the same equation
     Infix,
 }
 
-/// Returns true if the program would be duplicated by rearranging the terms of the equation
+/// Number of duplicated solutions removed by Programs::filter_duplicates for each reason
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DupStats {
+    /// Solutions with the terms or operators of a bracket group out of order
+    pub term_order: usize,
+    /// Solutions giving the same equation as an earlier solution
+    pub infix: usize,
+}
+
+impl DupStats {
+    /// Returns the total number of duplicated solutions removed
+    pub fn total(&self) -> usize {
+        self.term_order + self.infix
+    }
+}
+
+/// Returns why the program would be duplicated by rearranging the terms of the equation. The group
+/// vectors of the equation tree are returned to the pool once it has been checked
 pub(crate) fn duplicated(
     instructions: &[ProgOp],
     stack: &mut Vec<InfixGrpTypeElem>,
-    set: &mut HashSet<InfixGrpTypeElem>,
+    pool: &mut GroupPool,
+    set: &mut DupSet,
 ) -> DupReason {
     let mut grp_cb = |grp: &Vec<(ProgOp, InfixGrpTypeElem)>| -> bool {
         let mut second_op = false;
@@ -73,9 +95,13 @@ pub(crate) fn duplicated(
         true
     };
 
-    match infix_group_cb_stack(instructions, stack, &mut grp_cb) {
+    match infix_group_cb_stack(instructions, stack, pool, &mut grp_cb) {
         Some(grp) => {
-            if set.insert(grp) {
+            let inserted = set.insert(&grp);
+
+            pool.recycle(grp);
+
+            if inserted {
                 DupReason::NotDup
             } else {
                 DupReason::Infix
@@ -95,7 +121,7 @@ mod tests {
 
     fn test_int(
         rpn: &str,
-        numbers: &[u8],
+        numbers: &[u32],
         exp_infix: &str,
         exp_ans: u32,
         exp_grps: usize,
@@ -105,7 +131,7 @@ mod tests {
         let programs: Programs = rpn.into();
 
         // Create element vector
-        let elems = (0..numbers.len()).map(|i| i as u8).collect::<Vec<_>>();
+        let elems = (0..numbers.len()).map(|i| i as u32).collect::<Vec<_>>();
 
         // Get infix groups
         let mut groups = Vec::new();
@@ -122,9 +148,10 @@ mod tests {
 
         // Is a duplicate?
         let mut stack = Vec::new();
-        let mut set = HashSet::new();
+        let mut pool = GroupPool::default();
+        let mut set = DupSet::new();
 
-        let duplicate = duplicated(programs.instructions(0), &mut stack, &mut set);
+        let duplicate = duplicated(programs.instructions(0), &mut stack, &mut pool, &mut set);
 
         // Print details
         println!(
@@ -0,0 +1,206 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for remembering the equations already seen when looking for
+//! duplicated programs.
+//!
+//! Storing the operator grouped equation tree of every program takes a lot of memory when
+//! generating programs for 6 or more numbers. Instead a 128 bit fingerprint of each tree is stored.
+//! The fingerprint is an FNV-1a hash of a prefix free encoding of the tree, so two trees only share
+//! a fingerprint if the hash collides, which with 128 bits is vanishingly unlikely for the few
+//! hundred million equations generated.
+//!
+//! With the `verify-fingerprints` feature the full trees are kept as well, and every fingerprint
+//! match is checked against the stored tree. A match for a different tree (a false positive, which
+//! would wrongly drop a program) panics.
+
+#[cfg(feature = "verify-fingerprints")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use super::infix::InfixGrpTypeElem;
+
+/// FNV-1a 128 bit offset basis
+const FNV_OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+
+/// FNV-1a 128 bit prime
+const FNV_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+/// Encoding tag for a number
+const TAG_NUMBER: u8 = 0;
+
+/// Encoding tag for a term
+const TAG_TERM: u8 = 1;
+
+/// Encoding tag for a group
+const TAG_GROUP: u8 = 2;
+
+/// Set of the equations seen by duplicate checks
+#[derive(Debug, Default)]
+pub struct DupSet {
+    fingerprints: HashSet<u128, BuildHasherDefault<FingerprintHasher>>,
+    #[cfg(feature = "verify-fingerprints")]
+    trees: HashMap<u128, InfixGrpTypeElem>,
+}
+
+impl DupSet {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set with space for a number of equations
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            fingerprints: HashSet::with_capacity_and_hasher(capacity, Default::default()),
+            #[cfg(feature = "verify-fingerprints")]
+            trees: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of equations in the set
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Returns true if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+
+    /// Forgets all of the equations
+    pub fn clear(&mut self) {
+        self.fingerprints.clear();
+
+        #[cfg(feature = "verify-fingerprints")]
+        self.trees.clear();
+    }
+
+    /// Adds an equation tree to the set. Returns true if the equation wasn't already in the set
+    pub(crate) fn insert(&mut self, tree: &InfixGrpTypeElem) -> bool {
+        let fingerprint = fingerprint(tree);
+        let inserted = self.fingerprints.insert(fingerprint);
+
+        #[cfg(feature = "verify-fingerprints")]
+        if inserted {
+            self.trees.insert(fingerprint, tree.clone());
+        } else {
+            let seen = &self.trees[&fingerprint];
+
+            assert!(
+                seen == tree,
+                "Fingerprint {fingerprint:032x} collision between {seen:?} and {tree:?}"
+            );
+        }
+
+        inserted
+    }
+}
+
+/// Returns the 128 bit fingerprint of an equation tree
+pub(crate) fn fingerprint(tree: &InfixGrpTypeElem) -> u128 {
+    let mut hash = FNV_OFFSET;
+
+    write_elem(&mut hash, tree);
+
+    hash
+}
+
+/// Adds the encoding of an equation tree element to a hash
+fn write_elem(hash: &mut u128, elem: &InfixGrpTypeElem) {
+    match elem {
+        InfixGrpTypeElem::Number(n) => {
+            write_byte(hash, TAG_NUMBER);
+            write_byte(hash, *n);
+        }
+        InfixGrpTypeElem::Term(t1, op, t2) => {
+            write_byte(hash, TAG_TERM);
+            write_elem(hash, t1);
+            write_byte(hash, op.bits());
+            write_elem(hash, t2);
+        }
+        InfixGrpTypeElem::Group(terms) => {
+            // Include the length so the end of the group is covered
+            write_byte(hash, TAG_GROUP);
+            write_byte(hash, terms.len() as u8);
+
+            for (op, elem) in terms {
+                write_byte(hash, op.bits());
+                write_elem(hash, elem);
+            }
+        }
+    }
+}
+
+/// Adds a byte to an FNV-1a hash
+#[inline]
+fn write_byte(hash: &mut u128, b: u8) {
+    *hash = (*hash ^ b as u128).wrapping_mul(FNV_PRIME);
+}
+
+/// Hasher for fingerprints in a hash set. The fingerprint is already a hash so the low 64 bits are
+/// used as they are
+#[derive(Debug, Default)]
+struct FingerprintHasher(u64);
+
+impl Hasher for FingerprintHasher {
+    fn write(&mut self, _bytes: &[u8]) {
+        unreachable!("Only fingerprints are hashed")
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.0 = i as u64;
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::infix::infix_group;
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_fingerprint() {
+        let programs = Programs::new(4, true, false);
+
+        // Trees are equal exactly when their fingerprints are
+        let trees = (0..programs.len())
+            .map(|i| infix_group(programs.instructions(i)))
+            .collect::<Vec<_>>();
+
+        let by_tree = trees.iter().collect::<HashSet<_>>();
+        let by_fingerprint = trees.iter().map(fingerprint).collect::<HashSet<_>>();
+
+        assert_eq!(by_tree.len(), by_fingerprint.len());
+
+        for tree in &by_tree {
+            assert_eq!(fingerprint(tree), fingerprint(&(*tree).clone()));
+        }
+    }
+
+    #[test]
+    fn test_dupset() {
+        let programs: Programs = "0 1 + 2 +".into();
+        let other: Programs = "0 1 - 2 +".into();
+        let tree = infix_group(programs.instructions(0));
+
+        let mut set = DupSet::new();
+
+        assert!(set.is_empty());
+        assert!(set.insert(&tree));
+        assert!(!set.insert(&tree));
+        assert!(set.insert(&infix_group(other.instructions(0))));
+        assert_eq!(2, set.len());
+
+        set.clear();
+
+        assert!(set.is_empty());
+        assert!(set.insert(&tree));
+    }
+}
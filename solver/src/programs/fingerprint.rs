@@ -0,0 +1,148 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for fingerprinting a collection of programs so results and cache
+//! entries generated by one collection can be checked against another.
+//!
+//! Programs are referred to by their element number (position in the collection) in solutions,
+//! cache entries and result files, so the order they are generated in is part of the format. The
+//! order is:
+//!  * Programs using fewer numbers before programs using more numbers
+//!  * Canonical collections: within a number count, combinations of number positions in
+//!    lexicographic order, then programs in group generation order (see the canonical module)
+//!  * Generated collections (duplicates included, filtered, or operators which can't be generated
+//!    canonically): within a number count, number permutations in lexicographic order, then
+//!    operator placements, then operator combinations
+//!
+//! Generation is deterministic: the same number count, duplicate setting and operators always give
+//! the same programs in the same order. Any change to the order must increase
+//! [`PROGRAM_ORDER_VERSION`], which is included in the fingerprint.
+
+use super::Programs;
+
+/// Version of the program generation order
+pub const PROGRAM_ORDER_VERSION: u32 = 1;
+
+/// FNV-1a 64 bit offset basis
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a 64 bit prime
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl Programs {
+    /// Returns a fingerprint of the programs collection. The fingerprint covers the program order
+    /// version, the generation options and the instructions of every program in order, and is stable
+    /// between runs, platforms and builds. It is calculated when first needed
+    pub fn fingerprint(&self) -> u64 {
+        *self.fingerprint.get_or_init(|| {
+            let mut hash = Fnv1a::new();
+
+            hash.write(&PROGRAM_ORDER_VERSION.to_le_bytes());
+            hash.write(&[self.nums, self.inc_duplicated as u8]);
+            hash.write(&(self.programs.len() as u64).to_le_bytes());
+
+            for prog in &self.programs {
+                let instructions = self.instructions_for_program(prog);
+
+                // Include the length so program boundaries are covered
+                hash.write(&(instructions.len() as u32).to_le_bytes());
+                hash.write(&instructions.iter().map(|op| op.bits()).collect::<Vec<_>>());
+            }
+
+            hash.finish()
+        })
+    }
+
+    /// Returns the fingerprint as a hexadecimal string
+    pub fn fingerprint_hex(&self) -> String {
+        format!("{:016x}", self.fingerprint())
+    }
+}
+
+/// FNV-1a hasher. The standard library hashers aren't guaranteed to be stable between releases
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(FNV_OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::{ProgInstr, ProgOp};
+
+    #[test]
+    fn test_fnv1a() {
+        // Published FNV-1a 64 test vectors
+        let mut hash = Fnv1a::new();
+        assert_eq!(0xcbf29ce484222325, hash.finish());
+
+        hash.write(b"a");
+        assert_eq!(0xaf63dc4c8601ec8c, hash.finish());
+
+        let mut hash = Fnv1a::new();
+        hash.write(b"foobar");
+        assert_eq!(0x85944171f73967e8, hash.finish());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        for nums in 1..=4 {
+            for inc_duplicated in [false, true] {
+                let programs1 = Programs::new(nums, inc_duplicated, false);
+                let programs2 = Programs::new(nums, inc_duplicated, false);
+
+                assert_eq!(programs1.fingerprint(), programs2.fingerprint());
+                assert_eq!(16, programs1.fingerprint_hex().len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_options_differ() {
+        let fingerprints = [
+            Programs::new(3, false, false).fingerprint(),
+            Programs::new(3, true, false).fingerprint(),
+            Programs::new(4, false, false).fingerprint(),
+            Programs::new_filtered(3, false).fingerprint(),
+            Programs::new_with_operators(
+                3,
+                false,
+                vec![ProgOp::PROG_OP_ADD, ProgOp::PROG_OP_MUL],
+                false,
+            )
+            .fingerprint(),
+        ];
+
+        for (i, f1) in fingerprints.iter().enumerate() {
+            for f2 in &fingerprints[i + 1..] {
+                assert_ne!(f1, f2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_boundaries() {
+        // Same instructions split differently give different fingerprints
+        let programs1 = Programs::from("01+");
+        let mut programs2 = Programs::from("01+");
+
+        programs2.programs = vec![ProgInstr { start: 0, end: 1 }].into();
+        programs2.fingerprint = Default::default();
+
+        assert_ne!(programs1.fingerprint(), programs2.fingerprint());
+    }
+}
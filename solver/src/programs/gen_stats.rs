@@ -0,0 +1,237 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for the statistics gathered while generating a collection of
+//! programs.
+//!
+//! The statistics are returned by Programs::new_with_stats so binaries and the server can log or
+//! serialise them. Verbose generation prints them using the Display implementation.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::format::NumFormat;
+
+/// Statistics on the generation of a collection of programs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenerationStats {
+    /// Number of numbers the programs were generated for
+    pub nums: u8,
+    /// True if canonical programs were generated directly rather than generating all programs and
+    /// filtering out the duplicates
+    pub canonical: bool,
+    /// True if duplicated programs were kept
+    pub inc_duplicated: bool,
+    /// Number of permutations of the numbers (nums!). Zero for canonical generation
+    pub permutations: usize,
+    /// Statistics for each number of numbers used by a program (1 to nums)
+    pub counts: Vec<NumCountStats>,
+    /// Number of programs generated
+    pub programs: usize,
+    /// Number of instructions in all of the programs
+    pub instructions: usize,
+    /// Number of programs expected before generation started. None for canonical generation
+    pub expected_programs: Option<usize>,
+    /// Number of instructions expected before generation started. None for canonical generation
+    pub expected_instructions: Option<usize>,
+    /// Time taken to generate the programs
+    pub elapsed: Duration,
+}
+
+/// Statistics on the generation of the programs using a number of numbers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NumCountStats {
+    /// Number of numbers used by the programs
+    pub numbers: u8,
+    /// Number of ways of placing the operators between the numbers. Zero for canonical generation
+    pub op_placements: usize,
+    /// Number of combinations of operators. Zero for canonical generation
+    pub op_combinations: usize,
+    /// Number of programs generated
+    pub programs: usize,
+    /// Programs filtered out with the terms or operators of a bracket group out of order
+    pub term_dups: usize,
+    /// Programs filtered out giving the same equation as an earlier program
+    pub infix_dups: usize,
+    /// Time taken to generate the programs
+    pub elapsed: Duration,
+}
+
+impl GenerationStats {
+    /// Returns the total number of programs filtered out with terms or operators out of order
+    pub fn term_dups(&self) -> usize {
+        self.counts.iter().map(|count| count.term_dups).sum()
+    }
+
+    /// Returns the total number of programs filtered out giving the same equation as an earlier
+    /// program
+    pub fn infix_dups(&self) -> usize {
+        self.counts.iter().map(|count| count.infix_dups).sum()
+    }
+
+    /// Returns the statistics as a JSON object
+    pub fn to_json(&self) -> String {
+        let counts = self
+            .counts
+            .iter()
+            .map(|count| {
+                format!(
+                    "{{\"numbers\":{},\"op_placements\":{},\"op_combinations\":{},\"programs\":{},\"term_dups\":{},\"infix_dups\":{},\"elapsed_ms\":{}}}",
+                    count.numbers,
+                    count.op_placements,
+                    count.op_combinations,
+                    count.programs,
+                    count.term_dups,
+                    count.infix_dups,
+                    count.elapsed.as_millis()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"nums\":{},\"canonical\":{},\"inc_duplicated\":{},\"permutations\":{},\"programs\":{},\"instructions\":{},\"expected_programs\":{},\"expected_instructions\":{},\"elapsed_ms\":{},\"counts\":[{counts}]}}",
+            self.nums,
+            self.canonical,
+            self.inc_duplicated,
+            self.permutations,
+            self.programs,
+            self.instructions,
+            json_option(self.expected_programs),
+            json_option(self.expected_instructions),
+            self.elapsed.as_millis()
+        )
+    }
+}
+
+impl fmt::Display for GenerationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.canonical {
+            writeln!(f, "Canonical programs generated by number of numbers:")?;
+
+            for count in &self.counts {
+                writeln!(
+                    f,
+                    "  {:>5}: {:>10}",
+                    count.numbers,
+                    count.programs.num_format()
+                )?;
+            }
+        } else {
+            writeln!(f, "Card permutations: {}", self.permutations.num_format())?;
+
+            writeln!(
+                f,
+                "Operator placement counts and combinations for number of numbers:"
+            )?;
+
+            for count in &self.counts {
+                writeln!(
+                    f,
+                    "  {}: {:>6} {:>6}",
+                    count.numbers,
+                    count.op_placements.num_format(),
+                    count.op_combinations.num_format()
+                )?;
+            }
+
+            if !self.inc_duplicated {
+                writeln!(f, "Duplicate programs filtered by number of numbers:")?;
+
+                for count in &self.counts {
+                    writeln!(
+                        f,
+                        "  {:>5}: terms {:>10}  infix {:>10}",
+                        count.numbers,
+                        count.term_dups.num_format(),
+                        count.infix_dups.num_format()
+                    )?;
+                }
+
+                writeln!(
+                    f,
+                    "  Total: terms {:>10}  infix {:>10}",
+                    self.term_dups().num_format(),
+                    self.infix_dups().num_format()
+                )?;
+            }
+        }
+
+        write!(f, "{} programs generated", self.programs.num_format())?;
+
+        if let Some(expected) = self.expected_programs {
+            write!(f, " (expected {})", expected.num_format())?;
+        }
+
+        writeln!(f)?;
+
+        write!(f, "{} total instructions", self.instructions.num_format())?;
+
+        if let Some(expected) = self.expected_instructions {
+            write!(f, " (expected {})", expected.num_format())?;
+        }
+
+        writeln!(f)?;
+
+        write!(f, "Generated in {:.3}s", self.elapsed.as_secs_f64())
+    }
+}
+
+/// Formats an optional count as JSON
+fn json_option(value: Option<usize>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "null".to_string(),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_generation_stats() {
+        // Canonical generation
+        let (programs, stats) = Programs::new_with_stats(4, false);
+
+        assert!(stats.canonical);
+        assert_eq!(4, stats.nums);
+        assert_eq!(programs.len(), stats.programs);
+        assert_eq!(
+            programs.len(),
+            stats.counts.iter().map(|c| c.programs).sum::<usize>()
+        );
+        assert_eq!(None, stats.expected_programs);
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            stats.counts.iter().map(|c| c.numbers).collect::<Vec<_>>()
+        );
+
+        // All programs
+        let (programs, stats) = Programs::new_with_stats(4, true);
+
+        assert!(!stats.canonical);
+        assert!(stats.inc_duplicated);
+        assert_eq!(24, stats.permutations);
+        assert_eq!(programs.len(), stats.programs);
+        assert_eq!(Some(programs.len()), stats.expected_programs);
+        assert_eq!(0, stats.term_dups() + stats.infix_dups());
+        assert_eq!(
+            vec![(0, 0), (1, 4), (2, 16), (5, 64)],
+            stats
+                .counts
+                .iter()
+                .map(|c| (c.op_placements, c.op_combinations))
+                .collect::<Vec<_>>()
+        );
+
+        let json = stats.to_json();
+
+        assert!(json.starts_with("{\"nums\":4,\"canonical\":false,\"inc_duplicated\":true,"));
+        assert!(json.contains(&format!("\"programs\":{},", programs.len())));
+        assert!(json.contains("\"counts\":[{\"numbers\":1,"));
+
+        assert!(stats.to_string().contains("Card permutations: 24"));
+    }
+}
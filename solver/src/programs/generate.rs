@@ -10,44 +10,187 @@
 //! number of stacked numbers preceding it - 1. The counts of operators in each slot in
 //! this example would be:
 //! [0, 0, 3], [0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 1, 1]
+//!
+//! The number of programs which will be left after duplicates are filtered out is counted exactly
+//! up front from the shapes of the equations, so the program and instruction vectors can be
+//! allocated once at the right size.
 
 use std::cmp::min;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use super::duplicates::{duplicated, DupReason};
+use super::dupset::DupSet;
+use super::infix::GroupPool;
 use super::progop::ProgOp;
 use super::ProgInstr;
+use crate::cancel::{CancellationToken, Cancelled};
 
-/// Calculates the number of programs that will be generated for a given number of numbers.
-/// When duplicates are filtered out an estimate is returned
+/// Calculates the number of programs that will be generated for each number of numbers (1 to nums)
 pub(crate) fn calc_num_programs(
     nums: u8,
     inc_duplicated: bool,
-    num_perms: &Vec<Vec<u8>>,
+    num_perms: &[Vec<u8>],
     op_map: &HashMap<u8, (OpCounts, OpCombs)>,
-) -> usize {
-    let mut total = 0;
+    operators: &[ProgOp],
+) -> Vec<usize> {
+    if !inc_duplicated {
+        return calc_filtered_programs(nums, operators);
+    }
 
-    for num_cnt in 1..=nums {
-        let mult = if num_cnt == 1 {
-            // No operators
-            1
-        } else {
-            // Get operator counts and combinations
-            let (op_count, op_comb) = op_map.get(&num_cnt).unwrap();
+    (1..=nums)
+        .map(|num_cnt| {
+            let mult = if num_cnt == 1 {
+                // No operators
+                1
+            } else {
+                // Get operator counts and combinations
+                let (op_count, op_comb) = op_map.get(&num_cnt).unwrap();
+
+                op_count.len() * op_comb.len()
+            };
+
+            num_perms.len() * mult
+        })
+        .collect()
+}
 
-            op_count.len() * op_comb.len()
-        };
+/// Calculates the number of programs left for each number of numbers (1 to nums) when duplicates
+/// are filtered out.
+///
+/// A program is kept for each distinct operator grouped equation tree whose groups pass the term
+/// order checks (see the duplicates module). A group of one kind (+ and - or × and /) over a set of
+/// numbers is split in to elements with the first operator (+ or ×), including the first element,
+/// followed by elements with the second operator (- or /). Within each run the numbers come first
+/// in ascending order followed by sub-groups in any order. Sub-groups in the first run are of the
+/// other kind, sub-groups in the second run can be of either kind. The operator of the first element
+/// records the last operator applied, which can be either operator if both runs have more than one
+/// element. Programs using k of the numbers are counted once for each choice of k numbers
+fn calc_filtered_programs(nums: u8, operators: &[ProgOp]) -> Vec<usize> {
+    let nums = nums as usize;
+    let binom = binomials(nums);
+
+    // First and second operators available for each group kind (additive, multiplicative)
+    let kinds = [
+        (ProgOp::PROG_OP_ADD, ProgOp::PROG_OP_SUB),
+        (ProgOp::PROG_OP_MUL, ProgOp::PROG_OP_DIV),
+    ]
+    .map(|(first, second)| (operators.contains(&first), operators.contains(&second)));
+
+    // Number of groups of each kind over n numbers
+    let mut groups = [vec![0usize; nums + 1], vec![0usize; nums + 1]];
+
+    for n in 2..=nums {
+        for (kind, &(has_first, has_second)) in kinds.iter().enumerate() {
+            let other = 1 - kind;
+
+            // Sub-groups are always smaller than the group
+            let weight = |m: usize, same: bool| {
+                if m < n {
+                    let same_count = if same { groups[kind][m] } else { 0 };
+                    groups[other][m].saturating_add(same_count)
+                } else {
+                    0
+                }
+            };
+
+            let first_runs = runs(n, &binom, |m| weight(m, false));
+            let second_runs = runs(n, &binom, |m| weight(m, true));
+
+            let mut count = 0usize;
+
+            for n1 in 1..=n {
+                let n2 = n - n1;
+
+                for (c1, &first) in first_runs[n1].iter().enumerate().take(n1 + 1).skip(1) {
+                    if c1 > 1 && !has_first {
+                        continue;
+                    }
 
-        total += num_perms.len() * mult;
+                    for (c2, &second) in second_runs[n2].iter().enumerate().take(n2 + 1) {
+                        if (n2 == 0) != (c2 == 0) || c1 + c2 < 2 || (c2 > 0 && !has_second) {
+                            continue;
+                        }
+
+                        let roots = if c1 > 1 && c2 > 0 { 2 } else { 1 };
+
+                        count = count.saturating_add(
+                            binom[n][n1]
+                                .saturating_mul(first)
+                                .saturating_mul(second)
+                                .saturating_mul(roots),
+                        );
+                    }
+                }
+            }
+
+            groups[kind][n] = count;
+        }
     }
 
-    if !inc_duplicated {
-        // Guess about 1/7 of programs left behind after duplicate filtering
-        total /= 7;
+    (1..=nums)
+        .map(|num_cnt| {
+            let trees = if num_cnt == 1 {
+                1
+            } else {
+                groups[0][num_cnt].saturating_add(groups[1][num_cnt])
+            };
+
+            binom[nums][num_cnt].saturating_mul(trees)
+        })
+        .collect()
+}
+
+/// Returns the number of ways of arranging m numbers in to a run of c elements for m and c up to n.
+/// Numbers are in ascending order followed by sub-groups in any order, with the number of
+/// sub-groups over a number of numbers given by weight
+fn runs<W>(n: usize, binom: &[Vec<usize>], weight: W) -> Vec<Vec<usize>>
+where
+    W: Fn(usize) -> usize,
+{
+    // Ordered sequences of g sub-groups over m numbers
+    let mut seqs = vec![vec![0usize; n + 1]; n + 1];
+    seqs[0][0] = 1;
+
+    for m in 2..=n {
+        for g in 1..=m / 2 {
+            seqs[m][g] = (2..=m).fold(0usize, |total, first| {
+                total.saturating_add(
+                    binom[m][first]
+                        .saturating_mul(weight(first))
+                        .saturating_mul(seqs[m - first][g - 1]),
+                )
+            });
+        }
+    }
+
+    let mut runs = vec![vec![0usize; n + 1]; n + 1];
+
+    for m in 0..=n {
+        for c in 0..=m {
+            runs[m][c] = (0..=c).fold(0usize, |total, singles| {
+                total.saturating_add(
+                    binom[m][singles].saturating_mul(seqs[m - singles][c - singles]),
+                )
+            });
+        }
+    }
+
+    runs
+}
+
+/// Returns Pascal's triangle up to n
+fn binomials(n: usize) -> Vec<Vec<usize>> {
+    let mut binom = vec![vec![0usize; n + 1]; n + 1];
+
+    for i in 0..=n {
+        binom[i][0] = 1;
+
+        for j in 1..=i {
+            binom[i][j] = binom[i - 1][j - 1].saturating_add(binom[i - 1][j]);
+        }
     }
 
-    total
+    binom
 }
 
 /// Generates RPN programs for the given total number of numbers, the number of numbers selected
@@ -56,17 +199,19 @@ pub(crate) fn generate_num_programs(
     programs: &mut Vec<ProgInstr>,
     instructions: &mut Vec<ProgOp>,
     num_cnt: u8,
-    num_perms: &Vec<Vec<u8>>,
+    num_perms: &[Vec<u8>],
     op_map: &HashMap<u8, (OpCounts, OpCombs)>,
     inc_duplicated: bool,
-) -> (usize, usize) {
+    cancel: Option<&CancellationToken>,
+) -> Result<(usize, usize), Cancelled> {
     let mut stack = Vec::with_capacity(num_cnt as usize);
+    let mut pool = GroupPool::default();
 
     let mut set = if inc_duplicated {
         // Not used when duplicates are included
-        HashSet::new()
+        DupSet::new()
     } else {
-        HashSet::with_capacity(programs.capacity())
+        DupSet::with_capacity(programs.capacity())
     };
 
     // Get operator counts and combinations
@@ -85,7 +230,12 @@ pub(crate) fn generate_num_programs(
 
         // Duplicate check
         let ok = if !inc_duplicated {
-            let reason = duplicated(&instructions[inst_start..=inst_end], &mut stack, &mut set);
+            let reason = duplicated(
+                &instructions[inst_start..=inst_end],
+                &mut stack,
+                &mut pool,
+                &mut set,
+            );
 
             match reason {
                 DupReason::NotDup => true,
@@ -115,6 +265,10 @@ pub(crate) fn generate_num_programs(
     };
 
     for nums in num_perms {
+        if let Some(cancel) = cancel {
+            cancel.check()?;
+        }
+
         if num_cnt == 1 {
             // Push the number
             instructions.push(ProgOp::new_number(nums[0]));
@@ -146,7 +300,7 @@ pub(crate) fn generate_num_programs(
         }
     }
 
-    (term_dups, infix_dups)
+    Ok((term_dups, infix_dups))
 }
 
 type OpCounts = Vec<Vec<u8>>;
@@ -260,6 +414,7 @@ fn op_combs_rec(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::programs::Programs;
 
     #[test]
     fn test_op_counts() {
@@ -289,4 +444,52 @@ mod tests {
 
         assert_eq!(expected, combs);
     }
+
+    #[test]
+    fn test_filtered_counts() {
+        // Counts match generation with all operators
+        assert_eq!(
+            vec![4, 36, 368, 2_350],
+            calc_filtered_programs(4, &ProgOp::OPERATORS)
+        );
+        assert_eq!(
+            98_467,
+            calc_filtered_programs(5, &ProgOp::OPERATORS)
+                .iter()
+                .sum::<usize>()
+        );
+        assert_eq!(
+            4_599_164,
+            calc_filtered_programs(6, &ProgOp::OPERATORS)
+                .iter()
+                .sum::<usize>()
+        );
+
+        // Generated counts match for operator sets
+        for operators in [
+            ProgOp::OPERATORS.to_vec(),
+            vec![ProgOp::PROG_OP_ADD],
+            vec![ProgOp::PROG_OP_SUB],
+            vec![ProgOp::PROG_OP_ADD, ProgOp::PROG_OP_DIV],
+            vec![
+                ProgOp::PROG_OP_SUB,
+                ProgOp::PROG_OP_MUL,
+                ProgOp::PROG_OP_DIV,
+            ],
+        ] {
+            for nums in 1..=4 {
+                let programs = Programs::new_generated(nums, false, operators.clone(), None)
+                    .unwrap()
+                    .0;
+
+                assert_eq!(
+                    programs.len(),
+                    calc_filtered_programs(nums, &operators)
+                        .iter()
+                        .sum::<usize>(),
+                    "{nums} numbers with {operators:?}"
+                );
+            }
+        }
+    }
 }
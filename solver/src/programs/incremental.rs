@@ -0,0 +1,157 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for keeping the result of every program for a set of numbers so the
+//! results can be updated cheaply when one of the numbers changes. Only programs which use the
+//! changed number are run again.
+//!
+//! Most programs use every number so the saving is small: changing one of 6 cards reruns 97% of
+//! the canonical programs (98.5% when duplicates are included). It is mostly useful for keeping the
+//! result of each program up to date, eg. while a set of cards is edited interactively.
+
+use super::solution::Answer;
+use super::stack::ProgStack;
+use super::{ProgErr, Programs, Results, MAX_TARGET, MIN_TARGET};
+
+/// The result of every program for a set of numbers
+pub struct Evaluation {
+    /// Numbers used
+    numbers: Vec<u32>,
+    /// Bitmask of the numbers used by each program
+    masks: Vec<u32>,
+    /// Result of each program
+    answers: Vec<Result<Answer, ProgErr>>,
+}
+
+impl Evaluation {
+    /// Returns the numbers used for the evaluation
+    pub fn numbers(&self) -> &[u32] {
+        &self.numbers
+    }
+
+    /// Returns the result of a program
+    pub fn answer(&self, prog_elem: usize) -> Result<u32, ProgErr> {
+        self.answers[prog_elem].map(|answer| answer.value)
+    }
+
+    /// Returns the results in the same form as running all of the programs
+    pub fn results(&self, programs: &Programs) -> Results {
+        let mut results = Results::default();
+        let symmetry = programs.symmetry(&self.numbers);
+
+        for (i, answer) in self.answers.iter().enumerate() {
+            if symmetry
+                .as_ref()
+                .is_some_and(|s| s.skip(programs.instructions(i)))
+            {
+                results.symmetric += 1;
+                continue;
+            }
+
+            results.add(
+                i,
+                programs.instructions(i),
+                *answer,
+                &(MIN_TARGET..=MAX_TARGET),
+            );
+        }
+
+        results
+    }
+}
+
+impl Programs {
+    /// Runs all of the programs with a given set of numbers and keeps the result of each program
+    pub fn evaluate(&self, numbers: &[u32]) -> Evaluation {
+        assert!(numbers.len() == self.nums as usize);
+
+        let mut stack = ProgStack::new();
+
+        let mut masks = Vec::with_capacity(self.programs.len());
+        let mut answers = Vec::with_capacity(self.programs.len());
+
+        for program in &self.programs {
+            let instructions = self.instructions_for_program(program);
+
+            masks.push(
+                instructions
+                    .iter()
+                    .filter(|op| op.is_number())
+                    .fold(0, |mask, op| mask | (1 << op.bits())),
+            );
+
+            answers.push(Self::run_instructions_answer(
+                instructions,
+                numbers,
+                &mut stack,
+            ));
+        }
+
+        Evaluation {
+            numbers: numbers.to_vec(),
+            masks,
+            answers,
+        }
+    }
+
+    /// Changes one of the numbers in an evaluation, running only the programs which use it.
+    /// Returns the number of programs run
+    pub fn reevaluate(&self, evaluation: &mut Evaluation, index: usize, value: u32) -> usize {
+        assert!(index < self.nums as usize);
+        assert!(evaluation.answers.len() == self.programs.len());
+
+        evaluation.numbers[index] = value;
+
+        let mut stack = ProgStack::new();
+        let mut run = 0;
+        let bit = 1 << index;
+
+        for (i, program) in self.programs.iter().enumerate() {
+            if evaluation.masks[i] & bit != 0 {
+                let instructions = self.instructions_for_program(program);
+
+                evaluation.answers[i] =
+                    Self::run_instructions_answer(instructions, &evaluation.numbers, &mut stack);
+
+                run += 1;
+            }
+        }
+
+        run
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reevaluate() {
+        let programs = Programs::new(4, false, false);
+
+        let mut evaluation = programs.evaluate(&[100, 75, 3, 2]);
+
+        for (index, value) in [(2, 7), (0, 25), (3, 3), (1, 3)] {
+            let run = programs.reevaluate(&mut evaluation, index, value);
+
+            assert!(run < programs.len());
+
+            let numbers = evaluation.numbers().to_vec();
+
+            for i in 0..programs.len() {
+                assert_eq!(programs.run(i, &numbers), evaluation.answer(i));
+            }
+
+            let results = evaluation.results(&programs);
+            let expected = programs.run_all(&numbers);
+
+            assert_eq!(expected.solutions, results.solutions);
+            assert_eq!(expected.under_range, results.under_range);
+            assert_eq!(expected.non_integer, results.non_integer);
+            assert_eq!(expected.symmetric, results.symmetric);
+        }
+
+        assert_eq!(&[25, 3, 7, 3], evaluation.numbers());
+    }
+}
@@ -4,8 +4,20 @@
 //! For a given RPN program a tree of elements is returned describing the grouping of
 //! operations.
 
+use std::fmt::Write;
+
 use super::progop::ProgOp;
 use super::Programs;
+use crate::format::{machine_output, symbol_set, NumFormat};
+
+/// Appends a number to a string, formatted for the locale unless machine output mode is on
+pub(crate) fn write_number(n: u32, out: &mut String) {
+    if machine_output() {
+        write!(out, "{n}").expect("Write to string failed");
+    } else {
+        out.push_str(&n.num_format());
+    }
+}
 
 /// Operator type simplification equation element
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -23,11 +35,59 @@ pub enum InfixGrpTypeElem {
 
 impl InfixGrpTypeElem {
     /// Formats an operator type simplification equation element with optional colour
-    pub fn colour(&self, numbers: &[u8], colour: bool) -> String {
+    pub fn colour(&self, numbers: &[u32], colour: bool) -> String {
         self.colour_internal(numbers, colour, false)
     }
 
-    fn colour_internal(&self, numbers: &[u8], colour: bool, brackets: bool) -> String {
+    /// Appends the equation without colour to a string, without allocating a string for each
+    /// element
+    pub(crate) fn write_plain(&self, numbers: &[u32], out: &mut String) {
+        self.write_plain_internal(numbers, out, false)
+    }
+
+    fn write_plain_internal(&self, numbers: &[u32], out: &mut String, brackets: bool) {
+        let symbols = symbol_set();
+
+        match self {
+            InfixGrpTypeElem::Number(n) => write_number(numbers[*n as usize], out),
+            InfixGrpTypeElem::Term(t1, op, t2) => {
+                if brackets {
+                    out.push('(');
+                }
+
+                t1.write_plain_internal(numbers, out, true);
+                out.push(' ');
+                out.push_str(op.symbol_in(symbols).unwrap());
+                out.push(' ');
+                t2.write_plain_internal(numbers, out, true);
+
+                if brackets {
+                    out.push(')');
+                }
+            }
+            InfixGrpTypeElem::Group(terms) => {
+                if brackets {
+                    out.push('(');
+                }
+
+                for (i, (op, elem)) in terms.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                        out.push_str(op.symbol_in(symbols).unwrap());
+                        out.push(' ');
+                    }
+
+                    elem.write_plain_internal(numbers, out, true);
+                }
+
+                if brackets {
+                    out.push(')');
+                }
+            }
+        }
+    }
+
+    fn colour_internal(&self, numbers: &[u32], colour: bool, brackets: bool) -> String {
         let mut no_brackets = false;
 
         let inner = match self {
@@ -67,6 +127,59 @@ impl InfixGrpTypeElem {
     }
 }
 
+/// Maximum number of empty group vectors kept in a pool
+const MAX_POOLED_GROUPS: usize = 1024;
+
+/// Pool of group vectors reused when building equation trees. Counts the vectors allocated and the
+/// vectors reused from the pool
+#[derive(Debug, Default)]
+pub(crate) struct GroupPool {
+    groups: Vec<Vec<(ProgOp, InfixGrpTypeElem)>>,
+    pub(crate) allocated: usize,
+    pub(crate) reused: usize,
+}
+
+impl GroupPool {
+    /// Takes an empty group vector from the pool, or allocates one if the pool is empty
+    fn take(&mut self, capacity: usize) -> Vec<(ProgOp, InfixGrpTypeElem)> {
+        match self.groups.pop() {
+            Some(grp) => {
+                self.reused += 1;
+                grp
+            }
+            None => {
+                self.allocated += 1;
+                Vec::with_capacity(capacity)
+            }
+        }
+    }
+
+    /// Returns an empty group vector to the pool
+    fn give(&mut self, grp: Vec<(ProgOp, InfixGrpTypeElem)>) {
+        if grp.capacity() > 0 && self.groups.len() < MAX_POOLED_GROUPS {
+            self.groups.push(grp);
+        }
+    }
+
+    /// Returns the group vectors of an equation tree to the pool
+    pub(crate) fn recycle(&mut self, elem: InfixGrpTypeElem) {
+        match elem {
+            InfixGrpTypeElem::Number(_) => (),
+            InfixGrpTypeElem::Term(t1, _, t2) => {
+                self.recycle(*t1);
+                self.recycle(*t2);
+            }
+            InfixGrpTypeElem::Group(mut terms) => {
+                for (_, elem) in terms.drain(..) {
+                    self.recycle(elem);
+                }
+
+                self.give(terms);
+            }
+        }
+    }
+}
+
 /// Returns the infix structure for the program
 pub(crate) fn infix_group(instructions: &[ProgOp]) -> InfixGrpTypeElem {
     infix_group_cb(instructions, &mut |_| true).unwrap()
@@ -78,25 +191,38 @@ where
     F: FnMut(&Vec<(ProgOp, InfixGrpTypeElem)>) -> bool,
 {
     let mut stack = Vec::new();
+    let mut pool = GroupPool::default();
 
-    infix_group_cb_stack(instructions, &mut stack, grp_cb)
+    infix_group_cb_stack(instructions, &mut stack, &mut pool, grp_cb)
 }
 
-/// Returns an operator type simplified equation tree for a program
+/// Returns an operator type simplified equation tree for a program, taking group vectors from a
+/// pool. The vectors of a tree rejected by the callback are returned to the pool
 pub(crate) fn infix_group_cb_stack<F>(
     instructions: &[ProgOp],
     stack: &mut Vec<InfixGrpTypeElem>,
+    pool: &mut GroupPool,
     grp_cb: &mut F,
 ) -> Option<InfixGrpTypeElem>
 where
     F: FnMut(&Vec<(ProgOp, InfixGrpTypeElem)>) -> bool,
 {
-    stack.clear();
+    // Recycle anything left on the stack by the last program if it was rejected
+    for elem in stack.drain(..) {
+        pool.recycle(elem);
+    }
 
     let inst_cnt = instructions.len();
 
-    let build_grp = |other_op, t1, op, t2, inc_right, grp_cb: &mut F| -> Option<InfixGrpTypeElem> {
-        let mut grp = Vec::with_capacity(inst_cnt);
+    let build_grp = |other_op,
+                     t1,
+                     op,
+                     t2,
+                     inc_right,
+                     pool: &mut GroupPool,
+                     grp_cb: &mut F|
+     -> Option<InfixGrpTypeElem> {
+        let mut grp = pool.take(inst_cnt);
 
         match t1 {
             InfixGrpTypeElem::Group(mut t1_terms)
@@ -105,11 +231,15 @@ where
                 // Group with compatible operator (ie + and - or * and /)
                 t1_terms[0].0 = op;
                 grp.append(&mut t1_terms);
+                pool.give(t1_terms);
             }
             InfixGrpTypeElem::Group(ref t1_terms) => {
                 // Group with incompatible operators
                 if !grp_cb(t1_terms) {
-                    None?
+                    pool.recycle(t1);
+                    pool.recycle(t2);
+                    pool.give(grp);
+                    return None;
                 }
 
                 grp.push((op, t1))
@@ -124,12 +254,15 @@ where
                 {
                     // Group with compatible operator (ie + and - or * and /)
                     t2_terms[0].0 = op;
-                    grp.append(&mut t2_terms)
+                    grp.append(&mut t2_terms);
+                    pool.give(t2_terms);
                 }
                 InfixGrpTypeElem::Group(ref t2_terms) => {
                     // Group with incompatible operators
                     if !grp_cb(t2_terms) {
-                        None?
+                        pool.recycle(t2);
+                        pool.recycle(InfixGrpTypeElem::Group(grp));
+                        return None;
                     }
 
                     grp.push((op, t2))
@@ -140,7 +273,9 @@ where
             match t2 {
                 InfixGrpTypeElem::Group(ref t2_terms) => {
                     if !grp_cb(t2_terms) {
-                        None?
+                        pool.recycle(t2);
+                        pool.recycle(InfixGrpTypeElem::Group(grp));
+                        return None;
                     }
 
                     grp.push((op, t2))
@@ -152,38 +287,39 @@ where
         Some(InfixGrpTypeElem::Group(grp))
     };
 
-    let build_term = |t1, op, t2, grp_cb: &mut F| -> Option<InfixGrpTypeElem> {
-        if let InfixGrpTypeElem::Group(grp1) = &t1 {
-            if !grp_cb(grp1) {
-                None?
+    let build_term =
+        |t1, op, t2, pool: &mut GroupPool, grp_cb: &mut F| -> Option<InfixGrpTypeElem> {
+            let rejected = |t: &InfixGrpTypeElem, grp_cb: &mut F| match t {
+                InfixGrpTypeElem::Group(grp) => !grp_cb(grp),
+                _ => false,
+            };
+
+            if rejected(&t1, grp_cb) || rejected(&t2, grp_cb) {
+                pool.recycle(t1);
+                pool.recycle(t2);
+                return None;
             }
-        }
-
-        if let InfixGrpTypeElem::Group(grp2) = &t2 {
-            if !grp_cb(grp2) {
-                None?
-            }
-        }
 
-        Some(InfixGrpTypeElem::Term(Box::new(t1), op, Box::new(t2)))
-    };
+            Some(InfixGrpTypeElem::Term(Box::new(t1), op, Box::new(t2)))
+        };
 
     let outer_term = Programs::process_instructions(
         instructions,
         stack,
         |n| Some(InfixGrpTypeElem::Number(n)),
         |t1, op, t2| match op & ProgOp::PROG_OP_MASK {
-            ProgOp::PROG_OP_ADD => build_grp(ProgOp::PROG_OP_SUB, t1, op, t2, true, grp_cb),
-            ProgOp::PROG_OP_MUL => build_grp(ProgOp::PROG_OP_DIV, t1, op, t2, true, grp_cb),
-            ProgOp::PROG_OP_SUB => build_grp(ProgOp::PROG_OP_ADD, t1, op, t2, false, grp_cb),
-            ProgOp::PROG_OP_DIV => build_grp(ProgOp::PROG_OP_MUL, t1, op, t2, false, grp_cb),
-            _ => build_term(t1, op, t2, grp_cb),
+            ProgOp::PROG_OP_ADD => build_grp(ProgOp::PROG_OP_SUB, t1, op, t2, true, pool, grp_cb),
+            ProgOp::PROG_OP_MUL => build_grp(ProgOp::PROG_OP_DIV, t1, op, t2, true, pool, grp_cb),
+            ProgOp::PROG_OP_SUB => build_grp(ProgOp::PROG_OP_ADD, t1, op, t2, false, pool, grp_cb),
+            ProgOp::PROG_OP_DIV => build_grp(ProgOp::PROG_OP_MUL, t1, op, t2, false, pool, grp_cb),
+            _ => build_term(t1, op, t2, pool, grp_cb),
         },
     )?;
 
     if let InfixGrpTypeElem::Group(grp) = &outer_term {
         if !grp_cb(grp) {
-            None?
+            pool.recycle(outer_term);
+            return None;
         }
     }
 
@@ -206,7 +342,7 @@ mod tests {
             .filter(|i| i.is_number())
             .count();
 
-        let numbers = (0..num_count).map(|i| i as u8).collect::<Vec<_>>();
+        let numbers = (0..num_count).map(|i| i as u32).collect::<Vec<_>>();
 
         test_program_infix(&programs, exp_infix, &numbers);
     }
@@ -214,7 +350,7 @@ mod tests {
     fn test_rpn_infix_and_result(
         rpn: &str,
         exp_infix: &str,
-        numbers: &[u8],
+        numbers: &[u32],
         exp_ans: Result<u32, ProgErr>,
     ) {
         let programs: Programs = rpn.into();
@@ -226,7 +362,7 @@ mod tests {
         assert_eq!(exp_ans, ans);
     }
 
-    fn test_program_infix(programs: &Programs, exp_infix: &str, numbers: &[u8]) {
+    fn test_program_infix(programs: &Programs, exp_infix: &str, numbers: &[u32]) {
         let infix = infix_group(programs.instructions(0));
 
         println!(
@@ -0,0 +1,92 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for holding the programs for each number of cards, generated the
+//! first time they are needed. Front ends solving puzzles with differing numbers of cards share one
+//! between threads rather than generating the programs for every puzzle.
+
+use std::sync::OnceLock;
+
+use super::Programs;
+use crate::capabilities::MAX_CARDS;
+
+/// Programs for each number of cards (1 to MAX_CARDS), generated when first needed
+pub struct LazyPrograms {
+    programs: [OnceLock<Programs>; MAX_CARDS],
+}
+
+impl Default for LazyPrograms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LazyPrograms {
+    /// Creates the collection with no programs. This is const so it can be used in a static
+    pub const fn new() -> Self {
+        Self {
+            programs: [const { OnceLock::new() }; MAX_CARDS],
+        }
+    }
+
+    /// Returns the programs for a number of cards if they are ready
+    pub fn get(&self, cards: usize) -> Option<&Programs> {
+        self.slot(cards).get()
+    }
+
+    /// Sets the programs for a number of cards, returning them back if they are already set
+    pub fn set(&self, cards: usize, programs: Programs) -> Result<(), Programs> {
+        self.slot(cards).set(programs)
+    }
+
+    /// Returns the programs for a number of cards, creating them with a function the first time
+    pub fn get_or_init(&self, cards: usize, init: impl FnOnce() -> Programs) -> &Programs {
+        self.slot(cards).get_or_init(init)
+    }
+
+    /// Returns the programs for a number of cards, generating them the first time
+    pub fn get_or_generate(&self, cards: usize, inc_duplicated: bool) -> &Programs {
+        self.get_or_init(cards, || Programs::new(cards as u8, inc_duplicated, false))
+    }
+
+    /// Returns the slot for a number of cards. Panics if the number is 0 or more than MAX_CARDS
+    fn slot(&self, cards: usize) -> &OnceLock<Programs> {
+        assert!(
+            (1..=MAX_CARDS).contains(&cards),
+            "Number of cards must be 1 to {MAX_CARDS}, not {cards}"
+        );
+
+        &self.programs[cards - 1]
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_programs() {
+        let lazy = LazyPrograms::new();
+
+        assert!(lazy.get(2).is_none());
+
+        let programs = lazy.get_or_generate(2, false);
+
+        assert_eq!(Programs::new(2, false, false).len(), programs.len());
+        assert!(lazy.get(2).is_some());
+        assert!(lazy.get(3).is_none());
+
+        // Generated only once
+        assert!(std::ptr::eq(programs, lazy.get_or_generate(2, true)));
+
+        assert!(lazy.set(2, Programs::new(2, false, false)).is_err());
+        assert!(lazy.set(1, Programs::new(1, false, false)).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Number of cards must be 1 to")]
+    fn test_too_many_cards() {
+        LazyPrograms::new().get(MAX_CARDS + 1);
+    }
+}
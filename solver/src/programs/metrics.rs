@@ -0,0 +1,221 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for measuring how complex the equation of a program is:
+//!  * The depth of the expression tree (the longest chain of operations)
+//!  * The number of pairs of brackets in the simplified infix equation
+//!  * The number of different operators used
+//!  * The volatility of the intermediate values, the average change in the number of digits
+//!    (orders of magnitude) from one step to the next
+//!
+//! The metrics are used to rank equally long solutions, grade difficulty and order worksheets.
+
+use std::cmp::Ordering;
+
+use super::infix::{infix_group, InfixGrpTypeElem};
+use super::progop::ProgOp;
+use super::solution::{analyse_ops, Solution};
+use super::Programs;
+
+/// Complexity metrics of the equation of a program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// Depth of the expression tree. Zero for a single number
+    pub depth: u8,
+    /// Number of pairs of brackets in the simplified infix equation
+    pub brackets: u8,
+    /// Number of different operators used
+    pub operator_kinds: u8,
+    /// Average absolute change in the order of magnitude of the result of each step from the
+    /// previous step. Zero for fewer than two steps
+    pub volatility: f64,
+}
+
+impl Metrics {
+    /// Compares how simple two equations are: fewer brackets first, then shallower, then fewer
+    /// kinds of operator, then less volatile
+    pub fn cmp_simplicity(&self, other: &Self) -> Ordering {
+        self.brackets
+            .cmp(&other.brackets)
+            .then(self.depth.cmp(&other.depth))
+            .then(self.operator_kinds.cmp(&other.operator_kinds))
+            .then(self.volatility.total_cmp(&other.volatility))
+    }
+
+    /// Returns a key ordering metrics by simplicity (see cmp_simplicity)
+    pub(crate) fn simplicity_key(&self) -> (u8, u8, u8, u64) {
+        // The volatility is never negative so the bit patterns order the same as the values
+        (
+            self.brackets,
+            self.depth,
+            self.operator_kinds,
+            self.volatility.to_bits(),
+        )
+    }
+}
+
+impl Programs {
+    /// Returns the complexity metrics of a program run with a given set of numbers
+    pub fn metrics(&self, prog_elem: usize, numbers: &[u32]) -> Metrics {
+        metrics(self.instructions(prog_elem), numbers)
+    }
+
+    /// Sorts solutions by result then shortest first, ranking solutions of the same length by the
+    /// simplicity of their equations
+    pub fn rank_solutions(&self, solutions: &mut [Solution], numbers: &[u32]) {
+        solutions.sort_by_cached_key(|s| {
+            (
+                s.result,
+                s.length(),
+                s.metrics(self, numbers).simplicity_key(),
+                s.program,
+            )
+        });
+    }
+}
+
+impl Solution {
+    /// Returns the complexity metrics of the solution's equation
+    pub fn metrics(&self, programs: &Programs, numbers: &[u32]) -> Metrics {
+        programs.metrics(self.program, numbers)
+    }
+}
+
+/// Calculates the complexity metrics of a program
+pub(crate) fn metrics(instructions: &[ProgOp], numbers: &[u32]) -> Metrics {
+    let mut stack: Vec<(u8, f64)> = Vec::with_capacity(numbers.len());
+    let mut magnitudes = Vec::with_capacity(instructions.len() / 2);
+
+    // Work out the depth and the result of each step. Values are calculated with floating point
+    // so programs run with relaxed rules can be measured too
+    let (depth, _) = Programs::process_instructions(
+        instructions,
+        &mut stack,
+        |n| Some((0, numbers[n as usize] as f64)),
+        |(d2, n2), op, (d1, n1)| {
+            let ans = match op & ProgOp::PROG_OP_MASK {
+                ProgOp::PROG_OP_ADD => n2 + n1,
+                ProgOp::PROG_OP_SUB => n2 - n1,
+                ProgOp::PROG_OP_MUL => n2 * n1,
+                ProgOp::PROG_OP_DIV => n2 / n1,
+                _ => panic!("Non-operator not expected"),
+            };
+
+            magnitudes.push(ans.abs().max(1.0).log10());
+
+            Some((d2.max(d1) + 1, ans))
+        },
+    )
+    .expect("Program has no instructions");
+
+    let volatility = if magnitudes.len() < 2 {
+        0.0
+    } else {
+        magnitudes
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .sum::<f64>()
+            / (magnitudes.len() - 1) as f64
+    };
+
+    Metrics {
+        depth,
+        brackets: brackets(&infix_group(instructions)),
+        operator_kinds: analyse_ops(instructions).kinds() as u8,
+        volatility,
+    }
+}
+
+/// Counts the pairs of brackets in an infix equation tree. Every term which isn't a number is
+/// bracketed apart from the outermost
+fn brackets(elem: &InfixGrpTypeElem) -> u8 {
+    let inner = |elem: &InfixGrpTypeElem| match elem {
+        InfixGrpTypeElem::Number(_) => 0,
+        _ => 1 + brackets(elem),
+    };
+
+    match elem {
+        InfixGrpTypeElem::Number(_) => 0,
+        InfixGrpTypeElem::Term(t1, _, t2) => inner(t1) + inner(t2),
+        InfixGrpTypeElem::Group(terms) => terms.iter().map(|(_, elem)| inner(elem)).sum(),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let numbers = [100, 75, 8, 8, 3, 1];
+
+        let programs: Programs = "0".into();
+
+        assert_eq!(
+            Metrics {
+                depth: 0,
+                brackets: 0,
+                operator_kinds: 0,
+                volatility: 0.0
+            },
+            programs.metrics(0, &numbers)
+        );
+
+        // 100 + 75 + 8
+        let programs: Programs = "0 1 + 2 +".into();
+        let metrics = programs.metrics(0, &numbers);
+
+        assert_eq!(2, metrics.depth);
+        assert_eq!(0, metrics.brackets);
+        assert_eq!(1, metrics.operator_kinds);
+
+        // ((75 + 3) × 8) - 100 - 1
+        let programs: Programs = "1 4 + 2 * 0 - 5 -".into();
+        let metrics = programs.metrics(0, &numbers);
+
+        assert_eq!(
+            "((75 + 3) × 8) - 100 - 1",
+            programs.infix(0, &numbers, false)
+        );
+        assert_eq!(4, metrics.depth);
+        assert_eq!(2, metrics.brackets);
+        assert_eq!(3, metrics.operator_kinds);
+
+        // 78 -> 624 -> 524 -> 523
+        let expected = ((624f64.log10() - 78f64.log10())
+            + (624f64.log10() - 524f64.log10())
+            + (524f64.log10() - 523f64.log10()))
+            / 3.0;
+
+        assert!((metrics.volatility - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rank_solutions() {
+        let programs = Programs::new(5, false, false);
+        let numbers = [75, 25, 8, 3, 2];
+
+        let mut solutions = programs.run_all_target(600, &numbers);
+        assert!(solutions.len() > 10);
+
+        programs.rank_solutions(&mut solutions, &numbers);
+
+        // Shortest first, then simplest
+        for pair in solutions.windows(2) {
+            let ord = pair[0].length().cmp(&pair[1].length()).then(
+                pair[0]
+                    .metrics(&programs, &numbers)
+                    .cmp_simplicity(&pair[1].metrics(&programs, &numbers)),
+            );
+
+            assert_ne!(Ordering::Greater, ord);
+        }
+
+        // 75 × 8 is the simplest
+        assert_eq!(
+            "75 × 8",
+            programs.infix(solutions[0].program, &numbers, false)
+        );
+    }
+}
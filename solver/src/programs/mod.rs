@@ -2,40 +2,121 @@
 
 //! This module is responsible for holding and running a collection of RPN programs
 
+mod batch;
+mod canonical;
+mod coverage;
 mod duplicates;
+mod dupset;
+mod fingerprint;
+mod gen_stats;
 mod generate;
+mod incremental;
 mod infix;
+mod lazy;
+mod metrics;
+mod op_usage;
 mod progop;
+mod provenance;
+mod prune;
+mod reachable;
+mod scratch;
+mod segment;
+mod simplify;
 mod solution;
+mod stack;
+mod step;
+mod store;
+mod symmetry;
+mod trace;
+mod verify;
 
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
-
-use colored::Colorize;
-use duplicates::{duplicated, DupReason};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+pub use batch::EvalStrategy;
+use batch::Skeletons;
+use canonical::{canonical_supported, generate_canonical_programs};
+pub use coverage::Coverage;
+pub use coverage::{MAX_TARGET, MIN_TARGET};
+use duplicates::duplicated;
+pub use duplicates::{DupReason, DupStats};
+pub use dupset::DupSet;
+pub use fingerprint::PROGRAM_ORDER_VERSION;
+pub use gen_stats::{GenerationStats, NumCountStats};
 use generate::{calc_num_programs, generate_num_programs, op_combs, op_counts};
-use infix::{infix_group, InfixGrpTypeElem};
+pub use incremental::Evaluation;
+use infix::{infix_group, infix_group_cb_stack, write_number, GroupPool, InfixGrpTypeElem};
 use itertools::Itertools;
-use numformat::NumFormat;
-use progop::ProgOp;
-pub use solution::Solution;
-
-/// Collection of RPN programs to run for a set of numbers
+pub use lazy::LazyPrograms;
+pub use metrics::Metrics;
+pub use op_usage::{OpUsage, OP_NAMES};
+pub use progop::ProgOp;
+pub use provenance::Provenance;
+use prune::run_instructions_pruned;
+pub use scratch::{Scratch, ScratchStats};
+pub use segment::Segment;
+use solution::Answer;
+pub use solution::{OpCounts, Solution};
+use stack::{ProgStack, SmallStack};
+pub use step::{Operand, Step, StepAnnotation};
+use store::Table;
+use symmetry::Symmetry;
+pub use trace::{Trace, TraceError};
+pub use verify::{assignments, DedupReport};
+
+use crate::board::validate_rpn;
+use crate::cancel::{CancellationToken, Cancelled, CANCEL_CHECK_INTERVAL};
+use crate::format::symbol_set;
+
+/// Collection of RPN programs to run for a set of numbers.
+///
+/// Programs are immutable once generated and every method takes `&self`, so one collection can be
+/// shared between threads with an `Arc` (or a scoped thread borrow) without any locking:
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use solver::Programs;
+///
+/// let programs = Arc::new(Programs::new(3, false, false));
+///
+/// let handles = [[100, 7, 3], [25, 10, 4]].map(|numbers| {
+///     let programs = Arc::clone(&programs);
+///
+///     thread::spawn(move || programs.run_all(&numbers).solutions.len())
+/// });
+///
+/// for handle in handles {
+///     assert!(handle.join().unwrap() > 0);
+/// }
+/// ```
+#[derive(Clone)]
 pub struct Programs {
-    programs: Vec<ProgInstr>,
-    instructions: Vec<ProgOp>,
+    programs: Table<ProgInstr>,
+    instructions: Table<ProgOp>,
     nums: u8,
+    inc_duplicated: bool,
+    fingerprint: OnceLock<u64>,
+    skeletons: OnceLock<Skeletons>,
 }
 
+// Programs must remain shareable between threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Programs>();
+    assert_send_sync::<Results>();
+    assert_send_sync::<Coverage>();
+};
+
 impl Programs {
     /// Create a new Programs struct
     pub fn new(nums: u8, inc_duplicated: bool, verbose: bool) -> Self {
-        let operators = vec![
-            ProgOp::PROG_OP_ADD,
-            ProgOp::PROG_OP_SUB,
-            ProgOp::PROG_OP_MUL,
-            ProgOp::PROG_OP_DIV,
-        ];
+        let operators = ProgOp::OPERATORS.to_vec();
 
         Self::new_with_operators(nums, inc_duplicated, operators, verbose)
     }
@@ -47,20 +128,130 @@ impl Programs {
         operators: Vec<ProgOp>,
         verbose: bool,
     ) -> Self {
+        uncancelled(Self::new_with_cancel(
+            nums,
+            inc_duplicated,
+            operators,
+            verbose,
+            None,
+        ))
+        .0
+    }
+
+    /// Create a new Programs struct and return statistics on the generation instead of printing
+    /// them
+    pub fn new_with_stats(nums: u8, inc_duplicated: bool) -> (Self, GenerationStats) {
+        let operators = ProgOp::OPERATORS.to_vec();
+
+        uncancelled(Self::new_with_cancel(
+            nums,
+            inc_duplicated,
+            operators,
+            false,
+            None,
+        ))
+    }
+
+    /// Create a new Programs struct, stopping early if the token is cancelled
+    pub fn new_cancellable(
+        nums: u8,
+        inc_duplicated: bool,
+        verbose: bool,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Cancelled> {
+        let operators = ProgOp::OPERATORS.to_vec();
+
+        Self::new_with_cancel(nums, inc_duplicated, operators, verbose, Some(cancel))
+            .map(|(programs, _)| programs)
+    }
+
+    /// Create a new Programs struct without duplicates by generating all programs and rejecting
+    /// the duplicates. This is much slower than generating canonical programs and is kept to
+    /// verify the canonical generation
+    pub fn new_filtered(nums: u8, verbose: bool) -> Self {
+        let operators = ProgOp::OPERATORS.to_vec();
+        let (programs, stats) = uncancelled(Self::new_generated(nums, false, operators, None));
+
+        print_stats(&stats, verbose);
+
+        programs
+    }
+
+    /// Generates canonical programs if possible, otherwise all programs. Statistics are printed if
+    /// verbose
+    fn new_with_cancel(
+        nums: u8,
+        inc_duplicated: bool,
+        operators: Vec<ProgOp>,
+        verbose: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(Self, GenerationStats), Cancelled> {
+        let (programs, stats) = if !inc_duplicated && canonical_supported(&operators) {
+            Self::new_canonical(nums, &operators, cancel)?
+        } else {
+            Self::new_generated(nums, inc_duplicated, operators, cancel)?
+        };
+
+        print_stats(&stats, verbose);
+
+        Ok((programs, stats))
+    }
+
+    /// Generates canonical programs only
+    fn new_canonical(
+        nums: u8,
+        operators: &[ProgOp],
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(Self, GenerationStats), Cancelled> {
+        let start = Instant::now();
+        let mut program_vec = Vec::new();
+        let mut instruction_vec = Vec::new();
+
+        let counts = generate_canonical_programs(
+            &mut program_vec,
+            &mut instruction_vec,
+            nums,
+            operators,
+            cancel,
+        )?;
+
+        let stats = GenerationStats {
+            nums,
+            canonical: true,
+            counts,
+            programs: program_vec.len(),
+            instructions: instruction_vec.len(),
+            elapsed: start.elapsed(),
+            ..Default::default()
+        };
+
+        let programs = Programs {
+            programs: program_vec.into(),
+            instructions: instruction_vec.into(),
+            nums,
+            inc_duplicated: false,
+            fingerprint: OnceLock::new(),
+            skeletons: OnceLock::new(),
+        };
+
+        Ok((programs, stats))
+    }
+
+    /// Generates all programs, optionally rejecting duplicates
+    fn new_generated(
+        nums: u8,
+        inc_duplicated: bool,
+        operators: Vec<ProgOp>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(Self, GenerationStats), Cancelled> {
+        let start = Instant::now();
+
         // Calculate number permutations (=nums!)
         let num_perms: Vec<_> = (0..nums).permutations(nums as usize).collect();
 
-        if verbose {
-            println!("Card permutations: {}", num_perms.len().num_format());
-        }
-
         // Calculate operator counts and combintions
         let mut op_map = HashMap::with_capacity(nums as usize);
 
-        if verbose {
-            println!("Operator placement counts and combinations for number of numbers:")
-        }
-
         // Loop for the number of numbers in the RPN program
         for num_cnt in 1..=nums {
             // Generate operator count combinations for each operator slot
@@ -69,86 +260,79 @@ impl Programs {
             // Generte operator combination
             let op_comb = op_combs(num_cnt, &operators);
 
-            if verbose {
-                println!(
-                    "  {}: {:>6} {:>6}",
-                    num_cnt,
-                    op_count.len().num_format(),
-                    op_comb.len().num_format()
-                );
-            }
-
             // Add to the hash map
             assert!(op_map.insert(num_cnt, (op_count, op_comb)).is_none());
         }
 
         // Create a vector to store the programs
-        let prog_cnt_guess = calc_num_programs(nums, inc_duplicated, &num_perms, &op_map);
-        let mut program_vec = Vec::with_capacity(prog_cnt_guess);
+        let prog_cnts = calc_num_programs(nums, inc_duplicated, &num_perms, &op_map, &operators);
+        let prog_cnt_expected = prog_cnts.iter().sum();
+        let mut program_vec = Vec::with_capacity(prog_cnt_expected);
 
-        // Create a vector to store program instructions
-        let ins_cnt_guess = prog_cnt_guess * (nums as usize + (nums as usize - 1));
-        let mut instruction_vec = Vec::with_capacity(ins_cnt_guess);
+        // Create a vector to store program instructions. A program using n numbers has n - 1
+        // operators
+        let ins_cnt_expected = prog_cnts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| count * (2 * i + 1))
+            .sum();
+        let mut instruction_vec = Vec::with_capacity(ins_cnt_expected);
 
-        // Vector to hold duplicate count
-        let mut dups = Vec::with_capacity(nums as usize);
+        // Vector to hold statistics for each number of numbers
+        let mut counts = Vec::with_capacity(nums as usize);
 
         // Loop for the number of numbers in the RPN program
         for num_cnt in 1..=nums {
+            let count_start = Instant::now();
+            let start_cnt = program_vec.len();
+
             // Generate programs
-            dups.push(generate_num_programs(
+            let (term_dups, infix_dups) = generate_num_programs(
                 &mut program_vec,
                 &mut instruction_vec,
                 num_cnt,
                 &num_perms,
                 &op_map,
                 inc_duplicated,
-            ));
+                cancel,
+            )?;
+
+            let (op_count, op_comb) = &op_map[&num_cnt];
+
+            counts.push(NumCountStats {
+                numbers: num_cnt,
+                op_placements: op_count.len(),
+                op_combinations: op_comb.len(),
+                programs: program_vec.len() - start_cnt,
+                term_dups,
+                infix_dups,
+                elapsed: count_start.elapsed(),
+            });
         }
 
-        if verbose {
-            // Output some stats on the program generation
-            if !inc_duplicated {
-                println!("Duplicate programs filtered by number of numbers:");
-
-                for (i, (term_dups, infix_dups)) in dups.iter().enumerate() {
-                    println!(
-                        "  {:>5}: terms {:>10}  infix {:>10}",
-                        i + 1,
-                        term_dups.num_format(),
-                        infix_dups.num_format()
-                    );
-                }
-
-                let (tterms, tinfix) = dups
-                    .iter()
-                    .fold((0, 0), |(tt, ti), (t, i)| (tt + *t, ti + *i));
-
-                println!(
-                    "  Total: terms {:>10}  infix {:>10}",
-                    tterms.num_format(),
-                    tinfix.num_format()
-                );
-            }
-
-            println!(
-                "{} programs generated (guessed {})",
-                program_vec.len().num_format(),
-                prog_cnt_guess.num_format(),
-            );
-
-            println!(
-                "{} total instructions (guessed {})",
-                instruction_vec.len().num_format(),
-                ins_cnt_guess.num_format(),
-            );
-        }
-
-        Programs {
-            programs: program_vec,
-            instructions: instruction_vec,
+        let stats = GenerationStats {
             nums,
-        }
+            canonical: false,
+            inc_duplicated,
+            permutations: num_perms.len(),
+            counts,
+            programs: program_vec.len(),
+            instructions: instruction_vec.len(),
+            expected_programs: Some(prog_cnt_expected),
+            expected_instructions: Some(ins_cnt_expected),
+            elapsed: start.elapsed(),
+        };
+
+        let programs = Programs {
+            programs: program_vec.into(),
+            instructions: instruction_vec.into(),
+            nums,
+            inc_duplicated,
+            fingerprint: OnceLock::new(),
+            skeletons: OnceLock::new(),
+        };
+
+        Ok((programs, stats))
     }
 
     /// Returns number of programs contained in the programs collection
@@ -162,62 +346,252 @@ impl Programs {
     }
 
     /// Runs one of the programs with a given set of numbers
-    pub fn run(&self, prog_elem: usize, numbers: &[u8]) -> Result<u32, ProgErr> {
+    pub fn run(&self, prog_elem: usize, numbers: &[u32]) -> Result<u32, ProgErr> {
         let instructions = self.instructions(prog_elem);
-        let mut stack: Vec<u32> = Vec::with_capacity(self.nums as usize);
+        let mut stack = ProgStack::new();
 
         Self::run_instructions(instructions, numbers, &mut stack)
     }
 
+    /// Runs one of the programs with a given set of numbers and returns it as a solution for its
+    /// answer
+    pub(crate) fn solution(&self, prog_elem: usize, numbers: &[u32]) -> Result<Solution, ProgErr> {
+        let instructions = self.instructions(prog_elem);
+        let mut stack = ProgStack::new();
+
+        Self::run_instructions_answer(instructions, numbers, &mut stack)
+            .map(|answer| Solution::from_answer(prog_elem, instructions.len(), answer))
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and returns the results.
+    /// If the numbers contain duplicates and the collection excludes duplicated programs, programs which
+    /// only differ by swapping equal numbers are run once (see [`Results::symmetric`])
+    pub fn run_all(&self, numbers: &[u32]) -> Results {
+        uncancelled(self.run_all_internal(
+            numbers,
+            MIN_TARGET..=MAX_TARGET,
+            false,
+            None,
+            None,
+            no_observer,
+        ))
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and returns the results,
+    /// stopping early if the token is cancelled
+    pub fn run_all_cancellable(
+        &self,
+        numbers: &[u32],
+        cancel: &CancellationToken,
+    ) -> Result<Results, Cancelled> {
+        self.run_all_internal(
+            numbers,
+            MIN_TARGET..=MAX_TARGET,
+            false,
+            None,
+            Some(cancel),
+            no_observer,
+        )
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and returns the results.
+    /// Answers in the given range are solutions instead of the standard 100 to 999 target range
+    pub fn run_all_range(&self, numbers: &[u32], range: RangeInclusive<u32>) -> Results {
+        uncancelled(self.run_all_internal(numbers, range, false, None, None, no_observer))
+    }
+
     /// Runs all of the programs in the programs collection with a given set of numbers and returns the results
-    pub fn run_all(&self, numbers: &[u8]) -> Results {
-        let mut stack: Vec<u32> = Vec::with_capacity(self.nums as usize);
+    /// including a histogram of the distance of each answer from the target. Distances greater than
+    /// `max_distance` are counted in the last bucket of the histogram
+    pub fn run_all_distances(&self, numbers: &[u32], target: u32, max_distance: usize) -> Results {
+        uncancelled(self.run_all_internal(
+            numbers,
+            MIN_TARGET..=MAX_TARGET,
+            false,
+            Some((target, max_distance)),
+            None,
+            no_observer,
+        ))
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and returns the results.
+    /// Programs are abandoned as soon as an intermediate result is too large to give an answer in range
+    pub fn run_all_pruned(&self, numbers: &[u32]) -> Results {
+        uncancelled(self.run_all_internal(
+            numbers,
+            MIN_TARGET..=MAX_TARGET,
+            true,
+            None,
+            None,
+            no_observer,
+        ))
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and returns the results,
+    /// calling the observer with the program element and outcome of each program run. Programs skipped
+    /// because they are symmetric with another are not run so are not observed. The observer is
+    /// monomorphised in to the loop, so the other run_all functions (which don't observe) are unaffected
+    pub fn run_all_observed<O>(&self, numbers: &[u32], observer: O) -> Results
+    where
+        O: FnMut(usize, Result<u32, ProgErr>),
+    {
+        uncancelled(self.run_all_internal(
+            numbers,
+            MIN_TARGET..=MAX_TARGET,
+            false,
+            None,
+            None,
+            observer,
+        ))
+    }
+
+    fn run_all_internal<O>(
+        &self,
+        numbers: &[u32],
+        range: RangeInclusive<u32>,
+        prune: bool,
+        distance: Option<(u32, usize)>,
+        cancel: Option<&CancellationToken>,
+        mut observer: O,
+    ) -> Result<Results, Cancelled>
+    where
+        O: FnMut(usize, Result<u32, ProgErr>),
+    {
+        let mut stack = ProgStack::new();
         let mut results = Results::default();
 
+        if let Some((_, max_distance)) = distance {
+            results.distances = vec![0; max_distance + 2];
+        }
+
         assert!(numbers.len() == self.nums as usize);
 
+        let symmetry = self.symmetry(numbers);
+
         for (i, program) in self.programs.iter().enumerate() {
+            if i % CANCEL_CHECK_INTERVAL == 0 {
+                if let Some(cancel) = cancel {
+                    cancel.check()?;
+                }
+            }
+
             let instructions = self.instructions_for_program(program);
 
-            match Self::run_instructions(instructions, numbers, &mut stack) {
-                Ok(ans) => {
-                    if ans < 100 {
-                        results.under_range += 1;
-                    } else if ans > 999 {
-                        results.above_range += 1;
-                    } else {
-                        results
-                            .solutions
-                            .push(Solution::new(i, instructions.len(), ans));
-                    }
-                }
-                Err(e) => match e {
-                    ProgErr::Zero => results.zero += 1,
-                    ProgErr::Negative => results.negative += 1,
-                    ProgErr::DivZero => results.div_zero += 1,
-                    ProgErr::NonInteger => results.non_integer += 1,
-                    ProgErr::Mul1 => results.mult_by_1 += 1,
-                    ProgErr::Div1 => results.div_by_1 += 1,
-                },
+            if symmetry.as_ref().is_some_and(|s| s.skip(instructions)) {
+                results.symmetric += 1;
+                continue;
+            }
+
+            let result = if prune {
+                run_instructions_pruned(
+                    instructions,
+                    numbers,
+                    &mut stack,
+                    &mut results.pruned_instructions,
+                )
+            } else {
+                Self::run_instructions_answer(instructions, numbers, &mut stack)
+            };
+
+            observer(i, result.map(|answer| answer.value));
+
+            if let (Ok(answer), Some((target, max_distance))) = (&result, distance) {
+                results.distances
+                    [(answer.value.abs_diff(target) as usize).min(max_distance + 1)] += 1;
             }
+
+            results.add(i, instructions, result, &range);
         }
 
-        results
+        Ok(results)
     }
 
     /// Runs all of the programs in the programs collection with a given set of numbers and a target and returns the solutions
-    pub fn run_all_target(&self, target: u32, numbers: &[u8]) -> Vec<Solution> {
-        let mut stack: Vec<u32> = Vec::with_capacity(numbers.len());
+    pub fn run_all_target(&self, target: u32, numbers: &[u32]) -> Vec<Solution> {
+        self.run_target_iter(target, numbers).collect()
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and a target and returns the solutions,
+    /// stopping early if the token is cancelled
+    pub fn run_all_target_cancellable(
+        &self,
+        target: u32,
+        numbers: &[u32],
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Solution>, Cancelled> {
+        let mut stack = ProgStack::new();
         let mut solutions = Vec::new();
 
         assert!(numbers.len() == self.nums as usize);
 
         for (i, program) in self.programs.iter().enumerate() {
+            if i % CANCEL_CHECK_INTERVAL == 0 {
+                cancel.check()?;
+            }
+
             let instructions = self.instructions_for_program(program);
 
-            if let Ok(ans) = Self::run_instructions(instructions, numbers, &mut stack) {
-                if ans == target {
-                    solutions.push(Solution::new(i, instructions.len(), ans));
+            match Self::run_instructions_answer(instructions, numbers, &mut stack) {
+                Ok(answer) if answer.value == target => {
+                    solutions.push(Solution::from_answer(i, instructions.len(), answer))
+                }
+                _ => (),
+            }
+        }
+
+        Ok(solutions)
+    }
+
+    /// Returns an iterator which runs the programs with a given set of numbers as it is advanced and
+    /// yields each solution for a target as it is found. Programs using fewer numbers come first, so
+    /// short solutions are found quickly while the rest of the programs are still to be run
+    pub fn run_target_iter<'a>(
+        &'a self,
+        target: u32,
+        numbers: &'a [u32],
+    ) -> impl Iterator<Item = Solution> + 'a {
+        let mut stack = ProgStack::new();
+
+        assert!(numbers.len() == self.nums as usize);
+
+        self.programs
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, program)| {
+                let instructions = self.instructions_for_program(program);
+
+                match Self::run_instructions_answer(instructions, numbers, &mut stack) {
+                    Ok(answer) if answer.value == target => {
+                        Some(Solution::from_answer(i, instructions.len(), answer))
+                    }
+                    _ => None,
+                }
+            })
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers and a list of targets
+    /// and returns the solutions for each target in the same order as the targets
+    pub fn run_all_targets(&self, targets: &[u32], numbers: &[u32]) -> Vec<Vec<Solution>> {
+        let mut stack = ProgStack::new();
+        let mut solutions: Vec<Vec<Solution>> = targets.iter().map(|_| Vec::new()).collect();
+
+        assert!(numbers.len() == self.nums as usize);
+
+        // Map each target to its solution lists
+        let mut target_map: HashMap<u32, Vec<usize>> = HashMap::with_capacity(targets.len());
+
+        for (i, target) in targets.iter().enumerate() {
+            target_map.entry(*target).or_default().push(i);
+        }
+
+        for (i, program) in self.programs.iter().enumerate() {
+            let instructions = self.instructions_for_program(program);
+
+            if let Ok(answer) = Self::run_instructions_answer(instructions, numbers, &mut stack) {
+                if let Some(indexes) = target_map.get(&answer.value) {
+                    for &index in indexes {
+                        solutions[index].push(Solution::from_answer(i, instructions.len(), answer));
+                    }
                 }
             }
         }
@@ -225,21 +599,54 @@ impl Programs {
         solutions
     }
 
-    /// Returns the formatted steps of a program for a given set of numbers
-    pub fn steps(&self, prog_elem: usize, numbers: &[u8], colour: bool) -> Vec<String> {
-        let mut steps = Vec::new();
-        let mut stack: Vec<(u32, String)> = Vec::with_capacity(numbers.len());
+    /// Returns the formatted steps of a program for a given set of numbers (see structured_steps)
+    pub fn steps(&self, prog_elem: usize, numbers: &[u32], colour: bool) -> Vec<String> {
+        self.structured_steps(prog_elem, numbers, false)
+            .iter()
+            .map(|step| step.format(colour))
+            .collect()
+    }
+
+    /// Converts the RPN program to operator type grouped infix equation
+    pub fn infix(&self, prog_elem: usize, numbers: &[u32], colour: bool) -> String {
+        infix_group(self.instructions(prog_elem)).colour(numbers, colour)
+    }
+
+    /// Converts the RPN program to operator type grouped infix equation without colour like infix,
+    /// using a scratch space. The string can be handed back with Scratch::recycle_strings
+    pub fn infix_with(&self, prog_elem: usize, numbers: &[u32], scratch: &mut Scratch) -> String {
+        let tree = infix_group_cb_stack(
+            self.instructions(prog_elem),
+            &mut scratch.stack,
+            &mut scratch.pool,
+            &mut |_| true,
+        )
+        .unwrap();
+
+        let mut infix = scratch.strings.take();
+
+        tree.write_plain(numbers, &mut infix);
+        scratch.pool.recycle(tree);
+
+        infix
+    }
+
+    /// Returns the steps of a program without colour like steps, using a scratch space. The
+    /// strings can be handed back with Scratch::recycle_strings
+    pub fn steps_with(
+        &self,
+        prog_elem: usize,
+        numbers: &[u32],
+        scratch: &mut Scratch,
+    ) -> Vec<String> {
+        let mut steps = Vec::with_capacity(numbers.len());
+        let strings = &mut scratch.strings;
 
         self.process_program_instructions(
             prog_elem,
-            &mut stack,
-            |n| {
-                Some((
-                    numbers[n as usize] as u32,
-                    ProgOp::new_number(n).colour(numbers, colour),
-                ))
-            },
-            |(n2, s2), op, (n1, s1)| {
+            &mut scratch.values,
+            |n| Some(numbers[n as usize]),
+            |n2, op, n1| {
                 let ans = match op & ProgOp::PROG_OP_MASK {
                     ProgOp::PROG_OP_ADD => n2 + n1,
                     ProgOp::PROG_OP_SUB => n2 - n1,
@@ -248,24 +655,19 @@ impl Programs {
                     _ => panic!("Non-operator not expected"),
                 };
 
-                let ans_str = ans.num_format();
+                let mut step = strings.take();
 
-                let equals = if colour {
-                    "=".dimmed().to_string()
-                } else {
-                    "=".to_string()
-                };
+                write_number(n2, &mut step);
+                step.push(' ');
+                step.push_str(op.symbol_in(symbol_set()).unwrap());
+                step.push(' ');
+                write_number(n1, &mut step);
+                step.push_str(" = ");
+                write_number(ans, &mut step);
 
-                steps.push(format!(
-                    "{} {} {} {} {}",
-                    s2,
-                    op.colour(numbers, colour),
-                    s1,
-                    equals,
-                    ans_str
-                ));
+                steps.push(step);
 
-                Some((ans, ans_str))
+                Some(ans)
             },
         )
         .unwrap();
@@ -273,13 +675,8 @@ impl Programs {
         steps
     }
 
-    /// Converts the RPN program to operator type grouped infix equation
-    pub fn infix(&self, prog_elem: usize, numbers: &[u8], colour: bool) -> String {
-        infix_group(self.instructions(prog_elem)).colour(numbers, colour)
-    }
-
     /// Converts the RPN program to full infix equation
-    pub fn infix_full(&self, prog_elem: usize, numbers: &[u8], colour: bool) -> String {
+    pub fn infix_full(&self, prog_elem: usize, numbers: &[u32], colour: bool) -> String {
         let mut stack: Vec<String> = Vec::with_capacity(numbers.len());
 
         let infix = self
@@ -300,7 +697,7 @@ impl Programs {
     }
 
     /// Converts the RPN program to a string for a given set of numbers
-    pub fn rpn(&self, prog_elem: usize, numbers: &[u8], colour: bool) -> String {
+    pub fn rpn(&self, prog_elem: usize, numbers: &[u32], colour: bool) -> String {
         self.instructions(prog_elem)
             .iter()
             .map(|i| i.colour(numbers, colour))
@@ -312,98 +709,246 @@ impl Programs {
         &self,
         prog_elem: usize,
         stack: &mut Vec<InfixGrpTypeElem>,
-        set: &mut HashSet<InfixGrpTypeElem>,
+        set: &mut DupSet,
     ) -> bool {
-        duplicated(self.instructions(prog_elem), stack, set) != DupReason::NotDup
+        self.duplicate_reason(prog_elem, stack, set) != DupReason::NotDup
     }
 
-    // == Private functions ==
+    /// Returns why the program would be duplicated by rearranging the terms of the equation.
+    /// Infix duplicates are found against the programs previously checked with the same set
+    pub fn duplicate_reason(
+        &self,
+        prog_elem: usize,
+        stack: &mut Vec<InfixGrpTypeElem>,
+        set: &mut DupSet,
+    ) -> DupReason {
+        duplicated(
+            self.instructions(prog_elem),
+            stack,
+            &mut GroupPool::default(),
+            set,
+        )
+    }
+
+    /// Returns true if the program would be duplicated by rearranging the terms of the equation,
+    /// like duplicated but using a scratch space. Infix duplicates are found against the programs
+    /// checked since the scratch space was created or Scratch::clear_duplicates was last called
+    pub fn duplicated_with(&self, prog_elem: usize, scratch: &mut Scratch) -> bool {
+        duplicated(
+            self.instructions(prog_elem),
+            &mut scratch.stack,
+            &mut scratch.pool,
+            &mut scratch.set,
+        ) != DupReason::NotDup
+    }
+
+    /// Removes the solutions which would be duplicated by rearranging the terms of the equation
+    /// (only found when the programs include duplicates), keeping the first of each equation.
+    /// Returns the number removed for each reason
+    pub fn filter_duplicates(&self, solutions: &mut Vec<Solution>) -> DupStats {
+        self.filter_duplicates_with(solutions, &mut Scratch::new())
+    }
+
+    /// Removes the duplicated solutions like filter_duplicates, using a scratch space
+    pub fn filter_duplicates_with(
+        &self,
+        solutions: &mut Vec<Solution>,
+        scratch: &mut Scratch,
+    ) -> DupStats {
+        let mut stats = DupStats::default();
+
+        scratch.clear_duplicates();
+
+        solutions.retain(|s| {
+            match duplicated(
+                self.instructions(s.program),
+                &mut scratch.stack,
+                &mut scratch.pool,
+                &mut scratch.set,
+            ) {
+                DupReason::NotDup => true,
+                DupReason::TermOrder => {
+                    stats.term_order += 1;
+                    false
+                }
+                DupReason::Infix => {
+                    stats.infix += 1;
+                    false
+                }
+            }
+        });
+
+        stats
+    }
+
+    /// Removes the solutions with the same equation in RPN as an earlier solution (can happen when
+    /// a duplicate card is chosen), keeping the first of each equation. Returns the number removed
+    pub fn filter_identical(&self, solutions: &mut Vec<Solution>, numbers: &[u32]) -> usize {
+        let before = solutions.len();
+        let mut rpn_set = HashSet::with_capacity(before);
+
+        solutions.retain(|s| rpn_set.insert(self.rpn(s.program, numbers, false)));
+
+        before - solutions.len()
+    }
 
     /// Returns a slice of instructions for the program element
     #[inline]
-    pub(crate) fn instructions(&self, prog_elem: usize) -> &[ProgOp] {
+    pub fn instructions(&self, prog_elem: usize) -> &[ProgOp] {
         self.instructions_for_program(&self.programs[prog_elem])
     }
 
+    /// Creates a collection holding a single program. Panics if the program isn't valid RPN, as the
+    /// interpreter relies on every program having a valid stack layout
+    pub(crate) fn from_instructions(instructions: Vec<ProgOp>) -> Self {
+        if let Err(e) = validate_rpn(&instructions) {
+            panic!("Invalid program ({e})");
+        }
+
+        // Add instruction pointers
+        let programs = vec![ProgInstr {
+            start: 0,
+            end: (instructions.len() - 1) as u32,
+        }];
+
+        // Work out the maximum number present in the program
+        let nums = instructions.iter().fold(0, |max_n, i| {
+            if i.is_number() {
+                max(max_n, i.bits())
+            } else {
+                max_n
+            }
+        });
+
+        Programs {
+            programs: programs.into(),
+            instructions: instructions.into(),
+            nums,
+            inc_duplicated: true,
+            fingerprint: OnceLock::new(),
+            skeletons: OnceLock::new(),
+        }
+    }
+
+    // == Private functions ==
+
+    /// Returns the symmetry reduction for a set of numbers, if the numbers contain duplicates and the
+    /// collection excludes duplicated programs
+    fn symmetry(&self, numbers: &[u32]) -> Option<Symmetry> {
+        if self.inc_duplicated {
+            None
+        } else {
+            Symmetry::new(numbers)
+        }
+    }
+
     /// Returns a slice of instructions for the program instruction pointer struct
     #[inline]
     fn instructions_for_program(&self, program: &ProgInstr) -> &[ProgOp] {
         &self.instructions[program.start as usize..=program.end as usize]
     }
 
-    /// Runs the program with a given set of numbers and preallocated stack
+    /// Runs the program with a given set of numbers and preallocated fixed size stack
     #[inline]
-    fn run_instructions(
+    fn run_instructions<const N: usize>(
         instructions: &[ProgOp],
-        numbers: &[u8],
-        stack: &mut Vec<u32>,
+        numbers: &[u32],
+        stack: &mut SmallStack<N>,
     ) -> Result<u32, ProgErr> {
+        Self::run_instructions_answer(instructions, numbers, stack).map(|answer| answer.value)
+    }
+
+    /// Runs the program with a given set of numbers and preallocated fixed size stack, keeping the
+    /// largest value calculated and counting the operations so the program doesn't need to be run
+    /// again to find them
+    #[inline]
+    fn run_instructions_answer<const N: usize>(
+        instructions: &[ProgOp],
+        numbers: &[u32],
+        stack: &mut SmallStack<N>,
+    ) -> Result<Answer, ProgErr> {
         // NB this does not use the process function for speed
         stack.clear();
 
+        let mut largest = 0;
+        let mut ops = OpCounts::default();
+
         for op in instructions {
-            match *op & ProgOp::PROG_OP_MASK {
-                ProgOp::PROG_OP_ADD => {
-                    let n1 = stack.pop().unwrap();
-                    let n2 = stack.pop().unwrap();
+            if op.is_number() {
+                stack.push(numbers[op.bits() as usize]);
+                continue;
+            }
 
-                    stack.push(n2 + n1);
-                }
-                ProgOp::PROG_OP_SUB => {
-                    let n1 = stack.pop().unwrap();
-                    let n2 = stack.pop().unwrap();
+            let n1 = stack.pop();
+            let n2 = stack.pop();
 
-                    if n2 < n1 {
-                        Err(ProgErr::Negative)?
-                    }
+            let ans = Self::run_operator(n2, *op, n1)?;
 
-                    let int = n2 - n1;
+            largest = largest.max(ans);
+            ops.count(*op);
+            stack.push(ans);
+        }
 
-                    if int == 0 {
-                        Err(ProgErr::Zero)?
-                    }
+        let value = stack.pop();
 
-                    stack.push(int);
-                }
-                ProgOp::PROG_OP_MUL => {
-                    let n1 = stack.pop().unwrap();
-                    let n2 = stack.pop().unwrap();
+        // The answer is the largest value of a program with no operations
+        Ok(Answer {
+            value,
+            largest: largest.max(value),
+            ops,
+        })
+    }
 
-                    if n1 == 1 || n2 == 1 {
-                        Err(ProgErr::Mul1)?
-                    }
+    /// Carries out an operator on two values, returning the result or the reason the program is
+    /// rejected
+    #[inline(always)]
+    pub(crate) fn run_operator(n2: u32, op: ProgOp, n1: u32) -> Result<u32, ProgErr> {
+        let ans = match op & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_ADD => n2.checked_add(n1).ok_or(ProgErr::Overflow)?,
+            ProgOp::PROG_OP_SUB => {
+                if n2 < n1 {
+                    Err(ProgErr::Negative)?
+                }
 
-                    let int = n2 * n1;
+                let int = n2 - n1;
 
-                    if int == 0 {
-                        Err(ProgErr::Zero)?
-                    }
+                if int == 0 {
+                    Err(ProgErr::Zero)?
+                }
 
-                    stack.push(int);
+                int
+            }
+            ProgOp::PROG_OP_MUL => {
+                if n1 == 1 || n2 == 1 {
+                    Err(ProgErr::Mul1)?
                 }
-                ProgOp::PROG_OP_DIV => {
-                    let n1 = stack.pop().unwrap();
-                    let n2 = stack.pop().unwrap();
 
-                    if n1 == 0 {
-                        Err(ProgErr::DivZero)?
-                    }
+                let int = n2.checked_mul(n1).ok_or(ProgErr::Overflow)?;
 
-                    if n1 == 1 {
-                        Err(ProgErr::Div1)?
-                    }
+                if int == 0 {
+                    Err(ProgErr::Zero)?
+                }
 
-                    if n2 % n1 != 0 {
-                        Err(ProgErr::NonInteger)?
-                    }
+                int
+            }
+            _ => {
+                if n1 == 0 {
+                    Err(ProgErr::DivZero)?
+                }
+
+                if n1 == 1 {
+                    Err(ProgErr::Div1)?
+                }
 
-                    stack.push(n2 / n1);
+                if !n2.is_multiple_of(n1) {
+                    Err(ProgErr::NonInteger)?
                 }
-                _ => stack.push(numbers[op.bits() as usize] as u32),
+
+                n2 / n1
             }
-        }
+        };
 
-        Ok(stack.pop().unwrap())
+        Ok(ans)
     }
 
     /// Processes a set of instructions for a program element calling callbacks for numbers and operations
@@ -467,31 +1012,14 @@ impl From<&str> for Programs {
             })
             .collect();
 
-        // Add instruction pointers
-        let programs = vec![ProgInstr {
-            start: 0,
-            end: (instructions.len() - 1) as u32,
-        }];
-
-        // Work out the maximum number present in the program
-        let nums = instructions.iter().fold(0, |max_n, i| {
-            if i.is_number() {
-                max(max_n, i.bits())
-            } else {
-                max_n
-            }
-        });
-
-        Programs {
-            programs,
-            instructions,
-            nums,
-        }
+        Programs::from_instructions(instructions)
     }
 }
 
 /// Instruction element numbers for each program.
 /// Pointers are 32 bits to keep the size down
+#[derive(Clone)]
+#[repr(C)]
 pub(crate) struct ProgInstr {
     /// Start element of the instructions vector
     pub start: u32,
@@ -500,7 +1028,7 @@ pub(crate) struct ProgInstr {
 }
 
 /// Errors generated by program run
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ProgErr {
     /// Program generated a zero intermediate result
     Zero,
@@ -514,6 +1042,27 @@ pub enum ProgErr {
     Mul1,
     /// Program encountered divide by 1 (noop)
     Div1,
+    /// Program was abandoned because an intermediate result was too large
+    Pruned,
+    /// Program generated an intermediate result too large to hold
+    Overflow,
+}
+
+impl fmt::Display for ProgErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let desc = match self {
+            ProgErr::Zero => "An intermediate result is zero",
+            ProgErr::Negative => "An intermediate result is negative",
+            ProgErr::DivZero => "Division by zero",
+            ProgErr::NonInteger => "An intermediate result is not a whole number",
+            ProgErr::Mul1 => "Multiplies by 1",
+            ProgErr::Div1 => "Divides by 1",
+            ProgErr::Pruned => "Abandoned as an intermediate result is too large",
+            ProgErr::Overflow => "An intermediate result is too large",
+        };
+
+        write!(f, "{desc}")
+    }
 }
 
 /// Holds the results of running all programs with a set of numbers
@@ -523,7 +1072,7 @@ pub struct Results {
     pub solutions: Vec<Solution>,
     /// Number of programs with answer below valid range
     pub under_range: usize,
-    /// Number of programs with answer above valid range
+    /// Number of programs with answer above valid range, including intermediate results too large to hold
     pub above_range: usize,
     /// Number of programs with zero intermediate result
     pub zero: usize,
@@ -537,20 +1086,190 @@ pub struct Results {
     pub mult_by_1: usize,
     /// Number of programs containing a divide by 1
     pub div_by_1: usize,
+    /// Number of programs abandoned because an intermediate result was too large
+    pub pruned: usize,
+    /// Number of programs skipped because they give the same answer as another program with equal
+    /// numbers swapped
+    pub symmetric: usize,
+    /// Number of instructions not executed in abandoned programs
+    pub pruned_instructions: usize,
+    /// Histogram of the distance of each answer from the target, if a target was given.
+    /// The last bucket counts all answers further away
+    pub distances: Vec<usize>,
+    /// Number of programs abandoned because an intermediate result overflowed (also counted in
+    /// above_range)
+    pub overflow: usize,
+    /// Largest intermediate value calculated by any program which ran successfully
+    pub largest_intermediate: u32,
+    /// Histogram of the largest intermediate value of each program which ran successfully, by the
+    /// number of bits needed to hold it (element 0 counts values needing 1 bit)
+    pub intermediate_bits: [usize; 32],
+    /// Number of solutions removed by filter_duplicated because their terms were out of order
+    pub term_order_duplicates: usize,
+    /// Number of solutions removed by filter_duplicated because they gave the same equation as an
+    /// earlier solution
+    pub infix_duplicates: usize,
+}
+
+impl Results {
+    /// Adds the result of running a program
+    fn add(
+        &mut self,
+        prog_elem: usize,
+        instructions: &[ProgOp],
+        result: Result<Answer, ProgErr>,
+        range: &RangeInclusive<u32>,
+    ) {
+        match result {
+            Ok(answer) => {
+                if answer.value < *range.start() {
+                    self.under_range += 1;
+                } else if answer.value > *range.end() {
+                    self.above_range += 1;
+                } else {
+                    self.solutions.push(Solution::from_answer(
+                        prog_elem,
+                        instructions.len(),
+                        answer,
+                    ));
+                }
+
+                self.add_intermediate(answer.largest);
+            }
+            Err(e) => match e {
+                ProgErr::Zero => self.zero += 1,
+                ProgErr::Negative => self.negative += 1,
+                ProgErr::DivZero => self.div_zero += 1,
+                ProgErr::NonInteger => self.non_integer += 1,
+                ProgErr::Mul1 => self.mult_by_1 += 1,
+                ProgErr::Div1 => self.div_by_1 += 1,
+                ProgErr::Pruned => self.pruned += 1,
+                ProgErr::Overflow => {
+                    self.above_range += 1;
+                    self.overflow += 1;
+                }
+            },
+        }
+    }
+
+    /// Adds the largest intermediate value of a program to the headroom statistics
+    fn add_intermediate(&mut self, largest: u32) {
+        self.largest_intermediate = self.largest_intermediate.max(largest);
+        self.intermediate_bits[bits_needed(largest) as usize - 1] += 1;
+    }
+
+    /// Removes the solutions which would be duplicated by rearranging the terms of the equation
+    /// (only found when the programs include duplicates), counting the reason for each
+    pub fn filter_duplicated(&mut self, programs: &Programs) {
+        let stats = programs.filter_duplicates(&mut self.solutions);
+
+        self.term_order_duplicates += stats.term_order;
+        self.infix_duplicates += stats.infix;
+    }
+
+    /// Returns the number of bits needed to hold every intermediate value calculated
+    pub fn intermediate_bits_needed(&self) -> u32 {
+        bits_needed(self.largest_intermediate)
+    }
+
+    /// Returns the smallest distance from the target of any answer, if a target was given and the
+    /// distance is within the histogram
+    pub fn closest(&self) -> Option<usize> {
+        self.distances
+            .iter()
+            .take(self.distances.len().saturating_sub(1))
+            .position(|&count| count > 0)
+    }
+}
+
+/// Observer for run_all_internal which does nothing, compiled out of the loop
+#[inline(always)]
+fn no_observer(_prog_elem: usize, _result: Result<u32, ProgErr>) {}
+
+/// Unwraps the result of work run without a cancellation token
+fn uncancelled<T>(result: Result<T, Cancelled>) -> T {
+    result.expect("Work without a cancellation token can't be cancelled")
+}
+
+/// Prints the statistics of program generation if verbose output is enabled
+fn print_stats(stats: &GenerationStats, verbose: bool) {
+    if verbose && cfg!(feature = "verbose") {
+        println!("{stats}");
+    }
+}
+
+/// Returns the number of bits needed to hold a value (at least 1)
+fn bits_needed(value: u32) -> u32 {
+    (u32::BITS - value.leading_zeros()).max(1)
 }
 
 // Tests
 
 #[cfg(test)]
 mod tests {
-    use std::mem;
+    use std::sync::Arc;
+    use std::{mem, thread};
 
+    use super::solution::analyse_ops;
     use super::*;
 
     #[test]
     fn test_size() {
         // Make sure ProgInstr is packed correctly (2 * 32 bit uints)
         assert_eq!(32 * 2, mem::size_of::<ProgInstr>() * 8);
+
+        // Solutions are held for every answer in range so must stay small
+        assert_eq!(24, mem::size_of::<Solution>());
+    }
+
+    #[test]
+    fn test_cancel() {
+        let numbers = [100, 75, 3, 2];
+        let token = CancellationToken::new();
+
+        let programs = Programs::new_cancellable(4, false, false, &token).unwrap();
+
+        assert_eq!(Programs::new(4, false, false).len(), programs.len());
+        assert_eq!(
+            programs.run_all(&numbers).solutions,
+            programs
+                .run_all_cancellable(&numbers, &token)
+                .unwrap()
+                .solutions
+        );
+        assert_eq!(
+            programs.run_all_target(150, &numbers),
+            programs
+                .run_all_target_cancellable(150, &numbers, &token)
+                .unwrap()
+        );
+
+        token.cancel();
+
+        assert!(Programs::new_cancellable(4, true, false, &token).is_err());
+        assert!(programs.run_all_cancellable(&numbers, &token).is_err());
+        assert!(programs
+            .run_all_target_cancellable(150, &numbers, &token)
+            .is_err());
+    }
+
+    #[test]
+    fn test_valid_rpn() {
+        for programs in [
+            Programs::new(4, false, false),
+            Programs::new(4, true, false),
+            Programs::new_filtered(4, false),
+        ] {
+            for i in 0..programs.len() {
+                assert_eq!(Ok(()), validate_rpn(programs.instructions(i)));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid program")]
+    fn test_invalid_rpn() {
+        let _: Programs = "0 +".into();
     }
 
     #[test]
@@ -558,6 +1277,7 @@ mod tests {
         let programs: Programs = "0 1 +".into();
 
         assert_eq!(Ok(7), programs.run(0, &[3, 4]));
+        assert_eq!(Err(ProgErr::Overflow), programs.run(0, &[u32::MAX, 1]));
     }
 
     #[test]
@@ -579,6 +1299,7 @@ mod tests {
         assert_eq!(Err(ProgErr::Zero), programs.run(0, &[7, 0]));
         assert_eq!(Err(ProgErr::Zero), programs.run(0, &[0, 3]));
         assert_eq!(Err(ProgErr::Zero), programs.run(0, &[0, 0]));
+        assert_eq!(Err(ProgErr::Overflow), programs.run(0, &[100_000, 100_000]));
     }
 
     #[test]
@@ -590,4 +1311,242 @@ mod tests {
         assert_eq!(Err(ProgErr::DivZero), programs.run(0, &[3, 0]));
         assert_eq!(Err(ProgErr::Div1), programs.run(0, &[3, 1]));
     }
+
+    #[test]
+    fn test_run_all_targets() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [100, 25, 7, 3];
+        let targets = [128, 175, 128, 999];
+
+        let solutions = programs.run_all_targets(&targets, &numbers);
+
+        assert_eq!(targets.len(), solutions.len());
+
+        for (target, solutions) in targets.iter().zip(solutions.iter()) {
+            assert_eq!(&programs.run_all_target(*target, &numbers), solutions);
+        }
+
+        assert!(!solutions[0].is_empty());
+        assert!(solutions[3].is_empty());
+    }
+
+    #[test]
+    fn test_run_target_iter() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [100, 25, 7, 3];
+
+        let mut iter = programs.run_target_iter(128, &numbers);
+
+        // The first solution found is one of the shortest
+        let first = iter.next().unwrap();
+        let solutions = programs.run_all_target(128, &numbers);
+
+        assert_eq!(
+            solutions.iter().map(|s| s.length()).min(),
+            Some(first.length())
+        );
+        assert_eq!(solutions.len(), 1 + iter.count());
+    }
+
+    #[test]
+    fn test_run_all_observed() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [100, 25, 7, 3];
+
+        let mut observed = Vec::new();
+        let results = programs.run_all_observed(&numbers, |prog_elem, result| {
+            observed.push((prog_elem, result))
+        });
+
+        // Every program is observed in order with the outcome of running it
+        assert_eq!(programs.len(), observed.len());
+        assert!(observed
+            .iter()
+            .enumerate()
+            .all(|(i, (prog_elem, _))| i == *prog_elem));
+
+        for (prog_elem, result) in &observed {
+            assert_eq!(programs.run(*prog_elem, &numbers), *result);
+        }
+
+        // The results are the same as without an observer
+        let solutions = observed
+            .iter()
+            .filter(|(_, result)| result.is_ok_and(|ans| (MIN_TARGET..=MAX_TARGET).contains(&ans)))
+            .count();
+
+        assert_eq!(programs.run_all(&numbers).solutions.len(), solutions);
+        assert_eq!(results.solutions.len(), solutions);
+    }
+
+    #[test]
+    fn test_shared() {
+        let programs = Arc::new(Programs::new(4, false, false));
+        let numbers = [[100, 75, 3, 2], [10, 9, 8, 7], [50, 6, 4, 1]];
+
+        let handles = numbers.map(|numbers| {
+            let programs = Arc::clone(&programs);
+
+            thread::spawn(move || programs.coverage(&numbers))
+        });
+
+        for (numbers, handle) in numbers.iter().zip(handles) {
+            assert_eq!(programs.coverage(numbers), handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_run_all_distances() {
+        let programs = Programs::new(3, false, false);
+        let numbers = [3, 4, 5];
+
+        let results = programs.run_all_distances(&numbers, 62, 5);
+
+        assert_eq!(7, results.distances.len());
+
+        // 3 × 4 × 5 = 60 is the closest
+        assert_eq!(Some(2), results.closest());
+
+        // Every answer is counted once
+        let answers = (0..programs.len())
+            .filter(|&i| programs.run(i, &numbers).is_ok())
+            .count();
+
+        assert_eq!(answers, results.distances.iter().sum::<usize>());
+
+        // No histogram without a target
+        assert_eq!(None, programs.run_all(&numbers).closest());
+    }
+
+    #[test]
+    fn test_run_all_range() {
+        let programs = Programs::new(3, false, false);
+        let numbers = [1000, 250, 3];
+
+        let results = programs.run_all_range(&numbers, 1000..=9999);
+
+        let answers = results
+            .solutions
+            .iter()
+            .map(|s| s.result)
+            .collect::<HashSet<_>>();
+
+        assert!(answers.contains(&3750)); // (1000 + 250) × 3
+        assert!(answers.contains(&1253));
+        assert!(answers.iter().all(|ans| (1000..=9999).contains(ans)));
+
+        // 250 × 3 = 750 is only a solution in the standard range
+        assert!(!answers.contains(&750));
+        assert!(programs
+            .run_all(&numbers)
+            .solutions
+            .iter()
+            .any(|s| s.result == 750));
+    }
+
+    #[test]
+    fn test_headroom() {
+        let programs = Programs::new(3, false, false);
+
+        let results = programs.run_all(&[100, 75, 50]);
+
+        // 100 × 75 × 50
+        assert_eq!(375_000, results.largest_intermediate);
+        assert_eq!(19, results.intermediate_bits_needed());
+        assert_eq!(0, results.overflow);
+
+        // Every successful program is in the histogram
+        assert_eq!(
+            results.solutions.len() + results.under_range + results.above_range,
+            results.intermediate_bits.iter().sum::<usize>()
+        );
+        assert!(results.intermediate_bits[19..].iter().all(|&c| c == 0));
+
+        // The largest values and operation counts kept while running match a trace of each solution
+        for s in &results.solutions {
+            let trace = programs.trace(s.program, &[100, 75, 50]).unwrap();
+
+            assert_eq!(
+                trace.intermediates().map(|(_, value)| value).max(),
+                Some(s.largest_intermediate()).filter(|_| s.operations() > 0)
+            );
+            assert_eq!(analyse_ops(&trace.instructions), s.ops());
+        }
+
+        let results = programs.run_all(&[100_000, 100_000, 1000]);
+
+        assert!(results.overflow > 0);
+    }
+
+    #[test]
+    fn test_filter_duplicated() {
+        let numbers = [100, 75, 3, 2];
+
+        let programs = Programs::new(4, true, false);
+        let mut results = programs.run_all(&numbers);
+        let before = results.solutions.len();
+
+        results.filter_duplicated(&programs);
+
+        assert!(results.term_order_duplicates > 0);
+        assert_eq!(
+            before,
+            results.solutions.len() + results.term_order_duplicates + results.infix_duplicates
+        );
+
+        // The same answers are reached as without the duplicated programs
+        let results_nc = Programs::new(4, false, false).run_all(&numbers);
+
+        let answers = |results: &Results| {
+            results
+                .solutions
+                .iter()
+                .map(|s| s.result)
+                .collect::<HashSet<_>>()
+        };
+
+        assert_eq!(answers(&results_nc), answers(&results));
+
+        // Filtering the solutions directly gives the same counts
+        let mut solutions = programs.run_all(&numbers).solutions;
+
+        let stats = programs.filter_duplicates(&mut solutions);
+
+        assert_eq!(results.term_order_duplicates, stats.term_order);
+        assert_eq!(results.infix_duplicates, stats.infix);
+        assert_eq!(before, solutions.len() + stats.total());
+        assert_eq!(results.solutions, solutions);
+
+        // Checking a program again finds the equation already seen
+        let mut stack = Vec::new();
+        let mut set = DupSet::new();
+
+        assert_eq!(
+            DupReason::NotDup,
+            programs.duplicate_reason(0, &mut stack, &mut set)
+        );
+        assert_eq!(
+            DupReason::Infix,
+            programs.duplicate_reason(0, &mut stack, &mut set)
+        );
+    }
+
+    #[test]
+    fn test_filter_identical() {
+        let numbers = [100, 25, 25];
+
+        let programs = Programs::new(3, false, false);
+        let solutions = programs.run_all(&numbers).solutions;
+
+        // Each solution twice
+        let mut doubled = programs.run_all(&numbers).solutions;
+        doubled.extend(programs.run_all(&numbers).solutions);
+
+        assert_eq!(
+            solutions.len(),
+            programs.filter_identical(&mut doubled, &numbers)
+        );
+        assert_eq!(solutions, doubled);
+        assert_eq!(0, programs.filter_identical(&mut doubled, &numbers));
+    }
 }
@@ -0,0 +1,117 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for tallying the operators used by solutions: how many solutions use
+//! each operator, how many targets can only be reached using each operator, and how many solutions
+//! use each combination of operator counts.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::solution::{OpCounts, Solution};
+use super::Results;
+
+/// Short names of the operators in the order they are tallied
+pub const OP_NAMES: [&str; 4] = ["add", "sub", "mul", "div"];
+
+/// Operator usage of a set of solutions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpUsage {
+    /// Number of solutions
+    pub solutions: usize,
+    /// Number of targets reached
+    pub targets: usize,
+    /// Number of solutions using each operator at least once (add, subtract, multiply, divide)
+    pub using: [usize; 4],
+    /// Number of targets where every solution uses each operator
+    pub needing: [usize; 4],
+    /// Number of solutions with each combination of operator counts
+    pub combinations: BTreeMap<OpCounts, usize>,
+}
+
+impl OpUsage {
+    /// Tallies the operators used by a set of solutions
+    pub fn new(solutions: &[Solution]) -> Self {
+        let mut usage = Self {
+            solutions: solutions.len(),
+            ..Default::default()
+        };
+
+        // Operators used by every solution for each target
+        let mut target_ops: HashMap<u32, [bool; 4]> = HashMap::new();
+
+        for solution in solutions {
+            let ops = solution.ops();
+            let used = ops.to_array().map(|count| count > 0);
+
+            for (total, used) in usage.using.iter_mut().zip(used) {
+                *total += used as usize;
+            }
+
+            *usage.combinations.entry(ops).or_default() += 1;
+
+            target_ops
+                .entry(solution.result)
+                .and_modify(|needed| {
+                    for (needed, used) in needed.iter_mut().zip(used) {
+                        *needed &= used;
+                    }
+                })
+                .or_insert(used);
+        }
+
+        usage.targets = target_ops.len();
+
+        for needed in target_ops.values() {
+            for (total, needed) in usage.needing.iter_mut().zip(needed) {
+                *total += *needed as usize;
+            }
+        }
+
+        usage
+    }
+}
+
+impl Results {
+    /// Returns the operator usage of the solutions
+    pub fn op_usage(&self) -> OpUsage {
+        OpUsage::new(&self.solutions)
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_op_usage() {
+        let programs = Programs::new(3, false, false);
+        let results = programs.run_all_range(&[10, 4, 3], 1..=200);
+
+        let usage = results.op_usage();
+
+        assert_eq!(results.solutions.len(), usage.solutions);
+        assert_eq!(usage.solutions, usage.combinations.values().sum::<usize>());
+
+        // 120 = 10 × 4 × 3 needs multiplication only
+        let solutions_120 = programs.run_all_target(120, &[10, 4, 3]);
+        let usage_120 = OpUsage::new(&solutions_120);
+
+        assert_eq!(1, usage_120.targets);
+        assert_eq!([0, 0, 1, 0], usage_120.needing);
+        assert_eq!(
+            Some(&1),
+            usage_120.combinations.get(&OpCounts {
+                mul: 2,
+                ..Default::default()
+            })
+        );
+
+        // Counts are bounded by the number of solutions and targets
+        for op in 0..4 {
+            assert!(usage.needing[op] <= usage.targets);
+            assert!(usage.using[op] <= usage.solutions);
+        }
+    }
+}
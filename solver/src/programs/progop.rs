@@ -3,8 +3,9 @@
 //! This module contains operators for RPN programs and functions to process a stream of instructions
 
 use bitflags::bitflags;
-use colored::Colorize;
-use numformat::NumFormat;
+
+use crate::board::CardIdx;
+use crate::format::{dimmed, on_blue, symbol_set, NumFormat, SymbolSet};
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -24,6 +25,14 @@ bitflags! {
 }
 
 impl ProgOp {
+    /// All of the arithmetic operators supported
+    pub const OPERATORS: [ProgOp; 4] = [
+        ProgOp::PROG_OP_ADD,
+        ProgOp::PROG_OP_SUB,
+        ProgOp::PROG_OP_MUL,
+        ProgOp::PROG_OP_DIV,
+    ];
+
     /// Constructs a new number operator
     #[inline]
     pub fn new_number(n: u8) -> ProgOp {
@@ -35,27 +44,67 @@ impl ProgOp {
         result
     }
 
+    /// Constructs a new number operator for a card
+    #[inline]
+    pub fn new_card(idx: CardIdx) -> ProgOp {
+        ProgOp::new_number(idx.0)
+    }
+
+    /// Returns the card index for a number operator, or None if it is an operator
+    #[inline]
+    pub fn card(&self) -> Option<CardIdx> {
+        self.is_number().then(|| CardIdx(self.bits()))
+    }
+
     /// Returns true if the operator is a number
     #[inline]
     pub fn is_number(&self) -> bool {
         (*self & ProgOp::PROG_OP_MASK).is_empty()
     }
 
-    /// Returns the string representation of a program operator, optionally coloured
-    pub fn colour(&self, numbers: &[u8], colour: bool) -> String {
-        let mut res = match *self & ProgOp::PROG_OP_MASK {
-            ProgOp::PROG_OP_ADD => "+".to_string(),
-            ProgOp::PROG_OP_SUB => "-".to_string(),
-            ProgOp::PROG_OP_MUL => "×".to_string(),
-            ProgOp::PROG_OP_DIV => "/".to_string(),
-            _ => numbers[self.bits() as usize].num_format(),
+    /// Returns the symbol for an operator, or None if it is a number
+    pub fn symbol(&self) -> Option<&'static str> {
+        match *self & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_ADD => Some("+"),
+            ProgOp::PROG_OP_SUB => Some("-"),
+            ProgOp::PROG_OP_MUL => Some("×"),
+            ProgOp::PROG_OP_DIV => Some("/"),
+            _ => None,
+        }
+    }
+
+    /// Returns the symbol for an operator in a symbol set, or None if it is a number
+    pub fn symbol_in(&self, set: SymbolSet) -> Option<&'static str> {
+        match *self & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_MUL => Some(set.multiply()),
+            _ => self.symbol(),
+        }
+    }
+
+    /// Returns the name of an operator, or None if it is a number
+    pub fn name(&self) -> Option<&'static str> {
+        match *self & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_ADD => Some("add"),
+            ProgOp::PROG_OP_SUB => Some("subtract"),
+            ProgOp::PROG_OP_MUL => Some("multiply"),
+            ProgOp::PROG_OP_DIV => Some("divide"),
+            _ => None,
+        }
+    }
+
+    /// Returns the string representation of a program operator in the current symbol set,
+    /// optionally coloured
+    pub fn colour(&self, numbers: &[u32], colour: bool) -> String {
+        let mut res = match self.symbol_in(symbol_set()) {
+            Some(symbol) => symbol.to_string(),
+            None => numbers[self.bits() as usize].num_format(),
         };
 
         if colour {
             res = if self.is_number() {
-                res.on_blue().to_string()
+                on_blue(&res)
             } else {
-                res.dimmed().to_string()
+                dimmed(&res)
             }
         }
 
@@ -75,4 +124,23 @@ mod tests {
     fn test_size() {
         assert_eq!(1, mem::size_of::<ProgOp>());
     }
+
+    #[test]
+    fn test_symbols() {
+        let symbols = ProgOp::OPERATORS.map(|op| op.symbol().unwrap());
+
+        assert_eq!(["+", "-", "×", "/"], symbols);
+
+        let symbols = ProgOp::OPERATORS.map(|op| op.symbol_in(SymbolSet::Ascii).unwrap());
+
+        assert_eq!(["+", "-", "*", "/"], symbols);
+        assert_eq!(None, ProgOp::new_number(3).symbol());
+        assert_eq!(None, ProgOp::new_number(3).name());
+    }
+
+    #[test]
+    fn test_card() {
+        assert_eq!(Some(CardIdx(3)), ProgOp::new_card(CardIdx(3)).card());
+        assert_eq!(None, ProgOp::PROG_OP_ADD.card());
+    }
 }
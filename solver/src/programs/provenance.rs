@@ -0,0 +1,136 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for working out the generation parameters of a program: the number
+//! of numbers it uses and its operator skeleton, the number of operators in each slot after the
+//! numbers (see the generate module). The parameters are worked out from the instructions, so they
+//! are the same however the programs were generated.
+
+use super::generate::op_counts;
+use super::progop::ProgOp;
+use super::solution::{analyse_ops, OpCounts};
+use super::Programs;
+
+/// Generation parameters of a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// Number of numbers used by the program
+    pub num_cnt: u8,
+    /// Number of operators in each slot, following each number after the first
+    pub slots: Vec<u8>,
+    /// Index of the operator skeleton (slots) in the skeletons generated for the number of numbers
+    pub skeleton: usize,
+    /// Number of each type of operation
+    pub ops: OpCounts,
+}
+
+impl Programs {
+    /// Returns the generation parameters of a program
+    pub fn provenance(&self, prog_elem: usize) -> Provenance {
+        provenance(self.instructions(prog_elem))
+    }
+}
+
+/// Works out the generation parameters of a program from its instructions
+pub(crate) fn provenance(instructions: &[ProgOp]) -> Provenance {
+    let mut num_cnt = 0;
+    let mut slots = Vec::new();
+
+    for op in instructions {
+        if op.is_number() {
+            num_cnt += 1;
+
+            // Each number after the first opens a slot
+            if num_cnt > 1 {
+                slots.push(0);
+            }
+        } else {
+            *slots.last_mut().expect("Operator before second number") += 1;
+        }
+    }
+
+    let skeleton = if num_cnt > 1 {
+        op_counts(num_cnt)
+            .iter()
+            .position(|counts| *counts == slots)
+            .expect("Operator skeleton not generated")
+    } else {
+        0
+    };
+
+    Provenance {
+        num_cnt,
+        slots,
+        skeleton,
+        ops: analyse_ops(instructions),
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provenance() {
+        let programs: Programs = "0".into();
+
+        assert_eq!(
+            Provenance {
+                num_cnt: 1,
+                slots: vec![],
+                skeleton: 0,
+                ops: OpCounts::default()
+            },
+            programs.provenance(0)
+        );
+
+        // Slot skeletons for 3 numbers are [0, 2] and [1, 1]
+        let programs: Programs = "0 1 2 + *".into();
+        let provenance = programs.provenance(0);
+
+        assert_eq!(3, provenance.num_cnt);
+        assert_eq!(vec![0, 2], provenance.slots);
+        assert_eq!(0, provenance.skeleton);
+
+        let programs: Programs = "0 1 + 2 *".into();
+        let provenance = programs.provenance(0);
+
+        assert_eq!(vec![1, 1], provenance.slots);
+        assert_eq!(1, provenance.skeleton);
+        assert_eq!(
+            OpCounts {
+                add: 1,
+                mul: 1,
+                ..Default::default()
+            },
+            provenance.ops
+        );
+        assert_eq!(2, provenance.ops.kinds());
+    }
+
+    #[test]
+    fn test_provenance_all() {
+        let programs = Programs::new(4, false, false);
+
+        for segment in programs.segments() {
+            let skeletons = if segment.nums > 1 {
+                op_counts(segment.nums).len()
+            } else {
+                1
+            };
+
+            for prog_elem in segment.programs {
+                let provenance = programs.provenance(prog_elem);
+
+                assert_eq!(segment.nums, provenance.num_cnt);
+                assert!(provenance.skeleton < skeletons);
+                assert_eq!(
+                    segment.nums as usize - 1,
+                    provenance.ops.total(),
+                    "Program {prog_elem}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+#![warn(missing_docs)]
+
+//! This module contains a program interpreter which abandons a program early when an intermediate
+//! result is too large to ever be brought back in to the target range.
+//!
+//! The largest value any expression can take using a set of values v is less than the product of
+//! (v + 1). Let Q be this product for the values on the stack below an intermediate result X and the
+//! numbers still to be pushed. Any expression using X and those values has a value of at least
+//! X / Q - Q, and an expression which uses X as a divisor or subtrahend needs a value greater than X
+//! which can't be built. So if X > Q × (999 + Q) the program can never give an answer in range and
+//! is abandoned. No solutions are lost.
+
+use super::progop::ProgOp;
+use super::solution::{Answer, OpCounts};
+use super::stack::SmallStack;
+use super::ProgErr;
+
+/// Largest answer of interest
+const MAX_ANSWER: u64 = 999;
+
+/// Runs a program abandoning it early if an intermediate result grows too large, keeping the largest
+/// value calculated. The number of instructions not executed is added to skipped
+pub(crate) fn run_instructions_pruned<const N: usize>(
+    instructions: &[ProgOp],
+    numbers: &[u32],
+    stack: &mut SmallStack<N>,
+    skipped: &mut usize,
+) -> Result<Answer, ProgErr> {
+    stack.clear();
+
+    let mut largest = 0;
+    let mut ops = OpCounts::default();
+
+    // Product of (n + 1) for the numbers still to be pushed
+    let mut remaining: u64 = instructions
+        .iter()
+        .filter(|op| op.is_number())
+        .fold(1, |q, op| {
+            q.saturating_mul(numbers[op.bits() as usize] as u64 + 1)
+        });
+
+    let last = instructions.len() - 1;
+
+    for (i, op) in instructions.iter().enumerate() {
+        ops.count(*op);
+
+        let ans = match *op & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_ADD => {
+                let n1 = stack.pop();
+                let n2 = stack.pop();
+
+                n2.checked_add(n1).ok_or(ProgErr::Overflow)?
+            }
+            ProgOp::PROG_OP_SUB => {
+                let n1 = stack.pop();
+                let n2 = stack.pop();
+
+                if n2 < n1 {
+                    Err(ProgErr::Negative)?
+                }
+
+                let int = n2 - n1;
+
+                if int == 0 {
+                    Err(ProgErr::Zero)?
+                }
+
+                largest = largest.max(int);
+                stack.push(int);
+                continue;
+            }
+            ProgOp::PROG_OP_MUL => {
+                let n1 = stack.pop();
+                let n2 = stack.pop();
+
+                if n1 == 1 || n2 == 1 {
+                    Err(ProgErr::Mul1)?
+                }
+
+                let int = n2.checked_mul(n1).ok_or(ProgErr::Overflow)?;
+
+                if int == 0 {
+                    Err(ProgErr::Zero)?
+                }
+
+                int
+            }
+            ProgOp::PROG_OP_DIV => {
+                let n1 = stack.pop();
+                let n2 = stack.pop();
+
+                if n1 == 0 {
+                    Err(ProgErr::DivZero)?
+                }
+
+                if n1 == 1 {
+                    Err(ProgErr::Div1)?
+                }
+
+                if !n2.is_multiple_of(n1) {
+                    Err(ProgErr::NonInteger)?
+                }
+
+                largest = largest.max(n2 / n1);
+                stack.push(n2 / n1);
+                continue;
+            }
+            _ => {
+                let n = numbers[op.bits() as usize];
+
+                remaining /= n as u64 + 1;
+                stack.push(n);
+                continue;
+            }
+        };
+
+        // Result of an addition or multiplication - check it's not too large
+        if i != last {
+            let q = stack
+                .values()
+                .iter()
+                .fold(remaining, |q, &s| q.saturating_mul(s as u64 + 1));
+
+            if ans as u64 > q.saturating_mul(q.saturating_add(MAX_ANSWER)) {
+                *skipped += last - i;
+                Err(ProgErr::Pruned)?
+            }
+        }
+
+        largest = largest.max(ans);
+        stack.push(ans);
+    }
+
+    let value = stack.pop();
+
+    Ok(Answer {
+        value,
+        largest: largest.max(value),
+        ops,
+    })
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::stack::ProgStack;
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_no_solutions_lost() {
+        let programs = Programs::new(4, true, false);
+
+        for numbers in [
+            [100, 75, 50, 25],
+            [100, 75, 10, 1],
+            [10, 9, 8, 7],
+            [75, 50, 2, 1],
+        ] {
+            let results = programs.run_all(&numbers);
+            let pruned = programs.run_all_pruned(&numbers);
+
+            assert_eq!(results.solutions, pruned.solutions);
+        }
+    }
+
+    #[test]
+    fn test_pruned() {
+        let programs: Programs = "0 1 * 2 * 3 +".into();
+        let mut stack = ProgStack::new();
+        let mut skipped = 0;
+
+        // 100 × 75 × 50 can't be brought back in to range with 25
+        assert_eq!(
+            Err(ProgErr::Pruned),
+            run_instructions_pruned(
+                programs.instructions(0),
+                &[100, 75, 50, 25],
+                &mut stack,
+                &mut skipped
+            )
+        );
+        assert_eq!(2, skipped);
+
+        // 10 × 9 × 8 can
+        assert_eq!(
+            Ok(Answer {
+                value: 727,
+                largest: 727,
+                ops: OpCounts {
+                    add: 1,
+                    sub: 0,
+                    mul: 2,
+                    div: 0
+                }
+            }),
+            run_instructions_pruned(
+                programs.instructions(0),
+                &[10, 9, 8, 7],
+                &mut stack,
+                &mut skipped
+            )
+        );
+        assert_eq!(2, skipped);
+    }
+}
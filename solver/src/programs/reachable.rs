@@ -0,0 +1,78 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for finding every value which can be reached with a set of numbers,
+//! not just the values in the target range.
+
+use std::collections::BTreeMap;
+
+use super::stack::ProgStack;
+use super::Programs;
+
+impl Programs {
+    /// Runs the programs with a given set of numbers and returns every value reached with the
+    /// number of programs reaching it
+    pub fn reachable_values(&self, numbers: &[u32]) -> BTreeMap<u32, usize> {
+        let mut stack = ProgStack::new();
+        let mut values = BTreeMap::new();
+
+        assert!(numbers.len() == self.nums as usize);
+
+        for program in &self.programs {
+            let instructions = self.instructions_for_program(program);
+
+            if let Ok(ans) = Self::run_instructions(instructions, numbers, &mut stack) {
+                *values.entry(ans).or_insert(0) += 1;
+            }
+        }
+
+        values
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable_values() {
+        let programs = Programs::new(2, false, false);
+
+        // 25, 4, 25 + 4, 25 - 4, 25 × 4
+        assert_eq!(
+            BTreeMap::from([(4, 1), (21, 1), (25, 1), (29, 1), (100, 1)]),
+            programs.reachable_values(&[25, 4])
+        );
+
+        // 6 / 3 and 6 - 3
+        let values = Programs::new(2, false, false).reachable_values(&[6, 3]);
+
+        assert_eq!(Some(&1), values.get(&2));
+        assert_eq!(Some(&2), values.get(&3));
+        assert_eq!(Some((&18, &1)), values.last_key_value());
+    }
+
+    #[test]
+    fn test_reachable_values_range() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [75, 6, 2, 1];
+
+        let values = programs.reachable_values(&numbers);
+        let coverage = programs.coverage(&numbers);
+
+        // Agrees with the coverage of the target range
+        assert_eq!(
+            coverage.iter().collect::<Vec<_>>(),
+            values
+                .range(100..=999)
+                .map(|(&value, _)| value)
+                .collect::<Vec<_>>()
+        );
+
+        // Counts agree with the solutions found for a target
+        for (&value, &count) in values.range(100..=999) {
+            assert_eq!(count, programs.run_target_iter(value, &numbers).count());
+        }
+    }
+}
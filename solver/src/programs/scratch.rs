@@ -0,0 +1,180 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for scratch space reused by a worker thread when checking solutions
+//! for duplicates and formatting equations and steps.
+//!
+//! Building the equation tree of a program allocates a vector for each bracket group, and
+//! formatting allocates a string for each equation and step. A scratch space keeps the group
+//! vectors of trees which are finished with, and the strings handed back once they have been
+//! written, so a thread working through many card sets soon stops allocating. The allocations made
+//! and avoided are counted for verbose output.
+
+use super::dupset::DupSet;
+use super::infix::{GroupPool, InfixGrpTypeElem};
+
+/// Maximum number of empty strings kept in a pool
+const MAX_POOLED_STRINGS: usize = 65_536;
+
+/// Scratch space for duplicate checks and formatting, see the Programs *_with functions
+#[derive(Debug, Default)]
+pub struct Scratch {
+    pub(crate) stack: Vec<InfixGrpTypeElem>,
+    pub(crate) set: DupSet,
+    pub(crate) pool: GroupPool,
+    pub(crate) values: Vec<u32>,
+    pub(crate) strings: StringPool,
+}
+
+/// Counts of the allocations made and avoided by a scratch space
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScratchStats {
+    /// Group vectors allocated
+    pub groups_allocated: usize,
+    /// Group vectors reused
+    pub groups_reused: usize,
+    /// Strings allocated
+    pub strings_allocated: usize,
+    /// Strings reused
+    pub strings_reused: usize,
+}
+
+impl ScratchStats {
+    /// Returns the number of allocations made
+    pub fn allocated(&self) -> usize {
+        self.groups_allocated + self.strings_allocated
+    }
+
+    /// Returns the number of allocations which would have been made without the scratch space
+    pub fn without_scratch(&self) -> usize {
+        self.allocated() + self.groups_reused + self.strings_reused
+    }
+
+    /// Adds the counts of another scratch space
+    pub fn merge(&mut self, other: &ScratchStats) {
+        self.groups_allocated += other.groups_allocated;
+        self.groups_reused += other.groups_reused;
+        self.strings_allocated += other.strings_allocated;
+        self.strings_reused += other.strings_reused;
+    }
+}
+
+/// Pool of strings reused for equations and steps
+#[derive(Debug, Default)]
+pub(crate) struct StringPool {
+    strings: Vec<String>,
+    allocated: usize,
+    reused: usize,
+}
+
+impl StringPool {
+    /// Takes an empty string from the pool, or allocates one if the pool is empty
+    pub(crate) fn take(&mut self) -> String {
+        match self.strings.pop() {
+            Some(string) => {
+                self.reused += 1;
+                string
+            }
+            None => {
+                self.allocated += 1;
+                String::new()
+            }
+        }
+    }
+
+    /// Returns a string to the pool
+    fn give(&mut self, mut string: String) {
+        if string.capacity() > 0 && self.strings.len() < MAX_POOLED_STRINGS {
+            string.clear();
+            self.strings.push(string);
+        }
+    }
+}
+
+impl Scratch {
+    /// Creates an empty scratch space
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the allocations made and avoided so far
+    pub fn stats(&self) -> ScratchStats {
+        ScratchStats {
+            groups_allocated: self.pool.allocated,
+            groups_reused: self.pool.reused,
+            strings_allocated: self.strings.allocated,
+            strings_reused: self.strings.reused,
+        }
+    }
+
+    /// Forgets the programs checked for duplicates so the solutions of another set of cards can be
+    /// checked
+    pub fn clear_duplicates(&mut self) {
+        self.set.clear();
+    }
+
+    /// Returns equation or step strings to the scratch space once they have been written
+    pub fn recycle_strings(&mut self, strings: impl IntoIterator<Item = String>) {
+        for string in strings {
+            self.strings.give(string);
+        }
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_scratch() {
+        let programs = Programs::new(4, true, false);
+        let numbers = [100, 25, 3, 7];
+        let mut scratch = Scratch::new();
+
+        let mut solutions = programs.run_all_target(128, &numbers);
+        let mut expected = programs.run_all_target(128, &numbers);
+
+        // Results match the functions without scratch space
+        let stats = programs.filter_duplicates_with(&mut solutions, &mut scratch);
+        assert_eq!(programs.filter_duplicates(&mut expected), stats);
+        assert_eq!(expected, solutions);
+
+        let equations = solutions
+            .iter()
+            .map(|s| programs.infix_with(s.program, &numbers, &mut scratch))
+            .collect::<Vec<_>>();
+
+        for (solution, equation) in solutions.iter().zip(&equations) {
+            assert_eq!(&programs.infix(solution.program, &numbers, false), equation);
+            assert_eq!(
+                programs.steps(solution.program, &numbers, false),
+                programs.steps_with(solution.program, &numbers, &mut scratch)
+            );
+        }
+
+        let before = scratch.stats();
+
+        assert_eq!(0, before.strings_reused);
+        assert!(before.groups_allocated > 0);
+
+        // Recycled strings and trees are reused
+        scratch.recycle_strings(equations);
+        scratch.clear_duplicates();
+
+        let mut solutions = programs.run_all_target(128, &numbers);
+
+        programs.filter_duplicates_with(&mut solutions, &mut scratch);
+
+        for solution in &solutions {
+            programs.infix_with(solution.program, &numbers, &mut scratch);
+        }
+
+        let after = scratch.stats();
+
+        assert!(after.strings_reused > 0);
+        assert!(after.groups_reused > before.groups_reused);
+        assert!(after.allocated() < after.without_scratch());
+    }
+}
@@ -0,0 +1,124 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for splitting a collection of programs in to segments of programs
+//! which use the same number of numbers. Programs are always generated in order of the number of
+//! numbers used so each segment is a contiguous range of program elements.
+
+use std::ops::Range;
+
+use super::Programs;
+
+/// A range of programs which all use the same number of numbers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Number of numbers used by each program
+    pub nums: u8,
+    /// Range of program elements
+    pub programs: Range<usize>,
+    /// Total number of instructions in the programs
+    pub instructions: usize,
+}
+
+impl Segment {
+    /// Returns the number of programs in the segment
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    /// Returns true if the segment contains no programs
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+}
+
+impl Programs {
+    /// Returns the segments of programs for each number of numbers, shortest first
+    pub fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::with_capacity(self.nums as usize);
+        let mut start = 0;
+
+        for nums in 1..=self.nums {
+            // Programs with n numbers have 2n - 1 instructions
+            let length = (nums as u32 * 2) - 1;
+
+            let end = start + self.programs[start..].partition_point(|p| p.end - p.start < length);
+
+            let instructions = if end > start {
+                (self.programs[end - 1].end - self.programs[start].start) as usize + 1
+            } else {
+                0
+            };
+
+            segments.push(Segment {
+                nums,
+                programs: start..end,
+                instructions,
+            });
+
+            start = end;
+        }
+
+        segments
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        let programs = Programs::new(3, false, false);
+
+        let segments = programs.segments();
+
+        assert_eq!(
+            vec![
+                Segment {
+                    nums: 1,
+                    programs: 0..3,
+                    instructions: 3
+                },
+                Segment {
+                    nums: 2,
+                    programs: 3..21,
+                    instructions: 54
+                },
+                Segment {
+                    nums: 3,
+                    programs: 21..89,
+                    instructions: 340
+                },
+            ],
+            segments
+        );
+
+        for segment in segments {
+            for prog_elem in segment.programs {
+                assert_eq!(
+                    segment.nums as usize,
+                    programs
+                        .instructions(prog_elem)
+                        .iter()
+                        .filter(|op| op.is_number())
+                        .count()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_segments_duplicated() {
+        let programs = Programs::new(3, true, false);
+
+        let segments = programs.segments();
+
+        assert_eq!(
+            programs.len(),
+            segments.iter().map(|s| s.len()).sum::<usize>()
+        );
+        assert_eq!(6, segments[0].len());
+    }
+}
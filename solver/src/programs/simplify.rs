@@ -0,0 +1,177 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for simplifying a solution to an equivalent shorter expression over
+//! the same cards, eg. 75 × (6 + 2 - 6) to 75 × 2.
+//!
+//! Cards are removed from the expression (an operator with one of its operands removed is replaced
+//! by the other operand) and the shortest expression which is algebraically equivalent to the
+//! original is chosen. Cards with the same value are treated as the same card when checking the
+//! equivalence, so (75 + 6) - 6 simplifies to 75 whichever 6 is used. The simplified expression
+//! must give the answer with positive integer intermediate results.
+
+use std::cmp::Reverse;
+
+use super::progop::ProgOp;
+use super::stack::ProgStack;
+use super::{Programs, Solution};
+use crate::equivalence::{expressions_equivalent, normal_form};
+
+impl Programs {
+    /// Returns a solution using fewer cards which is equivalent to the given solution, or None if
+    /// the solution can't be simplified. The simplified solution is a program from this collection
+    pub fn simplify_solution(&self, solution: &Solution, numbers: &[u32]) -> Option<Solution> {
+        let instructions = self.instructions(solution.program);
+        let cards = solution.cards_used();
+
+        // Treat cards with the same value as the same card
+        let same_cards = |instructions: &[ProgOp]| -> Vec<ProgOp> {
+            instructions
+                .iter()
+                .map(|op| {
+                    if op.is_number() {
+                        let value = numbers[op.bits() as usize];
+                        let first = numbers.iter().position(|&n| n == value).unwrap();
+
+                        ProgOp::new_number(first as u8)
+                    } else {
+                        *op
+                    }
+                })
+                .collect()
+        };
+
+        let original = same_cards(instructions);
+
+        // Try removing the most cards first, keeping at least one
+        let mut masks = (1..(1u32 << cards) - 1).collect::<Vec<_>>();
+        masks.sort_by_key(|mask| Reverse(mask.count_ones()));
+
+        let segments = self.segments();
+        let mut stack = ProgStack::new();
+
+        for mask in masks {
+            let candidate = remove_cards(instructions, mask);
+
+            if !expressions_equivalent(&original, &same_cards(&candidate))
+                || Self::run_instructions(&candidate, numbers, &mut stack) != Ok(solution.result)
+            {
+                continue;
+            }
+
+            // Find the program in the collection with the same normal form
+            let used = card_mask(&candidate);
+            let norm = normal_form(&candidate);
+
+            let program = segments
+                .iter()
+                .find(|segment| segment.nums as usize == candidate.len().div_ceil(2))
+                .and_then(|segment| {
+                    segment.programs.clone().find(|&i| {
+                        let instructions = self.instructions(i);
+
+                        card_mask(instructions) == used && normal_form(instructions) == norm
+                    })
+                });
+
+            if let Some(program) = program {
+                return self.solution(program, numbers).ok();
+            }
+        }
+
+        None
+    }
+}
+
+/// Removes cards from a program. Bit n of the mask removes the nth card in the program. An operator
+/// with one operand removed is replaced by the other operand. At least one card must remain
+fn remove_cards(instructions: &[ProgOp], mask: u32) -> Vec<ProgOp> {
+    let mut stack: Vec<Option<Vec<ProgOp>>> = Vec::with_capacity(instructions.len());
+    let mut card = 0;
+
+    for op in instructions {
+        if op.is_number() {
+            stack.push(if mask & (1 << card) == 0 {
+                Some(vec![*op])
+            } else {
+                None
+            });
+
+            card += 1;
+        } else {
+            let t1 = stack.pop().unwrap();
+            let t2 = stack.pop().unwrap();
+
+            stack.push(match (t2, t1) {
+                (Some(mut t2), Some(t1)) => {
+                    t2.extend(t1);
+                    t2.push(*op);
+                    Some(t2)
+                }
+                (Some(t), None) | (None, Some(t)) => Some(t),
+                (None, None) => None,
+            });
+        }
+    }
+
+    stack.pop().unwrap().expect("All cards removed")
+}
+
+/// Returns a bit mask of the cards used by a program
+fn card_mask(instructions: &[ProgOp]) -> u32 {
+    instructions
+        .iter()
+        .filter(|op| op.is_number())
+        .fold(0, |mask, op| mask | (1 << op.bits()))
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_cards() {
+        let programs: Programs = "0 1 2 - *".into();
+        let instructions = programs.instructions(0);
+
+        let expected = |rpn: &str| Programs::from(rpn).instructions(0).to_vec();
+
+        assert_eq!(expected("0 2 *"), remove_cards(instructions, 0b010));
+        assert_eq!(expected("1 2 -"), remove_cards(instructions, 0b001));
+        assert_eq!(expected("0"), remove_cards(instructions, 0b110));
+    }
+
+    #[test]
+    fn test_simplify_solution() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [75, 6, 2, 6];
+
+        let solutions = programs.run_all_target(150, &numbers);
+
+        // 75 × (6 + 2 - 6) simplifies to 75 × 2
+        let cancelling = solutions
+            .iter()
+            .find(|s| programs.infix(s.program, &numbers, false) == "75 × (6 + 2 - 6)")
+            .unwrap();
+
+        let simplified = programs.simplify_solution(cancelling, &numbers).unwrap();
+
+        assert_eq!(150, simplified.result);
+        assert_eq!(
+            "75 × 2",
+            programs.infix(simplified.program, &numbers, false)
+        );
+
+        // 75 × 2 can't be simplified
+        assert_eq!(None, programs.simplify_solution(&simplified, &numbers));
+
+        // Every simplification uses fewer cards to give the same answer
+        for solution in &solutions {
+            if let Some(simplified) = programs.simplify_solution(solution, &numbers) {
+                assert!(simplified.cards_used() < solution.cards_used());
+                assert_eq!(solution.result, simplified.result);
+            }
+        }
+    }
+}
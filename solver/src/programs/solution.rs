@@ -1,25 +1,148 @@
 use std::cmp::Ordering;
 
+use super::progop::ProgOp;
+
+/// Number of each type of operation in a program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OpCounts {
+    /// Number of additions
+    pub add: u8,
+    /// Number of subtractions
+    pub sub: u8,
+    /// Number of multiplications
+    pub mul: u8,
+    /// Number of divisions
+    pub div: u8,
+}
+
+impl OpCounts {
+    /// Returns the total number of operations
+    pub fn total(&self) -> usize {
+        self.add as usize + self.sub as usize + self.mul as usize + self.div as usize
+    }
+
+    /// Returns the number of different types of operation used
+    pub fn kinds(&self) -> usize {
+        self.to_array().iter().filter(|&&count| count > 0).count()
+    }
+
+    /// Returns the counts in the order add, subtract, multiply, divide
+    pub fn to_array(&self) -> [u8; 4] {
+        [self.add, self.sub, self.mul, self.div]
+    }
+
+    /// Counts an instruction if it is an operator
+    #[inline(always)]
+    pub(crate) fn count(&mut self, op: ProgOp) {
+        match op & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_ADD => self.add += 1,
+            ProgOp::PROG_OP_SUB => self.sub += 1,
+            ProgOp::PROG_OP_MUL => self.mul += 1,
+            ProgOp::PROG_OP_DIV => self.div += 1,
+            _ => (),
+        }
+    }
+}
+
+/// Answer of a program which ran successfully, with the largest value calculated and the operations
+/// counted while running it. The largest value is the answer for a program with no operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Answer {
+    /// Result of the program
+    pub(crate) value: u32,
+    /// Largest value calculated by any step of the program
+    pub(crate) largest: u32,
+    /// Number of each type of operation
+    pub(crate) ops: OpCounts,
+}
+
 /// Holds the result of running a program
-#[derive(Eq)]
+#[derive(Debug, Eq)]
 pub struct Solution {
     /// Program element providing the solution
     pub program: usize,
     /// Length of the program instructions
-    length: usize,
+    length: u8,
     /// The result of running the program with the given numbers
     pub result: u32,
+    /// Largest value calculated while running the program
+    largest: u32,
+    /// Number of each type of operation
+    ops: OpCounts,
 }
 
 impl Solution {
-    /// Creates a new solution
-    pub fn new(program: usize, length: usize, result: u32) -> Self {
+    /// Creates a new solution from a program of the given length which gives the result, with its
+    /// operation counts and the largest value calculated while it ran
+    pub fn new(program: usize, length: usize, result: u32, ops: OpCounts, largest: u32) -> Self {
         Self {
             program,
-            length,
+            length: length as u8,
             result,
+            largest,
+            ops,
         }
     }
+
+    /// Creates a new solution from the answer of running a program
+    pub(crate) fn from_answer(program: usize, length: usize, answer: Answer) -> Self {
+        Self::new(program, length, answer.value, answer.ops, answer.largest)
+    }
+
+    /// Creates a new solution for a program run with relaxed rules, where the largest intermediate
+    /// value has already been found
+    pub(crate) fn with_largest(
+        program: usize,
+        instructions: &[ProgOp],
+        result: u32,
+        largest: u32,
+    ) -> Self {
+        Self::new(
+            program,
+            instructions.len(),
+            result,
+            analyse_ops(instructions),
+            largest,
+        )
+    }
+
+    /// Returns the length of the program instructions
+    pub fn length(&self) -> usize {
+        self.length as usize
+    }
+
+    /// Returns the number of cards used by the program. Every operator combines two values, so a
+    /// program using n cards has n - 1 operators
+    pub fn cards_used(&self) -> usize {
+        self.length().div_ceil(2)
+    }
+
+    /// Returns the number of each type of operation in the program
+    pub fn ops(&self) -> OpCounts {
+        self.ops
+    }
+
+    /// Returns the total number of operations in the program
+    pub fn operations(&self) -> usize {
+        self.ops.total()
+    }
+
+    /// Returns the largest value calculated by any step of the program. This is the result for a
+    /// program with no operations
+    pub fn largest_intermediate(&self) -> u32 {
+        self.largest
+    }
+}
+
+/// Returns the operation counts of a program
+pub(crate) fn analyse_ops(instructions: &[ProgOp]) -> OpCounts {
+    let mut ops = OpCounts::default();
+
+    for op in instructions {
+        ops.count(*op);
+    }
+
+    ops
 }
 
 impl Ord for Solution {
@@ -52,3 +175,58 @@ impl PartialEq for Solution {
         self.program == other.program
     }
 }
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_cards_used() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [50, 10, 3, 2];
+
+        let solutions = programs.run_all_target(60, &numbers);
+
+        // 50 + 10 and (50 - 10) × 3 / 2 among others
+        assert!(solutions.iter().any(|s| s.cards_used() == 2));
+        assert!(solutions.iter().any(|s| s.cards_used() == 4));
+
+        for s in &solutions {
+            assert_eq!(s.length(), s.cards_used() * 2 - 1);
+        }
+    }
+
+    #[test]
+    fn test_metadata() {
+        let programs: Programs = "0 1 - 2 * 3 /".into();
+        let numbers = [50, 10, 3, 2];
+
+        // (50 - 10) × 3 / 2
+        let solution = programs.solution(0, &numbers).unwrap();
+
+        assert_eq!(60, solution.result);
+
+        assert_eq!(7, solution.length());
+        assert_eq!(
+            OpCounts {
+                add: 0,
+                sub: 1,
+                mul: 1,
+                div: 1
+            },
+            solution.ops()
+        );
+        assert_eq!(3, solution.operations());
+        assert_eq!(120, solution.largest_intermediate());
+
+        // No operations
+        let programs: Programs = "0".into();
+        let solution = programs.solution(0, &numbers).unwrap();
+
+        assert_eq!(0, solution.operations());
+        assert_eq!(50, solution.largest_intermediate());
+    }
+}
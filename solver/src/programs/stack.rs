@@ -0,0 +1,123 @@
+#![warn(missing_docs)]
+
+//! This module contains the fixed size stack used by the program interpreter.
+//!
+//! The stack depth of a program is bounded by the number of numbers it uses, so the stack is held
+//! in an array rather than a heap allocated vector. The capacity is a const generic power of two and
+//! positions are masked with it, so the compiler can see every access is in bounds without a check
+//! and without any unsafe code.
+//!
+//! Masking means an underflow or overflow would silently wrap rather than fail, so every program
+//! must have a valid stack layout: generated programs always do, and programs built from RPN or
+//! loaded from a file are checked with `validate_rpn` first.
+
+/// Maximum stack depth of a program. Numbers are held in the low 4 bits of an instruction so a
+/// program can't use more than 16
+pub(crate) const STACK_SIZE: usize = 16;
+
+/// Stack for running programs
+pub(crate) type ProgStack = SmallStack<STACK_SIZE>;
+
+/// Fixed size stack of values
+#[derive(Debug, Clone)]
+pub(crate) struct SmallStack<const N: usize> {
+    values: [u32; N],
+    len: usize,
+}
+
+impl<const N: usize> SmallStack<N> {
+    /// Mask applied to positions
+    const MASK: usize = {
+        assert!(N.is_power_of_two(), "Stack size must be a power of two");
+        N - 1
+    };
+
+    /// Creates an empty stack
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self {
+            values: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Empties the stack
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Pushes a value on to the stack
+    #[inline]
+    pub(crate) fn push(&mut self, value: u32) {
+        debug_assert!(self.len < N, "Stack overflow");
+
+        self.values[self.len & Self::MASK] = value;
+        self.len += 1;
+    }
+
+    /// Pops a value off the stack
+    #[inline]
+    pub(crate) fn pop(&mut self) -> u32 {
+        debug_assert!(self.len > 0, "Stack underflow");
+
+        self.len = self.len.wrapping_sub(1);
+        self.values[self.len & Self::MASK]
+    }
+
+    /// Returns the values on the stack, bottom first
+    #[inline]
+    pub(crate) fn values(&self) -> &[u32] {
+        &self.values[..self.len]
+    }
+}
+
+impl<const N: usize> Default for SmallStack<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_stack() {
+        let mut stack = SmallStack::<4>::new();
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        stack.push(4);
+
+        assert_eq!(4, stack.pop());
+        assert_eq!(3, stack.pop());
+
+        stack.push(5);
+
+        assert_eq!(5, stack.pop());
+        assert_eq!(2, stack.pop());
+
+        assert_eq!(&[1], stack.values());
+
+        stack.clear();
+        stack.push(6);
+
+        assert_eq!(&[6], stack.values());
+        assert_eq!(6, stack.pop());
+    }
+
+    #[test]
+    #[should_panic(expected = "Stack overflow")]
+    #[cfg(debug_assertions)]
+    fn test_overflow() {
+        let mut stack = SmallStack::<2>::new();
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+    }
+}
@@ -0,0 +1,332 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for breaking a program down in to steps, each an operation on two
+//! values (a card or the result of an earlier step) giving a new value.
+//!
+//! Steps are returned as structures rather than formatted strings so callers can lay them out
+//! themselves. They can optionally be annotated with the values left to work with after each step
+//! and whether the step keeps to the rules. Steps stop at the first step breaking the rules, so
+//! the steps of a program which fails show where it goes wrong.
+
+use std::fmt;
+
+use super::progop::ProgOp;
+use super::{ProgErr, Programs};
+use crate::format::{dimmed, on_blue, symbol_set, NumFormat};
+
+/// A step of a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// Left hand operand
+    pub left: Operand,
+    /// Operator
+    pub op: ProgOp,
+    /// Right hand operand
+    pub right: Operand,
+    /// Result of the step. Zero if the step breaks the rules and has no whole number result
+    pub result: u32,
+    /// Annotation of the step if requested
+    pub annotation: Option<StepAnnotation>,
+}
+
+/// An operand of a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    /// Value of the operand
+    pub value: u32,
+    /// Index of the card if the operand is a card rather than the result of an earlier step
+    pub card: Option<u8>,
+}
+
+/// Annotation of a step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepAnnotation {
+    /// Values left to work with after the step, the cards not used yet and the results not used
+    /// yet (including the result of this step), largest first
+    pub remaining: Vec<u32>,
+    /// Why the step breaks the rules, or None if the step is valid. Multiplying or dividing by 1
+    /// is pointless but valid
+    pub error: Option<ProgErr>,
+}
+
+impl Step {
+    /// Returns true unless the step is annotated as breaking the rules
+    pub fn is_valid(&self) -> bool {
+        self.annotation
+            .as_ref()
+            .is_none_or(|annotation| annotation.error.is_none())
+    }
+
+    /// Formats the operation of the step without the result, eg. '75 × 4'
+    pub fn calculation(&self, colour: bool) -> String {
+        let op = self.op.symbol_in(symbol_set()).unwrap();
+
+        format!(
+            "{} {} {}",
+            self.left.format(colour),
+            if colour { dimmed(op) } else { op.to_string() },
+            self.right.format(colour)
+        )
+    }
+
+    /// Formats the step as 'a op b = result', colouring the cards and operator if required
+    pub fn format(&self, colour: bool) -> String {
+        let equals = if colour { dimmed("=") } else { "=".to_string() };
+
+        format!(
+            "{} {equals} {}",
+            self.calculation(colour),
+            self.result.num_format()
+        )
+    }
+
+    /// Returns the step as a JSON object
+    pub fn to_json(&self) -> String {
+        let operand = |operand: &Operand| match operand.card {
+            Some(card) => format!("{{\"value\":{},\"card\":{card}}}", operand.value),
+            None => format!("{{\"value\":{}}}", operand.value),
+        };
+
+        let annotation = match &self.annotation {
+            Some(annotation) => format!(
+                ",\"remaining\":[{}],\"valid\":{}",
+                annotation
+                    .remaining
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                annotation.error.is_none()
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            "{{\"left\":{},\"op\":\"{}\",\"right\":{},\"result\":{}{annotation}}}",
+            operand(&self.left),
+            self.op.symbol_in(symbol_set()).unwrap(),
+            operand(&self.right),
+            self.result
+        )
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(false))
+    }
+}
+
+impl Operand {
+    /// Formats the operand, colouring a card if required
+    fn format(&self, colour: bool) -> String {
+        let value = self.value.num_format();
+
+        if colour && self.card.is_some() {
+            on_blue(&value)
+        } else {
+            value
+        }
+    }
+}
+
+impl Programs {
+    /// Returns the steps of a program for a given set of numbers, optionally annotated with the
+    /// values left after each step and whether the step keeps to the rules. Steps stop after the
+    /// first step breaking the rules
+    pub fn structured_steps(&self, prog_elem: usize, numbers: &[u32], annotate: bool) -> Vec<Step> {
+        let instructions = self.instructions(prog_elem);
+
+        let mut steps = Vec::with_capacity(numbers.len());
+        let mut stack: Vec<Operand> = Vec::with_capacity(numbers.len());
+
+        // Cards not used yet
+        let mut unused = (0..numbers.len() as u8).collect::<Vec<_>>();
+
+        for op in instructions {
+            if op.is_number() {
+                stack.push(Operand {
+                    value: numbers[op.bits() as usize],
+                    card: Some(op.bits()),
+                });
+                continue;
+            }
+
+            let right = stack.pop().unwrap();
+            let left = stack.pop().unwrap();
+            let op = *op & ProgOp::PROG_OP_MASK;
+
+            let (result, error) = match run_step(left.value, op, right.value) {
+                Ok(result) => (result, None),
+                Err(e) => (0, Some(e)),
+            };
+
+            let annotation = annotate.then(|| {
+                for operand in [left, right] {
+                    if let Some(card) = operand.card {
+                        unused.retain(|&n| n != card);
+                    }
+                }
+
+                // Cards first so equal values keep a stable order, then the results on the stack
+                let mut remaining = unused
+                    .iter()
+                    .map(|&n| numbers[n as usize])
+                    .chain(
+                        stack
+                            .iter()
+                            .filter(|operand| operand.card.is_none())
+                            .map(|operand| operand.value),
+                    )
+                    .collect::<Vec<_>>();
+
+                if error.is_none() {
+                    remaining.push(result);
+                }
+
+                remaining.sort_unstable_by(|a, b| b.cmp(a));
+
+                StepAnnotation { remaining, error }
+            });
+
+            steps.push(Step {
+                left,
+                op,
+                right,
+                result,
+                annotation,
+            });
+
+            if error.is_some() {
+                break;
+            }
+
+            stack.push(Operand {
+                value: result,
+                card: None,
+            });
+        }
+
+        steps
+    }
+}
+
+/// Carries out the operation of a step, returning the result or why the step breaks the rules
+fn run_step(left: u32, op: ProgOp, right: u32) -> Result<u32, ProgErr> {
+    let result = match op {
+        ProgOp::PROG_OP_ADD => left.checked_add(right).ok_or(ProgErr::Overflow)?,
+        ProgOp::PROG_OP_SUB => {
+            if left < right {
+                return Err(ProgErr::Negative);
+            }
+
+            left - right
+        }
+        ProgOp::PROG_OP_MUL => left.checked_mul(right).ok_or(ProgErr::Overflow)?,
+        _ => {
+            if right == 0 {
+                return Err(ProgErr::DivZero);
+            }
+
+            if !left.is_multiple_of(right) {
+                return Err(ProgErr::NonInteger);
+            }
+
+            left / right
+        }
+    };
+
+    if result == 0 {
+        return Err(ProgErr::Zero);
+    }
+
+    Ok(result)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_steps() {
+        let programs: Programs = "0 1 + 2 * 3 -".into();
+        let numbers = [75, 25, 3, 1];
+
+        let steps = programs.structured_steps(0, &numbers, true);
+
+        assert_eq!(
+            vec!["75 + 25 = 100", "100 × 3 = 300", "300 - 1 = 299"],
+            steps.iter().map(|s| s.to_string()).collect::<Vec<_>>()
+        );
+
+        assert_eq!(Some(0), steps[0].left.card);
+        assert_eq!(None, steps[1].left.card);
+        assert_eq!(Some(2), steps[1].right.card);
+
+        let remaining = steps
+            .iter()
+            .map(|s| s.annotation.as_ref().unwrap().remaining.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![vec![100, 3, 1], vec![300, 1], vec![299]], remaining);
+        assert!(steps.iter().all(Step::is_valid));
+
+        assert_eq!(
+            "{\"left\":{\"value\":75,\"card\":0},\"op\":\"+\",\"right\":{\"value\":25,\"card\":1},\"result\":100,\"remaining\":[100,3,1],\"valid\":true}",
+            steps[0].to_json()
+        );
+
+        // Formatted steps match
+        assert_eq!(
+            programs.steps(0, &numbers, false),
+            programs
+                .structured_steps(0, &numbers, false)
+                .iter()
+                .map(|s| s.format(false))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unused_cards() {
+        // Cards not used by the program remain
+        let programs: Programs = "0 2 *".into();
+        let steps = programs.structured_steps(0, &[50, 10, 2], true);
+
+        assert_eq!(
+            vec![100, 10],
+            steps[0].annotation.as_ref().unwrap().remaining
+        );
+    }
+
+    #[test]
+    fn test_invalid_steps() {
+        // Steps stop at 3 - 25
+        let programs: Programs = "0 1 2 - *".into();
+        let numbers = [4, 3, 25];
+
+        let steps = programs.structured_steps(0, &numbers, true);
+
+        assert_eq!(1, steps.len());
+        assert!(!steps[0].is_valid());
+        assert_eq!(
+            Some(ProgErr::Negative),
+            steps[0].annotation.as_ref().unwrap().error
+        );
+        assert_eq!("3 - 25", steps[0].calculation(false));
+
+        // Unannotated steps aren't marked
+        let steps = programs.structured_steps(0, &numbers, false);
+
+        assert!(steps[0].is_valid());
+
+        // Dividing by 1 is valid
+        let programs: Programs = "0 1 /".into();
+        let steps = programs.structured_steps(0, &[7, 1], true);
+
+        assert!(steps[0].is_valid());
+        assert_eq!(7, steps[0].result);
+    }
+}
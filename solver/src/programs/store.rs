@@ -0,0 +1,457 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for saving a collection of programs to a file and loading it again.
+//!
+//! The file holds the program and instruction tables in the layout they have in memory, after a 64
+//! byte header. With the `mmap` feature the file can be memory mapped and the tables used in place
+//! without copying, so processes mapping the same file share its pages.
+//!
+//! Files aren't trusted: the sizes in the header are checked against the file size without
+//! overflowing, every program must lie within the instruction table and be valid RPN using only the
+//! cards in the file, and the fingerprint is calculated again and compared with the header.
+//!
+//! Header layout (little endian):
+//!  * 0: magic `CDPROGS\0`
+//!  * 8: program order version (u32)
+//!  * 12: number of cards (u8), 13: duplicates included (u8)
+//!  * 16: fingerprint (u64)
+//!  * 24: number of programs (u64)
+//!  * 32: number of instructions (u64)
+//!
+//! The program table (start and end instruction element of each program, u32 each) follows the
+//! header, then the instructions (one byte each).
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Deref;
+use std::path::Path;
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+use super::fingerprint::PROGRAM_ORDER_VERSION;
+use super::{ProgInstr, ProgOp, Programs};
+use crate::board::validate_rpn;
+
+/// File magic number
+const MAGIC: &[u8; 8] = b"CDPROGS\0";
+
+/// Size of the file header
+const HEADER_SIZE: usize = 64;
+
+/// Size of each entry in the program table
+const PROG_INSTR_SIZE: usize = 8;
+
+// The file layout must match the memory layout of the tables
+const _: () = {
+    assert!(std::mem::size_of::<ProgInstr>() == PROG_INSTR_SIZE);
+    assert!(std::mem::align_of::<ProgInstr>() == 4);
+    assert!(std::mem::size_of::<ProgOp>() == 1);
+};
+
+/// Table of program pointers or instructions, either owned or held in a memory mapped file
+pub(crate) enum Table<T> {
+    Owned(Vec<T>),
+    #[cfg(feature = "mmap")]
+    Mapped {
+        map: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl<T> Deref for Table<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        match self {
+            Table::Owned(vec) => vec,
+            #[cfg(feature = "mmap")]
+            Table::Mapped { map, offset, len } => {
+                // SAFETY: load_mapped checks the table lies within the map and is aligned for T,
+                // and T is only ProgInstr or ProgOp, for which any bit pattern is valid
+                unsafe { std::slice::from_raw_parts(map.as_ptr().add(*offset) as *const T, *len) }
+            }
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Table<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Clone> Clone for Table<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Table::Owned(vec) => Table::Owned(vec.clone()),
+            #[cfg(feature = "mmap")]
+            Table::Mapped { map, offset, len } => Table::Mapped {
+                map: map.clone(),
+                offset: *offset,
+                len: *len,
+            },
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Table<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Table::Owned(vec)
+    }
+}
+
+/// Details from a file header
+struct Header {
+    nums: u8,
+    inc_duplicated: bool,
+    fingerprint: u64,
+    programs: u64,
+    instructions: u64,
+}
+
+impl Header {
+    /// Parses a file header, checking it holds the expected programs
+    fn parse(bytes: &[u8], nums: u8, inc_duplicated: bool) -> io::Result<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..8] != MAGIC {
+            Err(invalid("Not a programs file"))?
+        }
+
+        let u32_at = |pos: usize| u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+        let u64_at = |pos: usize| u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap());
+
+        if u32_at(8) != PROGRAM_ORDER_VERSION {
+            Err(invalid(
+                "Programs file has a different program order version",
+            ))?
+        }
+
+        let header = Self {
+            nums: bytes[12],
+            inc_duplicated: bytes[13] != 0,
+            fingerprint: u64_at(16),
+            programs: u64_at(24),
+            instructions: u64_at(32),
+        };
+
+        if header.nums != nums || header.inc_duplicated != inc_duplicated {
+            Err(invalid("Programs file holds different programs"))?
+        }
+
+        Ok(header)
+    }
+
+    /// Returns the number of programs and instructions, checking the file size matches
+    fn table_sizes(&self, file_size: usize) -> io::Result<(usize, usize)> {
+        let sizes = usize::try_from(self.programs)
+            .ok()
+            .zip(usize::try_from(self.instructions).ok())
+            .filter(|&(programs, instructions)| {
+                programs
+                    .checked_mul(PROG_INSTR_SIZE)
+                    .and_then(|size| size.checked_add(HEADER_SIZE))
+                    .and_then(|size| size.checked_add(instructions))
+                    == Some(file_size)
+            });
+
+        sizes.ok_or_else(|| invalid("Programs file is the wrong size"))
+    }
+}
+
+impl Programs {
+    /// Returns the file name to save programs for a number of cards and duplicate setting under,
+    /// so processes sharing a directory of saved programs use the same files
+    pub fn file_name(nums: u8, inc_duplicated: bool) -> String {
+        format!(
+            "programs-{nums}-{}.bin",
+            if inc_duplicated { "C" } else { "NC" }
+        )
+    }
+
+    /// Saves the programs to a file. The file is written under a temporary name and renamed so
+    /// processes loading the file never see it partly written
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+
+        let mut header = [0u8; HEADER_SIZE];
+
+        header[0..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&PROGRAM_ORDER_VERSION.to_le_bytes());
+        header[12] = self.nums;
+        header[13] = self.inc_duplicated as u8;
+        header[16..24].copy_from_slice(&self.fingerprint().to_le_bytes());
+        header[24..32].copy_from_slice(&(self.programs.len() as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&(self.instructions.len() as u64).to_le_bytes());
+
+        writer.write_all(&header)?;
+
+        for program in self.programs.iter() {
+            writer.write_all(&program.start.to_le_bytes())?;
+            writer.write_all(&program.end.to_le_bytes())?;
+        }
+
+        writer.write_all(
+            &self
+                .instructions
+                .iter()
+                .map(|op| op.bits())
+                .collect::<Vec<_>>(),
+        )?;
+
+        writer.into_inner()?.sync_all()?;
+
+        fs::rename(tmp, path)
+    }
+
+    /// Loads programs saved by save, reading them in to memory. Returns an error of kind
+    /// InvalidData if the file doesn't hold programs for the number of cards and duplicate setting
+    pub fn load(path: &Path, nums: u8, inc_duplicated: bool) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        let header = Header::parse(&bytes, nums, inc_duplicated)?;
+        let (program_count, _) = header.table_sizes(bytes.len())?;
+
+        let instructions_start = HEADER_SIZE + (program_count * PROG_INSTR_SIZE);
+
+        let programs = bytes[HEADER_SIZE..instructions_start]
+            .chunks_exact(PROG_INSTR_SIZE)
+            .map(|chunk| ProgInstr {
+                start: u32::from_le_bytes(chunk[0..4].try_into().unwrap()),
+                end: u32::from_le_bytes(chunk[4..8].try_into().unwrap()),
+            })
+            .collect::<Vec<_>>();
+
+        let instructions = bytes[instructions_start..]
+            .iter()
+            .map(|&bits| ProgOp::from_bits_retain(bits))
+            .collect::<Vec<_>>();
+
+        Self::from_tables(programs.into(), instructions.into(), &header)
+    }
+
+    /// Loads programs saved by save by memory mapping the file. The tables are used in place, so
+    /// nothing is copied and processes mapping the same file share the memory. The file must not
+    /// be modified while it is mapped (save replaces the file rather than modifying it).
+    /// Returns an error of kind InvalidData if the file doesn't hold programs for the number of
+    /// cards and duplicate setting
+    #[cfg(feature = "mmap")]
+    pub fn load_mapped(path: &Path, nums: u8, inc_duplicated: bool) -> io::Result<Self> {
+        if cfg!(target_endian = "big") {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Programs files can't be mapped on big endian machines",
+            ))?
+        }
+
+        let file = File::open(path)?;
+
+        // SAFETY: the file is only replaced, never modified, by save
+        let map = Arc::new(unsafe { Mmap::map(&file)? });
+
+        let header = Header::parse(&map, nums, inc_duplicated)?;
+        let (program_count, instruction_count) = header.table_sizes(map.len())?;
+
+        // The map is page aligned and the header size is a multiple of the program table alignment
+        let instructions_start = HEADER_SIZE + (program_count * PROG_INSTR_SIZE);
+
+        let programs = Table::Mapped {
+            map: map.clone(),
+            offset: HEADER_SIZE,
+            len: program_count,
+        };
+
+        let instructions = Table::Mapped {
+            map,
+            offset: instructions_start,
+            len: instruction_count,
+        };
+
+        Self::from_tables(programs, instructions, &header)
+    }
+
+    /// Creates a programs collection from loaded tables, checking every program is valid and the
+    /// fingerprint matches the header
+    fn from_tables(
+        programs: Table<ProgInstr>,
+        instructions: Table<ProgOp>,
+        header: &Header,
+    ) -> io::Result<Self> {
+        check_tables(&programs, &instructions, header.nums)?;
+
+        let programs = Self {
+            programs,
+            instructions,
+            nums: header.nums,
+            inc_duplicated: header.inc_duplicated,
+            fingerprint: OnceLock::new(),
+            skeletons: OnceLock::new(),
+        };
+
+        if programs.fingerprint() != header.fingerprint {
+            Err(invalid(
+                "Programs file fingerprint doesn't match its programs",
+            ))?
+        }
+
+        Ok(programs)
+    }
+}
+
+/// Checks every program lies within the instruction table and is valid RPN using operators and
+/// cards less than the number of cards
+fn check_tables(programs: &[ProgInstr], instructions: &[ProgOp], nums: u8) -> io::Result<()> {
+    for program in programs {
+        let program = instructions
+            .get(program.start as usize..=program.end as usize)
+            .ok_or_else(|| invalid("Programs file has a program outside the instructions"))?;
+
+        let valid_op = |op: &ProgOp| {
+            if op.is_number() {
+                op.bits() < nums
+            } else {
+                ProgOp::OPERATORS.contains(op)
+            }
+        };
+
+        if !program.iter().all(valid_op) || validate_rpn(program).is_err() {
+            Err(invalid("Programs file has an invalid program"))?
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates an InvalidData error
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let path = env::temp_dir().join(format!("programs-test-{}.bin", std::process::id()));
+
+        let programs = Programs::new(3, false, false);
+
+        programs.save(&path).unwrap();
+
+        let loaded = Programs::load(&path, 3, false).unwrap();
+
+        assert_eq!(programs.len(), loaded.len());
+        assert_eq!(programs.fingerprint(), loaded.fingerprint());
+
+        for i in 0..programs.len() {
+            assert_eq!(programs.instructions(i), loaded.instructions(i));
+        }
+
+        #[cfg(feature = "mmap")]
+        {
+            let mapped = Programs::load_mapped(&path, 3, false).unwrap();
+
+            assert_eq!(programs.len(), mapped.len());
+
+            for i in 0..programs.len() {
+                assert_eq!(programs.instructions(i), mapped.instructions(i));
+            }
+
+            assert_eq!(
+                programs.run_all(&[100, 7, 3]).solutions.len(),
+                mapped.run_all(&[100, 7, 3]).solutions.len()
+            );
+        }
+
+        // The file holds programs for 3 cards without duplicates
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            Programs::load(&path, 4, false).err().unwrap().kind()
+        );
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            Programs::load(&path, 3, true).err().unwrap().kind()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let path = env::temp_dir().join(format!("programs-invalid-{}.bin", std::process::id()));
+
+        let programs = Programs::new(2, false, false);
+
+        programs.save(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+
+        let load_err = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+
+            let err = Programs::load(&path, 2, false).err().unwrap();
+
+            #[cfg(feature = "mmap")]
+            assert_eq!(
+                err.to_string(),
+                Programs::load_mapped(&path, 2, false)
+                    .err()
+                    .unwrap()
+                    .to_string()
+            );
+
+            err.to_string()
+        };
+
+        // Program count wrapping the file size around
+        let mut wrapped = bytes.clone();
+        let programs_count = u64::from_le_bytes(wrapped[24..32].try_into().unwrap());
+        wrapped[24..32].copy_from_slice(&(programs_count + (1 << 61)).to_le_bytes());
+
+        assert_eq!("Programs file is the wrong size", load_err(&wrapped));
+
+        // Program ending past the instructions
+        let mut outside = bytes.clone();
+        outside[HEADER_SIZE + 4..HEADER_SIZE + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert_eq!(
+            "Programs file has a program outside the instructions",
+            load_err(&outside)
+        );
+
+        // Card index past the number of cards
+        let mut card = bytes.clone();
+        let first = HEADER_SIZE + programs.len() * PROG_INSTR_SIZE;
+        card[first] = 2;
+
+        assert_eq!("Programs file has an invalid program", load_err(&card));
+
+        // Valid program not matching the fingerprint
+        let mut swapped = bytes.clone();
+        swapped[first] = 1;
+
+        assert_eq!(
+            "Programs file fingerprint doesn't match its programs",
+            load_err(&swapped)
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for skipping programs which are equivalent to another program when
+//! the numbers contain duplicates.
+//!
+//! Swapping two equal numbers in a program gives a program with the same answer. Within each group
+//! of equal numbers only programs which use the numbers in index order are run, eg. with the
+//! numbers [10, 10, 3] the program "1 2 + 0 *" is skipped because "0 2 + 1 *" gives the same
+//! answer. A program which uses a later number of a group must also use all of the earlier ones.
+
+use super::ProgOp;
+
+/// Symmetry reduction for a set of numbers containing duplicates
+pub(crate) struct Symmetry {
+    /// Bit mask of the previous equal number for each number (zero if there is none)
+    prev: Vec<u32>,
+}
+
+impl Symmetry {
+    /// Creates the symmetry reduction for a set of numbers. Returns None if there are no duplicates
+    pub(crate) fn new(numbers: &[u32]) -> Option<Self> {
+        assert!(numbers.len() <= 32);
+
+        let prev: Vec<u32> = numbers
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                numbers[..i]
+                    .iter()
+                    .rposition(|m| m == n)
+                    .map_or(0, |j| 1 << j)
+            })
+            .collect();
+
+        prev.iter().any(|&mask| mask != 0).then_some(Self { prev })
+    }
+
+    /// Returns true if the program uses equal numbers out of index order and can be skipped
+    #[inline]
+    pub(crate) fn skip(&self, instructions: &[ProgOp]) -> bool {
+        let mut used = 0;
+
+        for op in instructions {
+            if op.is_number() {
+                let n = op.bits() as usize;
+
+                if used & self.prev[n] != self.prev[n] {
+                    return true;
+                }
+
+                used |= 1 << n;
+            }
+        }
+
+        false
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::super::Programs;
+    use super::*;
+
+    #[test]
+    fn test_no_duplicates() {
+        assert!(Symmetry::new(&[100, 25, 7, 3]).is_none());
+    }
+
+    #[test]
+    fn test_skip() {
+        let symmetry = Symmetry::new(&[10, 10, 3]).unwrap();
+
+        let skip = |rpn: &str| {
+            let programs: Programs = rpn.into();
+
+            symmetry.skip(programs.instructions(0))
+        };
+
+        assert!(!skip("0 2 + 1 *"));
+        assert!(skip("1 2 + 0 *"));
+        assert!(!skip("0 2 +"));
+        assert!(skip("1 2 +"));
+        assert!(!skip("2 0 -"));
+    }
+
+    #[test]
+    fn test_answers() {
+        // Skipping symmetric programs must not lose any answers
+        let programs = Programs::new(4, false, false);
+
+        for numbers in [
+            [10, 10, 3, 2],
+            [75, 5, 5, 1],
+            [7, 7, 7, 100],
+            [4, 4, 2, 2],
+            [1, 1, 1, 1],
+        ] {
+            let all = (0..programs.len())
+                .filter_map(|i| programs.run(i, &numbers).ok())
+                .collect::<BTreeSet<_>>();
+
+            let symmetry = Symmetry::new(&numbers).unwrap();
+
+            let reduced = (0..programs.len())
+                .filter(|&i| !symmetry.skip(programs.instructions(i)))
+                .filter_map(|i| programs.run(i, &numbers).ok())
+                .collect::<BTreeSet<_>>();
+
+            assert_eq!(all, reduced);
+
+            // run_all skips the symmetric programs but finds every answer in range
+            let results = programs.run_all(&numbers);
+
+            assert!(results.symmetric > 0);
+
+            let in_range = all
+                .iter()
+                .filter(|&&ans| (100..=999).contains(&ans))
+                .copied()
+                .collect::<BTreeSet<_>>();
+
+            assert_eq!(
+                in_range,
+                results.solutions.iter().map(|s| s.result).collect()
+            );
+        }
+    }
+}
@@ -0,0 +1,175 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for running a program and recording every value it calculates.
+//!
+//! A trace runs the program with the same checks as the interpreter, so it gives the same answer or
+//! error as Programs::run, and also records the value produced by each instruction, the
+//! instruction which failed and how long the run took. It is slow compared with the interpreter
+//! and is meant for looking in to a single program, eg. a user reported wrong answer.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::progop::ProgOp;
+use super::{ProgErr, Programs};
+
+/// Trace of a program run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Instructions of the program
+    pub instructions: Vec<ProgOp>,
+    /// Value produced by each instruction run, the card value for a number and the result for an
+    /// operator. Only covers the instructions before the failing instruction if the program fails
+    pub values: Vec<u32>,
+    /// Time taken to run the program
+    pub elapsed: Duration,
+}
+
+/// A program run which failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceError {
+    /// Why the program failed
+    pub error: ProgErr,
+    /// Index of the failing instruction
+    pub instruction: usize,
+    /// Trace of the instructions run before the failure
+    pub trace: Trace,
+}
+
+impl Trace {
+    /// Returns the answer of the program, the value produced by the last instruction
+    pub fn answer(&self) -> Option<u32> {
+        if self.values.len() == self.instructions.len() {
+            self.values.last().copied()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the instruction index and result of each operator run
+    pub fn intermediates(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        self.instructions
+            .iter()
+            .zip(&self.values)
+            .enumerate()
+            .filter(|(_, (op, _))| !op.is_number())
+            .map(|(i, (_, &value))| (i, value))
+    }
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (instruction {})", self.error, self.instruction + 1)
+    }
+}
+
+impl From<TraceError> for ProgErr {
+    fn from(e: TraceError) -> Self {
+        e.error
+    }
+}
+
+impl Programs {
+    /// Runs one of the programs with a given set of numbers recording every value calculated. On
+    /// error the failing instruction and the values calculated before it are returned
+    pub fn trace(&self, prog_elem: usize, numbers: &[u32]) -> Result<Trace, TraceError> {
+        let start = Instant::now();
+        let instructions = self.instructions(prog_elem);
+
+        let mut values = Vec::with_capacity(instructions.len());
+        let mut stack = Vec::with_capacity(numbers.len());
+
+        let mut failure = None;
+
+        for (i, op) in instructions.iter().enumerate() {
+            let value = if op.is_number() {
+                numbers[op.bits() as usize]
+            } else {
+                let n1 = stack.pop().unwrap();
+                let n2 = stack.pop().unwrap();
+
+                match Self::run_operator(n2, *op, n1) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        failure = Some((error, i));
+                        break;
+                    }
+                }
+            };
+
+            stack.push(value);
+            values.push(value);
+        }
+
+        let trace = Trace {
+            instructions: instructions.to_vec(),
+            values,
+            elapsed: start.elapsed(),
+        };
+
+        match failure {
+            None => Ok(trace),
+            Some((error, instruction)) => Err(TraceError {
+                error,
+                instruction,
+                trace,
+            }),
+        }
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        // (75 + 25) × 3 - 1
+        let programs: Programs = "0 1 + 2 * 3 -".into();
+        let numbers = [75, 25, 3, 1];
+
+        let trace = programs.trace(0, &numbers).unwrap();
+
+        assert_eq!(vec![75, 25, 100, 3, 300, 1, 299], trace.values);
+        assert_eq!(Some(299), trace.answer());
+        assert_eq!(
+            vec![(2, 100), (4, 300), (6, 299)],
+            trace.intermediates().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_trace_error() {
+        // 3 - 25 fails
+        let programs: Programs = "0 1 2 - *".into();
+
+        let e = programs.trace(0, &[4, 3, 25]).unwrap_err();
+
+        assert_eq!(ProgErr::Negative, e.error);
+        assert_eq!(3, e.instruction);
+        assert_eq!(vec![4, 3, 25], e.trace.values);
+        assert_eq!(None, e.trace.answer());
+        assert_eq!(
+            "An intermediate result is negative (instruction 4)",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn test_trace_matches_run() {
+        let programs = Programs::new(4, true, false);
+
+        for numbers in [[100, 75, 3, 1], [10, 10, 5, 2]] {
+            for i in 0..programs.len() {
+                let trace = programs.trace(i, &numbers);
+
+                assert_eq!(
+                    programs.run(i, &numbers),
+                    trace.map(|t| t.answer().unwrap()).map_err(ProgErr::from)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for verifying duplicate filtering. A collection of programs with
+//! duplicates removed is compared with the collection of all programs by running both for a set of
+//! card assignments:
+//!  * Over-filtering - a value reachable by a program which was removed but not by any retained program
+//!  * Under-filtering - retained programs which give the same results for every assignment
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use itertools::Itertools;
+
+use super::Programs;
+
+/// Results of a duplicate filter verification
+#[derive(Debug, Default)]
+pub struct DedupReport {
+    /// Number of card assignments checked
+    pub assignments: usize,
+    /// Number of programs retained by the duplicate filter
+    pub retained: usize,
+    /// Number of programs removed by the duplicate filter
+    pub removed: usize,
+    /// Values which can only be reached by removed programs.
+    /// Each entry is the card assignment, the value and an example program element from the full set
+    pub over_filtered: Vec<(Vec<u32>, u32, usize)>,
+    /// Groups of retained program elements which give the same results for every card assignment
+    pub under_filtered: Vec<Vec<usize>>,
+}
+
+/// Returns every distinct ordered assignment of card values to the given number of numbers
+pub fn assignments(cards: &[u32], nums: usize) -> Vec<Vec<u32>> {
+    let mut seen = HashSet::new();
+
+    cards
+        .iter()
+        .copied()
+        .permutations(nums)
+        .filter(|perm| seen.insert(perm.clone()))
+        .collect()
+}
+
+impl Programs {
+    /// Verifies this duplicate filtered collection of programs against the collection of all
+    /// programs by running both for every card assignment given
+    pub fn verify_dedup(&self, all: &Programs, assignments: &[Vec<u32>]) -> DedupReport {
+        assert!(self.nums == all.nums);
+
+        // Find removed programs
+        let retained = (0..self.len())
+            .map(|i| self.instructions(i))
+            .collect::<HashSet<_>>();
+
+        let removed = (0..all.len())
+            .filter(|&i| !retained.contains(all.instructions(i)))
+            .collect::<Vec<_>>();
+
+        let mut report = DedupReport {
+            assignments: assignments.len(),
+            retained: self.len(),
+            removed: removed.len(),
+            ..Default::default()
+        };
+
+        // Running hash of each retained program's results
+        let mut hashers = vec![DefaultHasher::new(); self.len()];
+        let mut reachable = HashSet::new();
+
+        for numbers in assignments {
+            reachable.clear();
+
+            for (i, hasher) in hashers.iter_mut().enumerate() {
+                let result = self.run(i, numbers).ok();
+
+                result.hash(hasher);
+
+                if let Some(value) = result {
+                    reachable.insert(value);
+                }
+            }
+
+            // Check all values from removed programs can still be reached
+            for &i in &removed {
+                if let Ok(value) = all.run(i, numbers) {
+                    if reachable.insert(value) {
+                        report.over_filtered.push((numbers.clone(), value, i));
+                    }
+                }
+            }
+        }
+
+        // Group retained programs with the same result hash
+        let mut groups: HashMap<u64, Vec<usize>> = HashMap::new();
+
+        for (i, hasher) in hashers.iter().enumerate() {
+            groups.entry(hasher.finish()).or_default().push(i);
+        }
+
+        // Confirm the groups, discarding programs which never give a result
+        for group in groups.into_values().filter(|g| g.len() > 1) {
+            let results = |i| {
+                assignments
+                    .iter()
+                    .map(|numbers| self.run(i, numbers).ok())
+                    .collect::<Vec<_>>()
+            };
+
+            let mut confirmed: HashMap<Vec<Option<u32>>, Vec<usize>> = HashMap::new();
+
+            for i in group {
+                let results = results(i);
+
+                if results.iter().any(|r| r.is_some()) {
+                    confirmed.entry(results).or_default().push(i);
+                }
+            }
+
+            report
+                .under_filtered
+                .extend(confirmed.into_values().filter(|g| g.len() > 1));
+        }
+
+        report.under_filtered.sort();
+
+        report
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::{ProgInstr, ProgOp};
+
+    fn verify(nums: u8, programs: &Programs) -> DedupReport {
+        let all = Programs::new(nums, true, false);
+
+        programs.verify_dedup(&all, &assignments(&[100, 25, 7, 3, 2, 1], nums as usize))
+    }
+
+    #[test]
+    fn test_canonical() {
+        for nums in 1..=4 {
+            let report = verify(nums, &Programs::new(nums, false, false));
+
+            assert!(report.over_filtered.is_empty(), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn test_filtered() {
+        for nums in 1..=4 {
+            let report = verify(nums, &Programs::new_filtered(nums, false));
+
+            assert!(report.over_filtered.is_empty(), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn test_detects_over_filtering() {
+        // Only addition
+        let programs = Programs::new_with_operators(3, false, vec![ProgOp::PROG_OP_ADD], false);
+
+        let report = verify(3, &programs);
+
+        assert!(!report.over_filtered.is_empty());
+    }
+
+    #[test]
+    fn test_detects_under_filtering() {
+        // 0 + 1 and 1 + 0
+        let programs = Programs {
+            programs: vec![
+                ProgInstr { start: 0, end: 2 },
+                ProgInstr { start: 3, end: 5 },
+            ]
+            .into(),
+            instructions: "0 1 + 1 0 +"
+                .split(' ')
+                .map(|c| match c {
+                    "+" => ProgOp::PROG_OP_ADD,
+                    n => ProgOp::new_number(n.parse().unwrap()),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            nums: 2,
+            inc_duplicated: true,
+            fingerprint: Default::default(),
+            skeletons: Default::default(),
+        };
+
+        let report = verify(2, &programs);
+
+        assert_eq!(vec![vec![0, 1]], report.under_filtered);
+    }
+}
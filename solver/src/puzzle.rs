@@ -0,0 +1,410 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for describing a puzzle (target and cards) and encoding it as a
+//! compact shareable code.
+//!
+//! The code is the URL safe base64 encoding (without padding) of the bytes:
+//!  * Format version (1 or 2)
+//!  * Target (version 1: 16 bits, big endian)
+//!  * Number of cards (version 1: 8 bits)
+//!  * Card values (version 1: 8 bits each)
+//!
+//! Version 2 stores the target, number of cards and card values as LEB128 variable length
+//! integers so large targets and cards can be encoded. It is only used when a puzzle doesn't fit
+//! version 1, so codes for standard puzzles don't change.
+//!
+//! Puzzles can also be dealt deterministically from a seed so that, for example, every player sees
+//! the same puzzle of the day. The target can be weighted by how often each target appears (see
+//! the weights module).
+
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use cards::CardSet;
+
+use crate::rng::SplitMix64;
+use crate::weights::TargetWeights;
+
+/// Puzzle code format version with fixed size fields
+const CODE_VERSION_FIXED: u8 = 1;
+
+/// Puzzle code format version with variable length fields
+const CODE_VERSION_VARIABLE: u8 = 2;
+
+/// Base64 alphabet (URL safe)
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A numbers game puzzle
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Puzzle {
+    /// Target number
+    pub target: u32,
+    /// Card values
+    pub cards: Vec<u32>,
+}
+
+/// Errors generated when decoding a puzzle code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleError {
+    /// The code contains invalid characters or is the wrong length
+    InvalidCode,
+    /// The code was created with an unsupported format version
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleError::InvalidCode => write!(f, "Invalid puzzle code"),
+            PuzzleError::UnsupportedVersion(v) => write!(f, "Unsupported puzzle code version {v}"),
+        }
+    }
+}
+
+impl Error for PuzzleError {}
+
+impl Puzzle {
+    /// Creates a new puzzle
+    pub fn new(target: u32, cards: Vec<u32>) -> Self {
+        Self { target, cards }
+    }
+
+    /// Deals a puzzle from a deck of cards with a target in a range deterministically from a seed.
+    /// The number of big cards (greater than 10) is chosen at random between 0 and 4
+    pub fn from_seed(seed: u64, deck: &[u32], targets: &RangeInclusive<u32>) -> Self {
+        Self::from_seed_weighted(seed, deck, targets, &TargetWeights::uniform())
+    }
+
+    /// Deals a puzzle from a deck of cards with a target in a range picked using target weights
+    /// deterministically from a seed. The number of big cards (greater than 10) is chosen at random
+    /// between 0 and 4
+    pub fn from_seed_weighted(
+        seed: u64,
+        deck: &[u32],
+        targets: &RangeInclusive<u32>,
+        weights: &TargetWeights,
+    ) -> Self {
+        let mut rng = SplitMix64(seed);
+
+        let big_cnt = rng.below(CardSet::new(deck).max_big() + 1);
+
+        Self::deal(&mut rng, deck, big_cnt, targets, weights)
+    }
+
+    /// Deals a puzzle with a given number of big cards (greater than 10) from a deck of cards with a
+    /// target in a range deterministically from a seed
+    pub fn from_seed_with_big(
+        seed: u64,
+        deck: &[u32],
+        big_cnt: usize,
+        targets: &RangeInclusive<u32>,
+    ) -> Self {
+        Self::from_seed_with_big_weighted(seed, deck, big_cnt, targets, &TargetWeights::uniform())
+    }
+
+    /// Deals a puzzle with a given number of big cards (greater than 10) from a deck of cards with a
+    /// target in a range picked using target weights deterministically from a seed
+    pub fn from_seed_with_big_weighted(
+        seed: u64,
+        deck: &[u32],
+        big_cnt: usize,
+        targets: &RangeInclusive<u32>,
+        weights: &TargetWeights,
+    ) -> Self {
+        let mut rng = SplitMix64(seed);
+
+        Self::deal(&mut rng, deck, big_cnt, targets, weights)
+    }
+
+    /// Deals the cards and target
+    fn deal(
+        rng: &mut SplitMix64,
+        deck: &[u32],
+        big_cnt: usize,
+        targets: &RangeInclusive<u32>,
+        weights: &TargetWeights,
+    ) -> Self {
+        let cards = CardSet::new(deck)
+            .deal(big_cnt, |n| rng.below(n))
+            .unwrap_or_else(|e| panic!("{e}"));
+
+        let target = weights.pick(rng, targets);
+
+        Self::new(target, cards)
+    }
+
+    /// Encodes the puzzle as a shareable code
+    pub fn encode(&self) -> String {
+        let mut bytes = Vec::with_capacity(4 + self.cards.len());
+
+        let fixed = (
+            u16::try_from(self.target),
+            u8::try_from(self.cards.len()),
+            self.cards
+                .iter()
+                .map(|&c| u8::try_from(c))
+                .collect::<Result<Vec<_>, _>>(),
+        );
+
+        if let (Ok(target), Ok(card_cnt), Ok(cards)) = fixed {
+            bytes.push(CODE_VERSION_FIXED);
+            bytes.extend_from_slice(&target.to_be_bytes());
+            bytes.push(card_cnt);
+            bytes.extend_from_slice(&cards);
+        } else {
+            bytes.push(CODE_VERSION_VARIABLE);
+            leb128_encode(&mut bytes, self.target);
+            leb128_encode(&mut bytes, self.cards.len() as u32);
+
+            for &card in &self.cards {
+                leb128_encode(&mut bytes, card);
+            }
+        }
+
+        base64_encode(&bytes)
+    }
+
+    /// Decodes a shareable code in to a puzzle
+    pub fn decode(code: &str) -> Result<Self, PuzzleError> {
+        let bytes = base64_decode(code.trim()).ok_or(PuzzleError::InvalidCode)?;
+
+        match bytes.first() {
+            None => Err(PuzzleError::InvalidCode)?,
+            Some(&CODE_VERSION_FIXED) => {
+                if bytes.len() < 4 || bytes.len() != 4 + bytes[3] as usize {
+                    Err(PuzzleError::InvalidCode)?
+                }
+
+                let target = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+
+                Ok(Self::new(
+                    target,
+                    bytes[4..].iter().map(|&c| c as u32).collect(),
+                ))
+            }
+            Some(&CODE_VERSION_VARIABLE) => {
+                let mut rest = &bytes[1..];
+
+                let mut next = || leb128_decode(&mut rest).ok_or(PuzzleError::InvalidCode);
+
+                let target = next()?;
+                let card_cnt = next()?;
+
+                let cards = (0..card_cnt)
+                    .map(|_| next())
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if !rest.is_empty() {
+                    Err(PuzzleError::InvalidCode)?
+                }
+
+                Ok(Self::new(target, cards))
+            }
+            Some(&v) => Err(PuzzleError::UnsupportedVersion(v))?,
+        }
+    }
+}
+
+/// Appends a value as an unsigned LEB128 variable length integer
+fn leb128_encode(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            break;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Reads an unsigned LEB128 variable length integer from the start of a byte slice
+fn leb128_decode(bytes: &mut &[u8]) -> Option<u32> {
+    let mut value: u64 = 0;
+
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+
+        *bytes = rest;
+
+        value |= ((byte & 0x7f) as u64) << shift;
+
+        if byte & 0x80 == 0 {
+            return u32::try_from(value).ok();
+        }
+    }
+
+    None
+}
+
+/// Encodes bytes as unpadded URL safe base64
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | (b[2] as u32);
+
+        for i in 0..=chunk.len() {
+            result.push(BASE64[((n >> (18 - (i * 6))) & 0x3f) as usize] as char);
+        }
+    }
+
+    result
+}
+
+/// Decodes unpadded URL safe base64
+fn base64_decode(code: &str) -> Option<Vec<u8>> {
+    let values = code
+        .bytes()
+        .map(|c| BASE64.iter().position(|&b| b == c).map(|p| p as u32))
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut result = Vec::with_capacity((values.len() * 3) / 4);
+
+    for chunk in values.chunks(4) {
+        if chunk.len() == 1 {
+            // Not a valid length
+            None?
+        }
+
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, v)| n | (v << (18 - (i * 6))));
+
+        for i in 0..(chunk.len() - 1) {
+            result.push((n >> (16 - (i * 8))) as u8);
+        }
+    }
+
+    Some(result)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let puzzle = Puzzle::new(947, vec![100, 75, 5, 4, 3, 2]);
+
+        let code = puzzle.encode();
+
+        assert_eq!("AQOzBmRLBQQDAg", code);
+        assert_eq!(Ok(puzzle), Puzzle::decode(&code));
+    }
+
+    #[test]
+    fn test_round_trip_large() {
+        let puzzle = Puzzle::new(12_345, vec![1000, 250, 100, 7, 3, 1]);
+
+        let code = puzzle.encode();
+
+        assert_eq!(
+            Some(CODE_VERSION_VARIABLE),
+            base64_decode(&code).unwrap()[0].into()
+        );
+        assert_eq!(Ok(puzzle), Puzzle::decode(&code));
+
+        let puzzle = Puzzle::new(u32::MAX, vec![u32::MAX, 0]);
+
+        assert_eq!(Ok(puzzle.clone()), Puzzle::decode(&puzzle.encode()));
+    }
+
+    #[test]
+    fn test_from_seed() {
+        let deck = [
+            100, 75, 50, 25, 10, 10, 9, 9, 8, 8, 7, 7, 6, 6, 5, 5, 4, 4, 3, 3, 2, 2, 1, 1,
+        ];
+
+        for seed in 0..100 {
+            let puzzle = Puzzle::from_seed(seed, &deck, &(100..=999));
+
+            assert_eq!(puzzle, Puzzle::from_seed(seed, &deck, &(100..=999)));
+            assert_eq!(6, puzzle.cards.len());
+            assert!((100..=999).contains(&puzzle.target));
+            assert!(puzzle.cards.iter().filter(|&&c| c > 10).count() <= 4);
+        }
+
+        // Output must be stable
+        assert_eq!(
+            Puzzle::new(540, vec![10, 1, 2, 3, 5, 6]),
+            Puzzle::from_seed(0, &deck, &(100..=999))
+        );
+    }
+
+    #[test]
+    fn test_from_seed_with_big() {
+        let deck = [
+            100, 75, 50, 25, 10, 10, 9, 9, 8, 8, 7, 7, 6, 6, 5, 5, 4, 4, 3, 3, 2, 2, 1, 1,
+        ];
+
+        for big_cnt in 0..=4 {
+            for seed in 0..20 {
+                let puzzle = Puzzle::from_seed_with_big(seed, &deck, big_cnt, &(100..=500));
+
+                assert_eq!(6, puzzle.cards.len());
+                assert_eq!(big_cnt, puzzle.cards.iter().filter(|&&c| c > 10).count());
+                assert!((100..=500).contains(&puzzle.target));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_seed_weighted() {
+        let deck = [
+            100, 75, 50, 25, 10, 10, 9, 9, 8, 8, 7, 7, 6, 6, 5, 5, 4, 4, 3, 3, 2, 2, 1, 1,
+        ];
+
+        let weights = TargetWeights::from_counts([123, 456]);
+
+        for seed in 0..20 {
+            let puzzle = Puzzle::from_seed_weighted(seed, &deck, &(100..=999), &weights);
+
+            assert!([123, 456].contains(&puzzle.target));
+
+            // The cards dealt don't depend on the weights
+            assert_eq!(
+                Puzzle::from_seed(seed, &deck, &(100..=999)).cards,
+                puzzle.cards
+            );
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        for len in 0..10 {
+            let bytes = (0..len).map(|b| (b * 37) as u8).collect::<Vec<_>>();
+
+            assert_eq!(Some(bytes.clone()), base64_decode(&base64_encode(&bytes)));
+        }
+
+        assert_eq!("TWFu", base64_encode(b"Man"));
+        assert_eq!("TWE", base64_encode(b"Ma"));
+        assert_eq!("TQ", base64_encode(b"M"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Err(PuzzleError::InvalidCode), Puzzle::decode(""));
+        assert_eq!(Err(PuzzleError::InvalidCode), Puzzle::decode("AQ*"));
+        assert_eq!(Err(PuzzleError::InvalidCode), Puzzle::decode("AQOz"));
+        assert_eq!(
+            Err(PuzzleError::UnsupportedVersion(3)),
+            Puzzle::decode("AwOzBmRLBQQDAg")
+        );
+        assert_eq!(Err(PuzzleError::InvalidCode), Puzzle::decode("AtIH"));
+    }
+}
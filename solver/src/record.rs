@@ -0,0 +1,235 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for recording games so they can be replayed and reviewed later.
+//!
+//! A game record is stored as JSON. Every record carries a format version so older records can
+//! still be read when fields are added: new fields must be optional (default when missing) and
+//! any change which would stop an older record being read must increase the version.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Ruleset;
+
+/// Current game record format version
+pub const RECORD_VERSION: u32 = 1;
+
+/// Record of a game of one or more rounds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// Format version
+    pub version: u32,
+    /// Rules the game was played with
+    #[serde(default)]
+    pub rules: Ruleset,
+    /// Rounds played
+    pub rounds: Vec<RoundRecord>,
+}
+
+/// Record of one round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundRecord {
+    /// Target number
+    pub target: u32,
+    /// Card values
+    pub cards: Vec<u32>,
+    /// Number of seconds allowed to find an answer
+    pub time_limit: u64,
+    /// Player's answer, if one was given
+    pub player: Option<AnswerRecord>,
+    /// Computer opponent's answer, if there was an opponent and it found an answer
+    #[serde(default)]
+    pub opponent: Option<AnswerRecord>,
+    /// Points scored by the player
+    pub player_score: u32,
+    /// Points scored by the opponent
+    #[serde(default)]
+    pub opponent_score: u32,
+    /// Solver analysis of the round
+    pub analysis: Analysis,
+}
+
+/// Record of an answer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnswerRecord {
+    /// Answer as entered
+    pub answer: String,
+    /// Value of the answer, None if the answer was invalid
+    pub value: Option<u32>,
+    /// Reason the answer was invalid
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Time taken to answer in milliseconds, if known
+    #[serde(default)]
+    pub time_ms: Option<u64>,
+}
+
+/// Solver analysis of a round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Analysis {
+    /// Number of equations reaching the target
+    pub solutions: usize,
+    /// Value of the best answer, if any answer is in range
+    pub best_value: Option<u32>,
+    /// Best (closest then shortest) equation
+    pub best: Option<String>,
+}
+
+/// Errors generated when reading a game record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// The record is not valid JSON or is missing fields
+    Invalid(String),
+    /// The record was created with an unsupported format version
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Invalid(e) => write!(f, "Invalid game record ({e})"),
+            RecordError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported game record version {v}")
+            }
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+/// Used to read the version before the rest of the record
+#[derive(Deserialize)]
+struct RecordVersion {
+    version: u32,
+}
+
+impl GameRecord {
+    /// Creates a new empty game record
+    pub fn new() -> Self {
+        Self {
+            version: RECORD_VERSION,
+            rules: Ruleset::Tv,
+            rounds: Vec::new(),
+        }
+    }
+
+    /// Returns the record as pretty printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Game record serialisation failed")
+    }
+
+    /// Reads a record from JSON
+    pub fn from_json(json: &str) -> Result<Self, RecordError> {
+        // Check the version first so a newer record gives a sensible error
+        let version = serde_json::from_str::<RecordVersion>(json)
+            .map_err(|e| RecordError::Invalid(e.to_string()))?
+            .version;
+
+        if version == 0 || version > RECORD_VERSION {
+            Err(RecordError::UnsupportedVersion(version))?
+        }
+
+        serde_json::from_str(json).map_err(|e| RecordError::Invalid(e.to_string()))
+    }
+}
+
+impl Default for GameRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_record() -> GameRecord {
+        let mut record = GameRecord::new();
+
+        record.rounds.push(RoundRecord {
+            target: 952,
+            cards: vec![100, 75, 50, 25, 6, 3],
+            time_limit: 30,
+            player: Some(AnswerRecord {
+                answer: "100 * 9".to_string(),
+                value: None,
+                error: Some("Invalid number 9".to_string()),
+                time_ms: Some(12_345),
+            }),
+            opponent: Some(AnswerRecord {
+                answer: "((100 + 6) × 3 × 75 - 50) / 25".to_string(),
+                value: Some(952),
+                error: None,
+                time_ms: None,
+            }),
+            player_score: 0,
+            opponent_score: 10,
+            analysis: Analysis {
+                solutions: 16,
+                best_value: Some(952),
+                best: Some("((100 + 6) × 3 × 75 - 50) / 25".to_string()),
+            },
+        });
+
+        record
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut record = test_record();
+
+        assert_eq!(Ok(record.clone()), GameRecord::from_json(&record.to_json()));
+
+        record.rules = Ruleset::Junior;
+
+        assert!(record.to_json().contains("\"rules\": \"junior\""));
+        assert_eq!(Ok(record.clone()), GameRecord::from_json(&record.to_json()));
+    }
+
+    #[test]
+    fn test_optional_fields() {
+        let json = r#"{
+            "version": 1,
+            "rounds": [{
+                "target": 500,
+                "cards": [100, 5],
+                "time_limit": 30,
+                "player": {"answer": "100 * 5", "value": 500},
+                "player_score": 10,
+                "analysis": {"solutions": 1, "best_value": 500, "best": "100 × 5"}
+            }]
+        }"#;
+
+        let record = GameRecord::from_json(json).unwrap();
+
+        assert_eq!(None, record.rounds[0].opponent);
+        assert_eq!(None, record.rounds[0].player.as_ref().unwrap().time_ms);
+        assert_eq!(Ruleset::Tv, record.rules);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut record = test_record();
+
+        record.version = RECORD_VERSION + 1;
+
+        assert_eq!(
+            Err(RecordError::UnsupportedVersion(RECORD_VERSION + 1)),
+            GameRecord::from_json(&record.to_json())
+        );
+
+        assert!(matches!(
+            GameRecord::from_json("{\"version\": 1}"),
+            Err(RecordError::Invalid(_))
+        ));
+
+        assert!(matches!(
+            GameRecord::from_json("not json"),
+            Err(RecordError::Invalid(_))
+        ));
+    }
+}
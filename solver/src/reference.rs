@@ -0,0 +1,158 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for checking the programs against a reference solver. The reference
+//! solver is deliberately naive and shares no code with program generation, duplicate filtering
+//! or the interpreter: it repeatedly replaces any two of the remaining values with the result of
+//! combining them, recording every value seen. It is only practical for a few cards.
+//!
+//! Results follow the TV rules: every intermediate result must be a positive integer which fits in
+//! 32 bits.
+
+use std::collections::HashSet;
+
+use crate::expression::evaluate;
+use crate::programs::Programs;
+
+/// Returns every value which can be made from the numbers with the TV rules, using each number at
+/// most once
+pub fn reference_reachable(numbers: &[u32]) -> HashSet<u32> {
+    let mut reachable = HashSet::new();
+
+    reach(numbers, &mut reachable);
+
+    reachable
+}
+
+/// Records the values and recurses for every way of combining two of them
+fn reach(values: &[u32], reachable: &mut HashSet<u32>) {
+    reachable.extend(values.iter().copied());
+
+    for i in 0..values.len() {
+        for j in 0..values.len() {
+            if i == j {
+                continue;
+            }
+
+            let (a, b) = (values[i], values[j]);
+
+            let results = [
+                // Addition and multiplication are commutative so only try them once
+                if i < j { a.checked_add(b) } else { None },
+                if i < j { a.checked_mul(b) } else { None },
+                if a > b { Some(a - b) } else { None },
+                if b != 0 && a.is_multiple_of(b) {
+                    Some(a / b)
+                } else {
+                    None
+                },
+            ];
+
+            for result in results.into_iter().flatten() {
+                let mut remaining = values
+                    .iter()
+                    .enumerate()
+                    .filter(|&(k, _)| k != i && k != j)
+                    .map(|(_, &v)| v)
+                    .collect::<Vec<_>>();
+
+                remaining.push(result);
+
+                reach(&remaining, reachable);
+            }
+        }
+    }
+}
+
+/// A difference between the programs and the reference solver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// The reference solver reaches the target but the programs don't
+    Missed(u32),
+    /// The programs give solutions for a target the reference solver can't reach
+    Unexpected(u32),
+    /// A solution for a target doesn't give the target when run or when its equation is evaluated
+    WrongAnswer {
+        /// Target
+        target: u32,
+        /// Program element number
+        program: usize,
+    },
+}
+
+impl Programs {
+    /// Checks run_all_target for each of the targets against the reference solver, and checks each
+    /// solution gives the target when run and when its infix equation is evaluated. Returns the
+    /// discrepancies found
+    pub fn verify_reference(
+        &self,
+        numbers: &[u32],
+        targets: impl IntoIterator<Item = u32>,
+    ) -> Vec<Discrepancy> {
+        let reachable = reference_reachable(numbers);
+        let mut discrepancies = Vec::new();
+
+        for target in targets {
+            let solutions = self.run_all_target(target, numbers);
+
+            match (reachable.contains(&target), solutions.is_empty()) {
+                (true, true) => discrepancies.push(Discrepancy::Missed(target)),
+                (false, false) => discrepancies.push(Discrepancy::Unexpected(target)),
+                _ => (),
+            }
+
+            for solution in solutions {
+                let program = solution.program;
+
+                if solution.result != target
+                    || self.run(program, numbers) != Ok(target)
+                    || evaluate(&self.infix(program, numbers, false), numbers) != Ok(target)
+                {
+                    discrepancies.push(Discrepancy::WrongAnswer { target, program });
+                }
+            }
+        }
+
+        discrepancies
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_reachable() {
+        let reachable = reference_reachable(&[10, 4, 3]);
+
+        for value in [10, 4, 3, 14, 13, 7, 1, 40, 30, 12, 17, 11, 120, 42, 18, 70] {
+            assert!(reachable.contains(&value), "{value} not reachable");
+        }
+
+        // (10 + 4) / 3 isn't a whole number
+        assert!(!reachable.contains(&0));
+        assert!(!reachable.contains(&121));
+
+        // A zero card isn't divided by
+        let reachable = reference_reachable(&[6, 0]);
+
+        assert!(reachable.contains(&6));
+        assert!(reachable.contains(&0));
+    }
+
+    #[test]
+    fn test_verify_reference() {
+        for numbers in [[100, 7, 3, 2], [75, 10, 10, 4]] {
+            for inc_duplicated in [false, true] {
+                let programs = Programs::new(4, inc_duplicated, false);
+
+                assert_eq!(
+                    Vec::<Discrepancy>::new(),
+                    programs.verify_reference(&numbers, 100..=999),
+                    "{numbers:?} duplicates {inc_duplicated}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for running programs with relaxed rules, where intermediate results
+//! may be negative or fractions. Values are held as numerator and denominator pairs in their lowest
+//! terms with a positive denominator. Zero intermediate results and multiplying or dividing by 1
+//! are still rejected, as they never help to reach a target.
+
+use crate::format::{dimmed, NumFormat};
+use crate::programs::ProgOp;
+
+/// A fraction (numerator, denominator)
+pub(crate) type Fraction = (i64, i64);
+
+/// Runs a program with relaxed rules. Returns the answer and the largest intermediate value
+/// (rounded down, zero if there were no positive values) if the answer is a positive integer
+pub(crate) fn run_relaxed(
+    instructions: &[ProgOp],
+    numbers: &[u32],
+    negative: bool,
+    fractions: bool,
+) -> Option<(u32, u32)> {
+    let mut largest = 0;
+
+    let result = process(
+        instructions,
+        numbers,
+        negative,
+        fractions,
+        |_, _, _, (n, d)| {
+            if n > 0 {
+                largest = largest.max(u32::try_from(n / d).unwrap_or(u32::MAX));
+            }
+        },
+    )?;
+
+    match result {
+        (n, 1) if n > 0 => Some((u32::try_from(n).ok()?, largest.max(n as u32))),
+        _ => None,
+    }
+}
+
+/// Returns the formatted steps of a program run with relaxed rules, or None if the program fails
+pub(crate) fn relaxed_steps(
+    instructions: &[ProgOp],
+    numbers: &[u32],
+    negative: bool,
+    fractions: bool,
+    colour: bool,
+) -> Option<Vec<String>> {
+    let mut steps = Vec::new();
+
+    let equals = if colour { dimmed("=") } else { "=".to_string() };
+
+    process(
+        instructions,
+        numbers,
+        negative,
+        fractions,
+        |n2, op, n1, ans| {
+            steps.push(format!(
+                "{} {} {} {equals} {}",
+                format_fraction(n2),
+                op.colour(numbers, colour),
+                format_fraction(n1),
+                format_fraction(ans)
+            ))
+        },
+    )?;
+
+    Some(steps)
+}
+
+/// Formats a fraction, eg. 3, -2 or 5/4
+fn format_fraction((n, d): Fraction) -> String {
+    if d == 1 {
+        n.num_format()
+    } else {
+        format!("{}/{}", n.num_format(), d.num_format())
+    }
+}
+
+/// Runs a program calling a callback with the operands, operator and answer of each step. Returns
+/// the final value or None if a step fails
+pub(crate) fn process<F>(
+    instructions: &[ProgOp],
+    numbers: &[u32],
+    negative: bool,
+    fractions: bool,
+    mut step_cb: F,
+) -> Option<Fraction>
+where
+    F: FnMut(Fraction, ProgOp, Fraction, Fraction),
+{
+    let mut stack: Vec<Fraction> = Vec::with_capacity(numbers.len());
+
+    for op in instructions {
+        if op.is_number() {
+            stack.push((numbers[op.bits() as usize] as i64, 1));
+            continue;
+        }
+
+        let (n1, d1) = stack.pop()?;
+        let (n2, d2) = stack.pop()?;
+
+        let (n, d) = match *op & ProgOp::PROG_OP_MASK {
+            ProgOp::PROG_OP_ADD => (
+                n2.checked_mul(d1)?.checked_add(n1.checked_mul(d2)?)?,
+                d1.checked_mul(d2)?,
+            ),
+            ProgOp::PROG_OP_SUB => (
+                n2.checked_mul(d1)?.checked_sub(n1.checked_mul(d2)?)?,
+                d1.checked_mul(d2)?,
+            ),
+            ProgOp::PROG_OP_MUL => {
+                if (n1 == 1 && d1 == 1) || (n2 == 1 && d2 == 1) {
+                    None?
+                }
+
+                (n2.checked_mul(n1)?, d2.checked_mul(d1)?)
+            }
+            ProgOp::PROG_OP_DIV => {
+                if n1 == 0 || (n1 == 1 && d1 == 1) {
+                    None?
+                }
+
+                (n2.checked_mul(d1)?, d2.checked_mul(n1)?)
+            }
+            _ => unreachable!(),
+        };
+
+        // Normalise so the denominator is positive and the fraction is in its lowest terms
+        let g = gcd(n, d) * d.signum();
+        let (n, d) = (n / g, d / g);
+
+        if n == 0 || (n < 0 && !negative) || (d != 1 && !fractions) {
+            None?
+        }
+
+        step_cb((n2, d2), *op, (n1, d1), (n, d));
+
+        stack.push((n, d));
+    }
+
+    stack.pop()
+}
+
+/// Greatest common divisor of the magnitudes
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::Programs;
+
+    #[test]
+    fn test_run_relaxed() {
+        let numbers = [75, 6, 2, 1];
+
+        // 75 × (1 + 2 / 6) = 100
+        let programs: Programs = "0 3 2 1 / + *".into();
+        let instructions = programs.instructions(0);
+
+        assert_eq!(
+            Some((100, 100)),
+            run_relaxed(instructions, &numbers, false, true)
+        );
+        assert_eq!(None, run_relaxed(instructions, &numbers, true, false));
+
+        // (2 - 6) × (1 - 75) = 296
+        let programs: Programs = "2 1 - 3 0 - *".into();
+        let instructions = programs.instructions(0);
+
+        assert_eq!(
+            Some((296, 296)),
+            run_relaxed(instructions, &numbers, true, false)
+        );
+        assert_eq!(None, run_relaxed(instructions, &numbers, false, true));
+    }
+
+    #[test]
+    fn test_relaxed_steps() {
+        let programs: Programs = "0 3 2 1 / + *".into();
+
+        assert_eq!(
+            Some(vec![
+                "2 / 6 = 1/3".to_string(),
+                "1 + 1/3 = 4/3".to_string(),
+                "75 × 4/3 = 100".to_string(),
+            ]),
+            relaxed_steps(programs.instructions(0), &[75, 6, 2, 1], false, true, false)
+        );
+    }
+}
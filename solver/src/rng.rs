@@ -0,0 +1,25 @@
+#![warn(missing_docs)]
+
+//! This module contains a small deterministic pseudo random number generator (SplitMix64).
+//! The output for a given seed must never change as shared puzzles and sampled analyses rely on it
+
+/// SplitMix64 pseudo random number generator
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    /// Returns the next 64 bit number
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
@@ -0,0 +1,336 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for the named rule presets a game can be played with:
+//!  * TV - the rules of the television programme. Targets 100 to 999, big cards 100, 75, 50 and 25,
+//!    two each of the small cards 1 to 10, and intermediate results must be positive integers
+//!  * Junior - smaller targets (100 to 500) and big cards (50, 25 and 20) with the TV rules for
+//!    intermediate results
+//!  * Relaxed - the TV targets and cards, but intermediate results may be negative or fractions.
+//!    The answer must still be a positive integer
+//!
+//! Solving with the TV or Junior rules runs the programs as normal. Solving with relaxed rules runs
+//! each program with fractions, so is much slower.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::cancel::{CancellationToken, Cancelled, CANCEL_CHECK_INTERVAL};
+use crate::programs::{Programs, Solution};
+use crate::relaxed::{relaxed_steps, run_relaxed};
+
+/// Small cards (two of each in the deck) in descending order
+const SMALL_CARDS: [u32; 20] = [10, 10, 9, 9, 8, 8, 7, 7, 6, 6, 5, 5, 4, 4, 3, 3, 2, 2, 1, 1];
+
+/// A named rule preset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "record",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Ruleset {
+    /// The rules of the television programme
+    #[default]
+    Tv,
+    /// Smaller targets and cards
+    Junior,
+    /// Negative and fractional intermediate results allowed
+    Relaxed,
+}
+
+/// The rules of a game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    /// Smallest target
+    pub min_target: u32,
+    /// Largest target
+    pub max_target: u32,
+    /// Big cards in the deck in descending order
+    pub big_cards: &'static [u32],
+    /// Small cards in the deck in descending order
+    pub small_cards: &'static [u32],
+    /// Intermediate results may be negative
+    pub negative: bool,
+    /// Intermediate results may be fractions
+    pub fractions: bool,
+}
+
+const TV_RULES: Rules = Rules {
+    min_target: 100,
+    max_target: 999,
+    big_cards: &[100, 75, 50, 25],
+    small_cards: &SMALL_CARDS,
+    negative: false,
+    fractions: false,
+};
+
+const JUNIOR_RULES: Rules = Rules {
+    min_target: 100,
+    max_target: 500,
+    big_cards: &[50, 25, 20],
+    small_cards: &SMALL_CARDS,
+    negative: false,
+    fractions: false,
+};
+
+const RELAXED_RULES: Rules = Rules {
+    negative: true,
+    fractions: true,
+    ..TV_RULES
+};
+
+impl Ruleset {
+    /// All of the rule presets
+    pub const ALL: [Ruleset; 3] = [Ruleset::Tv, Ruleset::Junior, Ruleset::Relaxed];
+
+    /// Returns the rules for the preset
+    pub fn rules(&self) -> &'static Rules {
+        match self {
+            Ruleset::Tv => &TV_RULES,
+            Ruleset::Junior => &JUNIOR_RULES,
+            Ruleset::Relaxed => &RELAXED_RULES,
+        }
+    }
+
+    /// Returns the name of the preset as accepted by from_str
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ruleset::Tv => "tv",
+            Ruleset::Junior => "junior",
+            Ruleset::Relaxed => "relaxed",
+        }
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Ruleset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ruleset::ALL
+            .into_iter()
+            .find(|ruleset| ruleset.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown rules '{s}' (expected one of {})",
+                    Ruleset::ALL.map(|r| r.name()).join(", ")
+                )
+            })
+    }
+}
+
+impl Rules {
+    /// Returns the range of targets
+    pub fn targets(&self) -> RangeInclusive<u32> {
+        self.min_target..=self.max_target
+    }
+
+    /// Returns the full deck of cards in descending order
+    pub fn deck(&self) -> Vec<u32> {
+        self.big_cards
+            .iter()
+            .chain(self.small_cards)
+            .copied()
+            .collect()
+    }
+
+    /// Returns true if intermediate results must be positive integers
+    pub fn is_strict(&self) -> bool {
+        !self.negative && !self.fractions
+    }
+
+    /// Returns a description of the rules
+    pub fn description(&self) -> String {
+        let intermediate = match (self.negative, self.fractions) {
+            (false, false) => "positive integer",
+            (true, false) => "negative or positive integer",
+            (false, true) => "positive integer or fraction",
+            (true, true) => "any non-zero",
+        };
+
+        format!(
+            "targets {}-{}, big cards {}, {intermediate} intermediate results",
+            self.min_target,
+            self.max_target,
+            self.big_cards
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl Programs {
+    /// Runs all of the programs in the programs collection with a given set of numbers under a set
+    /// of rules and returns the solutions for a target
+    pub fn run_target_rules(&self, target: u32, numbers: &[u32], rules: &Rules) -> Vec<Solution> {
+        if rules.is_strict() {
+            return self.run_all_target(target, numbers);
+        }
+
+        self.run_relaxed_range(numbers, target..=target, rules, None)
+            .expect("Not cancellable")
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers under a set
+    /// of rules and returns the solutions for a target, stopping early if the token is cancelled
+    pub fn run_target_rules_cancellable(
+        &self,
+        target: u32,
+        numbers: &[u32],
+        rules: &Rules,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Solution>, Cancelled> {
+        if rules.is_strict() {
+            return self.run_all_target_cancellable(target, numbers, cancel);
+        }
+
+        self.run_relaxed_range(numbers, target..=target, rules, Some(cancel))
+    }
+
+    /// Runs all of the programs in the programs collection with a given set of numbers under a set
+    /// of rules and returns the solutions with answers in a range
+    pub fn run_range_rules(
+        &self,
+        numbers: &[u32],
+        range: RangeInclusive<u32>,
+        rules: &Rules,
+    ) -> Vec<Solution> {
+        if rules.is_strict() {
+            return self.run_all_range(numbers, range).solutions;
+        }
+
+        self.run_relaxed_range(numbers, range, rules, None)
+            .expect("Not cancellable")
+    }
+
+    /// Runs all of the programs with relaxed rules returning the solutions with answers in a range,
+    /// stopping early if the token is cancelled
+    fn run_relaxed_range(
+        &self,
+        numbers: &[u32],
+        range: RangeInclusive<u32>,
+        rules: &Rules,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Solution>, Cancelled> {
+        let mut solutions = Vec::new();
+
+        for i in 0..self.len() {
+            if i % CANCEL_CHECK_INTERVAL == 0 {
+                if let Some(cancel) = cancel {
+                    cancel.check()?;
+                }
+            }
+
+            let instructions = self.instructions(i);
+
+            match run_relaxed(instructions, numbers, rules.negative, rules.fractions) {
+                Some((ans, largest)) if range.contains(&ans) => {
+                    solutions.push(Solution::with_largest(i, instructions, ans, largest))
+                }
+                _ => (),
+            }
+        }
+
+        Ok(solutions)
+    }
+
+    /// Returns the formatted steps of a program for a given set of numbers under a set of rules
+    pub fn steps_rules(
+        &self,
+        prog_elem: usize,
+        numbers: &[u32],
+        colour: bool,
+        rules: &Rules,
+    ) -> Vec<String> {
+        if rules.is_strict() {
+            return self.steps(prog_elem, numbers, colour);
+        }
+
+        relaxed_steps(
+            self.instructions(prog_elem),
+            numbers,
+            rules.negative,
+            rules.fractions,
+            colour,
+        )
+        .expect("Program fails with the rules")
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        for ruleset in Ruleset::ALL {
+            assert_eq!(Ok(ruleset), ruleset.name().parse());
+            let deck = ruleset.rules().deck();
+
+            assert_eq!(ruleset.rules().big_cards.len() + 20, deck.len());
+            assert!(deck.windows(2).all(|w| w[0] >= w[1]));
+        }
+
+        assert_eq!(Ok(Ruleset::Junior), "Junior".parse());
+        assert!("olympic".parse::<Ruleset>().is_err());
+
+        assert!(Ruleset::Tv.rules().is_strict());
+        assert!(!Ruleset::Relaxed.rules().is_strict());
+        assert_eq!(100..=500, Ruleset::Junior.rules().targets());
+    }
+
+    #[test]
+    fn test_run_target_rules() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [75, 6, 2, 1];
+
+        // 75 × (1 + 2 / 6) = 100 needs a fraction
+        let strict = programs.run_target_rules(100, &numbers, Ruleset::Tv.rules());
+        let relaxed = programs.run_target_rules(100, &numbers, Ruleset::Relaxed.rules());
+
+        assert!(strict.is_empty());
+        assert!(!relaxed.is_empty());
+
+        for solution in &relaxed {
+            assert_eq!(100, solution.result);
+
+            let steps =
+                programs.steps_rules(solution.program, &numbers, false, Ruleset::Relaxed.rules());
+
+            assert!(steps.last().unwrap().ends_with("= 100"));
+        }
+
+        // Everything reachable with the TV rules is reachable with relaxed rules
+        let strict = programs.run_target_rules(156, &numbers, Ruleset::Tv.rules());
+        let relaxed = programs.run_target_rules(156, &numbers, Ruleset::Relaxed.rules());
+
+        assert!(!strict.is_empty());
+        assert!(relaxed.len() >= strict.len());
+    }
+
+    #[test]
+    fn test_run_range_rules() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [75, 6, 2, 1];
+
+        let strict = programs.run_range_rules(&numbers, 100..=999, Ruleset::Tv.rules());
+        let relaxed = programs.run_range_rules(&numbers, 100..=999, Ruleset::Relaxed.rules());
+
+        assert_eq!(
+            programs.run_all_range(&numbers, 100..=999).solutions,
+            strict
+        );
+        assert!(relaxed.len() > strict.len());
+        assert!(relaxed.iter().all(|s| (100..=999).contains(&s.result)));
+    }
+}
@@ -0,0 +1,108 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for scoring a numbers round following the official rules:
+//!  * 10 points for reaching the target exactly
+//!  * 7 points for being 1 to 5 away from the target
+//!  * 5 points for being 6 to 10 away from the target
+//!  * No points for being further away
+//!
+//! Only the contestant closest to the target scores. If both are equally close both score.
+//! A declaration which fails validation scores nothing and doesn't stop the other contestant scoring.
+
+/// A value declared by a contestant at the end of a numbers round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Declaration {
+    /// The value declared
+    pub value: u32,
+    /// True if the method given for the declared value is valid
+    pub valid: bool,
+}
+
+impl Declaration {
+    /// Creates a new declaration
+    pub fn new(value: u32, valid: bool) -> Self {
+        Self { value, valid }
+    }
+}
+
+/// Returns the points available for a value declared for a target
+pub fn points(target: u32, value: u32) -> u32 {
+    match target.abs_diff(value) {
+        0 => 10,
+        1..=5 => 7,
+        6..=10 => 5,
+        _ => 0,
+    }
+}
+
+/// Scores a numbers round for two contestants, returning the points for each.
+/// A contestant who doesn't declare is passed as None
+pub fn score(
+    target: u32,
+    contestant1: Option<Declaration>,
+    contestant2: Option<Declaration>,
+) -> (u32, u32) {
+    // Get the distance from the target of valid declarations
+    let distance = |decl: Option<Declaration>| {
+        decl.filter(|d| d.valid)
+            .map(|d| (target.abs_diff(d.value), points(target, d.value)))
+    };
+
+    match (distance(contestant1), distance(contestant2)) {
+        (None, None) => (0, 0),
+        (Some((_, p1)), None) => (p1, 0),
+        (None, Some((_, p2))) => (0, p2),
+        (Some((d1, p1)), Some((d2, p2))) => {
+            if d1 < d2 {
+                (p1, 0)
+            } else if d2 < d1 {
+                (0, p2)
+            } else {
+                (p1, p2)
+            }
+        }
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points() {
+        assert_eq!(10, points(500, 500));
+        assert_eq!(7, points(500, 495));
+        assert_eq!(7, points(500, 505));
+        assert_eq!(5, points(500, 490));
+        assert_eq!(5, points(500, 510));
+        assert_eq!(0, points(500, 489));
+        assert_eq!(0, points(500, 511));
+    }
+
+    #[test]
+    fn test_score() {
+        let valid = |v| Some(Declaration::new(v, true));
+        let invalid = |v| Some(Declaration::new(v, false));
+
+        // Closest scores
+        assert_eq!((10, 0), score(500, valid(500), valid(501)));
+        assert_eq!((0, 7), score(500, valid(490), valid(503)));
+
+        // Both equally close
+        assert_eq!((7, 7), score(500, valid(497), valid(503)));
+        assert_eq!((10, 10), score(500, valid(500), valid(500)));
+
+        // Too far away
+        assert_eq!((0, 0), score(500, valid(520), valid(530)));
+
+        // Invalid declarations
+        assert_eq!((0, 5), score(500, invalid(500), valid(510)));
+        assert_eq!((0, 0), score(500, invalid(500), invalid(500)));
+
+        // No declaration
+        assert_eq!((7, 0), score(500, valid(505), None));
+        assert_eq!((0, 0), score(500, None, None));
+    }
+}
@@ -0,0 +1,235 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for explaining why a target can't be reached with a set of cards.
+//!
+//! The explanation gives:
+//!  * The nearest values above and below the target which can be reached (see
+//!    Programs::reachable_values)
+//!  * Simple obstructions, eg. all of the cards are even and the target is odd
+//!  * The smallest relaxation of the rules (allowing negative or fractional intermediate results)
+//!    which would make the target reachable, with an example equation
+
+use std::fmt;
+
+use crate::programs::{ProgOp, Programs};
+use crate::relaxed;
+
+/// A reason a target can't be reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Obstruction {
+    /// All of the cards are even and the target is odd
+    Parity,
+    /// All of the cards are multiples of a number which doesn't divide the target
+    CommonFactor(u32),
+    /// The target is larger than the largest reachable value
+    TooLarge(u32),
+}
+
+impl fmt::Display for Obstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Obstruction::Parity => write!(
+                f,
+                "All of the cards are even and the target is odd, so a division is needed"
+            ),
+            Obstruction::CommonFactor(factor) => write!(
+                f,
+                "All of the cards are multiples of {factor} and the target isn't, so a division is needed"
+            ),
+            Obstruction::TooLarge(max) => write!(
+                f,
+                "The target is larger than the largest reachable value ({max})"
+            ),
+        }
+    }
+}
+
+/// A relaxation of the rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Relaxation {
+    /// Intermediate results may be negative
+    Negative,
+    /// Intermediate results may be fractions
+    Fractions,
+    /// Intermediate results may be negative or fractions
+    NegativeAndFractions,
+}
+
+impl fmt::Display for Relaxation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let desc = match self {
+            Relaxation::Negative => "negative intermediate results",
+            Relaxation::Fractions => "fractional intermediate results",
+            Relaxation::NegativeAndFractions => "negative and fractional intermediate results",
+        };
+
+        write!(f, "{desc}")
+    }
+}
+
+/// Explanation of why a target can't be reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// Nearest reachable value below the target
+    pub below: Option<u32>,
+    /// Nearest reachable value above the target
+    pub above: Option<u32>,
+    /// Obstructions which apply
+    pub obstructions: Vec<Obstruction>,
+    /// Smallest rule relaxation which reaches the target and the program element of an example
+    pub relaxation: Option<(Relaxation, usize)>,
+}
+
+/// Explains why a target can't be reached with a set of numbers. Returns None if the target can be reached
+pub fn explain_unreachable(
+    programs: &Programs,
+    target: u32,
+    numbers: &[u32],
+) -> Option<Explanation> {
+    let values = programs.reachable_values(numbers);
+
+    if values.contains_key(&target) {
+        return None;
+    }
+
+    let below = values.range(..target).next_back().map(|(&value, _)| value);
+    let above = values.range(target..).next().map(|(&value, _)| value);
+    let max = values.last_key_value().map(|(&value, _)| value);
+
+    // Find the smallest relaxation of the rules which lets a failing program reach the target
+    let mut relaxation: Option<(Relaxation, usize)> = None;
+
+    for i in 0..programs.len() {
+        if programs.run(i, numbers).is_ok() {
+            continue;
+        }
+
+        if relaxation.is_some_and(|(r, _)| r == Relaxation::Negative) {
+            // Can't do better than the first relaxation
+            break;
+        }
+
+        let instructions = programs.instructions(i);
+
+        for r in [
+            Relaxation::Negative,
+            Relaxation::Fractions,
+            Relaxation::NegativeAndFractions,
+        ] {
+            if relaxation.is_some_and(|(best, _)| best <= r) {
+                break;
+            }
+
+            if run_relaxed(instructions, numbers, r) == Some(target) {
+                relaxation = Some((r, i));
+                break;
+            }
+        }
+    }
+
+    // Find obstructions
+    let mut obstructions = Vec::new();
+
+    let factor = numbers.iter().fold(0, |g, &n| gcd(g, n));
+
+    if factor > 1 && !target.is_multiple_of(factor) {
+        if factor % 2 == 0 && target % 2 == 1 {
+            obstructions.push(Obstruction::Parity);
+        }
+
+        if factor != 2 {
+            obstructions.push(Obstruction::CommonFactor(factor));
+        }
+    }
+
+    if let Some(max) = max {
+        if target > max {
+            obstructions.push(Obstruction::TooLarge(max));
+        }
+    }
+
+    Some(Explanation {
+        below,
+        above,
+        obstructions,
+        relaxation,
+    })
+}
+
+/// Runs a program with relaxed rules using fractions. Returns the answer if it is a positive integer
+fn run_relaxed(instructions: &[ProgOp], numbers: &[u32], relaxation: Relaxation) -> Option<u32> {
+    let negative = relaxation != Relaxation::Fractions;
+    let fractions = relaxation != Relaxation::Negative;
+
+    relaxed::run_relaxed(instructions, numbers, negative, fractions).map(|(ans, _)| ans)
+}
+
+/// Greatest common divisor
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable() {
+        let programs = Programs::new(3, false, false);
+
+        assert_eq!(None, explain_unreachable(&programs, 120, &[10, 4, 3]));
+    }
+
+    #[test]
+    fn test_nearest() {
+        let programs = Programs::new(2, false, false);
+
+        let explanation = explain_unreachable(&programs, 130, &[25, 4]).unwrap();
+
+        assert_eq!(Some(100), explanation.below);
+        assert_eq!(None, explanation.above);
+        assert_eq!(vec![Obstruction::TooLarge(100)], explanation.obstructions);
+    }
+
+    #[test]
+    fn test_obstructions() {
+        let programs = Programs::new(3, false, false);
+
+        let explanation = explain_unreachable(&programs, 101, &[50, 10, 4]).unwrap();
+
+        assert!(explanation.obstructions.contains(&Obstruction::Parity));
+
+        let explanation = explain_unreachable(&programs, 100, &[75, 9, 6]).unwrap();
+
+        assert!(explanation
+            .obstructions
+            .contains(&Obstruction::CommonFactor(3)));
+    }
+
+    #[test]
+    fn test_relaxation() {
+        let programs = Programs::new(4, false, false);
+        let numbers = [75, 6, 2, 1];
+
+        // 75 × (1 + 2 / 6) = 100
+        let explanation = explain_unreachable(&programs, 100, &numbers).unwrap();
+
+        let (relaxation, prog) = explanation.relaxation.unwrap();
+
+        assert_eq!(Relaxation::Fractions, relaxation);
+        assert_eq!(
+            Some(100),
+            run_relaxed(programs.instructions(prog), &numbers, relaxation)
+        );
+        assert_eq!(
+            None,
+            run_relaxed(programs.instructions(prog), &numbers, Relaxation::Negative)
+        );
+    }
+}
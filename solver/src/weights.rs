@@ -0,0 +1,230 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for weighting targets by how often they appear, so dealt puzzles
+//! and simulated games can follow the targets seen in real games. Without weights every target in
+//! the range is equally likely.
+//!
+//! With the record feature the weights can be loaded from and saved to a JSON file mapping each
+//! target to its weight, for example the number of times it has been seen. Targets missing from
+//! the file have a weight of zero.
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+#[cfg(feature = "record")]
+use std::fs;
+#[cfg(feature = "record")]
+use std::io;
+#[cfg(feature = "record")]
+use std::path::Path;
+
+#[cfg(feature = "record")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "record")]
+use crate::record::RecordError;
+use crate::rng::SplitMix64;
+
+/// Current target weights file format version
+pub const WEIGHTS_VERSION: u32 = 1;
+
+/// Weight of each target
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub struct TargetWeights {
+    /// File format version
+    version: u32,
+    /// Weight of each target. Uniform when empty
+    weights: BTreeMap<u32, f64>,
+}
+
+impl Default for TargetWeights {
+    fn default() -> Self {
+        Self::uniform()
+    }
+}
+
+impl TargetWeights {
+    /// Creates uniform weights, where every target is equally likely
+    pub fn uniform() -> Self {
+        Self {
+            version: WEIGHTS_VERSION,
+            weights: BTreeMap::new(),
+        }
+    }
+
+    /// Creates weights from the number of times each target has appeared
+    pub fn from_counts(targets: impl IntoIterator<Item = u32>) -> Self {
+        let mut weights = Self::uniform();
+
+        for target in targets {
+            *weights.weights.entry(target).or_insert(0.0) += 1.0;
+        }
+
+        weights
+    }
+
+    /// Returns true if every target is equally likely
+    pub fn is_uniform(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// Returns the weight of a target
+    pub fn weight(&self, target: u32) -> f64 {
+        if self.is_uniform() {
+            1.0
+        } else {
+            self.weights.get(&target).copied().unwrap_or(0.0)
+        }
+    }
+
+    /// Sets the weight of a target. Panics if the weight is negative or not finite
+    pub fn set(&mut self, target: u32, weight: f64) {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "Invalid weight {weight} for target {target}"
+        );
+
+        self.weights.insert(target, weight);
+    }
+
+    /// Picks a target from a range. Falls back to a uniform choice when no target in the range
+    /// has a weight
+    pub fn pick(&self, rng: &mut SplitMix64, targets: &RangeInclusive<u32>) -> u32 {
+        let total = self
+            .weights
+            .range(targets.clone())
+            .map(|(_, w)| w)
+            .sum::<f64>();
+
+        if total <= 0.0 {
+            return targets.start()
+                + rng.below((targets.end() - targets.start()) as usize + 1) as u32;
+        }
+
+        // Uniform value in [0, total) from the top 53 bits
+        let mut point = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * total;
+
+        let mut last = *targets.start();
+
+        for (&target, &weight) in self.weights.range(targets.clone()) {
+            if weight <= 0.0 {
+                continue;
+            }
+
+            if point < weight {
+                return target;
+            }
+
+            point -= weight;
+            last = target;
+        }
+
+        // Rounding can leave a sliver past the last weighted target
+        last
+    }
+
+    /// Loads weights from a file
+    #[cfg(feature = "record")]
+    pub fn load(path: &Path) -> Result<Self, RecordError> {
+        let json = fs::read_to_string(path).map_err(|e| RecordError::Invalid(e.to_string()))?;
+
+        let weights: Self =
+            serde_json::from_str(&json).map_err(|e| RecordError::Invalid(e.to_string()))?;
+
+        if weights.version == 0 || weights.version > WEIGHTS_VERSION {
+            Err(RecordError::UnsupportedVersion(weights.version))?
+        }
+
+        if let Some((target, weight)) = weights
+            .weights
+            .iter()
+            .find(|(_, w)| !w.is_finite() || **w < 0.0)
+        {
+            Err(RecordError::Invalid(format!(
+                "Invalid weight {weight} for target {target}"
+            )))?
+        }
+
+        Ok(weights)
+    }
+
+    /// Saves weights to a file, creating the directory if necessary
+    #[cfg(feature = "record")]
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json = serde_json::to_string_pretty(self).expect("Target weights serialisation failed");
+
+        fs::write(path, json)
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform() {
+        let weights = TargetWeights::default();
+        let targets = 100..=999;
+
+        assert!(weights.is_uniform());
+        assert_eq!(1.0, weights.weight(500));
+
+        // Uniform weights pick the same targets as an unweighted deal
+        for seed in 0..100 {
+            let mut rng1 = SplitMix64(seed);
+            let mut rng2 = SplitMix64(seed);
+
+            assert_eq!(
+                100 + rng1.below(900) as u32,
+                weights.pick(&mut rng2, &targets)
+            );
+        }
+    }
+
+    #[test]
+    fn test_weighted() {
+        let weights = TargetWeights::from_counts([150, 150, 150, 700, 2000]);
+        let targets = 100..=999;
+
+        assert!(!weights.is_uniform());
+        assert_eq!(3.0, weights.weight(150));
+        assert_eq!(0.0, weights.weight(151));
+
+        let mut rng = SplitMix64(1);
+        let mut counts = BTreeMap::new();
+
+        for _ in 0..4000 {
+            *counts.entry(weights.pick(&mut rng, &targets)).or_insert(0) += 1;
+        }
+
+        // Only targets in range with a weight are picked
+        assert_eq!(vec![&150, &700], counts.keys().collect::<Vec<_>>());
+        assert!(counts[&150] > 2 * counts[&700]);
+
+        // No weights in range falls back to uniform
+        let mut rng = SplitMix64(1);
+
+        assert!((1..=10).contains(&weights.pick(&mut rng, &(1..=10))));
+    }
+
+    #[cfg(feature = "record")]
+    #[test]
+    fn test_json() {
+        let mut weights = TargetWeights::uniform();
+        weights.set(101, 0.5);
+        weights.set(999, 2.0);
+
+        let json = serde_json::to_string(&weights).unwrap();
+        assert_eq!(r#"{"version":1,"weights":{"101":0.5,"999":2.0}}"#, json);
+
+        let parsed: TargetWeights = serde_json::from_str(&json).unwrap();
+        assert_eq!(weights, parsed);
+    }
+}
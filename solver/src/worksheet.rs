@@ -0,0 +1,401 @@
+#![warn(missing_docs)]
+
+//! This module is responsible for laying out solutions as a printable worksheet. Each puzzle lists
+//! the cards and the steps of a solution in a scrambled order with the results blanked out, to be
+//! put back in order and filled in. The answers follow all of the puzzles.
+//!
+//! Puzzles are ordered easiest first: fewest steps, then the simplest equation (see Metrics).
+//!
+//! Worksheets can be written as Markdown or as HTML ready to print to PDF.
+
+use std::fmt::Write;
+
+use crate::format::NumFormat;
+use crate::programs::{Metrics, Step};
+use crate::rng::SplitMix64;
+
+/// Text replacing blanked out numbers
+const BLANK: &str = "______";
+
+/// A printable worksheet of puzzles
+#[derive(Debug, Clone, Default)]
+pub struct Worksheet {
+    /// Title of the worksheet
+    title: String,
+    /// Puzzles on the worksheet
+    puzzles: Vec<WorksheetPuzzle>,
+}
+
+/// A puzzle on a worksheet
+#[derive(Debug, Clone)]
+struct WorksheetPuzzle {
+    /// Target number
+    target: u32,
+    /// Card values
+    cards: Vec<u32>,
+    /// Steps of the solution in order
+    steps: Vec<WorksheetStep>,
+    /// Order to list the steps in on the worksheet
+    order: Vec<usize>,
+    /// Complexity of the solution's equation
+    metrics: Metrics,
+}
+
+/// A step of a solution on a worksheet
+#[derive(Debug, Clone)]
+struct WorksheetStep {
+    /// Calculation, eg. '75 + 3'
+    calculation: String,
+    /// Result of the calculation
+    result: String,
+}
+
+impl Worksheet {
+    /// Creates an empty worksheet
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            puzzles: Vec::new(),
+        }
+    }
+
+    /// Adds a puzzle with the formatted steps and metrics of a solution (see Programs::steps and
+    /// Programs::metrics), scrambling the steps deterministically from a seed
+    pub fn add(
+        &mut self,
+        target: u32,
+        cards: &[u32],
+        steps: Vec<String>,
+        metrics: Metrics,
+        seed: u64,
+    ) {
+        let steps = steps
+            .into_iter()
+            .map(|step| match step.rsplit_once(" = ") {
+                Some((calculation, result)) => WorksheetStep {
+                    calculation: calculation.to_string(),
+                    result: result.to_string(),
+                },
+                None => WorksheetStep {
+                    calculation: step,
+                    result: String::new(),
+                },
+            })
+            .collect();
+
+        self.add_puzzle(target, cards, steps, metrics, seed);
+    }
+
+    /// Adds a puzzle with the structured steps and metrics of a solution (see
+    /// Programs::structured_steps and Programs::metrics), scrambling the steps deterministically
+    /// from a seed
+    pub fn add_steps(
+        &mut self,
+        target: u32,
+        cards: &[u32],
+        steps: &[Step],
+        metrics: Metrics,
+        seed: u64,
+    ) {
+        let steps = steps
+            .iter()
+            .map(|step| WorksheetStep {
+                calculation: step.calculation(false),
+                result: step.result.num_format(),
+            })
+            .collect();
+
+        self.add_puzzle(target, cards, steps, metrics, seed);
+    }
+
+    /// Adds a puzzle, scrambling the steps deterministically from a seed
+    fn add_puzzle(
+        &mut self,
+        target: u32,
+        cards: &[u32],
+        steps: Vec<WorksheetStep>,
+        metrics: Metrics,
+        seed: u64,
+    ) {
+        let mut rng = SplitMix64(seed);
+        let mut order = (0..steps.len()).collect::<Vec<_>>();
+
+        // Fisher-Yates shuffle
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+
+        // Don't leave the steps in the right order if there's a choice
+        if order.len() > 1 && order.windows(2).all(|w| w[0] < w[1]) {
+            order.rotate_left(1);
+        }
+
+        self.puzzles.push(WorksheetPuzzle {
+            target,
+            cards: cards.to_vec(),
+            steps,
+            order,
+            metrics,
+        });
+
+        // Keep the easiest puzzles first
+        self.puzzles
+            .sort_by_key(|puzzle| (puzzle.steps.len(), puzzle.metrics.simplicity_key()));
+    }
+
+    /// Returns the number of puzzles on the worksheet
+    pub fn len(&self) -> usize {
+        self.puzzles.len()
+    }
+
+    /// Returns true if the worksheet has no puzzles
+    pub fn is_empty(&self) -> bool {
+        self.puzzles.is_empty()
+    }
+
+    /// Formats the worksheet as Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+
+        writeln!(md, "# {}", self.title).unwrap();
+
+        for (i, puzzle) in self.puzzles.iter().enumerate() {
+            writeln!(md).unwrap();
+            writeln!(md, "## Puzzle {}", i + 1).unwrap();
+            writeln!(md).unwrap();
+            writeln!(md, "Cards: {}", cards_string(&puzzle.cards)).unwrap();
+            writeln!(md).unwrap();
+            writeln!(md, "Target: {BLANK}").unwrap();
+            writeln!(md).unwrap();
+            writeln!(md, "Put the steps in order and fill in the blanks:").unwrap();
+            writeln!(md).unwrap();
+
+            for step in puzzle.scrambled() {
+                writeln!(md, "- [ ] {} = {BLANK}", step.calculation).unwrap();
+            }
+        }
+
+        writeln!(md).unwrap();
+        writeln!(md, "## Answers").unwrap();
+
+        for (i, puzzle) in self.puzzles.iter().enumerate() {
+            writeln!(md).unwrap();
+            writeln!(
+                md,
+                "{}. Target {} ({})",
+                i + 1,
+                puzzle.target,
+                puzzle.summary()
+            )
+            .unwrap();
+
+            for step in &puzzle.steps {
+                writeln!(md, "    1. {} = {}", step.calculation, step.result).unwrap();
+            }
+        }
+
+        md
+    }
+
+    /// Formats the worksheet as a standalone HTML page, starting the answers on a new page when
+    /// printed
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        writeln!(html, "<!DOCTYPE html>").unwrap();
+        writeln!(html, "<html>").unwrap();
+        writeln!(html, "<head>").unwrap();
+        writeln!(html, "<meta charset=\"utf-8\">").unwrap();
+        writeln!(html, "<title>{}</title>", escape(&self.title)).unwrap();
+        writeln!(
+            html,
+            "<style>body {{ font-family: sans-serif; }} .puzzle {{ break-inside: avoid; }} .answers {{ break-before: page; }} li {{ margin: 0.5em 0; }}</style>"
+        )
+        .unwrap();
+        writeln!(html, "</head>").unwrap();
+        writeln!(html, "<body>").unwrap();
+        writeln!(html, "<h1>{}</h1>", escape(&self.title)).unwrap();
+
+        for (i, puzzle) in self.puzzles.iter().enumerate() {
+            writeln!(html, "<div class=\"puzzle\">").unwrap();
+            writeln!(html, "<h2>Puzzle {}</h2>", i + 1).unwrap();
+            writeln!(html, "<p>Cards: {}</p>", cards_string(&puzzle.cards)).unwrap();
+            writeln!(html, "<p>Target: {BLANK}</p>").unwrap();
+            writeln!(
+                html,
+                "<p>Put the steps in order and fill in the blanks:</p>"
+            )
+            .unwrap();
+            writeln!(html, "<ul>").unwrap();
+
+            for step in puzzle.scrambled() {
+                writeln!(
+                    html,
+                    "<li>&#9744; {} = {BLANK}</li>",
+                    escape(&step.calculation)
+                )
+                .unwrap();
+            }
+
+            writeln!(html, "</ul>").unwrap();
+            writeln!(html, "</div>").unwrap();
+        }
+
+        writeln!(html, "<div class=\"answers\">").unwrap();
+        writeln!(html, "<h2>Answers</h2>").unwrap();
+        writeln!(html, "<ol>").unwrap();
+
+        for puzzle in &self.puzzles {
+            writeln!(
+                html,
+                "<li>Target {} ({})<ol>",
+                puzzle.target,
+                puzzle.summary()
+            )
+            .unwrap();
+
+            for step in &puzzle.steps {
+                writeln!(
+                    html,
+                    "<li>{} = {}</li>",
+                    escape(&step.calculation),
+                    escape(&step.result)
+                )
+                .unwrap();
+            }
+
+            writeln!(html, "</ol></li>").unwrap();
+        }
+
+        writeln!(html, "</ol>").unwrap();
+        writeln!(html, "</div>").unwrap();
+        writeln!(html, "</body>").unwrap();
+        writeln!(html, "</html>").unwrap();
+
+        html
+    }
+}
+
+impl WorksheetPuzzle {
+    /// Returns the steps in the scrambled order
+    fn scrambled(&self) -> impl Iterator<Item = &WorksheetStep> {
+        self.order.iter().map(|&i| &self.steps[i])
+    }
+
+    /// Describes the complexity of the solution
+    fn summary(&self) -> String {
+        let plural = |n: usize, what: &str| {
+            if n == 1 {
+                format!("1 {what}")
+            } else {
+                format!("{n} {what}s")
+            }
+        };
+
+        format!(
+            "{}, {} of brackets",
+            plural(self.steps.len(), "step"),
+            plural(self.metrics.brackets as usize, "pair")
+        )
+    }
+}
+
+/// Escapes text for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats cards separated by spaces
+fn cards_string(cards: &[u32]) -> String {
+    cards
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Programs;
+
+    fn worksheet() -> Worksheet {
+        // (75 + 3) × 8 - 100 - 1
+        let programs: Programs = "1 4 + 2 * 0 - 5 -".into();
+        let cards = [100, 75, 8, 8, 3, 1];
+
+        let mut worksheet = Worksheet::new("Countdown worksheet");
+        worksheet.add(
+            523,
+            &cards,
+            programs.steps(0, &cards, false),
+            programs.metrics(0, &cards),
+            1,
+        );
+
+        worksheet
+    }
+
+    #[test]
+    fn test_scramble() {
+        let worksheet = worksheet();
+        let puzzle = &worksheet.puzzles[0];
+
+        assert_eq!(1, worksheet.len());
+        assert_eq!(4, puzzle.steps.len());
+
+        let mut order = puzzle.order.clone();
+        assert_ne!(vec![0, 1, 2, 3], order);
+
+        order.sort();
+        assert_eq!(vec![0, 1, 2, 3], order);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let md = worksheet().to_markdown();
+
+        let (questions, answers) = md.split_once("## Answers").unwrap();
+
+        assert!(questions.contains("Cards: 100 75 8 8 3 1"));
+        assert!(questions.contains("- [ ] 75 + 3 = ______"));
+        assert!(!questions.contains("523"));
+        assert!(!questions.contains("= 78"));
+
+        assert!(answers.contains("1. Target 523 (4 steps, 2 pairs of brackets)"));
+        assert!(answers.contains("    1. 75 + 3 = 78"));
+    }
+
+    #[test]
+    fn test_structured_steps() {
+        // Structured steps give the same worksheet as formatted steps
+        let programs: Programs = "1 4 + 2 * 0 - 5 -".into();
+        let cards = [100, 75, 8, 8, 3, 1];
+
+        let mut worksheet = Worksheet::new("Countdown worksheet");
+        worksheet.add_steps(
+            523,
+            &cards,
+            &programs.structured_steps(0, &cards, false),
+            programs.metrics(0, &cards),
+            1,
+        );
+
+        assert_eq!(self::worksheet().to_markdown(), worksheet.to_markdown());
+    }
+
+    #[test]
+    fn test_html() {
+        let html = worksheet().to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<li>&#9744; 75 + 3 = ______</li>"));
+        assert!(html.contains("<li>Target 523 (4 steps, 2 pairs of brackets)<ol>"));
+        assert_eq!("a &lt; b &amp; c", escape("a < b & c"));
+    }
+}
@@ -1,5 +1,7 @@
 use num::ToPrimitive;
 
+use crate::csv::decimal;
+
 pub fn average<T, C>(total: T, count: C) -> f64
 where
     T: ToPrimitive,
@@ -14,7 +16,21 @@ where
     T: ToPrimitive,
 {
     format!(
-        "{:.2}%",
-        (num.to_f64().unwrap() / total.to_f64().unwrap()) * 100_f64
+        "{}%",
+        decimal((num.to_f64().unwrap() / total.to_f64().unwrap()) * 100_f64)
     )
 }
+
+pub fn percentile<T>(sorted: &[T], pct: usize) -> Option<T>
+where
+    T: Copy,
+{
+    if sorted.is_empty() {
+        return None;
+    }
+
+    // Nearest rank method
+    let rank = (pct * sorted.len()).div_ceil(100);
+
+    Some(sorted[rank.max(1) - 1])
+}
@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use cards::CardSet;
+
+use crate::sample::Sample;
+
+/// File in the results directory recording the number of card combinations the last run covers
+const COMBINATIONS_FILE: &str = "combinations.txt";
+
+/// Details of the card combinations the last run covers
+pub struct Combinations {
+    /// Number of card combinations to be solved
+    pub total: usize,
+    /// Number of cards picked and the deck they were picked from, None if not recorded
+    pub deck: Option<(usize, Vec<u32>)>,
+}
+
+impl Combinations {
+    /// Reads the card combination details from a results directory. Returns None if they weren't
+    /// recorded
+    pub fn read(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let contents = match fs::read_to_string(dir.join(COMBINATIONS_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e)?,
+        };
+
+        let value = |name: &str| {
+            contents
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|value| value.trim())
+        };
+
+        let total = value("combinations:")
+            .ok_or_else(|| format!("No 'combinations:' found in {COMBINATIONS_FILE}"))?
+            .parse()?;
+
+        // The pick and deck weren't recorded by earlier versions of solve_all
+        let deck = match (value("pick:"), value("deck:")) {
+            (Some(pick), Some(deck)) => Some((
+                pick.parse()?,
+                deck.split('-')
+                    .map(|c| c.parse::<u32>())
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            _ => None,
+        };
+
+        Ok(Some(Self { total, deck }))
+    }
+
+    /// Checks the number of card combinations recorded is the number of different sets of cards
+    /// which can be picked from the deck (or the sample population if sampled)
+    pub fn check(&self, sample: Option<&Sample>) -> Result<(), String> {
+        let Some((pick, deck)) = &self.deck else {
+            return Ok(());
+        };
+
+        let expected = CardSet::new(deck).combination_count(*pick);
+
+        let (recorded, what) = match sample {
+            Some(sample) => (sample.population, "sample population"),
+            None => (self.total, "card combinations"),
+        };
+
+        if recorded != expected {
+            Err(format!(
+                "{recorded} {what} recorded, but {expected} sets of {pick} cards can be picked from the deck"
+            ))?
+        }
+
+        Ok(())
+    }
+
+    /// Checks the number of card sets found against the number of card combinations. Returns a
+    /// description of the problem if they don't match
+    pub fn check_found(&self, found: usize) -> Result<(), String> {
+        if found > self.total {
+            Err(format!(
+                "{found} card sets found, more than the {} card combinations expected",
+                self.total
+            ))?
+        }
+
+        if found < self.total {
+            Err(format!(
+                "{} of {} card combinations are missing",
+                self.total - found,
+                self.total
+            ))?
+        }
+
+        Ok(())
+    }
+}
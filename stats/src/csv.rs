@@ -0,0 +1,106 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Field delimiter
+static DELIMITER: AtomicU32 = AtomicU32::new(',' as u32);
+
+/// Decimal separator
+static DECIMAL: AtomicU32 = AtomicU32::new('.' as u32);
+
+/// A character given on the command line, allowing 'tab' for a tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvChar(pub char);
+
+impl FromStr for CsvChar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+
+        match (s, chars.next(), chars.next()) {
+            ("tab" | "\\t", _, _) => Ok(Self('\t')),
+            (_, Some(c), None) if c != '"' && c != '\n' && c != '\r' => Ok(Self(c)),
+            _ => Err(format!(
+                "Invalid character '{s}' (expected a single character or 'tab')"
+            )),
+        }
+    }
+}
+
+/// Sets the field delimiter and decimal separator used for CSV output
+pub fn set_csv_format(delimiter: char, decimal: char) {
+    DELIMITER.store(delimiter as u32, Ordering::Relaxed);
+    DECIMAL.store(decimal as u32, Ordering::Relaxed);
+}
+
+/// Returns the field delimiter
+fn delimiter() -> char {
+    char::from_u32(DELIMITER.load(Ordering::Relaxed)).unwrap()
+}
+
+/// Formats a number with two decimal places using the decimal separator
+pub fn decimal(value: f64) -> String {
+    let formatted = format!("{value:.2}");
+
+    match char::from_u32(DECIMAL.load(Ordering::Relaxed)).unwrap() {
+        '.' => formatted,
+        sep => formatted.replace('.', &sep.to_string()),
+    }
+}
+
+/// A row of CSV fields
+#[derive(Default)]
+pub struct Row(Vec<String>);
+
+impl Row {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field
+    pub fn field(mut self, field: impl Display) -> Self {
+        self.0.push(field.to_string());
+        self
+    }
+
+    /// Adds a field for each item
+    pub fn fields<I>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        self.0.extend(fields.into_iter().map(|f| f.to_string()));
+        self
+    }
+
+    /// Prints the row
+    pub fn print(&self) {
+        println!("{self}");
+    }
+}
+
+impl std::fmt::Display for Row {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let delimiter = delimiter();
+
+        for (i, field) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{delimiter}")?;
+            }
+
+            write!(f, "{}", quote(field, delimiter))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Quotes a field if it contains the delimiter, a quote or a line break, doubling any quotes
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
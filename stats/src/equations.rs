@@ -0,0 +1,67 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use solver::LazyPrograms;
+
+use crate::results::Results;
+use crate::stats::Stats;
+
+/// Returns the card sets with the fewest and most targets reached, overall and for each number
+/// of big numbers. At most `limit` card sets are taken from each list
+pub fn interesting_sets(results: &Results, limit: usize) -> BTreeSet<Vec<u32>> {
+    let mut sets = BTreeSet::new();
+
+    let mut add = |stats: &Stats| {
+        for list in [&stats.min_sols, &stats.max_sols].into_iter().flatten() {
+            sets.extend(list.iter().take(limit).cloned());
+        }
+    };
+
+    add(&results.stats);
+
+    for stats in &results.big_stats {
+        add(stats);
+    }
+
+    sets
+}
+
+/// Runs the solver for each card set and writes the equations reaching targets to '<cards>-eqn.txt'
+/// in the directory, in the same format as solve_all
+pub fn write_equations(
+    dir: &Path,
+    sets: &BTreeSet<Vec<u32>>,
+    inc_duplicated: bool,
+) -> io::Result<()> {
+    // Programs for each number of cards, generated when first needed
+    let programs_map = LazyPrograms::new();
+
+    for cards in sets {
+        let programs = programs_map.get_or_generate(cards.len(), inc_duplicated);
+
+        let mut solutions = programs.run_all(cards).solutions;
+
+        solutions.sort();
+
+        let cards_str = cards
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        let path = dir.join(format!("{cards_str}-eqn.txt"));
+        let mut file = BufWriter::new(File::create(&path)?);
+
+        for solution in &solutions {
+            writeln!(file, "{}", programs.infix(solution.program, cards, false))?;
+        }
+
+        file.flush()?;
+
+        println!("Wrote {} equations to {}", solutions.len(), path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::calc::percent;
+use crate::results::Results;
+use crate::stats::{Stats, TARGET_COUNT};
+
+const CELL_WIDTH: usize = 1;
+const CELL_HEIGHT: usize = 24;
+const LABEL_WIDTH: usize = 110;
+const AXIS_HEIGHT: usize = 30;
+const MARGIN: usize = 10;
+
+/// Writes an SVG heat map of target coverage with targets along the x axis and
+/// big number counts along the y axis
+pub fn write_heatmap(results: &Results, path: &Path) -> io::Result<()> {
+    // Build list of rows to draw
+    let mut rows: Vec<(String, &Stats)> = vec![("Overall".to_string(), &results.stats)];
+
+    for (i, stats) in results.big_stats.iter().enumerate() {
+        if stats.files > 0 {
+            rows.push((format!("{} Big", results.big_label(i)), stats));
+        }
+    }
+
+    let width = (MARGIN * 2) + LABEL_WIDTH + (TARGET_COUNT * CELL_WIDTH);
+    let height = (MARGIN * 2) + (rows.len() * CELL_HEIGHT) + AXIS_HEIGHT;
+
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(
+        out,
+        r#"<rect x="0" y="0" width="{width}" height="{height}" fill="white"/>"#
+    )?;
+
+    // Draw the rows
+    for (row, (label, stats)) in rows.iter().enumerate() {
+        let y = MARGIN + (row * CELL_HEIGHT);
+
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}" dominant-baseline="middle">{}</text>"#,
+            MARGIN,
+            y + (CELL_HEIGHT / 2),
+            label
+        )?;
+
+        for (i, &n) in stats.sol_count.iter().enumerate() {
+            let fraction = if stats.files > 0 {
+                n as f64 / stats.files as f64
+            } else {
+                0.0
+            };
+
+            writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"><title>{}: {}</title></rect>"#,
+                MARGIN + LABEL_WIDTH + (i * CELL_WIDTH),
+                y,
+                CELL_WIDTH,
+                CELL_HEIGHT,
+                colour(fraction),
+                i + 100,
+                percent(n, stats.files)
+            )?;
+        }
+    }
+
+    // Draw the target axis
+    let axis_y = MARGIN + (rows.len() * CELL_HEIGHT);
+
+    for target in (100..=1000).step_by(100) {
+        let x = MARGIN + LABEL_WIDTH + ((target - 100) * CELL_WIDTH);
+
+        writeln!(
+            out,
+            r#"<line x1="{x}" y1="{axis_y}" x2="{x}" y2="{}" stroke="black"/>"#,
+            axis_y + 5
+        )?;
+
+        if target < 1000 {
+            writeln!(
+                out,
+                r#"<text x="{x}" y="{}" text-anchor="middle">{target}</text>"#,
+                axis_y + 18
+            )?;
+        }
+    }
+
+    writeln!(out, "</svg>")?;
+
+    out.flush()
+}
+
+/// Returns a colour from red (0.0) to green (1.0) for a fraction
+fn colour(fraction: f64) -> String {
+    let hue = (fraction.clamp(0.0, 1.0) * 120.0).round();
+
+    format!("hsl({hue}, 90%, 45%)")
+}
@@ -1,51 +1,303 @@
 mod calc;
+mod combinations;
+mod csv;
+mod equations;
+mod heatmap;
+mod operators;
+mod outcomes;
+mod pack;
+mod programs;
+mod progress;
 mod results;
+mod sample;
 mod stats;
 
+use std::collections::HashSet;
 use std::error::Error;
 use std::io::BufRead;
 use std::path::PathBuf;
 use std::{fs, io, path, process};
 
+use cards::BIG_THRESHOLD;
 use clap::Parser;
+use csv::{set_csv_format, CsvChar};
+use operators::Operators;
+use outcomes::Outcomes;
 use results::*;
+use solver::{normalise_cards, set_machine_output, set_symbol_set, SymbolSet, CAPABILITIES};
 use stats::*;
 
+/// File in the results directory written by solve_all --map-only
+const MAP_FILE: &str = "maps.txt";
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
     /// Directory to process
-    #[clap(value_parser)]
-    dir: PathBuf,
+    #[clap(required_unless_present = "capabilities", value_parser)]
+    dir: Option<PathBuf>,
+
+    /// Write an SVG heat map of target coverage to this file
+    #[clap(long = "heatmap", value_parser)]
+    heatmap: Option<PathBuf>,
+
+    /// Cards with a value greater than this are classed as big numbers
+    #[clap(long = "big-threshold", default_value_t = BIG_THRESHOLD, value_parser)]
+    big_threshold: u32,
+
+    /// Number of big number buckets, the last bucket collecting all higher counts.
+    /// Derived from the card sets found if not given
+    #[clap(long = "big-buckets", value_parser)]
+    big_buckets: Option<usize>,
+
+    /// Only process card sets with this number of cards
+    #[clap(short = 'n', long = "pick", value_parser)]
+    pick: Option<usize>,
+
+    /// Rerun the solver for the card sets with the fewest and most targets reached (overall and by
+    /// number of big numbers) and write their equations to the directory
+    #[clap(short = 'e', long = "equations", action)]
+    equations: bool,
+
+    /// Maximum number of card sets to write equations for from each fewest/most list
+    #[clap(long = "equations-limit", default_value_t = 5, value_parser)]
+    equations_limit: usize,
+
+    /// Output the card sets processed and average targets reached over time from the completion
+    /// times in the result files, with an estimate of the time remaining
+    #[clap(long = "progress", action)]
+    progress: bool,
+
+    /// Interval in seconds between progress rows. Longer gaps between card sets are treated as
+    /// breaks between runs
+    #[clap(long = "progress-interval", default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    progress_interval: u64,
+
+    /// Field delimiter for the CSV output (a single character or 'tab'). Defaults to ';' when the
+    /// decimal separator is ',', otherwise ','
+    #[clap(long = "delimiter", value_parser)]
+    delimiter: Option<CsvChar>,
+
+    /// Decimal separator for the CSV output, eg. ',' for locales which write 1,5 for one and a half
+    #[clap(long = "decimal", default_value = ".", value_parser)]
+    decimal: CsvChar,
+
+    /// Format numbers in equations as plain digits whatever the locale
+    #[clap(long = "machine", action)]
+    machine: bool,
+
+    /// Multiplication symbol to use in equations (unicode ×, ascii * or x)
+    #[clap(long = "symbols", default_value_t = SymbolSet::Unicode, value_parser)]
+    symbols: SymbolSet,
+
+    /// Fail if any of the card combinations the last run covers are missing
+    #[clap(long = "require-complete", action)]
+    require_complete: bool,
+
+    /// Print the solver capabilities as JSON and exit
+    #[clap(long = "capabilities", action)]
+    capabilities: bool,
 }
 
 fn main() {
     // Parse arguments
     let args = Args::parse();
 
+    if args.capabilities {
+        println!("{}", CAPABILITIES.to_json());
+        return;
+    }
+
+    set_machine_output(args.machine);
+    set_symbol_set(args.symbols);
+
+    let dir = args.dir.as_ref().unwrap();
+
+    let decimal = args.decimal.0;
+    let delimiter = args
+        .delimiter
+        .map(|d| d.0)
+        .unwrap_or(if decimal == ',' { ';' } else { ',' });
+
+    if delimiter == decimal {
+        eprintln!("The delimiter and decimal separator must be different");
+        process::exit(1);
+    }
+
+    set_csv_format(delimiter, decimal);
+
+    if args.big_buckets == Some(0) {
+        eprintln!("At least one big number bucket is required");
+        process::exit(1);
+    }
+
     // Create results struct
-    let mut results = Results::default();
+    let mut results = Results::new(args.big_threshold, args.big_buckets);
+
+    // Read the sample details if the results are from a sample
+    results.sample = match sample::Sample::read(dir) {
+        Ok(sample) => sample,
+        Err(e) => {
+            eprintln!("Failed to read sample details ({})", e);
+            process::exit(2);
+        }
+    };
+
+    // Read the details of the programs which generated the results
+    results.programs = match programs::ProgramsInfo::read(dir) {
+        Ok(programs) => programs,
+        Err(e) => {
+            eprintln!("Failed to read programs details ({})", e);
+            process::exit(2);
+        }
+    };
+
+    // Read the number of card combinations the last run covers
+    let combinations = match combinations::Combinations::read(dir) {
+        Ok(combinations) => combinations,
+        Err(e) => {
+            eprintln!("Failed to read combinations count ({})", e);
+            process::exit(2);
+        }
+    };
+
+    // Make sure the count is right for the deck
+    if let Some(combinations) = &combinations {
+        if let Err(e) = combinations.check(results.sample.as_ref()) {
+            eprintln!("Combination count mismatch: {e}");
+            process::exit(2);
+        }
+    }
+
+    results.progress.total = combinations.as_ref().map(|c| c.total);
 
     // Process the directory
-    let res = process_dir(&mut results, &args.dir);
+    let res = process_dir(&mut results, dir, args.pick);
 
     if res != 0 {
         process::exit(res);
     }
 
+    if results.card_counts.len() > 1 {
+        eprintln!(
+            "Warning: card sets with {} cards found, use --pick to select one size",
+            results
+                .card_counts
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    // Check the card sets found against the card combinations the last run covers
+    if let Some(combinations) = &combinations {
+        let picked = combinations.deck.as_ref().map(|(pick, _)| *pick);
+
+        if results.card_counts.len() == 1 && picked.is_none_or(|p| results.card_counts.contains(&p))
+        {
+            match combinations.check_found(results.stats.files) {
+                Ok(()) => (),
+                Err(e) if results.stats.files > combinations.total || args.require_complete => {
+                    eprintln!("Combination count mismatch: {e}");
+                    process::exit(5);
+                }
+                Err(e) => eprintln!("Warning: {e}"),
+            }
+        }
+    }
+
+    if let Some(first) = results.duplicates.first() {
+        eprintln!(
+            "Warning: {} card sets were found more than once and were only counted once (eg. {first})",
+            results.duplicates.len()
+        );
+    }
+
+    if let Some(desc) = results.inconsistent_options() {
+        eprintln!("Warning: result files were generated with different options ({desc})");
+    }
+
     // Output the results
     results.output();
+
+    if args.progress {
+        println!();
+        results.progress.output(args.progress_interval);
+    }
+
+    // Write the heat map
+    if let Some(heatmap) = &args.heatmap {
+        if let Err(e) = heatmap::write_heatmap(&results, heatmap) {
+            eprintln!("Failed to write {} ({})", heatmap.display(), e);
+            process::exit(3);
+        }
+    }
+
+    // Write equation files for the interesting card sets
+    if args.equations {
+        let sets = equations::interesting_sets(&results, args.equations_limit);
+
+        println!();
+
+        if let Err(e) = equations::write_equations(dir, &sets, results.duplicates_included) {
+            eprintln!("Failed to write equations ({})", e);
+            process::exit(4);
+        }
+    }
 }
 
-fn process_dir(results: &mut Results, dir: &PathBuf) -> i32 {
+fn process_dir(results: &mut Results, dir: &PathBuf, pick: Option<usize>) -> i32 {
     match fs::read_dir(dir) {
         Ok(files) => {
-            for f in files.flatten() {
-                if let Some(details) = result_file_details(f) {
-                    if let Err(e) = process_file(results, &details) {
-                        eprintln!("Failed to process {} ({})", details.path.display(), e);
+            let mut seen = HashSet::new();
+
+            // Process the files in card set order, with the file named in normalised card order
+            // first when the same card set has results in more than one file
+            let mut files = files
+                .flatten()
+                .filter_map(result_file_details)
+                .filter(|details| pick.is_none_or(|pick| pick == details.cards.len()))
+                .collect::<Vec<_>>();
+
+            files.sort_by(|a, b| {
+                a.cards
+                    .cmp(&b.cards)
+                    .then(b.normalised.cmp(&a.normalised))
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+
+            for details in files {
+                if seen.contains(&details.cards) {
+                    results.duplicates.push(file_name(&details.path));
+                    continue;
+                }
+
+                if let Err(e) = process_file(results, &details) {
+                    eprintln!("Failed to process {} ({})", details.path.display(), e);
+                }
+
+                seen.insert(details.cards);
+            }
+
+            // Card sets written to a pack are read using its index
+            match pack::Pack::open(dir) {
+                Ok(Some(mut pack)) => {
+                    if let Err(e) = process_pack(results, &mut pack, pick, &mut seen) {
+                        eprintln!("Failed to process {} ({})", pack.path.display(), e);
                     }
                 }
+                Ok(None) => (),
+                Err(e) => eprintln!("Failed to open results pack ({})", e),
+            }
+
+            // Card sets solved with --map-only are all in one file
+            let map_path = dir.join(MAP_FILE);
+
+            if map_path.is_file() {
+                if let Err(e) = process_map_file(results, &map_path, pick, &seen) {
+                    eprintln!("Failed to process {} ({})", map_path.display(), e);
+                }
             }
 
             0
@@ -60,7 +312,18 @@ fn process_dir(results: &mut Results, dir: &PathBuf) -> i32 {
 
 struct FileDetails {
     path: path::PathBuf,
-    cards: Vec<u8>,
+    /// Cards of the card set in normalised (descending) order
+    cards: Vec<u32>,
+    /// True if the cards in the file name were already in normalised order
+    normalised: bool,
+}
+
+/// Returns the file name of a path for messages
+fn file_name(path: &path::Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
 }
 
 fn result_file_details(f: fs::DirEntry) -> Option<FileDetails> {
@@ -88,52 +351,238 @@ fn result_file_details(f: fs::DirEntry) -> Option<FileDetails> {
     // Check file stem
     let cards = file_stem
         .split('-')
-        .map(|c| c.parse::<u8>())
+        .map(|c| c.parse::<u32>())
         .collect::<Result<Vec<_>, _>>()
         .ok()?;
 
     // Check we have some numbers
     (!cards.is_empty()).then_some(())?;
 
-    Some(FileDetails { path, cards })
+    // The same card set may be named in any order
+    let normalised_cards = normalise_cards(&cards);
+
+    Some(FileDetails {
+        path,
+        normalised: cards == normalised_cards,
+        cards: normalised_cards,
+    })
 }
 
 fn process_file(results: &mut Results, details: &FileDetails) -> Result<(), Box<dyn Error>> {
     let file = fs::File::open(&details.path)?;
-    let mut buf_reader = io::BufReader::new(file);
-    let mut line: String = String::new();
 
-    // Read the solution maps line
-    buf_reader.read_line(&mut line)?;
+    process_lines(results, details, io::BufReader::new(file))
+}
+
+/// Processes the results of each card set in a pack, skipping card sets which have a result file of
+/// their own
+fn process_pack(
+    results: &mut Results,
+    pack: &mut pack::Pack,
+    pick: Option<usize>,
+    seen: &mut HashSet<Vec<u32>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut in_pack = HashSet::new();
+
+    for entry in 0..pack.entries.len() {
+        let cards = normalise_cards(&pack.entries[entry].cards);
+
+        if pick.is_some_and(|pick| pick != cards.len()) {
+            continue;
+        }
+
+        if !in_pack.insert(cards.clone()) {
+            results
+                .duplicates
+                .push(format!("{} entry {}", file_name(&pack.path), entry + 1));
+            continue;
+        }
 
-    // Check it
-    if !line.starts_with("solution map: ") || line.len() <= 14 {
-        return Err(format!("No solution map found in {}", details.path.display()).into());
+        if seen.contains(&cards) {
+            continue;
+        }
+
+        let details = FileDetails {
+            path: pack.path.clone(),
+            normalised: cards == pack.entries[entry].cards,
+            cards,
+        };
+
+        let record = pack.read(entry)?;
+
+        process_lines(results, &details, record.as_bytes())?;
+
+        seen.insert(details.cards);
     }
 
-    // Process the solution map file
-    let mut sols: usize = 0;
-    let mut sol_reached: [bool; TARGET_COUNT] = [false; TARGET_COUNT];
+    Ok(())
+}
 
-    for (i, c) in line[14..].chars().enumerate() {
-        match c {
-            '#' => {
-                sol_reached[i] = true;
-                sols += 1;
-            }
-            '.' | '\n' => (),
-            _ => {
-                return Err(format!(
-                    "Invalid character '{}' found in {}",
-                    c,
-                    details.path.display()
-                )
-                .into())
-            }
+/// Processes the lines of the results of a card set
+fn process_lines(
+    results: &mut Results,
+    details: &FileDetails,
+    buf_reader: impl BufRead,
+) -> Result<(), Box<dyn Error>> {
+    // Find the solution counts, program outcome and option lines
+    let mut sol_counts = None;
+    let mut outcomes = Outcomes::default();
+    let mut operators = Operators::default();
+    let mut options = FileOptions::default();
+    let mut completed = None;
+
+    for line in buf_reader.lines() {
+        let line = line?;
+
+        if let Some(counts) = line.strip_prefix("solution counts: ") {
+            sol_counts = Some(parse_sol_counts(counts, details)?);
+        } else if let Some(duplicates) = line.strip_prefix("duplicates included: ") {
+            options.duplicates = match duplicates {
+                "Yes" => Some(true),
+                "No" => Some(false),
+                _ => Err(format!("Invalid duplicates flag '{line}'"))?,
+            };
+        } else if let Some(time) = line.strip_prefix("completed: ") {
+            completed = Some(
+                time.parse::<u64>()
+                    .map_err(|_| format!("Invalid completion time '{line}'"))?,
+            );
+        } else if operators.parse_line(&line)? {
+            operators.files = 1;
+        } else if outcomes.parse_line(&line)? {
+            outcomes.files = 1;
+
+            // The pruned count is only written when equations were pruned
+            options.pruned |= line.starts_with("pruned: ");
         }
     }
 
-    results.update(&details.cards, sols, &sol_reached);
+    let Some(sol_counts) = sol_counts else {
+        return Err(format!("No solution counts found in {}", details.path.display()).into());
+    };
+
+    if operators.files > 0 {
+        operators.targets = sol_counts.iter().filter(|&&c| c > 0).count();
+    }
+
+    results
+        .progress
+        .add(completed, sol_counts.iter().filter(|&&c| c > 0).count());
+    results.update(&details.cards, &sol_counts, &outcomes, &operators, options);
+
+    Ok(())
+}
+
+/// Processes the solution map lines written by solve_all --map-only, skipping card sets which have
+/// a result file of their own
+fn process_map_file(
+    results: &mut Results,
+    path: &path::Path,
+    pick: Option<usize>,
+    seen: &HashSet<Vec<u32>>,
+) -> Result<(), Box<dyn Error>> {
+    let file = fs::File::open(path)?;
+    let buf_reader = io::BufReader::new(file);
+
+    let mut options = FileOptions {
+        map_only: true,
+        ..Default::default()
+    };
+
+    let mut in_map = HashSet::new();
+
+    for (line_no, line) in buf_reader.lines().enumerate() {
+        let line = line?;
+
+        if let Some(duplicates) = line.strip_prefix("duplicates included: ") {
+            options.duplicates = match duplicates {
+                "Yes" => Some(true),
+                "No" => Some(false),
+                _ => Err(format!("Invalid duplicates flag '{line}'"))?,
+            };
+
+            continue;
+        }
+
+        let invalid = || format!("Invalid solution map line '{line}'");
+
+        let (stem, map) = line.split_once(": ").ok_or_else(invalid)?;
+
+        let cards = normalise_cards(
+            &stem
+                .split('-')
+                .map(|c| c.parse::<u32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?,
+        );
+
+        if pick.is_some_and(|pick| pick != cards.len()) {
+            continue;
+        }
+
+        if !in_map.insert(cards.clone()) {
+            results
+                .duplicates
+                .push(format!("{} line {}", file_name(path), line_no + 1));
+            continue;
+        }
+
+        if seen.contains(&cards) {
+            continue;
+        }
+
+        if map.len() != TARGET_COUNT {
+            Err(invalid())?
+        }
+
+        let sol_counts = map
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(1),
+                '.' => Ok(0),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        results
+            .progress
+            .add(None, sol_counts.iter().filter(|&&c| c > 0).count());
+        results.update(
+            &cards,
+            &sol_counts,
+            &Outcomes::default(),
+            &Operators::default(),
+            options,
+        );
+    }
 
     Ok(())
 }
+
+fn parse_sol_counts(counts: &str, details: &FileDetails) -> Result<Vec<u32>, Box<dyn Error>> {
+    let mut sol_counts = vec![0; TARGET_COUNT];
+
+    for entry in counts.split(", ") {
+        let invalid = || {
+            format!(
+                "Invalid solution count '{}' found in {}",
+                entry,
+                details.path.display()
+            )
+        };
+
+        // Each entry is target=count
+        let (target, count) = entry.split_once('=').ok_or_else(invalid)?;
+
+        let target = target.parse::<usize>().map_err(|_| invalid())?;
+        let count = count.parse::<u32>().map_err(|_| invalid())?;
+
+        if !(100..100 + TARGET_COUNT).contains(&target) {
+            Err(invalid())?
+        }
+
+        sol_counts[target - 100] = count;
+    }
+
+    Ok(sol_counts)
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use crate::calc::percent;
+use crate::csv::Row;
+
+/// Operator names in the order they are held in the count arrays
+const OP_NAMES: [&str; 4] = ["add", "sub", "mul", "div"];
+
+/// Result file prefix of the number of solutions using each operator
+const USING_PREFIX: &str = "solutions using: ";
+
+/// Result file prefix of the number of targets where every solution uses each operator
+const NEEDING_PREFIX: &str = "targets needing: ";
+
+/// Result file prefix of the number of solutions with each combination of operator counts
+const COMBINATIONS_PREFIX: &str = "operator counts (add:sub:mul:div): ";
+
+/// Operators used by the solutions
+#[derive(Default, Clone)]
+pub struct Operators {
+    /// Number of files with operator usage
+    pub files: usize,
+    /// Number of targets reached in the files with operator usage
+    pub targets: usize,
+    /// Number of solutions using each operator
+    pub using: [usize; 4],
+    /// Number of targets where every solution uses each operator
+    pub needing: [usize; 4],
+    /// Number of solutions with each combination of operator counts
+    pub combinations: BTreeMap<[u8; 4], usize>,
+}
+
+impl Operators {
+    /// Parses a result file line, returning true if it was an operator usage line
+    pub fn parse_line(&mut self, line: &str) -> Result<bool, String> {
+        if let Some(counts) = line.strip_prefix(USING_PREFIX) {
+            self.using = parse_op_counts(counts, line)?;
+        } else if let Some(counts) = line.strip_prefix(NEEDING_PREFIX) {
+            self.needing = parse_op_counts(counts, line)?;
+        } else if let Some(combinations) = line.strip_prefix(COMBINATIONS_PREFIX) {
+            for entry in combinations.split(", ").filter(|e| !e.is_empty()) {
+                let invalid = || format!("Invalid operator count combination '{entry}'");
+
+                let (ops, count) = entry.split_once('=').ok_or_else(invalid)?;
+
+                let ops = ops
+                    .split(':')
+                    .map(|c| c.parse::<u8>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid())?
+                    .try_into()
+                    .map_err(|_| invalid())?;
+
+                let count = count.parse::<usize>().map_err(|_| invalid())?;
+
+                self.combinations.insert(ops, count);
+            }
+        } else {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    pub fn merge(&mut self, other: &Operators) {
+        self.files += other.files;
+        self.targets += other.targets;
+
+        for (total, count) in self.using.iter_mut().zip(other.using) {
+            *total += count;
+        }
+
+        for (total, count) in self.needing.iter_mut().zip(other.needing) {
+            *total += count;
+        }
+
+        for (ops, count) in &other.combinations {
+            *self.combinations.entry(*ops).or_default() += count;
+        }
+    }
+
+    /// Returns the number of solutions
+    pub fn solutions(&self) -> usize {
+        self.combinations.values().sum()
+    }
+
+    pub fn output_header() {
+        let labels = |desc: &'static str| OP_NAMES.map(|name| format!("{desc} {name}"));
+
+        Row::new()
+            .fields(["Big Numbers", "Combinations", "Solutions"])
+            .fields(labels("Using"))
+            .field("Targets Reached")
+            .fields(labels("Needing"))
+            .print();
+    }
+
+    pub fn output(&self, desc: &str) {
+        let solutions = self.solutions();
+
+        let pcts = |counts: &[usize; 4], total: usize| {
+            counts
+                .iter()
+                .map(|&c| percent(c, total.max(1)))
+                .collect::<Vec<_>>()
+        };
+
+        Row::new()
+            .field(desc)
+            .field(self.files)
+            .field(solutions)
+            .fields(pcts(&self.using, solutions))
+            .field(self.targets)
+            .fields(pcts(&self.needing, self.targets))
+            .print();
+    }
+
+    /// Outputs the number of solutions with each combination of operator counts, most common first
+    pub fn output_combinations(&self) {
+        let solutions = self.solutions();
+
+        let mut combinations = self.combinations.iter().collect::<Vec<_>>();
+
+        combinations.sort_by(|(ops1, c1), (ops2, c2)| c2.cmp(c1).then(ops1.cmp(ops2)));
+
+        Row::new()
+            .fields(["Add", "Sub", "Mul", "Div", "Solutions", "Percent"])
+            .print();
+
+        for (ops, &count) in combinations {
+            Row::new()
+                .fields(ops)
+                .field(count)
+                .field(percent(count, solutions))
+                .print();
+        }
+    }
+}
+
+/// Parses a count for each operator in the form add=n, sub=n, mul=n, div=n
+fn parse_op_counts(counts: &str, line: &str) -> Result<[usize; 4], String> {
+    let invalid = || format!("Invalid operator counts '{line}'");
+
+    let mut result = [0; 4];
+
+    for entry in counts.split(", ") {
+        let (name, count) = entry.split_once('=').ok_or_else(invalid)?;
+
+        let op = OP_NAMES
+            .iter()
+            .position(|&n| n == name)
+            .ok_or_else(invalid)?;
+
+        result[op] = count.parse().map_err(|_| invalid())?;
+    }
+
+    Ok(result)
+}
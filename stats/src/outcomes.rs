@@ -0,0 +1,77 @@
+use crate::calc::percent;
+use crate::csv::Row;
+
+/// Program outcome result file prefixes and labels, in the order they are held in the counts array
+const OUTCOMES: [(&str, &str); OUTCOME_COUNT] = [
+    ("results: ", "In range"),
+    ("< 100: ", "< 100"),
+    ("> 999: ", "> 999"),
+    ("zero intermediate: ", "Zero intermediate"),
+    ("negative intermediate: ", "Negative intermediate"),
+    ("div by zero: ", "Div by zero"),
+    ("non-integer: ", "Non-integer"),
+    ("multiply by 1: ", "Multiply by 1"),
+    ("divide by 1: ", "Divide by 1"),
+    ("pruned: ", "Pruned"),
+    ("symmetric: ", "Symmetric"),
+];
+
+const OUTCOME_COUNT: usize = 11;
+
+#[derive(Default, Clone)]
+pub struct Outcomes {
+    pub files: usize,
+    pub counts: [usize; OUTCOME_COUNT],
+}
+
+impl Outcomes {
+    /// Parses a result file line, returning true if it was a program outcome count
+    pub fn parse_line(&mut self, line: &str) -> Result<bool, String> {
+        for (i, (prefix, _)) in OUTCOMES.iter().enumerate() {
+            if let Some(count) = line.strip_prefix(prefix) {
+                self.counts[i] = count
+                    .parse()
+                    .map_err(|_| format!("Invalid program outcome count '{line}'"))?;
+
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    pub fn merge(&mut self, other: &Outcomes) {
+        for (total, count) in self.counts.iter_mut().zip(other.counts) {
+            *total += count;
+        }
+
+        self.files += other.files;
+    }
+
+    /// Returns the number of programs giving an answer in the target range
+    pub fn results(&self) -> usize {
+        self.counts[0]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn output_header() {
+        Row::new()
+            .fields(["Big Numbers", "Combinations", "Programs"])
+            .fields(OUTCOMES.iter().map(|(_, label)| *label))
+            .print();
+    }
+
+    pub fn output(&self, desc: &str) {
+        let total = self.total();
+
+        Row::new()
+            .field(desc)
+            .field(self.files)
+            .field(total)
+            .fields(self.counts.iter().map(|&c| percent(c, total)))
+            .print();
+    }
+}
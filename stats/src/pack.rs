@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// File in the results directory holding the results written by solve_all --sink pack
+const PACK_FILE: &str = "results.pack";
+
+/// File in the results directory giving the offset and length of each card set in the pack
+const PACK_INDEX_FILE: &str = "results.idx";
+
+/// Location of the results for a card set in the pack
+pub struct PackEntry {
+    pub cards: Vec<u32>,
+    offset: u64,
+    length: usize,
+}
+
+/// Random access reader for the results of each card set in a pack file
+pub struct Pack {
+    pub path: PathBuf,
+    file: File,
+    pub entries: Vec<PackEntry>,
+}
+
+impl Pack {
+    /// Opens the pack in a results directory and reads its index. Returns None if there is no pack
+    pub fn open(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let contents = match fs::read_to_string(dir.join(PACK_INDEX_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e)?,
+        };
+
+        let path = dir.join(PACK_FILE);
+        let file = File::open(&path)?;
+
+        // An incomplete last entry is being written or was left by an interrupted run
+        let complete = contents.rfind('\n').map_or(0, |i| i + 1);
+
+        let entries = contents[..complete]
+            .lines()
+            .map(|line| -> Result<PackEntry, Box<dyn Error>> {
+                let invalid = || format!("Invalid index entry '{line}' in {PACK_INDEX_FILE}");
+
+                // Each entry is 'cards offset length'
+                let mut fields = line.split(' ');
+
+                let (Some(stem), Some(offset), Some(length), None) =
+                    (fields.next(), fields.next(), fields.next(), fields.next())
+                else {
+                    Err(invalid())?
+                };
+
+                Ok(PackEntry {
+                    cards: stem
+                        .split('-')
+                        .map(|c| c.parse::<u32>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| invalid())?,
+                    offset: offset.parse().map_err(|_| invalid())?,
+                    length: length.parse().map_err(|_| invalid())?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(Self {
+            path,
+            file,
+            entries,
+        }))
+    }
+
+    /// Reads the results of a card set
+    pub fn read(&mut self, entry: usize) -> io::Result<String> {
+        let entry = &self.entries[entry];
+        let mut record = vec![0; entry.length];
+
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        self.file.read_exact(&mut record)?;
+
+        String::from_utf8(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use solver::Ruleset;
+
+/// File in the results directory recording the collection of programs which generated the results
+const PROGRAMS_FILE: &str = "programs.txt";
+
+/// Details of the collection of programs which generated the results
+pub struct ProgramsInfo {
+    pub order_version: u32,
+    pub programs: usize,
+    pub fingerprint: String,
+    pub rules: Ruleset,
+}
+
+impl ProgramsInfo {
+    /// Reads the programs details from a results directory. Returns None if they weren't recorded
+    pub fn read(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let contents = match fs::read_to_string(dir.join(PROGRAMS_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e)?,
+        };
+
+        let value = |name: &str| -> Result<&str, Box<dyn Error>> {
+            Ok(contents
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .ok_or_else(|| format!("No '{name}' found in {PROGRAMS_FILE}"))?
+                .trim())
+        };
+
+        Ok(Some(Self {
+            order_version: value("program order version:")?.parse()?,
+            programs: value("programs:")?.parse()?,
+            fingerprint: value("fingerprint:")?.to_string(),
+            // Results written before rules were recorded used the TV rules
+            rules: match value("rules:") {
+                Ok(rules) => rules.parse()?,
+                Err(_) => Ruleset::Tv,
+            },
+        }))
+    }
+
+    /// Returns a description of the programs
+    pub fn description(&self) -> String {
+        format!(
+            "Generated by {} programs (order version {}, fingerprint {}) with the {} rules",
+            self.programs, self.order_version, self.fingerprint, self.rules
+        )
+    }
+}
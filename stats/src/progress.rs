@@ -0,0 +1,146 @@
+use crate::calc::{average, percent};
+use crate::csv::{decimal, Row};
+
+/// Completion times of the card sets in a results directory, for following incremental runs
+#[derive(Default)]
+pub struct Progress {
+    /// Completion time (seconds since the Unix epoch) and targets reached for each timed card set
+    completed: Vec<(u64, usize)>,
+    /// Number of card sets written before completion times were recorded
+    untimed: usize,
+    /// Total targets reached by the untimed card sets
+    untimed_coverage: usize,
+    /// Number of card combinations the last run covers
+    pub total: Option<usize>,
+}
+
+impl Progress {
+    /// Records a card set with its completion time if known
+    pub fn add(&mut self, completed: Option<u64>, coverage: usize) {
+        match completed {
+            Some(time) => self.completed.push((time, coverage)),
+            None => {
+                self.untimed += 1;
+                self.untimed_coverage += coverage;
+            }
+        }
+    }
+
+    /// Outputs the cumulative card sets processed and average coverage at the end of each interval
+    /// (in seconds) containing a completion, followed by the processing rate and an estimate of the
+    /// time remaining. Gaps between completions longer than the interval are taken to be breaks
+    /// between runs and don't count towards the rate
+    pub fn output(&mut self, interval: u64) {
+        self.completed.sort_unstable();
+
+        Row::new().field("Progress Over Time").print();
+        Row::new()
+            .fields([
+                "Completed (UTC)",
+                "Elapsed Hours",
+                "Card Sets",
+                "Percent Done",
+                "Average Achieved",
+            ])
+            .print();
+
+        let mut processed = self.untimed;
+        let mut coverage = self.untimed_coverage;
+        let mut active = 0;
+        let mut active_sets = 0;
+
+        if let Some(&(first, _)) = self.completed.first() {
+            let mut last = first;
+
+            for (i, &(time, covered)) in self.completed.iter().enumerate() {
+                if i > 0 && time - last <= interval {
+                    active += time - last;
+                    active_sets += 1;
+                }
+
+                last = time;
+                processed += 1;
+                coverage += covered;
+
+                // Output a row at the last completion in each interval
+                let bucket_end = self.completed.get(i + 1).is_none_or(|&(next, _)| {
+                    (next - first) / interval != (time - first) / interval
+                });
+
+                if bucket_end {
+                    Row::new()
+                        .field(utc_string(time))
+                        .field(decimal((time - first) as f64 / 3600.0))
+                        .field(processed)
+                        .field(
+                            self.total
+                                .map(|total| percent(processed, total))
+                                .unwrap_or_default(),
+                        )
+                        .field(decimal(average(coverage, processed)))
+                        .print();
+                }
+            }
+        }
+
+        println!();
+
+        if self.untimed > 0 {
+            Row::new()
+                .field("Card Sets Without Completion Time")
+                .field(self.untimed)
+                .print();
+        }
+
+        Row::new()
+            .field("Active Hours")
+            .field(decimal(active as f64 / 3600.0))
+            .print();
+
+        if active > 0 {
+            let rate = active_sets as f64 / active as f64 * 3600.0;
+
+            Row::new()
+                .field("Card Sets Per Hour")
+                .field(decimal(rate))
+                .print();
+
+            if let Some(total) = self.total {
+                let remaining = total.saturating_sub(processed);
+
+                Row::new()
+                    .field("Card Sets Remaining")
+                    .field(remaining)
+                    .print();
+                Row::new()
+                    .field("Estimated Hours Remaining")
+                    .field(decimal(remaining as f64 / rate))
+                    .print();
+            }
+        }
+    }
+}
+
+/// Formats a time in seconds since the Unix epoch as a UTC date and time
+fn utc_string(time: u64) -> String {
+    let days = (time / 86400) as i64;
+    let secs = time % 86400;
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
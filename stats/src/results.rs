@@ -1,49 +1,238 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use crate::calc::{average, percent};
+use crate::csv::{decimal, Row};
+use crate::operators::Operators;
+use crate::outcomes::Outcomes;
+use crate::programs::ProgramsInfo;
+use crate::progress::Progress;
+use crate::sample::Sample;
 use crate::stats::*;
 
+/// Options a result file was generated with
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileOptions {
+    /// Duplicated equations were included, None if not recorded
+    pub duplicates: Option<bool>,
+    /// Equations were pruned
+    pub pruned: bool,
+    /// Only the solution map was recorded, so there are no solution counts
+    pub map_only: bool,
+}
+
+impl FileOptions {
+    fn description(&self) -> String {
+        let duplicates = match self.duplicates {
+            Some(true) => "duplicates included",
+            Some(false) => "duplicates excluded",
+            None => "duplicates not recorded",
+        };
+
+        let mut description = duplicates.to_string();
+
+        if self.pruned {
+            description.push_str(", pruned");
+        }
+
+        if self.map_only {
+            description.push_str(", solution map only");
+        }
+
+        description
+    }
+}
+
 pub struct Results {
     pub stats: Stats,
     pub big_stats: Vec<Stats>,
+    big_threshold: u32,
+    big_buckets: Option<usize>,
+    pub card_counts: BTreeSet<usize>,
+    pub duplicates_included: bool,
+    pub options: BTreeMap<FileOptions, usize>,
+    pub sample: Option<Sample>,
+    pub programs: Option<ProgramsInfo>,
+    pub progress: Progress,
+    /// Card sets found more than once and only counted once
+    pub duplicates: Vec<String>,
 }
 
 impl Results {
-    pub fn update(&mut self, cards: &[u8], sols: usize, sol_reached: &[bool]) {
+    pub fn new(big_threshold: u32, big_buckets: Option<usize>) -> Self {
+        Self {
+            stats: Stats::default(),
+            big_stats: Vec::new(),
+            big_threshold,
+            big_buckets,
+            card_counts: BTreeSet::new(),
+            duplicates_included: false,
+            options: BTreeMap::new(),
+            sample: None,
+            programs: None,
+            progress: Progress::default(),
+            duplicates: Vec::new(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        cards: &[u32],
+        sol_counts: &[u32],
+        outcomes: &Outcomes,
+        operators: &Operators,
+        options: FileOptions,
+    ) {
+        self.card_counts.insert(cards.len());
+
+        // Record the options the file was generated with
+        *self.options.entry(options).or_default() += 1;
+
+        if options.duplicates == Some(true) {
+            self.duplicates_included = true;
+        }
+
         // Updte total stats
-        self.stats.update(cards, sols, sol_reached);
+        self.stats
+            .update(cards, sol_counts, !options.map_only, outcomes, operators);
 
         // Update big number stats
-        let big_cnt = cards.iter().filter(|&c| *c > 10).count();
+        let big_cnt = cards.iter().filter(|&c| *c > self.big_threshold).count();
+
+        let bucket = match self.big_buckets {
+            Some(buckets) => big_cnt.min(buckets - 1),
+            None => big_cnt,
+        };
 
-        if big_cnt < MAX_BIG {
-            self.big_stats[big_cnt].update(cards, sols, sol_reached);
+        if bucket >= self.big_stats.len() {
+            self.big_stats.resize(bucket + 1, Stats::default());
+        }
+
+        self.big_stats[bucket].update(cards, sol_counts, !options.map_only, outcomes, operators);
+    }
+
+    /// Returns a description of the options and number of files generated with each if the files
+    /// weren't all generated with the same options
+    pub fn inconsistent_options(&self) -> Option<String> {
+        (self.options.len() > 1).then(|| {
+            self.options
+                .iter()
+                .map(|(options, files)| format!("{files} {}", options.description()))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    pub fn big_label(&self, bucket: usize) -> String {
+        match self.big_buckets {
+            Some(buckets) if bucket == buckets - 1 => format!("{bucket}+"),
+            _ => bucket.to_string(),
         }
     }
 
     pub fn output(&self) {
-        self.stats.output("Overall");
+        let sample = self.sample.as_ref();
+
+        if let Some(programs) = &self.programs {
+            Row::new().field(programs.description()).print();
+        }
+
+        if let Some(sample) = sample {
+            Row::new().field(sample.description()).print();
+        }
+
+        if self.programs.is_some() || sample.is_some() {
+            println!();
+        }
+
+        self.stats.output("Overall", sample);
 
         println!();
-        println!("Big Number Average Achieved");
+        Row::new().field("Big Number Average Achieved").print();
+
+        for (i, stats) in self.big_stats.iter().enumerate() {
+            if stats.files == 0 {
+                continue;
+            }
+
+            let avg = average(stats.tot_sols, stats.files);
+
+            let mut row = Row::new()
+                .field(self.big_label(i))
+                .field(stats.files)
+                .field(decimal(avg))
+                .field(percent(avg, 900));
+
+            if let Some((lo, hi)) = stats.average_interval(sample) {
+                row = row.field(format!("{}-{}", decimal(lo), decimal(hi)));
+            }
+
+            row.print();
+        }
+
+        if self.stats.outcomes.files > 0 {
+            println!();
+            Row::new().field("Big Number Program Outcomes").print();
+            Outcomes::output_header();
+
+            for (i, stats) in self.big_stats.iter().enumerate() {
+                if stats.outcomes.files == 0 {
+                    continue;
+                }
+
+                stats.outcomes.output(&self.big_label(i));
+            }
 
-        for i in 0..MAX_BIG {
-            let files = self.big_stats[i].files;
-            let avg = average(self.big_stats[i].tot_sols, self.big_stats[i].files);
+            self.stats.outcomes.output("Total");
 
-            println!("{}, {}, {:.2}, {}", i, files, avg, percent(avg, 900));
+            println!();
+            Row::new().field("Big Number Average Results").print();
+            Row::new()
+                .fields([
+                    "Big Numbers",
+                    "Combinations",
+                    "Average Results",
+                    "Average Results Per Target Reached",
+                ])
+                .print();
+
+            for (i, stats) in self.big_stats.iter().enumerate() {
+                if stats.outcomes.files == 0 {
+                    continue;
+                }
+
+                stats.output_average_results(&self.big_label(i));
+            }
+
+            self.stats.output_average_results("Total");
         }
 
-        for i in 0..MAX_BIG {
+        if self.stats.operators.files > 0 {
             println!();
-            self.big_stats[i].output(&format!("{i} Big Numbers"));
+            Row::new().field("Big Number Operator Usage").print();
+            Operators::output_header();
+
+            for (i, stats) in self.big_stats.iter().enumerate() {
+                if stats.operators.files == 0 {
+                    continue;
+                }
+
+                stats.operators.output(&self.big_label(i));
+            }
+
+            self.stats.operators.output("Total");
+
+            println!();
+            Row::new().field("Operator Count Combinations").print();
+            self.stats.operators.output_combinations();
         }
-    }
-}
 
-impl Default for Results {
-    fn default() -> Self {
-        Self {
-            stats: Stats::default(),
-            big_stats: vec![Stats::default(); MAX_BIG],
+        for (i, stats) in self.big_stats.iter().enumerate() {
+            if stats.files == 0 {
+                continue;
+            }
+
+            println!();
+            stats.output(&format!("{} Big Numbers", self.big_label(i)), sample);
         }
     }
 }
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File in the results directory recording the details of a sample
+const SAMPLE_FILE: &str = "sample.txt";
+
+/// Normal distribution value for a 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// Details of a random sample of card combinations
+pub struct Sample {
+    pub size: usize,
+    pub population: usize,
+    pub seed: u64,
+}
+
+impl Sample {
+    /// Reads the sample details from a results directory. Returns None if the results are not from a sample
+    pub fn read(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let contents = match fs::read_to_string(dir.join(SAMPLE_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e)?,
+        };
+
+        let value = |name: &str| -> Result<u64, Box<dyn Error>> {
+            let value = contents
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .ok_or_else(|| format!("No '{name}' found in {SAMPLE_FILE}"))?;
+
+            Ok(value.trim().parse::<u64>()?)
+        };
+
+        Ok(Some(Self {
+            size: value("sample size:")? as usize,
+            population: value("population:")? as usize,
+            seed: value("seed:")?,
+        }))
+    }
+
+    /// Finite population correction for the standard error
+    fn fpc(&self) -> f64 {
+        if self.population > 1 && self.size < self.population {
+            ((self.population - self.size) as f64 / (self.population - 1) as f64).sqrt()
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the 95% confidence interval of a proportion (Wilson score interval)
+    pub fn proportion_interval(&self, successes: usize, n: usize) -> (f64, f64) {
+        if n == 0 {
+            return (0.0, 1.0);
+        }
+
+        let n = n as f64;
+        let p = successes as f64 / n;
+        let z = Z_95 * self.fpc();
+        let z2 = z * z;
+
+        let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let half = (z / (1.0 + z2 / n)) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+
+        ((centre - half).max(0.0), (centre + half).min(1.0))
+    }
+
+    /// Returns the 95% confidence interval of a mean given the sum and sum of squares of the values
+    pub fn mean_interval(&self, sum: f64, sum_sq: f64, n: usize) -> (f64, f64) {
+        if n == 0 {
+            return (0.0, 0.0);
+        }
+
+        let mean = sum / n as f64;
+
+        if n == 1 {
+            return (mean, mean);
+        }
+
+        let n = n as f64;
+        let variance = ((sum_sq - sum * sum / n) / (n - 1.0)).max(0.0);
+        let half = Z_95 * self.fpc() * (variance / n).sqrt();
+
+        (mean - half, mean + half)
+    }
+
+    /// Returns a description of the sample
+    pub fn description(&self) -> String {
+        format!(
+            "Sampled {} of {} card combinations (seed {}), intervals are 95% confidence intervals",
+            self.size, self.population, self.seed
+        )
+    }
+}
@@ -1,6 +1,9 @@
-use crate::calc::{average, percent};
+use crate::calc::{average, percent, percentile};
+use crate::csv::{decimal, Row};
+use crate::operators::Operators;
+use crate::outcomes::Outcomes;
+use crate::sample::Sample;
 
-pub const MAX_BIG: usize = 5;
 pub const TARGET_COUNT: usize = 900;
 
 #[derive(Clone)]
@@ -8,22 +11,41 @@ pub struct Stats {
     pub files: usize,
     pub sol_count: Vec<usize>,
     pub min_sol_cnt: usize,
-    pub min_sols: Option<Vec<Vec<u8>>>,
+    pub min_sols: Option<Vec<Vec<u32>>>,
     pub max_sol_cnt: usize,
-    pub max_sols: Option<Vec<Vec<u8>>>,
+    pub max_sols: Option<Vec<Vec<u32>>>,
     pub tot_sols: usize,
+    pub tot_sols_sq: usize,
     pub sol_25_bucket: Vec<usize>,
     pub sol_50_bucket: Vec<usize>,
     pub sol_100_bucket: Vec<usize>,
+    pub sol_dist: Vec<Vec<u32>>,
     pub tot_combs: usize,
     pub tot_combs_reached: usize,
+    pub outcomes: Outcomes,
+    pub operators: Operators,
 }
 
 impl Stats {
-    pub fn update(&mut self, cards: &[u8], sols: usize, sol_reached: &[bool]) {
-        for (i, reached) in sol_reached.iter().enumerate() {
-            if *reached {
+    pub fn update(
+        &mut self,
+        cards: &[u32],
+        sol_counts: &[u32],
+        counted: bool,
+        outcomes: &Outcomes,
+        operators: &Operators,
+    ) {
+        let mut sols = 0;
+
+        for (i, &count) in sol_counts.iter().enumerate() {
+            if count > 0 {
                 self.sol_count[i] += 1;
+                sols += 1;
+            }
+
+            // Record solution count distribution if the counts are known
+            if counted {
+                self.sol_dist[i].push(count);
             }
         }
 
@@ -32,6 +54,7 @@ impl Stats {
 
         // Add solution count to the total number of solutions
         self.tot_sols += sols;
+        self.tot_sols_sq += sols * sols;
 
         if sols > 0 {
             // Add count to the count buckets
@@ -76,19 +99,109 @@ impl Stats {
 
         self.tot_combs += 900;
         self.tot_combs_reached += sols;
+
+        // Add program outcome counts
+        self.outcomes.merge(outcomes);
+
+        // Add operator usage counts
+        self.operators.merge(operators);
+    }
+
+    /// Returns the 95% confidence interval of the average number of targets achieved, if the stats are from a sample
+    pub fn average_interval(&self, sample: Option<&Sample>) -> Option<(f64, f64)> {
+        sample.map(|sample| {
+            sample.mean_interval(self.tot_sols as f64, self.tot_sols_sq as f64, self.files)
+        })
     }
 
-    pub fn output(&self, desc: &str) {
+    /// Outputs the average number of programs giving an answer in the target range per card set
+    /// and per target reached
+    pub fn output_average_results(&self, desc: &str) {
+        let results = self.outcomes.results();
+
+        Row::new()
+            .field(desc)
+            .field(self.outcomes.files)
+            .field(decimal(average(results, self.outcomes.files)))
+            .field(decimal(average(results, self.tot_sols.max(1))))
+            .print();
+    }
+
+    /// Outputs the quartiles of the number of solutions for each target
+    fn output_distribution(&self, desc: &str) {
+        println!();
+        Row::new()
+            .field(format!("{desc} Solution Count Distribution"))
+            .print();
+        Row::new()
+            .fields([
+                "Target",
+                "Min",
+                "Lower Quartile",
+                "Median",
+                "Upper Quartile",
+                "Max",
+            ])
+            .print();
+
+        let dist_output = |label: &str, sorted: &[u32]| {
+            let pct = |p| percentile(sorted, p).unwrap_or_default();
+
+            Row::new()
+                .field(label)
+                .fields([pct(0), pct(25), pct(50), pct(75), pct(100)])
+                .print();
+        };
+
+        let mut all_counts = Vec::with_capacity(self.files * TARGET_COUNT);
+
+        for (i, counts) in self.sol_dist.iter().enumerate() {
+            let mut sorted = counts.clone();
+            sorted.sort_unstable();
+
+            dist_output(&(i + 100).to_string(), &sorted);
+
+            all_counts.append(&mut sorted);
+        }
+
+        all_counts.sort_unstable();
+        dist_output("All", &all_counts);
+    }
+
+    pub fn output(&self, desc: &str, sample: Option<&Sample>) {
         let mut min_sols = self.sol_count[0];
         let mut min_sol_elems = Vec::new();
         let mut max_sols = self.sol_count[0];
         let mut max_sol_elems = Vec::new();
 
-        println!("===== {desc} =====");
-        println!("Target, Combinations");
+        Row::new().field(format!("===== {desc} =====")).print();
+
+        if sample.is_some() {
+            Row::new()
+                .fields(["Target", "Combinations", "95% CI"])
+                .print();
+        } else {
+            Row::new().fields(["Target", "Combinations"]).print();
+        }
 
         for (i, &n) in self.sol_count.iter().enumerate() {
-            println!("{}, {}, {}", i + 100, n, percent(n, self.files));
+            match sample {
+                Some(sample) => {
+                    let (lo, hi) = sample.proportion_interval(n, self.files);
+
+                    Row::new()
+                        .field(i + 100)
+                        .field(n)
+                        .field(percent(n, self.files))
+                        .field(format!("{}-{}", percent(lo, 1), percent(hi, 1)))
+                        .print();
+                }
+                None => Row::new()
+                    .field(i + 100)
+                    .field(n)
+                    .field(percent(n, self.files))
+                    .print(),
+            }
 
             // Calculate the target(s) with the minimum number of solutions
             if n < min_sols {
@@ -116,20 +229,20 @@ impl Stats {
             let mut cumul = 0;
 
             println!();
-            println!("{desc} Targets Achieved (buckets of {size})");
+            Row::new()
+                .field(format!("{desc} Targets Achieved (buckets of {size})"))
+                .print();
 
             for (i, n) in buckets.iter().enumerate() {
                 cumul += n;
 
-                println!(
-                    "{}-{}, {}, {}, {}, {}",
-                    (i * size) + 1,
-                    (i + 1) * size,
-                    n,
-                    percent(*n, self.files),
-                    cumul,
-                    percent(cumul, self.files)
-                );
+                Row::new()
+                    .field(format!("{}-{}", (i * size) + 1, (i + 1) * size))
+                    .field(n)
+                    .field(percent(*n, self.files))
+                    .field(cumul)
+                    .field(percent(cumul, self.files))
+                    .print();
             }
         };
 
@@ -137,82 +250,86 @@ impl Stats {
         bucket_output(&self.sol_50_bucket, 50);
         bucket_output(&self.sol_100_bucket, 100);
 
+        // Output solution count distribution, unless only solution maps were recorded
+        if self.sol_dist.iter().any(|counts| !counts.is_empty()) {
+            self.output_distribution(desc);
+        }
+
         // General statistics section
         println!();
-        println!("{desc} Statistics");
-
-        let elems = min_sol_elems
-            .iter()
-            .map(|n| (n + 100).to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        println!(
-            "Min Target Achieved, {}, {}, Targets, {}",
-            min_sols,
-            percent(min_sols, self.files),
-            elems
-        );
-
-        let elems = max_sol_elems
-            .iter()
-            .map(|n| (n + 100).to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        println!(
-            "Max Target Achieved, {}, {}, Targets, {}",
-            max_sols,
-            percent(max_sols, self.files),
-            elems
-        );
+        Row::new().field(format!("{desc} Statistics")).print();
+
+        Row::new()
+            .field("Min Target Achieved")
+            .field(min_sols)
+            .field(percent(min_sols, self.files))
+            .field("Targets")
+            .fields(min_sol_elems.iter().map(|n| n + 100))
+            .print();
+
+        Row::new()
+            .field("Max Target Achieved")
+            .field(max_sols)
+            .field(percent(max_sols, self.files))
+            .field("Targets")
+            .fields(max_sol_elems.iter().map(|n| n + 100))
+            .print();
 
         let avg_achieved = average(self.tot_sols, self.files);
-        println!(
-            "Average Target Achieved, {:.2}, {}",
-            avg_achieved,
-            percent(avg_achieved, 900)
-        );
 
-        // Minimum solutions
-        let sols = self.min_sols.as_ref().unwrap();
-        let count = sols.len();
-        print!(
-            "Min Solutions, {}, {}, Count, {}",
-            self.min_sol_cnt,
-            percent(self.min_sol_cnt, 900),
-            count
-        );
+        let mut row = Row::new()
+            .field("Average Target Achieved")
+            .field(decimal(avg_achieved))
+            .field(percent(avg_achieved, 900));
 
-        if count <= 5 {
-            println!(", Cards, {sols:?}");
-        } else {
-            println!();
+        if let Some((lo, hi)) = self.average_interval(sample) {
+            row = row
+                .field("95% CI")
+                .field(format!("{}-{}", decimal(lo), decimal(hi)));
         }
 
-        // Maximum solutions
-        let sols = self.max_sols.as_ref().unwrap();
-        let count = sols.len();
-        print!(
-            "Max Solutions, {}, {}, Count, {}",
-            self.max_sol_cnt,
-            percent(self.max_sol_cnt, 900),
-            count
-        );
+        row.print();
 
-        if count <= 5 {
-            println!(", Cards, {sols:?}");
-        } else {
-            println!();
-        }
+        // Minimum and maximum solutions
+        let sols_row = |desc: &str, cnt: usize, sols: &Vec<Vec<u32>>| {
+            let mut row = Row::new()
+                .field(desc)
+                .field(cnt)
+                .field(percent(cnt, 900))
+                .field("Count")
+                .field(sols.len());
 
-        println!("Card Combinations, {}", self.files);
-        println!("Card/Target combinations, {}", self.tot_combs);
-        println!(
-            "Card/Target combinations reached, {}, {}",
-            self.tot_combs_reached,
-            percent(self.tot_combs_reached, self.tot_combs)
+            if sols.len() <= 5 {
+                row = row.field("Cards").field(format!("{sols:?}"));
+            }
+
+            row.print();
+        };
+
+        sols_row(
+            "Min Solutions",
+            self.min_sol_cnt,
+            self.min_sols.as_ref().unwrap(),
         );
+        sols_row(
+            "Max Solutions",
+            self.max_sol_cnt,
+            self.max_sols.as_ref().unwrap(),
+        );
+
+        Row::new()
+            .field("Card Combinations")
+            .field(self.files)
+            .print();
+        Row::new()
+            .field("Card/Target combinations")
+            .field(self.tot_combs)
+            .print();
+        Row::new()
+            .field("Card/Target combinations reached")
+            .field(self.tot_combs_reached)
+            .field(percent(self.tot_combs_reached, self.tot_combs))
+            .print();
     }
 }
 
@@ -226,11 +343,15 @@ impl Default for Stats {
             max_sol_cnt: 0,
             max_sols: None,
             tot_sols: 0,
+            tot_sols_sq: 0,
             sol_25_bucket: vec![0; TARGET_COUNT / 25],
             sol_50_bucket: vec![0; TARGET_COUNT / 50],
             sol_100_bucket: vec![0; TARGET_COUNT / 100],
+            sol_dist: vec![Vec::new(); TARGET_COUNT],
             tot_combs: 0,
             tot_combs_reached: 0,
+            outcomes: Outcomes::default(),
+            operators: Operators::default(),
         }
     }
 }